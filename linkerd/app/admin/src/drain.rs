@@ -0,0 +1,188 @@
+use crate::{Latch, Readiness};
+use linkerd_app_core::{drain, serve};
+use parking_lot::Mutex;
+use serde_json::json;
+use std::sync::Arc;
+use tokio::{
+    sync::watch,
+    time::{self, Duration, Instant},
+};
+use tracing::{info, warn};
+
+/// Drains the proxy's connections so that it may be shut down without
+/// dropping requests.
+///
+/// Once a drain is started, the proxy is no longer ready, its servers stop
+/// accepting connections, and HTTP connections are closed as their in-flight
+/// requests complete. The drain completes when all connections have been
+/// closed or when the grace period elapses, whichever happens first.
+#[derive(Clone, Debug)]
+pub struct Drain(Arc<Inner>);
+
+#[derive(Debug)]
+struct Inner {
+    grace_period: Duration,
+    connections: serve::Connections,
+    readiness: Readiness,
+    signal: Mutex<Option<drain::Signal>>,
+    // Holds the process unready once the drain has started.
+    latch: Mutex<Option<Latch>>,
+    state_tx: watch::Sender<State>,
+    state_rx: watch::Receiver<State>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum State {
+    Serving,
+    Draining { started: Instant },
+    Drained { elapsed: Duration, timed_out: bool },
+}
+
+// === impl Drain ===
+
+impl Drain {
+    pub fn new(
+        signal: drain::Signal,
+        connections: serve::Connections,
+        readiness: Readiness,
+        grace_period: Duration,
+    ) -> Self {
+        let (state_tx, state_rx) = watch::channel(State::Serving);
+        Self(Arc::new(Inner {
+            grace_period,
+            connections,
+            readiness,
+            signal: Mutex::new(Some(signal)),
+            latch: Mutex::new(None),
+            state_tx,
+            state_rx,
+        }))
+    }
+
+    /// Starts draining the proxy's connections, if a drain has not already
+    /// been started.
+    ///
+    /// Returns false if the proxy was already draining.
+    pub fn start(&self) -> bool {
+        let signal = match self.0.signal.lock().take() {
+            Some(signal) => signal,
+            None => return false,
+        };
+
+        info!(grace_period = ?self.0.grace_period, "Draining connections");
+        *self.0.latch.lock() = Some(self.0.readiness.latch());
+        let started = Instant::now();
+        let _ = self.0.state_tx.send(State::Draining { started });
+
+        let inner = self.0.clone();
+        tokio::spawn(async move {
+            let timed_out = time::timeout(inner.grace_period, signal.drain())
+                .await
+                .is_err();
+            if timed_out {
+                warn!(
+                    connections = inner.connections.count(),
+                    "Grace period elapsed before all connections were drained"
+                );
+            } else {
+                info!("Drained all connections");
+            }
+            let _ = inner.state_tx.send(State::Drained {
+                elapsed: Instant::now().saturating_duration_since(started),
+                timed_out,
+            });
+        });
+        true
+    }
+
+    /// Starts draining the proxy's connections, if necessary, and waits for
+    /// the drain to complete.
+    pub async fn drain(self) {
+        self.start();
+        let mut state = self.0.state_rx.clone();
+        while !matches!(*state.borrow(), State::Drained { .. }) {
+            if state.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Describes the progress of the drain.
+    pub fn to_json(&self) -> serde_json::Value {
+        let grace_period_ms = self.0.grace_period.as_millis() as u64;
+        let connections = self.0.connections.count();
+        match *self.0.state_rx.borrow() {
+            State::Serving => json!({
+                "state": "serving",
+                "grace_period_ms": grace_period_ms,
+                "connections": connections,
+            }),
+            State::Draining { started } => json!({
+                "state": "draining",
+                "grace_period_ms": grace_period_ms,
+                "elapsed_ms": Instant::now().saturating_duration_since(started).as_millis() as u64,
+                "connections": connections,
+            }),
+            State::Drained { elapsed, timed_out } => json!({
+                "state": "drained",
+                "grace_period_ms": grace_period_ms,
+                "elapsed_ms": elapsed.as_millis() as u64,
+                "connections": connections,
+                "timed_out": timed_out,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mk(grace_period: Duration) -> (Drain, drain::Watch, Readiness) {
+        let (signal, watch) = drain::channel();
+        let (readiness, latch) = Readiness::new();
+        latch.release();
+        let drain = Drain::new(signal, Default::default(), readiness.clone(), grace_period);
+        (drain, watch, readiness)
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn drains_connections() {
+        let (drain, watch, readiness) = mk(Duration::from_secs(10));
+        assert_eq!(drain.to_json()["state"], "serving");
+        assert!(readiness.is_ready());
+
+        let conn = tokio::spawn(async move {
+            let release = watch.signaled().await;
+            time::sleep(Duration::from_secs(1)).await;
+            drop(release);
+        });
+
+        assert!(drain.start());
+        assert!(!drain.start(), "the drain may only be started once");
+        assert_eq!(drain.to_json()["state"], "draining");
+        assert!(!readiness.is_ready());
+
+        drain.clone().drain().await;
+        conn.await.unwrap();
+        let json = drain.to_json();
+        assert_eq!(json["state"], "drained");
+        assert_eq!(json["elapsed_ms"], 1000);
+        assert_eq!(json["timed_out"], false);
+        assert!(
+            !readiness.is_ready(),
+            "the proxy must not become ready again"
+        );
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn drain_times_out() {
+        let (drain, watch, _) = mk(Duration::from_secs(10));
+        drain.clone().drain().await;
+        let json = drain.to_json();
+        assert_eq!(json["state"], "drained");
+        assert_eq!(json["elapsed_ms"], 10_000);
+        assert_eq!(json["timed_out"], true);
+        drop(watch);
+    }
+}
@@ -6,8 +6,10 @@
 )]
 #![forbid(unsafe_code)]
 
+mod drain;
 mod server;
 mod stack;
 
+pub use self::drain::Drain;
 pub use self::server::{Admin, Latch, Readiness};
 pub use self::stack::{Config, Task};
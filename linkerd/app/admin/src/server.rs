@@ -1,14 +1,29 @@
 //! Serves an HTTP admin server.
 //!
-//! * `GET /metrics` -- reports prometheus-formatted metrics.
+//! * `GET /metrics` -- reports prometheus-formatted metrics. The `prefix` query
+//!   parameter selects metric families by name prefix and the `drop` parameter
+//!   aggregates the given labels away (e.g. `/metrics?prefix=tcp_&drop=dst_pod`).
+//!   Either parameter may be repeated or hold a comma-separated list.
 //! * `GET /ready` -- returns 200 when the proxy is ready to participate in meshed
 //!   traffic.
 //! * `GET /live` -- returns 200 when the proxy is live.
 //! * `GET /proxy-log-level` -- returns the current proxy tracing filter.
 //! * `PUT /proxy-log-level` -- sets a new tracing filter.
+//! * `GET /debug/destinations` -- returns a JSON description of the outbound
+//!   proxy's logical targets, including their service profiles, resolved
+//!   endpoints and endpoint loads. Only permitted from localhost.
+//! * `GET /debug/policies` -- returns a JSON description of the inbound proxy's
+//!   default server policy and the policy of each known port, including when
+//!   each was last updated by the policy controller. Only permitted from
+//!   localhost.
 //! * `GET /tasks` -- returns a dump of spawned Tokio tasks (when enabled by the
 //!   tracing configuration).
-//! * `POST /shutdown` -- shuts down the proxy.
+//! * `GET /drain` -- returns a JSON description of the progress of a drain,
+//!   including the number of connections that remain open.
+//! * `POST /drain` -- starts draining the proxy's connections: the proxy
+//!   reports that it is not ready, stops accepting connections, and closes
+//!   HTTP connections as their in-flight requests complete.
+//! * `POST /shutdown` -- drains the proxy's connections and shuts it down.
 
 use futures::future;
 use http::StatusCode;
@@ -17,10 +32,12 @@ use hyper::{
     Request, Response,
 };
 use linkerd_app_core::{
-    metrics::{self as metrics, FmtMetrics},
+    metrics::{self as metrics, Encoder, FmtMetrics},
     proxy::http::ClientHandle,
     trace, Error,
 };
+use linkerd_app_inbound as inbound;
+use linkerd_app_outbound as outbound;
 use std::{
     future::Future,
     pin::Pin,
@@ -39,6 +56,9 @@ pub struct Admin<M> {
     tracing: trace::Handle,
     ready: Readiness,
     shutdown_tx: mpsc::UnboundedSender<()>,
+    destinations: outbound::inspect::Registry,
+    policies: inbound::policy::Store,
+    drain: crate::Drain,
 }
 
 pub type ResponseFuture =
@@ -50,12 +70,18 @@ impl<M> Admin<M> {
         ready: Readiness,
         shutdown_tx: mpsc::UnboundedSender<()>,
         tracing: trace::Handle,
+        destinations: outbound::inspect::Registry,
+        policies: inbound::policy::Store,
+        drain: crate::Drain,
     ) -> Self {
         Self {
             metrics: metrics::Serve::new(metrics),
             ready,
             shutdown_tx,
             tracing,
+            destinations,
+            policies,
+            drain,
         }
     }
 
@@ -98,6 +124,58 @@ impl<M> Admin<M> {
         }
     }
 
+    fn destinations_rsp(&self) -> Response<Body> {
+        Self::json_rsp(self.destinations.to_json())
+    }
+
+    fn policies_rsp(&self) -> Response<Body> {
+        Self::json_rsp(self.policies.to_json())
+    }
+
+    fn drain_rsp(&self) -> Response<Body> {
+        Self::json_rsp(self.drain.to_json())
+    }
+
+    fn json_rsp(json: serde_json::Value) -> Response<Body> {
+        let body = json.to_string();
+        Response::builder()
+            .status(StatusCode::OK)
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .body(body.into())
+            .expect("builder with known status code must not fail")
+    }
+
+    /// Parses the query parameters of a `/metrics` request.
+    fn metrics_filter(query: Option<&str>) -> Result<metrics::Filter, String> {
+        let mut filter = metrics::Filter::default();
+        let params = form_urlencoded::parse(query.unwrap_or_default().as_bytes());
+        for (key, values) in params {
+            let values = values
+                .split(',')
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(String::from)
+                .collect::<Vec<_>>();
+            filter = match &*key {
+                "prefix" => values.into_iter().fold(filter, |f, p| f.select_prefix(p)),
+                "drop" => values
+                    .into_iter()
+                    .try_fold(filter, |f, l| f.drop_label(l))
+                    .map_err(|e| format!("{}\n", e))?,
+                _ => return Err(format!("unsupported query parameter: {}\n", key)),
+            };
+        }
+        Ok(filter)
+    }
+
+    fn bad_request(msg: impl Into<Body>) -> Response<Body> {
+        Response::builder()
+            .status(http::StatusCode::BAD_REQUEST)
+            .header(http::header::CONTENT_TYPE, "text/plain")
+            .body(msg.into())
+            .expect("builder with known status code must not fail")
+    }
+
     fn internal_error_rsp(error: impl ToString) -> http::Response<Body> {
         http::Response::builder()
             .status(http::StatusCode::INTERNAL_SERVER_ERROR)
@@ -156,10 +234,17 @@ where
             "/live" => Box::pin(future::ok(Self::live_rsp())),
             "/ready" => Box::pin(future::ok(self.ready_rsp())),
             "/metrics" => {
-                let rsp = self.metrics.serve(req).unwrap_or_else(|error| {
-                    ::tracing::error!(%error, "Failed to format metrics");
-                    Self::internal_error_rsp(error)
-                });
+                let filter = match Self::metrics_filter(req.uri().query()) {
+                    Ok(filter) => filter,
+                    Err(msg) => return Box::pin(future::ok(Self::bad_request(msg))),
+                };
+                let rsp = self
+                    .metrics
+                    .serve_filtered(req, &filter)
+                    .unwrap_or_else(|error| {
+                        ::tracing::error!(%error, "Failed to format metrics");
+                        Self::internal_error_rsp(error)
+                    });
                 Box::pin(future::ok(rsp))
             }
             "/proxy-log-level" => {
@@ -181,6 +266,36 @@ where
                     Box::pin(future::ok(Self::forbidden_not_localhost()))
                 }
             }
+            "/debug/destinations" => {
+                if req.method() != http::Method::GET {
+                    Box::pin(future::ok(Self::method_not_allowed()))
+                } else if Self::client_is_localhost(&req) {
+                    Box::pin(future::ok(self.destinations_rsp()))
+                } else {
+                    Box::pin(future::ok(Self::forbidden_not_localhost()))
+                }
+            }
+            "/debug/policies" => {
+                if req.method() != http::Method::GET {
+                    Box::pin(future::ok(Self::method_not_allowed()))
+                } else if Self::client_is_localhost(&req) {
+                    Box::pin(future::ok(self.policies_rsp()))
+                } else {
+                    Box::pin(future::ok(Self::forbidden_not_localhost()))
+                }
+            }
+            "/drain" => match *req.method() {
+                http::Method::GET => Box::pin(future::ok(self.drain_rsp())),
+                http::Method::POST => {
+                    if Self::client_is_localhost(&req) {
+                        self.drain.start();
+                        Box::pin(future::ok(self.drain_rsp()))
+                    } else {
+                        Box::pin(future::ok(Self::forbidden_not_localhost()))
+                    }
+                }
+                _ => Box::pin(future::ok(Self::method_not_allowed())),
+            },
             "/shutdown" => {
                 if req.method() == http::Method::POST {
                     if Self::client_is_localhost(&req) {
@@ -207,6 +322,20 @@ mod tests {
 
     const TIMEOUT: Duration = Duration::from_secs(1);
 
+    fn policies() -> inbound::policy::Store {
+        inbound::policy::Store::fixed(inbound::DefaultPolicy::Deny, None)
+    }
+
+    fn drain() -> crate::Drain {
+        let (signal, _) = linkerd_app_core::drain::channel();
+        crate::Drain::new(
+            signal,
+            Default::default(),
+            Readiness::new().0,
+            Duration::from_secs(1),
+        )
+    }
+
     #[tokio::test]
     async fn ready_when_latches_dropped() {
         let (r, l0) = Readiness::new();
@@ -214,7 +343,7 @@ mod tests {
 
         let (_, t) = trace::Settings::default().build();
         let (s, _) = mpsc::unbounded_channel();
-        let admin = Admin::new((), r, s, t);
+        let admin = Admin::new((), r, s, t, Default::default(), policies(), drain());
         macro_rules! call {
             () => {{
                 let r = Request::builder()
@@ -235,4 +364,232 @@ mod tests {
         drop(l1);
         assert_eq!(call!().status(), StatusCode::OK);
     }
+
+    #[tokio::test]
+    async fn filters_metrics() {
+        use linkerd_app_core::metrics::{metrics, Counter, FmtLabels};
+        use std::fmt;
+
+        metrics! {
+            request_total: Counter { "Total requests" },
+            tcp_open_total: Counter { "Total connections" }
+        }
+
+        struct Pod(&'static str);
+        impl FmtLabels for Pod {
+            fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "dst_pod=\"{}\",direction=\"outbound\"", self.0)
+            }
+        }
+
+        #[derive(Clone)]
+        struct Metrics;
+        impl FmtMetrics for Metrics {
+            fn fmt_metrics(&self, f: &mut Encoder<'_>) -> fmt::Result {
+                request_total.fmt_help(f)?;
+                request_total.fmt_metric_labeled(f, &Counter::from(1), &Pod("a"))?;
+                request_total.fmt_metric_labeled(f, &Counter::from(2), &Pod("b"))?;
+                tcp_open_total.fmt_help(f)?;
+                tcp_open_total.fmt_metric(f, &Counter::from(3))?;
+                Ok(())
+            }
+        }
+
+        let (_, t) = trace::Settings::default().build();
+        let (s, _) = mpsc::unbounded_channel();
+        let admin = Admin::new(
+            Metrics,
+            Readiness::new().0,
+            s,
+            t,
+            Default::default(),
+            policies(),
+            drain(),
+        );
+        let get = |uri: &'static str| {
+            let admin = admin.clone();
+            async move {
+                let r = Request::builder()
+                    .method(Method::GET)
+                    .uri(uri)
+                    .body(Body::empty())
+                    .unwrap();
+                let rsp = timeout(TIMEOUT, admin.oneshot(r))
+                    .await
+                    .expect("timeout")
+                    .expect("call");
+                let status = rsp.status();
+                let body = hyper::body::to_bytes(rsp.into_body()).await.unwrap();
+                (status, String::from_utf8(body.to_vec()).unwrap())
+            }
+        };
+
+        let (status, body) = get("http://0.0.0.0/metrics?prefix=request_&drop=dst_pod").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body,
+            "# HELP request_total Total requests\n\
+             # TYPE request_total counter\n\
+             request_total{direction=\"outbound\"} 3\n"
+        );
+
+        let (status, _) = get("http://0.0.0.0/metrics?name=request_").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, _) = get("http://0.0.0.0/metrics?drop=le").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn serves_destinations() {
+        let (_, t) = trace::Settings::default().build();
+        let (s, _) = mpsc::unbounded_channel();
+        let admin = Admin::new(
+            (),
+            Readiness::new().0,
+            s,
+            t,
+            Default::default(),
+            policies(),
+            drain(),
+        );
+        let r = Request::builder()
+            .method(Method::GET)
+            .uri("http://0.0.0.0/debug/destinations")
+            .body(Body::empty())
+            .unwrap();
+        let rsp = timeout(TIMEOUT, admin.clone().oneshot(r))
+            .await
+            .expect("timeout")
+            .expect("call");
+        assert_eq!(rsp.status(), StatusCode::FORBIDDEN);
+
+        let mut r = Request::builder()
+            .method(Method::GET)
+            .uri("http://0.0.0.0/debug/destinations")
+            .body(Body::empty())
+            .unwrap();
+        let (handle, _) = ClientHandle::new(([127, 0, 0, 1], 4191).into());
+        r.extensions_mut().insert(handle);
+        let rsp = timeout(TIMEOUT, admin.clone().oneshot(r))
+            .await
+            .expect("timeout")
+            .expect("call");
+        assert_eq!(rsp.status(), StatusCode::OK);
+        assert_eq!(
+            rsp.headers()[http::header::CONTENT_TYPE],
+            "application/json"
+        );
+        let body = hyper::body::to_bytes(rsp.into_body()).await.unwrap();
+        assert_eq!(&body[..], br#"{"logicals":[]}"#);
+
+        let r = Request::builder()
+            .method(Method::POST)
+            .uri("http://0.0.0.0/debug/destinations")
+            .body(Body::empty())
+            .unwrap();
+        let rsp = timeout(TIMEOUT, admin.oneshot(r))
+            .await
+            .expect("timeout")
+            .expect("call");
+        assert_eq!(rsp.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[tokio::test]
+    async fn serves_policies() {
+        let (_, t) = trace::Settings::default().build();
+        let (s, _) = mpsc::unbounded_channel();
+        let admin = Admin::new(
+            (),
+            Readiness::new().0,
+            s,
+            t,
+            Default::default(),
+            policies(),
+            drain(),
+        );
+        let r = Request::builder()
+            .method(Method::GET)
+            .uri("http://0.0.0.0/debug/policies")
+            .body(Body::empty())
+            .unwrap();
+        let rsp = timeout(TIMEOUT, admin.clone().oneshot(r))
+            .await
+            .expect("timeout")
+            .expect("call");
+        assert_eq!(rsp.status(), StatusCode::FORBIDDEN);
+
+        let mut r = Request::builder()
+            .method(Method::GET)
+            .uri("http://0.0.0.0/debug/policies")
+            .body(Body::empty())
+            .unwrap();
+        let (handle, _) = ClientHandle::new(([127, 0, 0, 1], 4191).into());
+        r.extensions_mut().insert(handle);
+        let rsp = timeout(TIMEOUT, admin.oneshot(r))
+            .await
+            .expect("timeout")
+            .expect("call");
+        assert_eq!(rsp.status(), StatusCode::OK);
+        assert_eq!(
+            rsp.headers()[http::header::CONTENT_TYPE],
+            "application/json"
+        );
+        let body = hyper::body::to_bytes(rsp.into_body()).await.unwrap();
+        assert_eq!(&body[..], br#"{"default":"deny","ports":[]}"#);
+    }
+
+    #[tokio::test]
+    async fn serves_drain() {
+        let (r, l) = Readiness::new();
+        l.release();
+        let (signal, _) = linkerd_app_core::drain::channel();
+        let drain = crate::Drain::new(signal, Default::default(), r.clone(), TIMEOUT);
+        let (_, t) = trace::Settings::default().build();
+        let (s, _) = mpsc::unbounded_channel();
+        let admin = Admin::new((), r, s, t, Default::default(), policies(), drain);
+        let call = |method: Method, uri: &'static str, localhost: bool| {
+            let admin = admin.clone();
+            async move {
+                let mut r = Request::builder()
+                    .method(method)
+                    .uri(uri)
+                    .body(Body::empty())
+                    .unwrap();
+                if localhost {
+                    let (handle, _) = ClientHandle::new(([127, 0, 0, 1], 4191).into());
+                    r.extensions_mut().insert(handle);
+                }
+                let rsp = timeout(TIMEOUT, admin.oneshot(r))
+                    .await
+                    .expect("timeout")
+                    .expect("call");
+                let status = rsp.status();
+                let body = hyper::body::to_bytes(rsp.into_body()).await.unwrap();
+                (status, body)
+            }
+        };
+        let state = |body: &[u8]| {
+            let json = serde_json::from_slice::<serde_json::Value>(body).unwrap();
+            json["state"].as_str().unwrap().to_string()
+        };
+
+        let (status, body) = call(Method::GET, "http://0.0.0.0/drain", false).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(state(&body), "serving");
+        let (status, _) = call(Method::GET, "http://0.0.0.0/ready", false).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, _) = call(Method::POST, "http://0.0.0.0/drain", false).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, body) = call(Method::POST, "http://0.0.0.0/drain", true).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(state(&body), "draining");
+        let (status, _) = call(Method::GET, "http://0.0.0.0/ready", false).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+
+        let (status, _) = call(Method::PUT, "http://0.0.0.0/drain", true).await;
+        assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+    }
 }
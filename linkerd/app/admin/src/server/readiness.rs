@@ -1,10 +1,12 @@
+use parking_lot::Mutex;
 use std::sync::{Arc, Weak};
 
 /// Tracks the processes's readiness to serve traffic.
 ///
-/// Once `is_ready()` returns true, it will never return false.
+/// Once `is_ready()` returns true, it only returns false again if a new latch
+/// is acquired via `latch()`.
 #[derive(Clone, Debug)]
-pub struct Readiness(Weak<()>);
+pub struct Readiness(Arc<Mutex<Weak<()>>>);
 
 /// When all latches are dropped, the process is considered ready.
 #[derive(Clone, Debug)]
@@ -13,11 +15,27 @@ pub struct Latch(Arc<()>);
 impl Readiness {
     pub fn new() -> (Readiness, Latch) {
         let r = Arc::new(());
-        (Readiness(Arc::downgrade(&r)), Latch(r))
+        (
+            Readiness(Arc::new(Mutex::new(Arc::downgrade(&r)))),
+            Latch(r),
+        )
     }
 
     pub fn is_ready(&self) -> bool {
-        self.0.upgrade().is_none()
+        self.0.lock().upgrade().is_none()
+    }
+
+    /// Returns a latch that holds the process unready until it is released.
+    pub fn latch(&self) -> Latch {
+        let mut weak = self.0.lock();
+        match weak.upgrade() {
+            Some(r) => Latch(r),
+            None => {
+                let r = Arc::new(());
+                *weak = Arc::downgrade(&r);
+                Latch(r)
+            }
+        }
     }
 }
 
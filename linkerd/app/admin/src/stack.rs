@@ -10,7 +10,8 @@ use linkerd_app_core::{
     transport::{self, listen::Bind, ClientAddr, Local, OrigDstAddr, Remote, ServerAddr},
     Error, Result,
 };
-use linkerd_app_inbound as inbound;
+use linkerd_app_inbound::{self as inbound, policy::CheckPolicy};
+use linkerd_app_outbound as outbound;
 use std::{pin::Pin, time::Duration};
 use thiserror::Error;
 use tokio::sync::mpsc;
@@ -20,11 +21,16 @@ use tracing::debug;
 pub struct Config {
     pub server: ServerConfig,
     pub metrics_retain_idle: Duration,
+    /// The initial schema of native latency histograms.
+    pub metrics_native_histogram_schema: i32,
+    /// How long a drain waits for the proxy's connections to close.
+    pub shutdown_grace_period: Duration,
 }
 
 pub struct Task {
     pub listen_addr: Local<ServerAddr>,
     pub latch: crate::Latch,
+    pub drain: crate::Drain,
     pub serve: Pin<Box<dyn std::future::Future<Output = ()> + Send + 'static>>,
 }
 
@@ -73,13 +79,16 @@ impl Config {
     pub fn build<B, R>(
         self,
         bind: B,
-        policy: impl inbound::policy::CheckPolicy,
+        policies: inbound::policy::Store,
         identity: identity::Server,
         report: R,
         metrics: inbound::Metrics,
         trace: trace::Handle,
         drain: drain::Watch,
         shutdown: mpsc::UnboundedSender<()>,
+        destinations: outbound::inspect::Registry,
+        proxy_drain: drain::Signal,
+        proxy_connections: serve::Connections,
     ) -> Result<Task>
     where
         R: FmtMetrics + Clone + Send + Sync + Unpin + 'static,
@@ -89,17 +98,35 @@ impl Config {
         let (listen_addr, listen) = bind.bind(&self.server)?;
 
         // Get the policy for the admin server.
-        let policy = policy.check_policy(OrigDstAddr(listen_addr.into()))?;
+        let policy = policies.check_policy(OrigDstAddr(listen_addr.into()))?;
 
         let (ready, latch) = crate::server::Readiness::new();
-        let admin = crate::server::Admin::new(report, ready, shutdown, trace);
+        let proxy_drain = crate::Drain::new(
+            proxy_drain,
+            proxy_connections,
+            ready.clone(),
+            self.shutdown_grace_period,
+        );
+        let admin = crate::server::Admin::new(
+            report,
+            ready,
+            shutdown,
+            trace,
+            destinations,
+            policies,
+            proxy_drain.clone(),
+        );
         let admin = svc::stack(move |_| admin.clone())
             .push(metrics.proxy.http_endpoint.to_layer::<classify::Response, _, Permitted>())
             .push_map_target(|(permit, http)| Permitted { permit, http })
             .push(inbound::policy::NewAuthorizeHttp::layer(metrics.http_authz.clone()))
             .push(Rescue::layer())
             .push_on_service(http::BoxResponse::layer())
-            .push(http::NewServeHttp::layer(Default::default(), drain.clone()))
+            .push(http::NewServeHttp::layer(
+                Default::default(),
+                Default::default(),
+                drain.clone(),
+            ))
             .push_request_filter(
                 |(http, tcp): (
                     Result<Option<http::Version>, detect::DetectTimeoutError<_>>,
@@ -158,10 +185,16 @@ impl Config {
             }))
             .into_inner();
 
-        let serve = Box::pin(serve::serve(listen, admin, drain.signaled()));
+        let serve = Box::pin(serve::serve(
+            listen,
+            admin,
+            drain.signaled(),
+            Default::default(),
+        ));
         Ok(Task {
             listen_addr,
             latch,
+            drain: proxy_drain,
             serve,
         })
     }
@@ -224,6 +224,12 @@ impl Class {
     }
 }
 
+impl linkerd_failure_accrual::http::IsFailure for Class {
+    fn is_failure(&self) -> bool {
+        Class::is_failure(self)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{Class, SuccessOrFailure};
@@ -11,6 +11,7 @@ pub struct ServerConfig {
     pub addr: ListenAddr,
     pub keepalive: Keepalive,
     pub h2_settings: h2::Settings,
+    pub request_limits: http::RequestLimits,
 }
 
 #[derive(Clone, Debug)]
@@ -107,6 +107,15 @@ impl SyntheticHttpResponse {
         }
     }
 
+    pub fn unavailable(msg: impl ToString) -> Self {
+        Self {
+            http_status: http::StatusCode::SERVICE_UNAVAILABLE,
+            grpc_status: tonic::Code::Unavailable,
+            close_connection: false,
+            message: Cow::Owned(msg.to_string()),
+        }
+    }
+
     pub fn gateway_timeout(msg: impl ToString) -> Self {
         Self {
             close_connection: true,
@@ -134,6 +143,35 @@ impl SyntheticHttpResponse {
         }
     }
 
+    pub fn rate_limited(msg: impl ToString) -> Self {
+        Self {
+            http_status: http::StatusCode::TOO_MANY_REQUESTS,
+            grpc_status: tonic::Code::ResourceExhausted,
+            close_connection: false,
+            message: Cow::Owned(msg.to_string()),
+        }
+    }
+
+    pub fn header_fields_too_large(msg: impl ToString) -> Self {
+        Self {
+            http_status: http::StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE,
+            grpc_status: tonic::Code::ResourceExhausted,
+            close_connection: false,
+            message: Cow::Owned(msg.to_string()),
+        }
+    }
+
+    pub fn payload_too_large(msg: impl ToString) -> Self {
+        Self {
+            http_status: http::StatusCode::PAYLOAD_TOO_LARGE,
+            grpc_status: tonic::Code::ResourceExhausted,
+            // The remainder of the request body is not read, so the connection
+            // cannot be reused.
+            close_connection: true,
+            message: Cow::Owned(msg.to_string()),
+        }
+    }
+
     pub fn loop_detected(msg: impl ToString) -> Self {
         Self {
             http_status: http::StatusCode::LOOP_DETECTED,
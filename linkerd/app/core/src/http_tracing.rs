@@ -1,107 +1,127 @@
+use crate::{classify, errors::respond::L5D_PROXY_ERROR, tls, Conditional};
+use futures::{future::Either, prelude::*};
 use linkerd_error::Error;
-use linkerd_opencensus::proto::trace::v1 as oc;
-use linkerd_stack::layer;
-use linkerd_trace_context::{self as trace_context, TraceContext};
-use std::{collections::HashMap, sync::Arc};
-use thiserror::Error;
+use linkerd_http_classify::{ClassifyEos, ClassifyResponse};
+use linkerd_proxy_tap::Inspect;
+use linkerd_stack::{layer, NewService};
+use linkerd_trace_context::{self as trace_context, SpanLabels, TraceContext};
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
 use tokio::sync::mpsc;
 
-pub type OpenCensusSink = Option<mpsc::Sender<oc::Span>>;
 pub type Labels = Arc<HashMap<String, String>>;
 
-/// SpanConverter converts trace_context::Span objects into OpenCensus agent
-/// protobuf span objects. SpanConverter receives trace_context::Span objects by
-/// implmenting the SpanSink trait. For each span that it receives, it converts
-/// it to an OpenCensus span and then sends it on the provided mpsc::Sender.
+/// Sends completed spans to a trace collector.
+#[derive(Clone, Debug)]
+pub struct SpanSink {
+    pub spans: mpsc::Sender<ExportSpan>,
+
+    /// The order in which trace context propagation formats are read from
+    /// requests.
+    pub propagations: trace_context::Propagations,
+
+    /// Decides whether traces are started for requests that have no trace
+    /// context. Only server-side stacks start traces.
+    pub sampler: trace_context::Sampler,
+}
+
+/// A completed span, annotated with the labels of the proxy stack that
+/// produced it.
+///
+/// Spans are converted into a collector's protocol by its exporter.
+#[derive(Debug)]
+pub struct ExportSpan {
+    pub span: trace_context::Span,
+    pub kind: SpanKind,
+    pub labels: Labels,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SpanKind {
+    Server,
+    Client,
+}
+
+/// SpanConverter converts trace_context::Span objects into `ExportSpan`s.
+/// SpanConverter receives trace_context::Span objects by implmenting the
+/// SpanSink trait. For each span that it receives, it annotates it with the
+/// stack's span kind and labels and then sends it on the provided
+/// mpsc::Sender.
 #[derive(Clone)]
 pub struct SpanConverter {
-    kind: Kind,
-    sink: mpsc::Sender<oc::Span>,
+    kind: SpanKind,
+    sink: mpsc::Sender<ExportSpan>,
     labels: Labels,
 }
 
-#[derive(Debug, Error)]
-#[error("ID '{:?} should have {} bytes, but it has {}", self.id, self.expected_size, self.actual_size)]
-pub struct IdLengthError {
-    id: Vec<u8>,
-    expected_size: usize,
-    actual_size: usize,
+/// Labels a span with the classification of its response and the proxy error
+/// that caused it, if any.
+///
+/// Responses are classified by their headers, as spans are completed before
+/// the response body is read.
+#[derive(Clone, Debug)]
+pub struct RecordResponse<S> {
+    inner: S,
+}
+
+/// Builds services that label spans with their target's endpoint, TLS
+/// identities and route, as described by the target's `Inspect`
+/// implementation.
+#[derive(Clone, Debug)]
+pub struct NewRecordTarget<N> {
+    inner: N,
+}
+
+#[derive(Clone, Debug)]
+pub struct RecordTarget<T, S> {
+    target: T,
+    inner: S,
 }
 
 pub fn server<S>(
-    sink: OpenCensusSink,
+    sink: Option<SpanSink>,
     labels: impl Into<Labels>,
-) -> impl layer::Layer<S, Service = TraceContext<Option<SpanConverter>, S>> + Clone {
-    SpanConverter::layer(Kind::Server, sink, labels)
+) -> impl layer::Layer<S, Service = TraceContext<Option<SpanConverter>, RecordResponse<S>>> + Clone
+{
+    SpanConverter::layer(SpanKind::Server, sink, labels)
 }
 
 pub fn client<S>(
-    sink: OpenCensusSink,
+    sink: Option<SpanSink>,
     labels: impl Into<Labels>,
-) -> impl layer::Layer<S, Service = TraceContext<Option<SpanConverter>, S>> + Clone {
-    SpanConverter::layer(Kind::Client, sink, labels)
-}
-
-#[derive(Copy, Clone, Debug, PartialEq)]
-enum Kind {
-    Server = 1,
-    Client = 2,
+) -> impl layer::Layer<S, Service = TraceContext<Option<SpanConverter>, RecordResponse<S>>> + Clone
+{
+    SpanConverter::layer(SpanKind::Client, sink, labels)
 }
 
 impl SpanConverter {
     fn layer<S>(
-        kind: Kind,
-        sink: OpenCensusSink,
+        kind: SpanKind,
+        sink: Option<SpanSink>,
         labels: impl Into<Labels>,
-    ) -> impl layer::Layer<S, Service = TraceContext<Option<Self>, S>> + Clone {
-        TraceContext::layer(sink.map(move |sink| Self {
-            kind,
-            sink,
-            labels: labels.into(),
-        }))
-    }
-
-    fn mk_span(&self, mut span: trace_context::Span) -> Result<oc::Span, IdLengthError> {
-        let mut attributes = HashMap::<String, oc::AttributeValue>::new();
-        for (k, v) in self.labels.iter() {
-            attributes.insert(
-                k.clone(),
-                oc::AttributeValue {
-                    value: Some(oc::attribute_value::Value::StringValue(truncatable(
-                        v.clone(),
-                    ))),
-                },
-            );
-        }
-        for (k, v) in span.labels.drain() {
-            attributes.insert(
-                k.to_string(),
-                oc::AttributeValue {
-                    value: Some(oc::attribute_value::Value::StringValue(truncatable(v))),
-                },
-            );
-        }
-        Ok(oc::Span {
-            trace_id: into_bytes(span.trace_id, 16)?,
-            span_id: into_bytes(span.span_id, 8)?,
-            tracestate: None,
-            parent_span_id: into_bytes(span.parent_id, 8)?,
-            name: Some(truncatable(span.span_name)),
-            kind: self.kind as i32,
-            start_time: Some(span.start.into()),
-            end_time: Some(span.end.into()),
-            attributes: Some(oc::span::Attributes {
-                attribute_map: attributes,
-                dropped_attributes_count: 0,
+    ) -> impl layer::Layer<S, Service = TraceContext<Option<Self>, RecordResponse<S>>> + Clone {
+        let propagations = sink
+            .as_ref()
+            .map(|s| s.propagations.clone())
+            .unwrap_or_default();
+        let sampler = match (kind, sink.as_ref()) {
+            (SpanKind::Server, Some(sink)) => sink.sampler.clone(),
+            _ => trace_context::Sampler::default(),
+        };
+        let trace = TraceContext::layer(
+            propagations,
+            sampler,
+            sink.map(move |sink| Self {
+                kind,
+                sink: sink.spans,
+                labels: labels.into(),
             }),
-            stack_trace: None,
-            time_events: None,
-            links: None,
-            status: None, // TODO: this is gRPC status; we must read response trailers to populate this
-            resource: None,
-            same_process_as_parent_span: Some(self.kind == Kind::Client),
-            child_span_count: None,
-        })
+        );
+        layer::mk(move |inner| layer::Layer::layer(&trace, RecordResponse { inner }))
     }
 }
 
@@ -112,28 +132,170 @@ impl trace_context::SpanSink for SpanConverter {
     }
 
     fn try_send(&mut self, span: trace_context::Span) -> Result<(), Error> {
-        let span = self.mk_span(span)?;
+        let span = ExportSpan {
+            span,
+            kind: self.kind,
+            labels: self.labels.clone(),
+        };
         self.sink.try_send(span).map_err(Into::into)
     }
 }
 
-fn into_bytes(id: trace_context::Id, size: usize) -> Result<Vec<u8>, IdLengthError> {
-    let bytes: Vec<u8> = id.into();
-    if bytes.len() == size {
-        Ok(bytes)
+// === impl RecordResponse ===
+
+impl<S, ReqB, RspB> tower::Service<http::Request<ReqB>> for RecordResponse<S>
+where
+    S: tower::Service<http::Request<ReqB>, Response = http::Response<RspB>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Either<
+        S::Future,
+        Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send + 'static>>,
+    >;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<ReqB>) -> Self::Future {
+        let labels = match req.extensions().get::<SpanLabels>() {
+            Some(labels) => labels.clone(),
+            None => return Either::Left(self.inner.call(req)),
+        };
+        // Routes set a classifier that may be configured by a service profile.
+        // Otherwise, responses are classified by their status.
+        let classify = req
+            .extensions()
+            .get::<classify::Response>()
+            .cloned()
+            .unwrap_or_default();
+
+        Either::Right(Box::pin(self.inner.call(req).map_ok(move |rsp| {
+            record_response(&labels, classify, &rsp);
+            rsp
+        })))
+    }
+}
+
+fn record_response<B>(labels: &SpanLabels, classify: classify::Response, rsp: &http::Response<B>) {
+    let class = classify.start(rsp).eos(None);
+    let class = if class.is_failure() {
+        "failure"
     } else {
-        let actual_size = bytes.len();
-        Err(IdLengthError {
-            id: bytes,
-            expected_size: size,
-            actual_size,
-        })
+        "success"
+    };
+    labels.insert("classification", class);
+
+    if let Some(error) = rsp
+        .headers()
+        .get(L5D_PROXY_ERROR)
+        .and_then(|v| v.to_str().ok())
+    {
+        labels.insert("proxy.error", error);
     }
 }
 
-fn truncatable(value: String) -> oc::TruncatableString {
-    oc::TruncatableString {
-        value,
-        truncated_byte_count: 0,
+// === impl NewRecordTarget ===
+
+impl<N> NewRecordTarget<N> {
+    pub fn layer() -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(|inner| Self { inner })
+    }
+}
+
+impl<T, N> NewService<T> for NewRecordTarget<N>
+where
+    T: Inspect + Clone,
+    N: NewService<T>,
+{
+    type Service = RecordTarget<T, N::Service>;
+
+    fn new_service(&self, target: T) -> Self::Service {
+        let inner = self.inner.new_service(target.clone());
+        RecordTarget { target, inner }
+    }
+}
+
+// === impl RecordTarget ===
+
+impl<T, S, B> tower::Service<http::Request<B>> for RecordTarget<T, S>
+where
+    T: Inspect,
+    S: tower::Service<http::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        if let Some(labels) = req.extensions().get::<SpanLabels>() {
+            record_target(labels, &self.target, &req);
+        }
+        self.inner.call(req)
+    }
+}
+
+fn record_target<T: Inspect, B>(labels: &SpanLabels, target: &T, req: &http::Request<B>) {
+    if let Some(addr) = target.dst_addr(req) {
+        labels.insert("endpoint.addr", addr.to_string());
+    }
+    if let Some(dst_labels) = target.dst_labels(req) {
+        for (k, v) in dst_labels.iter() {
+            labels.insert(format!("endpoint.label.{}", k), v.clone());
+        }
+    }
+
+    if let Conditional::Some(tls) = target.dst_tls(req) {
+        labels.insert("tls.server.id", tls.server_id.to_string());
+    }
+    if let Conditional::Some(tls::ServerTls::Established {
+        client_id: Some(id),
+        ..
+    }) = target.src_tls(req)
+    {
+        labels.insert("tls.client.id", id.to_string());
+    }
+
+    if let Some(name) = target
+        .route_labels(req)
+        .and_then(|route| route.get("route").cloned())
+    {
+        labels.insert("route.name", name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::StatusCode;
+
+    #[test]
+    fn records_response() {
+        let labels = SpanLabels::default();
+        record_response(
+            &labels,
+            classify::Response::default(),
+            &http::Response::new(()),
+        );
+        assert_eq!(labels.get("classification").unwrap(), "success");
+        assert!(labels.get("proxy.error").is_none());
+
+        let labels = SpanLabels::default();
+        let rsp = http::Response::builder()
+            .status(StatusCode::BAD_GATEWAY)
+            .header(L5D_PROXY_ERROR, "connection refused")
+            .body(())
+            .unwrap();
+        record_response(&labels, classify::Response::default(), &rsp);
+        assert_eq!(labels.get("classification").unwrap(), "failure");
+        assert_eq!(labels.get("proxy.error").unwrap(), "connection refused");
     }
 }
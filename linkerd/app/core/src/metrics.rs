@@ -1,7 +1,8 @@
 pub use crate::transport::labels::{TargetAddr, TlsAccept};
 use crate::{
     classify::{Class, SuccessOrFailure},
-    control, http_metrics, http_metrics as metrics, opencensus, profiles, stack_metrics,
+    control, http_metrics, http_metrics as metrics, opencensus, opentelemetry, profiles,
+    stack_metrics,
     svc::Param,
     telemetry, tls,
     transport::{self, labels::TlsConnect},
@@ -30,6 +31,7 @@ pub struct Metrics {
     pub proxy: Proxy,
     pub control: ControlHttp,
     pub opencensus: opencensus::metrics::Registry,
+    pub opentelemetry: opentelemetry::metrics::Registry,
 }
 
 #[derive(Clone, Debug)]
@@ -69,6 +71,13 @@ pub struct ServerLabel {
     pub name: Arc<str>,
 }
 
+/// A label referencing an inbound HTTP route (i.e. for policy).
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct HttpRouteLabel {
+    pub kind: Arc<str>,
+    pub name: Arc<str>,
+}
+
 /// Labels referencing an inbound `ServerAuthorization.
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub struct AuthzLabels {
@@ -124,25 +133,30 @@ where
 // === impl Metrics ===
 
 impl Metrics {
-    pub fn new(retain_idle: Duration) -> (Self, impl FmtMetrics + Clone + Send + 'static) {
+    /// Creates the proxy's metrics registries. Native latency histograms have
+    /// the given initial schema.
+    pub fn new(
+        retain_idle: Duration,
+        native_schema: i32,
+    ) -> (Self, impl FmtMetrics + Clone + Send + 'static) {
         let process = telemetry::process::Report::new(SystemTime::now());
 
         let build_info = telemetry::build_info::Report::new();
 
         let (control, control_report) = {
-            let m = metrics::Requests::<ControlLabels, Class>::default();
+            let m = metrics::Requests::<ControlLabels, Class>::new(native_schema);
             let r = m.clone().into_report(retain_idle).with_prefix("control");
             (m, r)
         };
 
         let (http_endpoint, endpoint_report) = {
-            let m = metrics::Requests::<EndpointLabels, Class>::default();
+            let m = metrics::Requests::<EndpointLabels, Class>::new(native_schema);
             let r = m.clone().into_report(retain_idle);
             (m, r)
         };
 
         let (http_route, route_report) = {
-            let m = metrics::Requests::<RouteLabels, Class>::default();
+            let m = metrics::Requests::<RouteLabels, Class>::new(native_schema);
             let r = m.clone().into_report(retain_idle).with_prefix("route");
             (m, r)
         };
@@ -154,7 +168,7 @@ impl Metrics {
         };
 
         let (http_route_actual, actual_report) = {
-            let m = metrics::Requests::<RouteLabels, Class>::default();
+            let m = metrics::Requests::<RouteLabels, Class>::new(native_schema);
             let r = m
                 .clone()
                 .into_report(retain_idle)
@@ -164,7 +178,7 @@ impl Metrics {
 
         let stack = stack_metrics::Registry::default();
 
-        let (transport, transport_report) = transport::Metrics::new(retain_idle);
+        let (transport, transport_report) = transport::Metrics::new(retain_idle, native_schema);
 
         let proxy = Proxy {
             http_endpoint,
@@ -176,11 +190,13 @@ impl Metrics {
         };
 
         let (opencensus, opencensus_report) = opencensus::metrics::new();
+        let (opentelemetry, opentelemetry_report) = opentelemetry::metrics::new();
 
         let metrics = Metrics {
             proxy,
             control,
             opencensus,
+            opentelemetry,
         };
 
         let report = endpoint_report
@@ -190,6 +206,7 @@ impl Metrics {
             .and_report(control_report)
             .and_report(transport_report)
             .and_report(opencensus_report)
+            .and_report(opentelemetry_report)
             .and_report(stack)
             .and_report(process)
             .and_report(build_info);
@@ -299,6 +316,16 @@ impl FmtLabels for ServerLabel {
     }
 }
 
+impl FmtLabels for HttpRouteLabel {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "route_kind=\"{}\",route_name=\"{}\"",
+            self.kind, self.name
+        )
+    }
+}
+
 impl FmtLabels for AuthzLabels {
     fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         self.server.fmt_labels(f)?;
@@ -6,16 +6,30 @@ use crate::{
 };
 use futures::prelude::*;
 use linkerd_error::Error;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
 use tower::util::ServiceExt;
 use tracing::{debug, debug_span, info, instrument::Instrument, warn};
 
+/// Counts the connections that are being served.
+#[derive(Clone, Debug, Default)]
+pub struct Connections(Arc<AtomicUsize>);
+
+/// Decrements the count of open connections when dropped.
+#[derive(Debug)]
+struct Open(Connections);
+
 /// Spawns a task that binds an `L`-typed listener with an `A`-typed connection-accepting service.
 ///
-/// The task is driven until shutdown is signaled.
+/// The task is driven until shutdown is signaled. Each accepted connection is
+/// counted by `connections` until it is closed.
 pub async fn serve<M, S, I, A>(
     listen: impl Stream<Item = Result<(A, I)>>,
     new_accept: M,
     shutdown: impl Future,
+    connections: Connections,
 ) where
     I: Send + 'static,
     A: Param<Remote<ClientAddr>>,
@@ -43,6 +57,7 @@ pub async fn serve<M, S, I, A>(
                     let Remote(ClientAddr(client_addr)) = addrs.param();
                     let span = debug_span!("accept", client.addr = %client_addr).entered();
                     let accept = new_accept.new_service(addrs);
+                    let open = connections.open();
 
                     // Dispatch all of the work for a given connection onto a
                     // connection-specific task.
@@ -72,6 +87,7 @@ pub async fn serve<M, S, I, A>(
                                     warn!(error, client.addr = %client_addr, "Server failed to become ready");
                                 }
                             }
+                            drop(open);
                         }
                         .instrument(span.exit().or_current()),
                     );
@@ -90,6 +106,26 @@ pub async fn serve<M, S, I, A>(
     }
 }
 
+// === impl Connections ===
+
+impl Connections {
+    /// Returns the number of connections that are currently open.
+    pub fn count(&self) -> usize {
+        self.0.load(Ordering::Acquire)
+    }
+
+    fn open(&self) -> Open {
+        self.0.fetch_add(1, Ordering::AcqRel);
+        Open(self.clone())
+    }
+}
+
+impl Drop for Open {
+    fn drop(&mut self) {
+        (self.0).0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
 fn is_io(e: &(dyn std::error::Error + 'static)) -> bool {
     e.is::<io::Error>() || e.source().map(is_io).unwrap_or(false)
 }
@@ -211,6 +211,21 @@ impl<S> Stack<S> {
         self.push(cache::Cache::layer(idle))
     }
 
+    /// Like `push_cache`, but the cache's entries may be observed via
+    /// `inspect`.
+    pub fn push_cache_inspect<T>(
+        self,
+        idle: Duration,
+        inspect: cache::Inspect<T>,
+    ) -> Stack<cache::Cache<T, S>>
+    where
+        T: Clone + Eq + std::fmt::Debug + std::hash::Hash + Send + Sync + 'static,
+        S: NewService<T> + 'static,
+        S::Service: Send + Sync + 'static,
+    {
+        self.push(cache::Cache::layer_inspect(idle, inspect))
+    }
+
     /// Push a service that either calls the inner service if it is ready, or
     /// calls a `secondary` service if the inner service fails to become ready
     /// for the `skip_after` duration.
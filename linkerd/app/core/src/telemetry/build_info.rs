@@ -1,4 +1,4 @@
-use linkerd_metrics::{metrics, FmtLabels, FmtMetric, FmtMetrics, Gauge};
+use linkerd_metrics::{metrics, Encoder, FmtLabels, FmtMetric, FmtMetrics, Gauge};
 use std::env;
 use std::fmt;
 use std::string::String;
@@ -52,7 +52,7 @@ impl Report {
 }
 
 impl FmtMetrics for Report {
-    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fn fmt_metrics(&self, f: &mut Encoder<'_>) -> fmt::Result {
         proxy_build_info.fmt_help(f)?;
         self.value
             .fmt_metric_labeled(f, self.name.as_str(), self.labels.as_ref())?;
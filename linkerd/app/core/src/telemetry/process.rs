@@ -1,4 +1,4 @@
-use linkerd_metrics::{metrics, FmtMetrics, Gauge};
+use linkerd_metrics::{metrics, Encoder, FmtMetrics, Gauge};
 use std::fmt;
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -36,7 +36,7 @@ impl Report {
 }
 
 impl FmtMetrics for Report {
-    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fn fmt_metrics(&self, f: &mut Encoder<'_>) -> fmt::Result {
         process_start_time_seconds.fmt_help(f)?;
         process_start_time_seconds.fmt_metric(f, self.start_time.as_ref())?;
 
@@ -49,7 +49,7 @@ impl FmtMetrics for Report {
 
 #[cfg(target_os = "linux")]
 mod linux {
-    use linkerd_metrics::{metrics, Counter, FmtMetrics, Gauge, MillisAsSeconds};
+    use linkerd_metrics::{metrics, Counter, Encoder, FmtMetrics, Gauge, MillisAsSeconds};
     use linkerd_system as sys;
     use std::fmt;
     use tracing::warn;
@@ -98,7 +98,7 @@ mod linux {
     }
 
     impl FmtMetrics for System {
-        fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn fmt_metrics(&self, f: &mut Encoder<'_>) -> fmt::Result {
             let stat = match sys::blocking_stat() {
                 Ok(stat) => stat,
                 Err(err) => {
@@ -11,8 +11,11 @@ pub use self::allow_ips::AllowIps;
 pub struct Metrics(metrics::Registry<labels::Key>);
 
 impl Metrics {
-    pub fn new(retain_idle: std::time::Duration) -> (Self, metrics::Report<labels::Key>) {
-        let (reg, report) = metrics::new(retain_idle);
+    pub fn new(
+        retain_idle: std::time::Duration,
+        native_schema: i32,
+    ) -> (Self, metrics::Report<labels::Key>) {
+        let (reg, report) = metrics::new(retain_idle, native_schema);
         (Self(reg), report)
     }
 }
@@ -123,7 +123,7 @@ mod tests {
     #[tokio::test(flavor = "current_thread")]
     async fn default_allow() {
         let (io, _) = io::duplex(1);
-        let (policies, _tx) = Store::fixed(
+        let policies = Store::fixed(
             ServerPolicy {
                 protocol: linkerd_server_policy::Protocol::Opaque,
                 authorizations: vec![Authorization {
@@ -134,6 +134,8 @@ mod tests {
                 }],
                 kind: "server".into(),
                 name: "testsrv".into(),
+                http_routes: vec![],
+                rate_limit: None,
             },
             None,
         );
@@ -149,7 +151,7 @@ mod tests {
 
     #[tokio::test(flavor = "current_thread")]
     async fn default_deny() {
-        let (policies, _tx) = Store::fixed(DefaultPolicy::Deny, None);
+        let policies = Store::fixed(DefaultPolicy::Deny, None);
         let (io, _) = io::duplex(1);
         inbound()
             .with_stack(new_ok())
@@ -163,7 +165,7 @@ mod tests {
 
     #[tokio::test(flavor = "current_thread")]
     async fn direct() {
-        let (policies, _tx) = Store::fixed(DefaultPolicy::Deny, None);
+        let policies = Store::fixed(DefaultPolicy::Deny, None);
         let (io, _) = io::duplex(1);
         inbound()
             .with_stack(new_panic("detect stack must not be built"))
@@ -474,6 +474,8 @@ mod tests {
                 }],
                 kind: "server".into(),
                 name: "testsrv".into(),
+                http_routes: vec![],
+                rate_limit: None,
             },
         );
         allow
@@ -221,6 +221,8 @@ pub mod fuzz {
                     }],
                     kind: "server".into(),
                     name: "testsrv".into(),
+                    http_routes: vec![],
+                    rate_limit: None,
                 },
             );
             policy
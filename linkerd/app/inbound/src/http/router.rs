@@ -124,6 +124,9 @@ impl<C> Inbound<C> {
                         .http_endpoint
                         .to_layer::<classify::Response, _, _>(),
                 )
+                // Labels the span with the target's address, the client's identity and
+                // the request's route.
+                .push(http_tracing::NewRecordTarget::layer())
                 .push_on_service(
                     svc::layers()
                         .push(http_tracing::client(
@@ -235,6 +238,7 @@ impl<C> Inbound<C> {
                 // minimize it's type footprint with a Box.
                 .push(svc::ArcNewService::layer())
                 .push(svc::NewRouter::layer(LogicalPerRequest::from))
+                .push(policy::NewRateLimitHttp::layer(rt.metrics.http_rate_limit.clone()))
                 .push(policy::NewAuthorizeHttp::layer(rt.metrics.http_authz.clone()))
                 // Used by tap.
                 .push_http_insert_target::<tls::ConditionalServerTls>()
@@ -41,8 +41,14 @@ impl<H> Inbound<H> {
         HSvc::Future: Send,
     {
         self.map_stack(|config, rt, http| {
+            let access_log_headers = config.access_log_headers.clone();
             let ProxyConfig {
-                server: ServerConfig { h2_settings, .. },
+                server:
+                    ServerConfig {
+                        h2_settings,
+                        request_limits,
+                        ..
+                    },
                 dispatch_timeout,
                 max_in_flight_requests,
                 ..
@@ -66,7 +72,10 @@ impl<H> Inbound<H> {
                         // driven outside of the request path, so there's no need
                         // for SpawnReady
                         .push(svc::ConcurrencyLimitLayer::new(max_in_flight_requests))
-                        .push(svc::FailFast::layer("HTTP Server", dispatch_timeout)),
+                        .push(svc::FailFast::layer("HTTP Server", dispatch_timeout))
+                        // Reject requests that exceed the server's size limits
+                        // before they are dispatched.
+                        .push(http::LimitRequests::layer(request_limits)),
                 )
                 .push(rt.metrics.http_errors.to_layer())
                 .push(ServerRescue::layer())
@@ -80,10 +89,14 @@ impl<H> Inbound<H> {
                         .push(http::normalize_uri::MarkAbsoluteForm::layer())
                         .push(http::BoxResponse::layer()),
                 )
-                .check_new_service::<T, http::Request<_>>()
-                .push(NewAccessLog::layer())
+                .check_new_service::<T, http::Request<http::UpgradeBody>>()
+                .push(NewAccessLog::layer(access_log_headers))
                 .instrument(|t: &T| debug_span!("http", v = %Param::<Version>::param(t)))
-                .push(http::NewServeHttp::layer(h2_settings, rt.drain.clone()))
+                .push(http::NewServeHttp::layer(
+                    h2_settings,
+                    request_limits,
+                    rt.drain.clone(),
+                ))
                 .push_on_service(svc::BoxService::layer())
                 .push(svc::ArcNewService::layer())
         })
@@ -131,6 +144,17 @@ impl errors::HttpRescue<Error> for ServerRescue {
         if cause.is::<crate::policy::DeniedUnauthorized>() {
             return Ok(errors::SyntheticHttpResponse::permission_denied(cause));
         }
+        if cause.is::<crate::policy::RateLimited>() {
+            return Ok(errors::SyntheticHttpResponse::rate_limited(cause));
+        }
+        if cause.is::<http::limits::HeadersTooLarge>() {
+            return Ok(errors::SyntheticHttpResponse::header_fields_too_large(
+                cause,
+            ));
+        }
+        if cause.is::<http::limits::BodyTooLarge>() {
+            return Ok(errors::SyntheticHttpResponse::payload_too_large(cause));
+        }
         if cause.is::<crate::GatewayDomainInvalid>() {
             return Ok(errors::SyntheticHttpResponse::not_found(cause));
         }
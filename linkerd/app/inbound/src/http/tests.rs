@@ -474,6 +474,90 @@ async fn grpc_unmeshed_response_error_header() {
 }
 
 #[tracing::instrument]
+#[tokio::test(flavor = "current_thread")]
+async fn http1_request_limits() {
+    let mut server = hyper::server::conn::Http::new();
+    server.http1_only(true);
+    let mut client = ClientBuilder::new();
+    let _trace = trace_init();
+
+    let connect = support::connect().endpoint_fn_boxed(Target::addr(), hello_server(server));
+    let profiles = profile::resolver();
+    let profile_tx =
+        profiles.profile_tx(NameAddr::from_str_and_port("foo.svc.cluster.local", 5550).unwrap());
+    profile_tx.send(profile::Profile::default()).unwrap();
+
+    let mut cfg = default_config();
+    cfg.proxy.server.request_limits = http::RequestLimits {
+        max_header_bytes: Some(64),
+        max_body_bytes: Some(4),
+    };
+    let (rt, _shutdown) = runtime();
+    let server = build_server(cfg, rt, profiles, connect).new_service(Target::UNMESHED_HTTP1);
+    let (mut client, bg) = http_util::connect_and_accept(&mut client, server).await;
+
+    let req = Request::builder()
+        .method(http::Method::GET)
+        .uri("http://foo.svc.cluster.local:5550")
+        .header("x-large", "x".repeat(64))
+        .body(Body::default())
+        .unwrap();
+    let rsp = http_util::http_request(&mut client, req).await.unwrap();
+    assert_eq!(
+        rsp.status(),
+        http::StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE
+    );
+
+    let req = Request::builder()
+        .method(http::Method::POST)
+        .uri("http://foo.svc.cluster.local:5550")
+        .header(http::header::CONTENT_LENGTH, "5")
+        .body(Body::from("hello"))
+        .unwrap();
+    let rsp = http_util::http_request(&mut client, req).await.unwrap();
+    assert_eq!(rsp.status(), http::StatusCode::PAYLOAD_TOO_LARGE);
+
+    drop(client);
+    let _ = bg.await;
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn grpc_request_limits() {
+    let _trace = trace_init();
+
+    let connect = support::connect().endpoint_fn_boxed(Target::addr(), connect_error());
+    let mut client = ClientBuilder::new();
+    client.http2_only(true);
+    let profiles = profile::resolver();
+    let profile_tx =
+        profiles.profile_tx(NameAddr::from_str_and_port("foo.svc.cluster.local", 5550).unwrap());
+    profile_tx.send(profile::Profile::default()).unwrap();
+
+    let mut cfg = default_config();
+    cfg.proxy.server.request_limits.max_body_bytes = Some(4);
+    let (rt, _shutdown) = runtime();
+    let server = build_server(cfg, rt, profiles, connect).new_service(Target::meshed_h2());
+    let (mut client, bg) = http_util::connect_and_accept(&mut client, server).await;
+
+    let req = Request::builder()
+        .method(http::Method::POST)
+        .uri("http://foo.svc.cluster.local:5550")
+        .header(http::header::CONTENT_TYPE, "application/grpc")
+        .header(http::header::CONTENT_LENGTH, "5")
+        .body(Body::from("hello"))
+        .unwrap();
+    let rsp = http_util::http_request(&mut client, req).await.unwrap();
+    assert_eq!(rsp.status(), http::StatusCode::OK);
+    assert_eq!(
+        rsp.headers().get("grpc-status").unwrap(),
+        "8",
+        "expected RESOURCE_EXHAUSTED"
+    );
+
+    drop(client);
+    let _ = bg.await;
+}
+
 fn hello_server(
     http: hyper::server::conn::Http,
 ) -> impl Fn(Remote<ServerAddr>) -> io::Result<io::BoxedIo> {
@@ -615,6 +699,8 @@ impl svc::Param<policy::AllowPolicy> for Target {
                 }],
                 kind: "server".into(),
                 name: "testsrv".into(),
+                http_routes: vec![],
+                rate_limit: None,
             },
         );
         policy
@@ -24,15 +24,13 @@ pub(crate) mod test_util;
 pub use self::{metrics::Metrics, policy::DefaultPolicy};
 use linkerd_app_core::{
     config::{ConnectConfig, ProxyConfig},
-    drain,
-    http_tracing::OpenCensusSink,
-    identity, io,
+    drain, http_tracing, identity, io,
     proxy::{tap, tcp},
-    svc,
+    serve, svc,
     transport::{self, Remote, ServerAddr},
     Error, NameMatch, ProxyRuntime,
 };
-use std::{fmt::Debug, time::Duration};
+use std::{fmt::Debug, sync::Arc, time::Duration};
 use thiserror::Error;
 use tracing::debug_span;
 
@@ -46,6 +44,9 @@ pub struct Config {
     pub policy: policy::Config,
     pub profile_idle_timeout: Duration,
     pub allowed_ips: transport::AllowIps,
+
+    /// Request headers whose values are recorded in the access log.
+    pub access_log_headers: Arc<[linkerd_app_core::proxy::http::HeaderName]>,
 }
 
 #[derive(Clone)]
@@ -60,8 +61,9 @@ struct Runtime {
     metrics: Metrics,
     identity: identity::creds::Receiver,
     tap: tap::Registry,
-    span_sink: OpenCensusSink,
+    span_sink: Option<http_tracing::SpanSink>,
     drain: drain::Watch,
+    connections: serve::Connections,
 }
 
 // The inbound HTTP server handles gateway traffic; so gateway error types are defined here (so that
@@ -138,6 +140,7 @@ impl Inbound<()> {
             tap: runtime.tap,
             span_sink: runtime.span_sink,
             drain: runtime.drain,
+            connections: runtime.connections,
         };
         Self {
             config,
@@ -10,6 +10,7 @@
 
 pub(crate) mod authz;
 pub(crate) mod error;
+pub(crate) mod rate_limit;
 
 pub use linkerd_app_core::metrics::*;
 
@@ -18,6 +19,7 @@ pub use linkerd_app_core::metrics::*;
 pub struct Metrics {
     pub http_authz: authz::HttpAuthzMetrics,
     pub http_errors: error::HttpErrorMetrics,
+    pub http_rate_limit: rate_limit::HttpRateLimitMetrics,
 
     pub(crate) tcp_authz: authz::TcpAuthzMetrics,
     pub tcp_errors: error::TcpErrorMetrics,
@@ -32,6 +34,7 @@ impl Metrics {
         Self {
             http_authz: authz::HttpAuthzMetrics::default(),
             http_errors: error::HttpErrorMetrics::default(),
+            http_rate_limit: rate_limit::HttpRateLimitMetrics::default(),
             tcp_authz: authz::TcpAuthzMetrics::default(),
             tcp_errors: error::TcpErrorMetrics::default(),
             proxy,
@@ -40,9 +43,10 @@ impl Metrics {
 }
 
 impl FmtMetrics for Metrics {
-    fn fmt_metrics(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt_metrics(&self, f: &mut Encoder<'_>) -> std::fmt::Result {
         self.http_authz.fmt_metrics(f)?;
         self.http_errors.fmt_metrics(f)?;
+        self.http_rate_limit.fmt_metrics(f)?;
 
         self.tcp_authz.fmt_metrics(f)?;
         self.tcp_errors.fmt_metrics(f)?;
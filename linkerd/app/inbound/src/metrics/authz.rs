@@ -1,6 +1,9 @@
 use crate::policy::{AllowPolicy, Permit};
 use linkerd_app_core::{
-    metrics::{metrics, AuthzLabels, Counter, FmtMetrics, ServerLabel, TargetAddr, TlsAccept},
+    metrics::{
+        metrics, AuthzLabels, Counter, Encoder, FmtMetrics, HttpRouteLabel, ServerLabel,
+        TargetAddr, TlsAccept,
+    },
     tls,
 };
 use parking_lot::Mutex;
@@ -34,7 +37,7 @@ pub(crate) struct TcpAuthzMetrics(Arc<TcpInner>);
 #[derive(Debug, Default)]
 struct HttpInner {
     allow: Mutex<HashMap<AuthzKey, Counter>>,
-    deny: Mutex<HashMap<SrvKey, Counter>>,
+    deny: Mutex<HashMap<(SrvKey, Option<HttpRouteLabel>), Counter>>,
 }
 
 #[derive(Debug, Default)]
@@ -55,6 +58,7 @@ struct SrvKey {
 struct AuthzKey {
     target: TargetAddr,
     authz: AuthzLabels,
+    route: Option<HttpRouteLabel>,
     tls: tls::ConditionalServerTls,
 }
 
@@ -70,26 +74,32 @@ impl HttpAuthzMetrics {
             .incr();
     }
 
-    pub fn deny(&self, policy: &AllowPolicy, tls: tls::ConditionalServerTls) {
+    pub fn deny(
+        &self,
+        policy: &AllowPolicy,
+        route: Option<HttpRouteLabel>,
+        tls: tls::ConditionalServerTls,
+    ) {
         self.0
             .deny
             .lock()
-            .entry(SrvKey::new(policy, tls))
+            .entry((SrvKey::new(policy, tls), route))
             .or_default()
             .incr();
     }
 }
 
 impl FmtMetrics for HttpAuthzMetrics {
-    fn fmt_metrics(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt_metrics(&self, f: &mut Encoder<'_>) -> std::fmt::Result {
         let allow = self.0.allow.lock();
         if !allow.is_empty() {
             inbound_http_authz_allow_total.fmt_help(f)?;
             inbound_http_authz_allow_total.fmt_scopes(
                 f,
-                allow
-                    .iter()
-                    .map(|(k, c)| ((k.target, (&k.authz, TlsAccept(&k.tls))), c)),
+                allow.iter().map(|(k, c)| {
+                    let labels = (&k.authz, TlsAccept(&k.tls));
+                    ((k.target, (labels, k.route.as_ref())), c)
+                }),
                 |c| c,
             )?;
         }
@@ -100,8 +110,10 @@ impl FmtMetrics for HttpAuthzMetrics {
             inbound_http_authz_deny_total.fmt_help(f)?;
             inbound_http_authz_deny_total.fmt_scopes(
                 f,
-                deny.iter()
-                    .map(|(k, c)| ((k.target, (&k.server, TlsAccept(&k.tls))), c)),
+                deny.iter().map(|((k, route), c)| {
+                    let labels = (&k.server, TlsAccept(&k.tls));
+                    ((k.target, (labels, route.as_ref())), c)
+                }),
                 |c| c,
             )?;
         }
@@ -143,7 +155,7 @@ impl TcpAuthzMetrics {
 }
 
 impl FmtMetrics for TcpAuthzMetrics {
-    fn fmt_metrics(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt_metrics(&self, f: &mut Encoder<'_>) -> std::fmt::Result {
         let allow = self.0.allow.lock();
         if !allow.is_empty() {
             inbound_tcp_authz_allow_total.fmt_help(f)?;
@@ -205,6 +217,7 @@ impl AuthzKey {
         Self {
             target: TargetAddr(permit.dst.into()),
             authz: permit.labels.clone(),
+            route: permit.route.clone(),
             tls,
         }
     }
@@ -3,20 +3,27 @@ mod tcp;
 
 pub(crate) use self::{http::HttpErrorMetrics, tcp::TcpErrorMetrics};
 use crate::{
-    policy::{DeniedUnauthorized, DeniedUnknownPort},
+    policy::{DeniedUnauthorized, DeniedUnknownPort, RateLimited},
     GatewayDomainInvalid, GatewayIdentityRequired, GatewayLoop,
 };
-use linkerd_app_core::{errors::FailFastError, metrics::FmtLabels, tls};
+use linkerd_app_core::{
+    errors::FailFastError,
+    metrics::FmtLabels,
+    proxy::http::limits::{BodyTooLarge, HeadersTooLarge},
+    tls,
+};
 use std::fmt;
 
 /// Inbound proxy error types.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 enum ErrorKind {
+    BodyTooLarge,
     DeniedUnknown,
     FailFast,
     GatewayDomainInvalid,
     GatewayIdentityRequired,
     GatewayLoop,
+    HeadersTooLarge,
     Io,
     TlsDetectTimeout,
     Unexpected,
@@ -29,10 +36,17 @@ impl ErrorKind {
         if err.is::<DeniedUnauthorized>() {
             // Unauthorized metrics are tracked separately.and are not considered to be errors.
             None
+        } else if err.is::<RateLimited>() {
+            // Rate limit metrics are tracked separately and are not considered to be errors.
+            None
         } else if err.is::<DeniedUnknownPort>() {
             Some(ErrorKind::DeniedUnknown)
         } else if err.is::<FailFastError>() {
             Some(ErrorKind::FailFast)
+        } else if err.is::<HeadersTooLarge>() {
+            Some(ErrorKind::HeadersTooLarge)
+        } else if err.is::<BodyTooLarge>() {
+            Some(ErrorKind::BodyTooLarge)
         } else if err.is::<std::io::Error>() {
             Some(ErrorKind::Io)
         } else if err.is::<tls::server::ServerTlsTimeoutError>() {
@@ -57,12 +71,14 @@ impl FmtLabels for ErrorKind {
             f,
             "error=\"{}\"",
             match self {
+                ErrorKind::BodyTooLarge => "request body too large",
                 ErrorKind::DeniedUnknown => "unknown port denied",
                 ErrorKind::FailFast => "failfast",
                 ErrorKind::TlsDetectTimeout => "tls detection timeout",
                 ErrorKind::GatewayIdentityRequired => "gateway identity required",
                 ErrorKind::GatewayLoop => "gateway loop",
                 ErrorKind::GatewayDomainInvalid => "gateway domain invalid",
+                ErrorKind::HeadersTooLarge => "request headers too large",
                 ErrorKind::Io => "i/o",
                 ErrorKind::Unexpected => "unexpected",
             }
@@ -1,6 +1,6 @@
 use super::ErrorKind;
 use linkerd_app_core::{
-    metrics::{metrics, Counter, FmtMetrics},
+    metrics::{metrics, Counter, Encoder, FmtMetrics},
     svc::{self, stack::NewMonitor},
     transport::{labels::TargetAddr, OrigDstAddr},
     Error,
@@ -47,7 +47,7 @@ where
 }
 
 impl FmtMetrics for TcpErrorMetrics {
-    fn fmt_metrics(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt_metrics(&self, f: &mut Encoder<'_>) -> std::fmt::Result {
         let metrics = self.0.lock();
         if metrics.is_empty() {
             return Ok(());
@@ -0,0 +1,104 @@
+use crate::policy::RateLimitKind;
+use linkerd_app_core::{
+    metrics::{metrics, Counter, Encoder, FmtLabels, FmtMetrics, ServerLabel, TargetAddr},
+    transport::OrigDstAddr,
+};
+use parking_lot::Mutex;
+use std::{collections::HashMap, fmt, sync::Arc};
+
+metrics! {
+    inbound_http_ratelimit_allow_total: Counter {
+        "The total number of inbound HTTP requests that were admitted by a server's rate limit"
+    },
+    inbound_http_ratelimit_deny_total: Counter {
+        "The total number of inbound HTTP requests that were rejected by a server's rate limit"
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct HttpRateLimitMetrics(Arc<Inner>);
+
+#[derive(Debug, Default)]
+struct Inner {
+    allow: Mutex<HashMap<SrvKey, Counter>>,
+    deny: Mutex<HashMap<(SrvKey, RateLimitKind), Counter>>,
+}
+
+#[derive(Debug, Hash, PartialEq, Eq)]
+struct SrvKey {
+    target: TargetAddr,
+    server: ServerLabel,
+}
+
+// === impl HttpRateLimitMetrics ===
+
+impl HttpRateLimitMetrics {
+    pub(crate) fn allow(&self, dst: OrigDstAddr, server: &ServerLabel) {
+        self.0
+            .allow
+            .lock()
+            .entry(SrvKey::new(dst, server))
+            .or_default()
+            .incr();
+    }
+
+    pub(crate) fn deny(&self, dst: OrigDstAddr, server: &ServerLabel, kind: RateLimitKind) {
+        self.0
+            .deny
+            .lock()
+            .entry((SrvKey::new(dst, server), kind))
+            .or_default()
+            .incr();
+    }
+}
+
+impl FmtMetrics for HttpRateLimitMetrics {
+    fn fmt_metrics(&self, f: &mut Encoder<'_>) -> fmt::Result {
+        let allow = self.0.allow.lock();
+        if !allow.is_empty() {
+            inbound_http_ratelimit_allow_total.fmt_help(f)?;
+            inbound_http_ratelimit_allow_total.fmt_scopes(
+                f,
+                allow.iter().map(|(k, c)| ((k.target, &k.server), c)),
+                |c| c,
+            )?;
+        }
+        drop(allow);
+
+        let deny = self.0.deny.lock();
+        if !deny.is_empty() {
+            inbound_http_ratelimit_deny_total.fmt_help(f)?;
+            inbound_http_ratelimit_deny_total.fmt_scopes(
+                f,
+                deny.iter()
+                    .map(|((k, kind), c)| ((k.target, (&k.server, *kind)), c)),
+                |c| c,
+            )?;
+        }
+        drop(deny);
+
+        Ok(())
+    }
+}
+
+// === impl SrvKey ===
+
+impl SrvKey {
+    fn new(dst: OrigDstAddr, server: &ServerLabel) -> Self {
+        Self {
+            target: TargetAddr(dst.into()),
+            server: server.clone(),
+        }
+    }
+}
+
+// === impl RateLimitKind ===
+
+impl FmtLabels for RateLimitKind {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Total => write!(f, "ratelimit=\"total\""),
+            Self::Identity => write!(f, "ratelimit=\"identity\""),
+        }
+    }
+}
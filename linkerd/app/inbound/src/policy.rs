@@ -2,21 +2,29 @@ mod authorize;
 mod config;
 pub mod defaults;
 mod discover;
+mod inspect;
+mod jwt;
+mod rate_limit;
 mod store;
 #[cfg(test)]
 mod tests;
 
 pub use self::authorize::{NewAuthorizeHttp, NewAuthorizeTcp};
-pub use self::config::Config;
-pub(crate) use self::store::Store;
+pub use self::config::{Config, LocalPolicy};
+use self::jwt::{Bearer, JwksStore};
+pub use self::rate_limit::NewRateLimitHttp;
+pub use self::store::Store;
 
-pub use linkerd_app_core::metrics::{AuthzLabels, ServerLabel};
+pub use linkerd_app_core::metrics::{AuthzLabels, HttpRouteLabel, ServerLabel};
 use linkerd_app_core::{
     tls,
     transport::{ClientAddr, OrigDstAddr, Remote},
     Result,
 };
-pub use linkerd_server_policy::{Authentication, Authorization, Protocol, ServerPolicy, Suffix};
+pub use linkerd_server_policy::{
+    Authentication, Authorization, HeaderMatch, HeaderValueMatch, HttpRoute, HttpRouteMatch, Limit,
+    PathMatch, Protocol, RateLimit, ServerPolicy, Suffix,
+};
 use thiserror::Error;
 use tokio::sync::watch;
 
@@ -24,10 +32,24 @@ use tokio::sync::watch;
 #[error("unauthorized connection on unknown port {0}")]
 pub struct DeniedUnknownPort(pub u16);
 
-#[derive(Clone, Debug, Error)]
-#[error("unauthorized connection on server {server}")]
+#[derive(Clone, Debug)]
 pub struct DeniedUnauthorized {
     server: std::sync::Arc<str>,
+    route: Option<HttpRouteLabel>,
+}
+
+#[derive(Clone, Debug, Error)]
+#[error("too many requests on server {server}")]
+pub struct RateLimited {
+    server: std::sync::Arc<str>,
+    kind: RateLimitKind,
+}
+
+/// Describes which of a server's rate limits was exceeded.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum RateLimitKind {
+    Total,
+    Identity,
 }
 
 pub trait CheckPolicy {
@@ -45,6 +67,7 @@ pub enum DefaultPolicy {
 pub struct AllowPolicy {
     dst: OrigDstAddr,
     server: watch::Receiver<ServerPolicy>,
+    jwks: JwksStore,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -52,6 +75,9 @@ pub struct Permit {
     pub dst: OrigDstAddr,
     pub protocol: Protocol,
 
+    /// The HTTP route that authorized the request, if any.
+    pub route: Option<HttpRouteLabel>,
+
     pub labels: AuthzLabels,
 }
 
@@ -72,6 +98,8 @@ impl From<DefaultPolicy> for ServerPolicy {
                 authorizations: vec![],
                 kind: "default".into(),
                 name: "deny".into(),
+                http_routes: vec![],
+                rate_limit: None,
             },
         }
     }
@@ -86,7 +114,11 @@ impl AllowPolicy {
         server: ServerPolicy,
     ) -> (Self, watch::Sender<ServerPolicy>) {
         let (tx, server) = watch::channel(server);
-        let p = Self { dst, server };
+        let p = Self {
+            dst,
+            server,
+            jwks: JwksStore::default(),
+        };
         (p, tx)
     }
 
@@ -95,6 +127,11 @@ impl AllowPolicy {
         self.server.borrow().protocol
     }
 
+    #[inline]
+    pub(crate) fn rate_limit(&self) -> Option<RateLimit> {
+        self.server.borrow().rate_limit
+    }
+
     #[inline]
     pub fn dst_addr(&self) -> OrigDstAddr {
         self.dst
@@ -124,55 +161,147 @@ impl AllowPolicy {
         tls: &tls::ConditionalServerTls,
     ) -> Result<Permit, DeniedUnauthorized> {
         let server = self.server.borrow();
-        for authz in server.authorizations.iter() {
-            if authz.networks.iter().any(|n| n.contains(&client_addr.ip())) {
-                match authz.authentication {
-                    Authentication::Unauthenticated => {
-                        return Ok(Permit::new(self.dst, &*server, authz));
-                    }
+        match authorize(&server.authorizations, client_addr, tls, None) {
+            Some((authz, _)) => Ok(Permit::new(self.dst, &*server, authz, None)),
+            None => Err(DeniedUnauthorized {
+                server: server.name.clone(),
+                route: None,
+            }),
+        }
+    }
+
+    /// Checks whether the destination port's `AllowPolicy` is authorized to accept the given
+    /// HTTP request.
+    ///
+    /// If the request matches one of the server's HTTP routes, the route's authorizations are
+    /// used instead of the server's. If the request was authorized by a bearer token, the token's
+    /// claims are returned with the permit.
+    pub(crate) fn check_authorized_http<B>(
+        &self,
+        client_addr: Remote<ClientAddr>,
+        tls: &tls::ConditionalServerTls,
+        req: &http::Request<B>,
+    ) -> Result<(Permit, Option<linkerd_jwt::Claims>), DeniedUnauthorized> {
+        let server = self.server.borrow();
+        let bearer = linkerd_jwt::bearer_token(req.headers()).map(|token| Bearer {
+            token,
+            jwks: &self.jwks,
+        });
+
+        let route = server.http_routes.iter().find(|r| r.matches(req));
+        let (authorizations, label) = match route {
+            Some(route) => {
+                let label = HttpRouteLabel {
+                    kind: route.kind.clone(),
+                    name: route.name.clone(),
+                };
+                (&route.authorizations, Some(label))
+            }
+            None => (&server.authorizations, None),
+        };
+
+        match authorize(authorizations, client_addr, tls, bearer) {
+            Some((authz, claims)) => Ok((Permit::new(self.dst, &*server, authz, label), claims)),
+            None => Err(DeniedUnauthorized {
+                server: server.name.clone(),
+                route: label,
+            }),
+        }
+    }
+}
+
+/// Returns the first authorization that permits the client, with the claims of the client's
+/// bearer token if the authorization requires one.
+fn authorize<'a>(
+    authorizations: &'a [Authorization],
+    client_addr: Remote<ClientAddr>,
+    tls: &tls::ConditionalServerTls,
+    bearer: Option<Bearer<'_>>,
+) -> Option<(&'a Authorization, Option<linkerd_jwt::Claims>)> {
+    for authz in authorizations {
+        if !authz.networks.iter().any(|n| n.contains(&client_addr.ip())) {
+            continue;
+        }
+
+        match authz.authentication {
+            Authentication::Unauthenticated => return Some((authz, None)),
+
+            Authentication::TlsUnauthenticated => {
+                if let tls::ConditionalServerTls::Some(tls::ServerTls::Established { .. }) = tls {
+                    return Some((authz, None));
+                }
+            }
 
-                    Authentication::TlsUnauthenticated => {
-                        if let tls::ConditionalServerTls::Some(tls::ServerTls::Established {
-                            ..
-                        }) = tls
-                        {
-                            return Ok(Permit::new(self.dst, &*server, authz));
-                        }
+            Authentication::TlsAuthenticated {
+                ref identities,
+                ref suffixes,
+            } => {
+                if let tls::ConditionalServerTls::Some(tls::ServerTls::Established {
+                    client_id: Some(tls::server::ClientId(ref id)),
+                    ..
+                }) = tls
+                {
+                    if identities.contains(id.as_str())
+                        || suffixes.iter().any(|s| s.contains(id.as_str()))
+                    {
+                        return Some((authz, None));
                     }
+                }
+            }
 
-                    Authentication::TlsAuthenticated {
-                        ref identities,
-                        ref suffixes,
-                    } => {
-                        if let tls::ConditionalServerTls::Some(tls::ServerTls::Established {
-                            client_id: Some(tls::server::ClientId(ref id)),
-                            ..
-                        }) = tls
-                        {
-                            if identities.contains(id.as_str())
-                                || suffixes.iter().any(|s| s.contains(id.as_str()))
-                            {
-                                return Ok(Permit::new(self.dst, &*server, authz));
-                            }
-                        }
+            Authentication::Jwt {
+                ref jwks_path,
+                ref issuer,
+                ref audiences,
+            } => {
+                if let Some(bearer) = bearer {
+                    if let Some(claims) = bearer.validate(jwks_path, issuer.as_deref(), audiences) {
+                        return Some((authz, Some(claims)));
                     }
                 }
             }
         }
+    }
+
+    None
+}
+
+// === impl DeniedUnauthorized ===
 
-        Err(DeniedUnauthorized {
-            server: server.name.clone(),
-        })
+impl DeniedUnauthorized {
+    pub(crate) fn route(&self) -> Option<&HttpRouteLabel> {
+        self.route.as_ref()
     }
 }
 
+impl std::fmt::Display for DeniedUnauthorized {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.route {
+            Some(ref route) => write!(
+                f,
+                "unauthorized request on route {} of server {}",
+                route.name, self.server
+            ),
+            None => write!(f, "unauthorized connection on server {}", self.server),
+        }
+    }
+}
+
+impl std::error::Error for DeniedUnauthorized {}
+
 // === impl Permit ===
 
 impl Permit {
-    fn new(dst: OrigDstAddr, server: &ServerPolicy, authz: &Authorization) -> Self {
+    fn new(
+        dst: OrigDstAddr,
+        server: &ServerPolicy,
+        authz: &Authorization,
+        route: Option<HttpRouteLabel>,
+    ) -> Self {
         Self {
             dst,
             protocol: server.protocol,
+            route,
             labels: AuthzLabels {
                 kind: authz.kind.clone(),
                 name: authz.name.clone(),
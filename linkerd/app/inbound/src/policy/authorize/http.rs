@@ -1,14 +1,16 @@
 use crate::metrics::authz::HttpAuthzMetrics;
 
 use super::super::{AllowPolicy, Permit};
-use futures::{future, TryFutureExt};
+use futures::{future, ready, TryFuture};
 use linkerd_app_core::{
     svc::{self, ServiceExt},
     tls,
     transport::{ClientAddr, Remote},
     Error,
 };
-use std::task;
+use linkerd_http_access_log::RouteLabels;
+use pin_project::pin_project;
+use std::{future::Future, pin::Pin, task};
 
 /// A middleware that enforces policy on each HTTP request.
 ///
@@ -33,6 +35,17 @@ pub struct AuthorizeHttp<T, N> {
     inner: N,
 }
 
+/// Adds the request's route and the claims of its bearer token, if it was authorized by one, to
+/// the response's extensions so that they may be recorded in the access log.
+#[pin_project]
+#[derive(Debug)]
+pub struct ResponseFuture<F> {
+    #[pin]
+    inner: F,
+    route: Option<RouteLabels>,
+    claims: Option<linkerd_jwt::Claims>,
+}
+
 // === impl NewAuthorizeHttp ===
 
 impl<N> NewAuthorizeHttp<N> {
@@ -70,17 +83,17 @@ where
 
 // === impl AuthorizeHttp ===
 
-impl<Req, T, N, S> svc::Service<Req> for AuthorizeHttp<T, N>
+impl<B, RspB, T, N, S> svc::Service<http::Request<B>> for AuthorizeHttp<T, N>
 where
     T: Clone,
     N: svc::NewService<(Permit, T), Service = S>,
-    S: svc::Service<Req>,
+    S: svc::Service<http::Request<B>, Response = http::Response<RspB>>,
     S::Error: Into<Error>,
 {
     type Response = S::Response;
     type Error = Error;
     type Future = future::Either<
-        future::ErrInto<svc::stack::Oneshot<S, Req>, Error>,
+        ResponseFuture<svc::stack::Oneshot<S, http::Request<B>>>,
         future::Ready<Result<Self::Response, Error>>,
     >;
 
@@ -89,30 +102,66 @@ where
         task::Poll::Ready(Ok(()))
     }
 
-    fn call(&mut self, req: Req) -> Self::Future {
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
         tracing::trace!(policy = ?self.policy, "Authorizing request");
-        match self.policy.check_authorized(self.client_addr, &self.tls) {
-            Ok(permit) => {
+        match self
+            .policy
+            .check_authorized_http(self.client_addr, &self.tls, &req)
+        {
+            Ok((permit, claims)) => {
                 tracing::debug!(
                     ?permit,
+                    jwt.sub = ?claims.as_ref().and_then(|c| c.sub.as_deref()),
                     tls = ?self.tls,
                     client = %self.client_addr,
                     "Request authorized",
                 );
                 self.metrics.allow(&permit, self.tls.clone());
+                let route = permit.route.as_ref().map(|r| RouteLabels {
+                    kind: r.kind.clone(),
+                    name: r.name.clone(),
+                });
                 let svc = self.inner.new_service((permit, self.target.clone()));
-                future::Either::Left(svc.oneshot(req).err_into::<Error>())
+                future::Either::Left(ResponseFuture {
+                    inner: svc.oneshot(req),
+                    route,
+                    claims,
+                })
             }
             Err(e) => {
                 tracing::info!(
                     server = %format_args!("{}:{}", self.policy.server_label().kind, self.policy.server_label().name),
+                    route = ?e.route(),
                     tls = ?self.tls,
                     client = %self.client_addr,
                     "Request denied",
                 );
-                self.metrics.deny(&self.policy, self.tls.clone());
+                self.metrics
+                    .deny(&self.policy, e.route().cloned(), self.tls.clone());
                 future::Either::Right(future::err(e.into()))
             }
         }
     }
 }
+
+// === impl ResponseFuture ===
+
+impl<F, B> Future for ResponseFuture<F>
+where
+    F: TryFuture<Ok = http::Response<B>>,
+    F::Error: Into<Error>,
+{
+    type Output = Result<http::Response<B>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> task::Poll<Self::Output> {
+        let this = self.project();
+        let mut rsp = ready!(this.inner.try_poll(cx)).map_err(Into::into)?;
+        if let Some(route) = this.route.take() {
+            rsp.extensions_mut().insert(route);
+        }
+        if let Some(claims) = this.claims.take() {
+            rsp.extensions_mut().insert(claims);
+        }
+        task::Poll::Ready(Ok(rsp))
+    }
+}
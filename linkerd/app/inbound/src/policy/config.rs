@@ -1,4 +1,6 @@
-use super::{discover::Discover, DefaultPolicy, ServerPolicy, Store};
+use super::{
+    discover::Discover, Authorization, DefaultPolicy, HttpRoute, RateLimit, ServerPolicy, Store,
+};
 use linkerd_app_core::{control, dns, identity, metrics, svc::NewService};
 use std::collections::{HashMap, HashSet};
 
@@ -14,13 +16,30 @@ pub enum Config {
         workload: String,
         default: DefaultPolicy,
         ports: HashSet<u16>,
+        local: LocalPolicy,
     },
     Fixed {
         default: DefaultPolicy,
         ports: HashMap<u16, ServerPolicy>,
+        local: LocalPolicy,
     },
 }
 
+/// Configures server policy settings that the policy API does not describe.
+///
+/// These settings apply to every server, whether its policy is discovered or
+/// fixed. Settings that a policy already configures are not overridden.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LocalPolicy {
+    pub rate_limit: Option<RateLimit>,
+
+    /// Routes that are added to every server's routes.
+    pub http_routes: Vec<HttpRoute>,
+
+    /// Authorizations that are added to the servers on specific ports.
+    pub port_authorizations: HashMap<u16, Vec<Authorization>>,
+}
+
 // === impl Config ===
 
 impl Config {
@@ -31,28 +50,94 @@ impl Config {
         identity: identity::NewClient,
     ) -> Store {
         match self {
-            Self::Fixed { default, ports } => {
-                let (store, tx) = Store::fixed(default, ports);
-                if let Some(tx) = tx {
-                    tokio::spawn(async move {
-                        tx.closed().await;
-                    });
-                }
-                store
+            Self::Fixed {
+                default,
+                ports,
+                local,
+            } => {
+                let ports = local.apply_ports(&default, ports);
+                Store::fixed(local.apply_default(default), ports)
             }
             Self::Discover {
                 control,
-                ports,
+                mut ports,
                 workload,
                 default,
+                local,
             } => {
+                // Ports with local authorizations must be discovered so that
+                // the authorizations are added to their servers.
+                ports.extend(local.port_authorizations.keys());
+                let default = local.apply_default(default);
                 let watch = {
                     let backoff = control.connect.backoff;
                     let c = control.build(dns, metrics, identity).new_service(());
-                    Discover::new(workload, c).into_watch(backoff)
+                    Discover::new(workload, local, c).into_watch(backoff)
                 };
                 Store::spawn_discover(default, ports, watch)
             }
         }
     }
+
+    /// Replaces the store's default policy and, if port policies are fixed,
+    /// its port policies.
+    ///
+    /// The local settings of discovered policies are not changed.
+    pub fn reload(self, store: &Store) {
+        match self {
+            Self::Fixed {
+                default,
+                ports,
+                local,
+            } => {
+                let ports = local.apply_ports(&default, ports);
+                store.reload_fixed(local.apply_default(default), ports)
+            }
+            Self::Discover { default, local, .. } => {
+                store.reload_default(local.apply_default(default))
+            }
+        }
+    }
+}
+
+// === impl LocalPolicy ===
+
+impl LocalPolicy {
+    /// Applies the local settings to the policy of the server on `port`, or
+    /// to the default policy if `port` is `None`.
+    pub(super) fn apply(&self, port: Option<u16>, mut policy: ServerPolicy) -> ServerPolicy {
+        if policy.rate_limit.is_none() {
+            policy.rate_limit = self.rate_limit;
+        }
+        policy.http_routes.extend(self.http_routes.iter().cloned());
+        if let Some(authzs) = port.and_then(|p| self.port_authorizations.get(&p)) {
+            policy.authorizations.extend(authzs.iter().cloned());
+        }
+        policy
+    }
+
+    fn apply_default(&self, default: DefaultPolicy) -> DefaultPolicy {
+        match default {
+            DefaultPolicy::Allow(policy) => DefaultPolicy::Allow(self.apply(None, policy)),
+            DefaultPolicy::Deny => DefaultPolicy::Deny,
+        }
+    }
+
+    /// Applies the local settings to fixed port policies. Ports that have
+    /// local authorizations but no policy start from the default policy.
+    fn apply_ports(
+        &self,
+        default: &DefaultPolicy,
+        mut ports: HashMap<u16, ServerPolicy>,
+    ) -> HashMap<u16, ServerPolicy> {
+        if let DefaultPolicy::Allow(policy) = default {
+            for port in self.port_authorizations.keys() {
+                ports.entry(*port).or_insert_with(|| policy.clone());
+            }
+        }
+        ports
+            .into_iter()
+            .map(|(port, policy)| (port, self.apply(Some(port), policy)))
+            .collect()
+    }
 }
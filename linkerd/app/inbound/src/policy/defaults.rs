@@ -1,6 +1,6 @@
 use linkerd_app_core::{IpNet, Ipv4Net, Ipv6Net};
 use linkerd_server_policy::{Authentication, Authorization, Protocol, ServerPolicy, Suffix};
-use std::time::Duration;
+use std::{path::PathBuf, time::Duration};
 
 pub fn all_authenticated(timeout: Duration) -> ServerPolicy {
     mk("all-authenticated", all_nets(), authenticated(), timeout)
@@ -43,6 +43,25 @@ pub fn all_mtls_unauthenticated(timeout: Duration) -> ServerPolicy {
     )
 }
 
+/// Authorizes HTTP requests from all networks that carry a bearer token
+/// signed by a key in the JWKS file at `jwks_path`.
+pub fn jwt_authorization(
+    jwks_path: PathBuf,
+    issuer: Option<String>,
+    audiences: Vec<String>,
+) -> Authorization {
+    Authorization {
+        networks: all_nets().map(Into::into).collect(),
+        authentication: Authentication::Jwt {
+            jwks_path,
+            issuer,
+            audiences,
+        },
+        kind: "default".into(),
+        name: "jwt".into(),
+    }
+}
+
 fn all_nets() -> impl Iterator<Item = IpNet> {
     vec![Ipv4Net::default().into(), Ipv6Net::default().into()].into_iter()
 }
@@ -70,5 +89,7 @@ fn mk(
         }],
         kind: "default".into(),
         name: name.into(),
+        http_routes: vec![],
+        rate_limit: None,
     }
 }
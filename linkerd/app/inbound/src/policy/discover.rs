@@ -1,3 +1,4 @@
+use super::LocalPolicy;
 use futures::prelude::*;
 use linkerd2_proxy_api::inbound::{
     self as api, inbound_server_policies_client::InboundServerPoliciesClient as ApiClient,
@@ -17,6 +18,7 @@ use std::{net::IpAddr, sync::Arc};
 #[derive(Clone, Debug)]
 pub(super) struct Discover<S> {
     workload: String,
+    local: Arc<LocalPolicy>,
     client: ApiClient<S>,
 }
 
@@ -31,9 +33,10 @@ where
     S::ResponseBody:
         http::HttpBody<Data = tonic::codegen::Bytes, Error = Error> + Default + Send + 'static,
 {
-    pub(super) fn new(workload: String, client: S) -> Self {
+    pub(super) fn new(workload: String, local: LocalPolicy, client: S) -> Self {
         Self {
             workload,
+            local: Arc::new(local),
             client: ApiClient::new(client),
         }
     }
@@ -69,12 +72,14 @@ where
             workload: self.workload.clone(),
         };
         let mut client = self.client.clone();
+        let local = self.local.clone();
         Box::pin(async move {
             let rsp = client.watch_port(tonic::Request::new(req)).await?;
             Ok(rsp.map(|updates| {
                 updates
-                    .map(|up| match to_policy(up?) {
+                    .map(move |up| match to_policy(up?) {
                         Ok(policy) => {
+                            let policy = local.apply(Some(port), policy);
                             tracing::debug!(?policy);
                             Ok(policy)
                         }
@@ -197,6 +202,10 @@ fn to_policy(proto: api::Server) -> Result<ServerPolicy> {
         authorizations,
         kind,
         name,
+        http_routes: vec![],
+        // The policy API does not describe rate limits, so they are set by
+        // the proxy's local policy.
+        rate_limit: None,
     })
 }
 
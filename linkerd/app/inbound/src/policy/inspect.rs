@@ -0,0 +1,133 @@
+//! Describes the inbound proxy's effective server policies so that they may be
+//! inspected via the admin server.
+
+use super::{Authentication, Authorization, HttpRoute, Limit, Protocol, ServerPolicy, Store};
+use linkerd_app_core::metrics;
+use serde_json::{json, Value};
+
+// === impl Store ===
+
+impl Store {
+    /// Describes the default policy and the policy of each known port, as
+    /// JSON.
+    ///
+    /// Each port's `updated_at` is the time, in milliseconds since the Unix
+    /// epoch, at which the policy controller last updated it, or `null` if the
+    /// policy has not been updated since the proxy started.
+    pub fn to_json(&self) -> Value {
+        let ports = self
+            .port_policies()
+            .into_iter()
+            .map(|(port, policy, updated)| {
+                json!({
+                    "port": port,
+                    "updated_at": updated.map(|t| metrics::unix_time(t).as_millis() as u64),
+                    "policy": policy_to_json(&policy),
+                })
+            })
+            .collect::<Vec<_>>();
+        json!({
+            "default": match self.default_policy() {
+                Some(policy) => policy_to_json(&policy),
+                None => Value::from("deny"),
+            },
+            "ports": ports,
+        })
+    }
+}
+
+fn policy_to_json(policy: &ServerPolicy) -> Value {
+    json!({
+        "kind": &*policy.kind,
+        "name": &*policy.name,
+        "protocol": protocol_to_json(policy.protocol),
+        "authorizations": authzs_to_json(&policy.authorizations),
+        "http_routes": policy.http_routes.iter().map(route_to_json).collect::<Vec<_>>(),
+        "rate_limit": policy.rate_limit.map(|rl| json!({
+            "total": rl.total.map(limit_to_json),
+            "identity": rl.identity.map(limit_to_json),
+        })),
+    })
+}
+
+fn protocol_to_json(protocol: Protocol) -> Value {
+    match protocol {
+        Protocol::Detect { timeout } => json!({
+            "detect": { "timeout_ms": timeout.as_millis() as u64 },
+        }),
+        Protocol::Http1 => "http/1".into(),
+        Protocol::Http2 => "http/2".into(),
+        Protocol::Grpc => "grpc".into(),
+        Protocol::Opaque => "opaque".into(),
+        Protocol::Tls => "tls".into(),
+    }
+}
+
+fn limit_to_json(limit: Limit) -> Value {
+    json!({
+        "requests_per_second": limit.requests_per_second,
+        "burst": limit.burst,
+    })
+}
+
+fn route_to_json(route: &HttpRoute) -> Value {
+    json!({
+        "kind": &*route.kind,
+        "name": &*route.name,
+        "matches": route.matches.iter().map(|m| format!("{:?}", m)).collect::<Vec<_>>(),
+        "authorizations": authzs_to_json(&route.authorizations),
+    })
+}
+
+fn authzs_to_json(authzs: &[Authorization]) -> Vec<Value> {
+    authzs
+        .iter()
+        .map(|authz| {
+            let networks = authz
+                .networks
+                .iter()
+                .map(|n| {
+                    json!({
+                        "net": n.net.to_string(),
+                        "except": n.except.iter().map(ToString::to_string).collect::<Vec<_>>(),
+                    })
+                })
+                .collect::<Vec<_>>();
+            json!({
+                "kind": &*authz.kind,
+                "name": &*authz.name,
+                "networks": networks,
+                "authentication": authn_to_json(&authz.authentication),
+            })
+        })
+        .collect()
+}
+
+fn authn_to_json(authn: &Authentication) -> Value {
+    match authn {
+        Authentication::Unauthenticated => json!({ "kind": "unauthenticated" }),
+        Authentication::TlsUnauthenticated => json!({ "kind": "tls-unauthenticated" }),
+        Authentication::TlsAuthenticated {
+            identities,
+            suffixes,
+        } => {
+            let mut identities = identities.iter().cloned().collect::<Vec<_>>();
+            identities.sort();
+            json!({
+                "kind": "tls-authenticated",
+                "identities": identities,
+                "suffixes": suffixes.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            })
+        }
+        Authentication::Jwt {
+            jwks_path,
+            issuer,
+            audiences,
+        } => json!({
+            "kind": "jwt",
+            "jwks_path": jwks_path.display().to_string(),
+            "issuer": issuer,
+            "audiences": audiences,
+        }),
+    }
+}
@@ -0,0 +1,70 @@
+use linkerd_jwt::{Claims, Jwks, Validation};
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use tokio::sync::watch;
+
+/// How often JWKS files are checked for changes.
+const JWKS_RELOAD_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Holds the key sets referenced by JWT authorizations.
+///
+/// Each JWKS file is loaded in the background when it is first referenced and
+/// is then watched for changes. Tokens are not validated until the file has
+/// been read.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct JwksStore(Arc<Mutex<HashMap<PathBuf, watch::Receiver<Option<Arc<Jwks>>>>>>);
+
+/// A bearer token presented on an HTTP request.
+#[derive(Copy, Clone, Debug)]
+pub(super) struct Bearer<'a> {
+    pub(super) token: &'a str,
+    pub(super) jwks: &'a JwksStore,
+}
+
+// === impl JwksStore ===
+
+impl JwksStore {
+    fn get(&self, path: &Path) -> Option<Arc<Jwks>> {
+        let mut files = self.0.lock();
+        let rx = files
+            .entry(path.to_path_buf())
+            .or_insert_with(|| linkerd_jwt::watch_file(path.to_path_buf(), JWKS_RELOAD_INTERVAL));
+        let jwks = rx.borrow().clone();
+        jwks
+    }
+}
+
+// === impl Bearer ===
+
+impl Bearer<'_> {
+    /// Validates the token against the key set at `jwks_path`, returning its
+    /// claims if it is valid.
+    pub(super) fn validate(
+        &self,
+        jwks_path: &Path,
+        issuer: Option<&str>,
+        audiences: &[String],
+    ) -> Option<Claims> {
+        let jwks = match self.jwks.get(jwks_path) {
+            Some(jwks) => jwks,
+            None => {
+                tracing::debug!(path = %jwks_path.display(), "JWKS not loaded");
+                return None;
+            }
+        };
+
+        let validation = Validation { issuer, audiences };
+        match jwks.validate(self.token, validation, SystemTime::now()) {
+            Ok(claims) => Some(claims),
+            Err(error) => {
+                tracing::debug!(%error, path = %jwks_path.display(), "Invalid bearer token");
+                None
+            }
+        }
+    }
+}
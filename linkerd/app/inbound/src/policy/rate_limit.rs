@@ -0,0 +1,418 @@
+use super::{AllowPolicy, Permit, RateLimitKind, RateLimited, ServerLabel};
+use crate::metrics::rate_limit::HttpRateLimitMetrics;
+use futures::{future, TryFutureExt};
+use linkerd_app_core::{svc, tls, transport::OrigDstAddr, Error};
+use linkerd_server_policy::{Limit, RateLimit};
+use parking_lot::Mutex;
+use std::{collections::HashMap, sync::Arc, task};
+use tokio::time::Instant;
+
+/// Bounds the number of per-identity buckets retained for each server. Buckets
+/// that have not been used recently are discarded first.
+const MAX_IDENTITY_BUCKETS: usize = 1_000;
+
+/// A middleware that enforces a server's rate limit on each HTTP request.
+///
+/// Token buckets are shared by all connections to a server. The server's rate
+/// limit is read from its policy on each request so that policy updates are
+/// honored; the server's buckets are reset when its rate limit changes.
+#[derive(Clone, Debug)]
+pub struct NewRateLimitHttp<N> {
+    limiters: Limiters,
+    metrics: HttpRateLimitMetrics,
+    inner: N,
+}
+
+#[derive(Clone, Debug)]
+pub struct RateLimitHttp<S> {
+    inner: S,
+    limit: Option<Check>,
+}
+
+#[derive(Clone, Debug)]
+struct Check {
+    config: RateLimit,
+    dst: OrigDstAddr,
+    server: ServerLabel,
+    client_id: Option<tls::server::ClientId>,
+    limiters: Limiters,
+    metrics: HttpRateLimitMetrics,
+}
+
+/// Holds the token buckets for each server.
+#[derive(Clone, Debug, Default)]
+struct Limiters(Arc<Mutex<HashMap<ServerLabel, Limiter>>>);
+
+#[derive(Debug)]
+struct Limiter {
+    config: RateLimit,
+    total: Option<Bucket>,
+    identities: IdentityBuckets,
+}
+
+/// Holds at most `MAX_IDENTITY_BUCKETS` buckets.
+///
+/// Buckets are moved into `recent` as they are used. Once `recent` holds half
+/// of the buckets, it replaces `stale`, discarding the buckets that were not
+/// used since the last replacement.
+#[derive(Debug, Default)]
+struct IdentityBuckets {
+    recent: HashMap<Option<tls::server::ClientId>, Bucket>,
+    stale: HashMap<Option<tls::server::ClientId>, Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+// === impl NewRateLimitHttp ===
+
+impl<N> NewRateLimitHttp<N> {
+    pub fn layer(
+        metrics: HttpRateLimitMetrics,
+    ) -> impl svc::layer::Layer<N, Service = Self> + Clone {
+        let limiters = Limiters::default();
+        svc::layer::mk(move |inner| Self {
+            limiters: limiters.clone(),
+            metrics: metrics.clone(),
+            inner,
+        })
+    }
+}
+
+impl<T, N> svc::NewService<(Permit, T)> for NewRateLimitHttp<N>
+where
+    T: svc::Param<AllowPolicy> + svc::Param<tls::ConditionalServerTls>,
+    N: svc::NewService<(Permit, T)>,
+{
+    type Service = RateLimitHttp<N::Service>;
+
+    fn new_service(&self, (permit, target): (Permit, T)) -> Self::Service {
+        let policy: AllowPolicy = target.param();
+        let limit = policy.rate_limit().map(|config| {
+            let client_id = match target.param() {
+                tls::ConditionalServerTls::Some(tls::ServerTls::Established {
+                    client_id, ..
+                }) => client_id,
+                _ => None,
+            };
+            Check {
+                config,
+                dst: permit.dst,
+                server: permit.labels.server.clone(),
+                client_id,
+                limiters: self.limiters.clone(),
+                metrics: self.metrics.clone(),
+            }
+        });
+
+        RateLimitHttp {
+            inner: self.inner.new_service((permit, target)),
+            limit,
+        }
+    }
+}
+
+// === impl RateLimitHttp ===
+
+impl<Req, S> svc::Service<Req> for RateLimitHttp<S>
+where
+    S: svc::Service<Req>,
+    S::Error: Into<Error>,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = future::Either<
+        future::ErrInto<S::Future, Error>,
+        future::Ready<Result<Self::Response, Error>>,
+    >;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut task::Context<'_>) -> task::Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        if let Some(check) = self.limit.as_ref() {
+            if let Err(kind) = check.acquire() {
+                tracing::info!(
+                    server = %format_args!("{}:{}", check.server.kind, check.server.name),
+                    client.id = ?check.client_id,
+                    limit = ?kind,
+                    "Request rate limited",
+                );
+                return future::Either::Right(future::err(
+                    RateLimited {
+                        server: check.server.name.clone(),
+                        kind,
+                    }
+                    .into(),
+                ));
+            }
+        }
+
+        future::Either::Left(self.inner.call(req).err_into::<Error>())
+    }
+}
+
+// === impl Check ===
+
+impl Check {
+    fn acquire(&self) -> Result<(), RateLimitKind> {
+        let res = self.limiters.acquire(
+            &self.server,
+            self.config,
+            self.client_id.as_ref(),
+            Instant::now(),
+        );
+        match res {
+            Ok(()) => self.metrics.allow(self.dst, &self.server),
+            Err(kind) => self.metrics.deny(self.dst, &self.server, kind),
+        }
+        res
+    }
+}
+
+// === impl Limiters ===
+
+impl Limiters {
+    fn acquire(
+        &self,
+        server: &ServerLabel,
+        config: RateLimit,
+        client_id: Option<&tls::server::ClientId>,
+        now: Instant,
+    ) -> Result<(), RateLimitKind> {
+        let mut limiters = self.0.lock();
+        let limiter = limiters
+            .entry(server.clone())
+            .or_insert_with(|| Limiter::new(config, now));
+        if limiter.config != config {
+            tracing::debug!(?config, "Rate limit changed");
+            *limiter = Limiter::new(config, now);
+        }
+        limiter.acquire(client_id, now)
+    }
+}
+
+// === impl Limiter ===
+
+impl Limiter {
+    fn new(config: RateLimit, now: Instant) -> Self {
+        Self {
+            config,
+            total: config.total.map(|limit| Bucket::new(limit, now)),
+            identities: IdentityBuckets::default(),
+        }
+    }
+
+    /// Takes a token from each of the configured buckets, or fails without
+    /// taking any tokens if any bucket is empty.
+    fn acquire(
+        &mut self,
+        client_id: Option<&tls::server::ClientId>,
+        now: Instant,
+    ) -> Result<(), RateLimitKind> {
+        if let (Some(limit), Some(bucket)) = (self.config.total, self.total.as_mut()) {
+            bucket.refill(limit, now);
+            if bucket.tokens < 1.0 {
+                return Err(RateLimitKind::Total);
+            }
+        }
+
+        if let Some(limit) = self.config.identity {
+            let bucket = self.identities.get(client_id, limit, now);
+            bucket.refill(limit, now);
+            if bucket.tokens < 1.0 {
+                return Err(RateLimitKind::Identity);
+            }
+            bucket.tokens -= 1.0;
+        }
+
+        if let Some(bucket) = self.total.as_mut() {
+            bucket.tokens -= 1.0;
+        }
+
+        Ok(())
+    }
+}
+
+// === impl IdentityBuckets ===
+
+impl IdentityBuckets {
+    fn get(
+        &mut self,
+        client_id: Option<&tls::server::ClientId>,
+        limit: Limit,
+        now: Instant,
+    ) -> &mut Bucket {
+        let key = client_id.cloned();
+        if self.recent.len() >= MAX_IDENTITY_BUCKETS / 2 && !self.recent.contains_key(&key) {
+            tracing::trace!(buckets = self.recent.len(), "Discarding stale buckets");
+            self.stale = std::mem::take(&mut self.recent);
+        }
+        let stale = &mut self.stale;
+        self.recent
+            .entry(key)
+            .or_insert_with_key(|key| stale.remove(key).unwrap_or_else(|| Bucket::new(limit, now)))
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.recent.len() + self.stale.len()
+    }
+}
+
+// === impl Bucket ===
+
+impl Bucket {
+    fn new(limit: Limit, now: Instant) -> Self {
+        Self {
+            tokens: Self::capacity(limit),
+            updated: now,
+        }
+    }
+
+    /// A bucket always holds at least one token so that a limit without a
+    /// burst still admits requests.
+    fn capacity(limit: Limit) -> f64 {
+        limit.burst.max(1) as f64
+    }
+
+    fn refill(&mut self, limit: Limit, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated);
+        self.updated = now;
+        let tokens = self.tokens + elapsed.as_secs_f64() * limit.requests_per_second as f64;
+        self.tokens = tokens.min(Self::capacity(limit));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::identity;
+    use std::{str::FromStr, time::Duration};
+
+    fn limit(requests_per_second: u32, burst: u32) -> Option<Limit> {
+        Some(Limit {
+            requests_per_second,
+            burst,
+        })
+    }
+
+    fn client_id(name: &str) -> tls::server::ClientId {
+        tls::server::ClientId(identity::Name::from_str(name).unwrap())
+    }
+
+    #[test]
+    fn total_limit_refills() {
+        let now = Instant::now();
+        let mut limiter = Limiter::new(
+            RateLimit {
+                total: limit(10, 2),
+                identity: None,
+            },
+            now,
+        );
+
+        assert!(limiter.acquire(None, now).is_ok());
+        assert!(limiter.acquire(None, now).is_ok());
+        assert_eq!(limiter.acquire(None, now), Err(RateLimitKind::Total));
+
+        // A token is added every 100ms.
+        let now = now + Duration::from_millis(100);
+        assert!(limiter.acquire(None, now).is_ok());
+        assert_eq!(limiter.acquire(None, now), Err(RateLimitKind::Total));
+
+        // The bucket never holds more than its burst.
+        let now = now + Duration::from_secs(10);
+        assert!(limiter.acquire(None, now).is_ok());
+        assert!(limiter.acquire(None, now).is_ok());
+        assert_eq!(limiter.acquire(None, now), Err(RateLimitKind::Total));
+    }
+
+    #[test]
+    fn identity_limits_are_independent() {
+        let now = Instant::now();
+        let mut limiter = Limiter::new(
+            RateLimit {
+                total: limit(100, 3),
+                identity: limit(1, 1),
+            },
+            now,
+        );
+        let foo = client_id("foo.ns.serviceaccount.identity.linkerd.cluster.local");
+        let bar = client_id("bar.ns.serviceaccount.identity.linkerd.cluster.local");
+
+        assert!(limiter.acquire(Some(&foo), now).is_ok());
+        assert_eq!(
+            limiter.acquire(Some(&foo), now),
+            Err(RateLimitKind::Identity)
+        );
+        assert!(limiter.acquire(Some(&bar), now).is_ok());
+        assert!(limiter.acquire(None, now).is_ok());
+
+        // Requests rejected by the identity limit do not consume the total
+        // limit, but the total limit applies across all identities.
+        assert_eq!(limiter.acquire(None, now), Err(RateLimitKind::Total));
+    }
+
+    #[test]
+    fn identity_buckets_are_bounded() {
+        let now = Instant::now();
+        let mut limiter = Limiter::new(
+            RateLimit {
+                total: None,
+                identity: limit(1, 1),
+            },
+            now,
+        );
+        let foo = client_id("foo.ns.serviceaccount.identity.linkerd.cluster.local");
+        assert!(limiter.acquire(Some(&foo), now).is_ok());
+
+        for i in 0..(MAX_IDENTITY_BUCKETS * 5) {
+            let id = client_id(&format!(
+                "client-{}.ns.serviceaccount.identity.linkerd.cluster.local",
+                i
+            ));
+            assert!(limiter.acquire(Some(&id), now).is_ok());
+            assert!(limiter.identities.len() <= MAX_IDENTITY_BUCKETS);
+
+            // An identity that remains active keeps its bucket.
+            if i % 100 == 0 {
+                assert_eq!(
+                    limiter.acquire(Some(&foo), now),
+                    Err(RateLimitKind::Identity)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn config_change_resets_buckets() {
+        let limiters = Limiters::default();
+        let server = ServerLabel {
+            kind: "server".into(),
+            name: "test".into(),
+        };
+        let now = Instant::now();
+        let config = RateLimit {
+            total: limit(1, 1),
+            identity: None,
+        };
+
+        assert!(limiters.acquire(&server, config, None, now).is_ok());
+        assert_eq!(
+            limiters.acquire(&server, config, None, now),
+            Err(RateLimitKind::Total)
+        );
+
+        let config = RateLimit {
+            total: limit(2, 1),
+            identity: None,
+        };
+        assert!(limiters.acquire(&server, config, None, now).is_ok());
+    }
+}
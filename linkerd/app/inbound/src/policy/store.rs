@@ -1,10 +1,12 @@
-use super::{discover, AllowPolicy, CheckPolicy, DefaultPolicy, DeniedUnknownPort};
+use super::{discover, AllowPolicy, CheckPolicy, DefaultPolicy, DeniedUnknownPort, JwksStore};
 use linkerd_app_core::{proxy::http, transport::OrigDstAddr, Error, Result};
 pub use linkerd_server_policy::{Authentication, Authorization, Protocol, ServerPolicy, Suffix};
+use parking_lot::{Mutex, RwLock};
 use std::{
     collections::{HashMap, HashSet},
     hash::{BuildHasherDefault, Hasher},
     sync::Arc,
+    time::SystemTime,
 };
 use tokio::sync::watch;
 use tracing::info_span;
@@ -12,12 +14,28 @@ use tracing::info_span;
 #[derive(Clone, Debug)]
 pub struct Store {
     // When None, the default policy is 'deny'.
-    default: Option<Rx>,
-    ports: Arc<PortMap<Rx>>,
+    default: Arc<RwLock<Option<Rx>>>,
+    ports: Arc<RwLock<PortMap<Rx>>>,
+    // Publishes updates to the default policy and to fixed port policies when
+    // the proxy's configuration is reloaded.
+    senders: Arc<Mutex<Senders>>,
+    // The time at which each port's policy was last updated by the policy
+    // controller. Fixed policies are never updated.
+    updated: Arc<PortMap<UpdatedAt>>,
+    // Key sets referenced by JWT authorizations, shared by all ports.
+    jwks: JwksStore,
 }
 
 type Tx = watch::Sender<ServerPolicy>;
 type Rx = watch::Receiver<ServerPolicy>;
+type UpdatedAt = Arc<Mutex<Option<SystemTime>>>;
+
+#[derive(Debug, Default)]
+struct Senders {
+    default: Option<Tx>,
+    // None when port policies are discovered.
+    ports: Option<PortMap<Tx>>,
+}
 
 /// A `HashMap` optimized for lookups by port number.
 type PortMap<T> = HashMap<u16, T, BuildHasherDefault<PortHasher>>;
@@ -39,31 +57,36 @@ impl Store {
         }
     }
 
-    pub(crate) fn fixed(
+    /// Builds a store with fixed policies for the given ports.
+    ///
+    /// The default and port policies may be changed via [`Store::reload_fixed`].
+    pub fn fixed(
         default: impl Into<DefaultPolicy>,
         ports: impl IntoIterator<Item = (u16, ServerPolicy)>,
-    ) -> (Self, Option<Tx>) {
-        let rxs = ports
+    ) -> Self {
+        let (txs, rxs) = ports
             .into_iter()
             .map(|(p, s)| {
-                // When using a fixed policy, we don't need to watch for changes. It's
-                // safe to discard the sender, as the receiver will continue to let us
-                // borrow/clone each fixed policy.
-                let (_, rx) = watch::channel(s);
-                (p, rx)
+                let (tx, rx) = watch::channel(s);
+                ((p, tx), (p, rx))
             })
-            .collect();
+            .unzip();
 
         let (default_tx, default) = match Self::mk_default(default.into()) {
             Some((tx, rx)) => (Some(tx), Some(rx)),
             None => (None, None),
         };
 
-        let store = Self {
-            default,
-            ports: Arc::new(rxs),
-        };
-        (store, default_tx)
+        Self {
+            default: Arc::new(RwLock::new(default)),
+            ports: Arc::new(RwLock::new(rxs)),
+            senders: Arc::new(Mutex::new(Senders {
+                default: default_tx,
+                ports: Some(txs),
+            })),
+            updated: Default::default(),
+            jwks: JwksStore::default(),
+        }
     }
 
     /// Spawns a watch for each of the given ports.
@@ -94,22 +117,112 @@ impl Store {
                 (port, rx)
             })
             .collect::<PortMap<_>>();
+        let updated = rxs
+            .iter()
+            .map(|(port, rx)| (*port, Self::record_updates(rx.clone())))
+            .collect::<PortMap<_>>();
 
-        let default = match Self::mk_default(default) {
-            Some((tx, rx)) => {
-                tokio::spawn(async move {
-                    tx.closed().await;
-                });
-                Some(rx)
-            }
-            None => None,
+        let (default_tx, default) = match Self::mk_default(default) {
+            Some((tx, rx)) => (Some(tx), Some(rx)),
+            None => (None, None),
         };
 
         Self {
-            default,
-            ports: Arc::new(rxs),
+            default: Arc::new(RwLock::new(default)),
+            ports: Arc::new(RwLock::new(rxs)),
+            senders: Arc::new(Mutex::new(Senders {
+                default: default_tx,
+                ports: None,
+            })),
+            updated: Arc::new(updated),
+            jwks: JwksStore::default(),
+        }
+    }
+
+    /// Replaces the default policy.
+    ///
+    /// Connections that use the default policy observe a change to the allowed
+    /// policy, but a change between `allow` and `deny` only applies to new
+    /// connections.
+    pub fn reload_default(&self, default: DefaultPolicy) {
+        let mut senders = self.senders.lock();
+        match (default, senders.default.as_ref()) {
+            (DefaultPolicy::Allow(policy), Some(tx)) => {
+                let _ = tx.send(policy);
+            }
+            (default, _) => {
+                let (tx, rx) = match Self::mk_default(default) {
+                    Some((tx, rx)) => (Some(tx), Some(rx)),
+                    None => (None, None),
+                };
+                senders.default = tx;
+                *self.default.write() = rx;
+            }
         }
     }
+
+    /// Replaces the default policy and the policies of all fixed ports.
+    ///
+    /// Ports that are no longer configured use the default policy for new
+    /// connections. This has no effect on the ports of a store whose policies
+    /// are discovered.
+    pub fn reload_fixed(&self, default: DefaultPolicy, ports: HashMap<u16, ServerPolicy>) {
+        self.reload_default(default);
+
+        let mut senders = self.senders.lock();
+        let txs = match senders.ports.as_mut() {
+            Some(txs) => txs,
+            None => return,
+        };
+        let mut rxs = self.ports.write();
+        rxs.retain(|port, _| ports.contains_key(port));
+        txs.retain(|port, _| ports.contains_key(port));
+        for (port, policy) in ports {
+            match txs.get(&port) {
+                Some(tx) => {
+                    let _ = tx.send(policy);
+                }
+                None => {
+                    let (tx, rx) = watch::channel(policy);
+                    txs.insert(port, tx);
+                    rxs.insert(port, rx);
+                }
+            }
+        }
+    }
+
+    /// Spawns a task that records the time of each update to a port's policy.
+    fn record_updates(mut rx: Rx) -> UpdatedAt {
+        let updated = UpdatedAt::default();
+        let handle = updated.clone();
+        tokio::spawn(async move {
+            while rx.changed().await.is_ok() {
+                *handle.lock() = Some(SystemTime::now());
+            }
+        });
+        updated
+    }
+
+    /// Returns the default policy, or `None` if unknown ports are denied.
+    pub(super) fn default_policy(&self) -> Option<ServerPolicy> {
+        self.default.read().as_ref().map(|rx| rx.borrow().clone())
+    }
+
+    /// Returns the current policy for each known port, along with the time at
+    /// which it was last updated by the policy controller, if ever.
+    pub(super) fn port_policies(&self) -> Vec<(u16, ServerPolicy, Option<SystemTime>)> {
+        let mut ports = self
+            .ports
+            .read()
+            .iter()
+            .map(|(port, rx)| {
+                let updated = self.updated.get(port).and_then(|u| *u.lock());
+                (*port, rx.borrow().clone(), updated)
+            })
+            .collect::<Vec<_>>();
+        ports.sort_by_key(|(port, ..)| *port);
+        ports
+    }
 }
 
 impl CheckPolicy for Store {
@@ -122,15 +235,20 @@ impl CheckPolicy for Store {
     fn check_policy(&self, dst: OrigDstAddr) -> Result<AllowPolicy, DeniedUnknownPort> {
         let server = self
             .ports
+            .read()
             .get(&dst.port())
             .cloned()
             .map(Ok)
-            .unwrap_or_else(|| match &self.default {
+            .unwrap_or_else(|| match &*self.default.read() {
                 Some(rx) => Ok(rx.clone()),
                 None => Err(DeniedUnknownPort(dst.port())),
             })?;
 
-        Ok(AllowPolicy { dst, server })
+        Ok(AllowPolicy {
+            dst,
+            server,
+            jwks: self.jwks.clone(),
+        })
     }
 }
 
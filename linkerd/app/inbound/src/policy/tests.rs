@@ -1,6 +1,12 @@
 use super::*;
-use linkerd_server_policy::{Authentication, Authorization, Protocol, ServerPolicy, Suffix};
-use std::collections::HashSet;
+use linkerd_server_policy::{
+    Authentication, Authorization, HttpRoute, HttpRouteMatch, PathMatch, Protocol, ServerPolicy,
+    Suffix,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
 
 #[test]
 fn unauthenticated_allowed() {
@@ -14,9 +20,11 @@ fn unauthenticated_allowed() {
         }],
         kind: "server".into(),
         name: "test".into(),
+        http_routes: vec![],
+        rate_limit: None,
     };
 
-    let (policies, _tx) = Store::fixed(policy.clone(), None);
+    let policies = Store::fixed(policy.clone(), None);
     let allowed = policies
         .check_policy(orig_dst_addr())
         .expect("port must be known");
@@ -31,6 +39,7 @@ fn unauthenticated_allowed() {
         Permit {
             dst: orig_dst_addr(),
             protocol: policy.protocol,
+            route: None,
             labels: AuthzLabels {
                 kind: "serverauthorization".into(),
                 name: "unauth".into(),
@@ -58,9 +67,11 @@ fn authenticated_identity() {
         }],
         kind: "server".into(),
         name: "test".into(),
+        http_routes: vec![],
+        rate_limit: None,
     };
 
-    let (policies, _tx) = Store::fixed(policy.clone(), None);
+    let policies = Store::fixed(policy.clone(), None);
     let allowed = policies
         .check_policy(orig_dst_addr())
         .expect("port must be known");
@@ -78,6 +89,7 @@ fn authenticated_identity() {
         Permit {
             dst: orig_dst_addr(),
             protocol: policy.protocol,
+            route: None,
             labels: AuthzLabels {
                 kind: "serverauthorization".into(),
                 name: "tls-auth".into(),
@@ -117,9 +129,11 @@ fn authenticated_suffix() {
         }],
         kind: "server".into(),
         name: "test".into(),
+        http_routes: vec![],
+        rate_limit: None,
     };
 
-    let (policies, _tx) = Store::fixed(policy.clone(), None);
+    let policies = Store::fixed(policy.clone(), None);
     let allowed = policies
         .check_policy(orig_dst_addr())
         .expect("port must be known");
@@ -136,6 +150,7 @@ fn authenticated_suffix() {
         Permit {
             dst: orig_dst_addr(),
             protocol: policy.protocol,
+            route: None,
             labels: AuthzLabels {
                 kind: "serverauthorization".into(),
                 name: "tls-auth".into(),
@@ -172,9 +187,11 @@ fn tls_unauthenticated() {
         }],
         kind: "server".into(),
         name: "test".into(),
+        http_routes: vec![],
+        rate_limit: None,
     };
 
-    let (policies, _tx) = Store::fixed(policy.clone(), None);
+    let policies = Store::fixed(policy.clone(), None);
     let allowed = policies
         .check_policy(orig_dst_addr())
         .expect("port must be known");
@@ -191,6 +208,7 @@ fn tls_unauthenticated() {
         Permit {
             dst: orig_dst_addr(),
             protocol: policy.protocol,
+            route: None,
             labels: AuthzLabels {
                 kind: "serverauthorization".into(),
                 name: "tls-unauth".into(),
@@ -212,6 +230,236 @@ fn tls_unauthenticated() {
         .expect_err("policy must require a TLS termination identity");
 }
 
+#[test]
+fn http_route_authorizations() {
+    let mesh = Authorization {
+        authentication: Authentication::TlsAuthenticated {
+            identities: HashSet::default(),
+            suffixes: vec![Suffix::from(vec!["cluster".into(), "local".into()])],
+        },
+        networks: vec!["192.0.2.0/24".parse().unwrap()],
+        kind: "serverauthorization".into(),
+        name: "mesh".into(),
+    };
+    let admin = Authorization {
+        authentication: Authentication::TlsAuthenticated {
+            identities: Some("admin.testns.serviceaccount.identity.linkerd.cluster.local".into())
+                .into_iter()
+                .collect(),
+            suffixes: vec![],
+        },
+        networks: vec!["192.0.2.0/24".parse().unwrap()],
+        kind: "serverauthorization".into(),
+        name: "admin".into(),
+    };
+    let policy = ServerPolicy {
+        protocol: Protocol::Http1,
+        authorizations: vec![mesh],
+        kind: "server".into(),
+        name: "test".into(),
+        http_routes: vec![HttpRoute {
+            matches: vec![HttpRouteMatch {
+                path: Some(PathMatch::Prefix("/admin".into())),
+                ..Default::default()
+            }],
+            authorizations: vec![admin],
+            kind: "httproute".into(),
+            name: "admin".into(),
+        }],
+        rate_limit: None,
+    };
+
+    let policies = Store::fixed(policy.clone(), None);
+    let allowed = policies
+        .check_policy(orig_dst_addr())
+        .expect("port must be known");
+
+    let req = |path: &str| {
+        ::http::Request::get(format!("http://example.com{}", path))
+            .body(())
+            .unwrap()
+    };
+    let tls = |id: tls::ClientId| {
+        tls::ConditionalServerTls::Some(tls::ServerTls::Established {
+            client_id: Some(id),
+            negotiated_protocol: None,
+        })
+    };
+    let route = HttpRouteLabel {
+        kind: "httproute".into(),
+        name: "admin".into(),
+    };
+
+    // Requests that do not match a route are authorized by the server.
+    let (permit, _) = allowed
+        .check_authorized_http(client_addr(), &tls(client_id()), &req("/api"))
+        .expect("mesh clients must be permitted");
+    assert_eq!(permit.route, None);
+    assert_eq!(permit.labels.name.as_ref(), "mesh");
+
+    let err = allowed
+        .check_authorized_http(client_addr(), &tls(client_id()), &req("/admin/users"))
+        .expect_err("only the admin identity may access the admin route");
+    assert_eq!(err.route(), Some(&route));
+
+    let admin_id = "admin.testns.serviceaccount.identity.linkerd.cluster.local"
+        .parse()
+        .unwrap();
+    let (permit, _) = allowed
+        .check_authorized_http(client_addr(), &tls(admin_id), &req("/admin/users"))
+        .expect("the admin identity must be permitted");
+    assert_eq!(permit.route, Some(route));
+    assert_eq!(permit.labels.name.as_ref(), "admin");
+}
+
+#[test]
+fn describes_policies() {
+    let policy = ServerPolicy {
+        protocol: Protocol::Detect {
+            timeout: std::time::Duration::from_secs(10),
+        },
+        authorizations: vec![Authorization {
+            authentication: Authentication::TlsAuthenticated {
+                identities: Some(client_id().to_string()).into_iter().collect(),
+                suffixes: vec![Suffix::from(vec!["cluster".into(), "local".into()])],
+            },
+            networks: vec!["192.0.2.0/24".parse().unwrap()],
+            kind: "serverauthorization".into(),
+            name: "mesh".into(),
+        }],
+        kind: "server".into(),
+        name: "test".into(),
+        http_routes: vec![],
+        rate_limit: None,
+    };
+
+    let policies = Store::fixed(DefaultPolicy::Deny, Some((1000, policy)));
+    assert_eq!(
+        policies.to_json(),
+        serde_json::json!({
+            "default": "deny",
+            "ports": [{
+                "port": 1000,
+                "updated_at": null,
+                "policy": {
+                    "kind": "server",
+                    "name": "test",
+                    "protocol": { "detect": { "timeout_ms": 10_000 } },
+                    "authorizations": [{
+                        "kind": "serverauthorization",
+                        "name": "mesh",
+                        "networks": [{ "net": "192.0.2.0/24", "except": [] }],
+                        "authentication": {
+                            "kind": "tls-authenticated",
+                            "identities": [client_id().to_string()],
+                            "suffixes": ["*.cluster.local"],
+                        },
+                    }],
+                    "http_routes": [],
+                    "rate_limit": null,
+                },
+            }],
+        })
+    );
+}
+
+#[test]
+fn reloads_fixed_policies() {
+    let policy = |name: &'static str| ServerPolicy {
+        protocol: Protocol::Opaque,
+        authorizations: vec![],
+        kind: "server".into(),
+        name: name.into(),
+        http_routes: vec![],
+        rate_limit: None,
+    };
+    let dst = |port: u16| OrigDstAddr(([192, 0, 2, 2], port).into());
+
+    let policies = Store::fixed(policy("default"), Some((1000, policy("a"))));
+    let allowed = policies
+        .check_policy(dst(1000))
+        .expect("port must be known");
+    assert_eq!(allowed.server.borrow().name.as_ref(), "a");
+
+    // Existing connections observe updates to their port's policy, and new
+    // ports are added.
+    policies.reload_fixed(
+        DefaultPolicy::Allow(policy("default")),
+        vec![(1000, policy("b")), (2000, policy("c"))]
+            .into_iter()
+            .collect(),
+    );
+    assert_eq!(allowed.server.borrow().name.as_ref(), "b");
+    let allowed = policies
+        .check_policy(dst(2000))
+        .expect("port must be known");
+    assert_eq!(allowed.server.borrow().name.as_ref(), "c");
+
+    // Ports that are removed use the default policy.
+    policies.reload_fixed(DefaultPolicy::Allow(policy("default")), HashMap::default());
+    let allowed = policies
+        .check_policy(dst(2000))
+        .expect("default must allow");
+    assert_eq!(allowed.server.borrow().name.as_ref(), "default");
+
+    policies.reload_fixed(DefaultPolicy::Deny, HashMap::default());
+    policies
+        .check_policy(dst(2000))
+        .expect_err("default must deny");
+}
+
+#[test]
+fn local_policy_is_applied() {
+    let route = HttpRoute {
+        matches: vec![HttpRouteMatch {
+            path: Some(PathMatch::Prefix("/admin".into())),
+            ..Default::default()
+        }],
+        authorizations: vec![],
+        kind: "default".into(),
+        name: "/admin".into(),
+    };
+    let limit = RateLimit {
+        total: Some(Limit {
+            requests_per_second: 10,
+            burst: 10,
+        }),
+        identity: None,
+    };
+    let jwt = defaults::jwt_authorization("/var/run/jwks.json".into(), None, vec![]);
+    let local = LocalPolicy {
+        rate_limit: Some(limit),
+        http_routes: vec![route.clone()],
+        port_authorizations: Some((1000, vec![jwt.clone()])).into_iter().collect(),
+    };
+
+    let default = defaults::all_unauthenticated(Duration::from_secs(10));
+    let policy = local.apply(None, default.clone());
+    assert_eq!(policy.rate_limit, Some(limit));
+    assert_eq!(policy.http_routes, vec![route]);
+    assert_eq!(policy.authorizations, default.authorizations);
+
+    // Port authorizations are only added to the servers on their ports.
+    let policy = local.apply(Some(1000), default.clone());
+    assert_eq!(policy.authorizations.last(), Some(&jwt));
+    let policy = local.apply(Some(2000), default.clone());
+    assert_eq!(policy.authorizations, default.authorizations);
+
+    // Rate limits configured by the policy are not overridden.
+    let configured = RateLimit {
+        total: None,
+        identity: limit.total,
+    };
+    let policy = local.apply(
+        None,
+        ServerPolicy {
+            rate_limit: Some(configured),
+            ..default
+        },
+    );
+    assert_eq!(policy.rate_limit, Some(configured));
+}
+
 fn client_id() -> tls::ClientId {
     "testsa.testns.serviceaccount.identity.linkerd.cluster.local"
         .parse()
@@ -48,6 +48,7 @@ impl Inbound<()> {
         P::Future: Send,
     {
         let shutdown = self.runtime.drain.clone().signaled();
+        let connections = self.runtime.connections.clone();
 
         // Handles connections to ports that can't be determined to be HTTP.
         let forward = self
@@ -94,7 +95,7 @@ impl Inbound<()> {
             .push_accept(addr.port(), policies, direct)
             .into_inner();
 
-        serve::serve(listen, server, shutdown).await;
+        serve::serve(listen, server, shutdown, connections).await;
     }
 }
 
@@ -28,6 +28,7 @@ pub fn default_config() -> Config {
                 addr: ListenAddr(([0, 0, 0, 0], 0).into()),
                 keepalive: Keepalive(None),
                 h2_settings: h2::Settings::default(),
+                request_limits: Default::default(),
             },
             connect: config::ConnectConfig {
                 keepalive: Keepalive(None),
@@ -63,25 +64,33 @@ pub fn default_config() -> Config {
                 }],
                 kind: "server".into(),
                 name: "testsrv".into(),
+                http_routes: vec![],
+                rate_limit: None,
             }
             .into(),
             ports: Default::default(),
+            local: Default::default(),
         },
         profile_idle_timeout: Duration::from_millis(500),
         allowed_ips: Default::default(),
+        access_log_headers: Vec::new().into(),
     }
 }
 
 pub fn runtime() -> (ProxyRuntime, drain::Signal) {
     let (drain_tx, drain) = drain::channel();
     let (tap, _) = tap::new();
-    let (metrics, _) = metrics::Metrics::new(std::time::Duration::from_secs(10));
+    let (metrics, _) = metrics::Metrics::new(
+        std::time::Duration::from_secs(10),
+        metrics::latency::DEFAULT_SCHEMA,
+    );
     let runtime = ProxyRuntime {
         identity: rustls::creds::default_for_test().1.into(),
         metrics: metrics.proxy,
         tap,
         span_sink: None,
         drain,
+        connections: Default::default(),
     };
     (runtime, drain_tx)
 }
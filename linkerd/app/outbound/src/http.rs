@@ -1,12 +1,16 @@
 pub mod detect;
 mod endpoint;
+mod hedge;
 pub mod logical;
+mod mirror;
 mod proxy_connection_close;
 mod require_id_header;
 mod retry;
 mod server;
 mod strip_proxy_error;
+mod trace_sampling;
 
+pub use self::{mirror::MirrorConfig, retry::RetryConfig, trace_sampling::TraceSamplingConfig};
 use self::{
     proxy_connection_close::ProxyConnectionClose, require_id_header::NewRequireIdentity,
     strip_proxy_error::NewStripProxyError,
@@ -19,7 +23,7 @@ use linkerd_app_core::{
     profiles::{self, LogicalAddr},
     proxy::{api_resolve::ProtocolHint, tap},
     svc::Param,
-    tls, Addr, Conditional, CANONICAL_DST_HEADER,
+    tls, trace_context, Addr, Conditional, CANONICAL_DST_HEADER,
 };
 use std::{net::SocketAddr, str::FromStr};
 
@@ -202,6 +206,12 @@ impl Param<ResponseTimeout> for Route {
     }
 }
 
+impl Param<trace_context::RouteSampleRatio> for Route {
+    fn param(&self) -> trace_context::RouteSampleRatio {
+        trace_context::RouteSampleRatio(self.route.trace_sampling().map(|s| s.ratio()))
+    }
+}
+
 impl classify::CanClassify for Route {
     type Classify = classify::Request;
 
@@ -29,7 +29,11 @@ impl<N> Outbound<N> {
         U: From<(http::Version, T)> + svc::Param<http::Version> + 'static,
     {
         self.map_stack(|config, rt, tcp| {
-            let ServerConfig { h2_settings, .. } = config.proxy.server;
+            let ServerConfig {
+                h2_settings,
+                request_limits,
+                ..
+            } = config.proxy.server;
 
             let skipped = tcp
                 .clone()
@@ -43,7 +47,11 @@ impl<N> Outbound<N> {
                         .push(svc::MapErr::layer(Into::into)),
                 )
                 .check_new_service::<U, _>()
-                .push(http::NewServeHttp::layer(h2_settings, rt.drain.clone()))
+                .push(http::NewServeHttp::layer(
+                    h2_settings,
+                    request_limits,
+                    rt.drain.clone(),
+                ))
                 .push_map_target(U::from)
                 .instrument(|(v, _): &(http::Version, _)| debug_span!("http", %v))
                 .push(svc::UnwrapOr::layer(
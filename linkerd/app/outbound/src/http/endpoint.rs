@@ -81,6 +81,8 @@ impl<C> Outbound<C> {
                         .http_endpoint
                         .to_layer::<classify::Response, _, _>(),
                 )
+                // Labels the endpoint's span with its address, metadata and identity.
+                .push(http_tracing::NewRecordTarget::layer())
                 .push_on_service(http_tracing::client(
                     rt.span_sink.clone(),
                     crate::trace_labels(),
@@ -0,0 +1,510 @@
+//! Hedges idempotent route requests.
+//!
+//! When a route is configured with a hedging policy, a request that has not
+//! received a response within the configured delay is dispatched a second time
+//! through the same balancer. Because the balancer picks the less loaded of
+//! two endpoints and the original endpoint still holds the outstanding
+//! request, the hedge is typically sent to a different endpoint. Whichever
+//! attempt responds first is returned and the other is canceled.
+//!
+//! Hedges are withdrawn from the route's retry budget, so routes without a
+//! retry budget are never hedged.
+
+use super::Route;
+use futures::prelude::*;
+use hdrhistogram::Histogram;
+use linkerd_app_core::{
+    http_metrics::retries::Handle,
+    metrics, profiles,
+    proxy::http::{self, ClientHandle, HttpBody},
+    svc::{self, layer, NewService, Param, ServiceExt},
+    Error,
+};
+use linkerd_http_retry::ReplayBody;
+use linkerd_retry::Budget;
+use parking_lot::Mutex;
+use std::{
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::time::{self, Duration, Instant};
+use tracing::{debug, trace};
+
+/// Allow buffering hedged request bodies up to 64 kb
+const MAX_BUFFERED_BYTES: usize = 64 * 1024;
+
+/// Percentile-based hedging is disabled until this many latencies have been
+/// observed.
+const MIN_DATA_POINTS: u64 = 10;
+
+/// Latencies observed over this period are used to compute the hedging delay.
+const LATENCY_PERIOD: Duration = Duration::from_secs(10);
+
+/// Sets a route's hedging policy, if one is configured and the route may be
+/// retried.
+pub(super) fn configure_route(
+    hedge: Option<profiles::http::Hedge>,
+    route: &mut profiles::http::Route,
+) {
+    if let Some(hedge) = hedge {
+        if route.retries().is_some() {
+            route.set_hedge(hedge);
+        }
+    }
+}
+
+pub fn layer<N>(
+    metrics: metrics::HttpRouteRetry,
+) -> impl layer::Layer<N, Service = NewHedge<N>> + Clone {
+    layer::mk(move |inner| NewHedge {
+        inner,
+        metrics: metrics.clone(),
+    })
+}
+
+#[derive(Clone, Debug)]
+pub struct NewHedge<N> {
+    inner: N,
+    metrics: metrics::HttpRouteRetry,
+}
+
+#[derive(Clone, Debug)]
+pub struct Hedge<S> {
+    inner: S,
+    policy: Option<Policy>,
+}
+
+#[derive(Clone, Debug)]
+struct Policy {
+    delay: Delay,
+    budget: Arc<Budget>,
+    metrics: Handle,
+}
+
+#[derive(Clone, Debug)]
+enum Delay {
+    Latency(Duration),
+    Percentile {
+        percentile: f64,
+        latencies: Arc<Mutex<RotatingHistogram>>,
+    },
+}
+
+/// Records latencies into a histogram that is made readable after each period
+/// so that the hedging delay reflects recent latencies.
+#[derive(Debug)]
+struct RotatingHistogram {
+    read: Histogram<u64>,
+    write: Histogram<u64>,
+    last_rotation: Instant,
+    period: Duration,
+}
+
+type ResponseFuture<R> = Pin<Box<dyn Future<Output = Result<R, Error>> + Send + 'static>>;
+
+// === impl NewHedge ===
+
+impl<N> NewService<Route> for NewHedge<N>
+where
+    N: NewService<Route>,
+{
+    type Service = Hedge<N::Service>;
+
+    fn new_service(&self, route: Route) -> Self::Service {
+        let policy = route.route.hedge().and_then(|hedge| {
+            let retries = route.route.retries()?;
+            Some(Policy {
+                delay: Delay::new(hedge),
+                budget: retries.budget().clone(),
+                metrics: self.metrics.get_handle(route.param()),
+            })
+        });
+        if route.route.hedge().is_some() && policy.is_none() {
+            debug!("Route has no retry budget; requests will not be hedged");
+        }
+
+        Hedge {
+            inner: self.inner.new_service(route),
+            policy,
+        }
+    }
+}
+
+// === impl Hedge ===
+
+impl<S> svc::Service<http::Request<http::BoxBody>> for Hedge<S>
+where
+    S: svc::Service<http::Request<http::BoxBody>> + Clone + Send + 'static,
+    S::Response: Send + 'static,
+    S::Error: Into<Error>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = ResponseFuture<S::Response>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: http::Request<http::BoxBody>) -> Self::Future {
+        // Only idempotent requests may be sent more than once.
+        let policy = match self.policy.as_ref() {
+            Some(policy) if req.method().is_idempotent() => policy.clone(),
+            _ => return Box::pin(self.inner.call(req).err_into::<Error>()),
+        };
+
+        let (head, body) = req.into_parts();
+        let body = match ReplayBody::try_new(body, MAX_BUFFERED_BYTES) {
+            Ok(body) => body,
+            Err(body) => {
+                debug!(
+                    size = body.size_hint().lower(),
+                    "Body is too large to hedge"
+                );
+                let req = http::Request::from_parts(head, body);
+                return Box::pin(self.inner.call(req).err_into::<Error>());
+            }
+        };
+
+        let mut hedge = http::Request::new(body.clone());
+        *hedge.method_mut() = head.method.clone();
+        *hedge.uri_mut() = head.uri.clone();
+        *hedge.headers_mut() = head.headers.clone();
+        *hedge.version_mut() = head.version;
+        // The HTTP server sets a ClientHandle with the client's address and a
+        // means to close the server-side connection.
+        if let Some(client_handle) = head.extensions.get::<ClientHandle>().cloned() {
+            hedge.extensions_mut().insert(client_handle);
+        }
+
+        let primary = self
+            .inner
+            .call(http::Request::from_parts(head, http::BoxBody::new(body)))
+            .err_into::<Error>();
+        let inner = self.inner.clone();
+
+        Box::pin(async move {
+            let start = Instant::now();
+            tokio::pin!(primary);
+
+            let delay = match policy.delay.get() {
+                Some(delay) => delay,
+                None => {
+                    let rsp = primary.await;
+                    policy
+                        .delay
+                        .record(Instant::now().saturating_duration_since(start));
+                    return rsp;
+                }
+            };
+
+            // The hedge's body is replayed concurrently with the primary
+            // request, so the hedge may be dispatched while the primary request
+            // (or a retry holding its body) is still outstanding.
+            tokio::select! {
+                rsp = &mut primary => {
+                    policy.delay.record(Instant::now().saturating_duration_since(start));
+                    return rsp;
+                }
+                () = time::sleep(delay) => {}
+            }
+
+            // Whether the body exceeded the maximum length limit.
+            if hedge.body().is_capped() {
+                debug!("Body exceeded the maximum buffer size; not hedging");
+                return primary.await;
+            }
+
+            let withdrew = policy.budget.withdraw().is_ok();
+            policy.metrics.incr_hedged(withdrew);
+            if !withdrew {
+                trace!("No budget to hedge request");
+                return primary.await;
+            }
+
+            debug!(?delay, "Hedging request");
+            let hedge_start = Instant::now();
+            let hedge = inner
+                .oneshot(hedge.map(http::BoxBody::new))
+                .err_into::<Error>();
+            tokio::pin!(hedge);
+
+            // Return the first successful response. If either attempt fails,
+            // wait for the other.
+            tokio::select! {
+                rsp = &mut primary => match rsp {
+                    Ok(rsp) => {
+                        policy.delay.record(Instant::now().saturating_duration_since(start));
+                        Ok(rsp)
+                    }
+                    Err(_) => hedge.await,
+                },
+                rsp = &mut hedge => match rsp {
+                    Ok(rsp) => {
+                        policy.delay.record(Instant::now().saturating_duration_since(hedge_start));
+                        Ok(rsp)
+                    }
+                    Err(_) => primary.await,
+                },
+            }
+        })
+    }
+}
+
+// === impl Delay ===
+
+impl Delay {
+    fn new(hedge: profiles::http::Hedge) -> Self {
+        match hedge {
+            profiles::http::Hedge::Latency(latency) => Self::Latency(latency),
+            profiles::http::Hedge::Percentile(percentile) => Self::Percentile {
+                percentile: percentile.into(),
+                latencies: Arc::new(Mutex::new(RotatingHistogram::new(LATENCY_PERIOD))),
+            },
+        }
+    }
+
+    /// Returns the delay after which a request should be hedged, if enough
+    /// latencies have been observed to compute it.
+    fn get(&self) -> Option<Duration> {
+        match self {
+            Self::Latency(latency) => Some(*latency),
+            Self::Percentile {
+                percentile,
+                latencies,
+            } => {
+                let mut latencies = latencies.lock();
+                let read = latencies.read();
+                if read.len() < MIN_DATA_POINTS {
+                    trace!(data_points = read.len(), "Not enough data to hedge");
+                    return None;
+                }
+                Some(Duration::from_millis(read.value_at_quantile(*percentile)))
+            }
+        }
+    }
+
+    fn record(&self, latency: Duration) {
+        if let Self::Percentile { latencies, .. } = self {
+            let ms = latency.as_millis().try_into().unwrap_or(u64::MAX);
+            latencies.lock().write().saturating_record(ms);
+        }
+    }
+}
+
+// === impl RotatingHistogram ===
+
+impl RotatingHistogram {
+    fn new(period: Duration) -> Self {
+        Self {
+            read: Histogram::new(3).expect("histogram parameters must be valid"),
+            write: Histogram::new(3).expect("histogram parameters must be valid"),
+            last_rotation: Instant::now(),
+            period,
+        }
+    }
+
+    fn read(&mut self) -> &Histogram<u64> {
+        self.maybe_rotate();
+        &self.read
+    }
+
+    fn write(&mut self) -> &mut Histogram<u64> {
+        self.maybe_rotate();
+        &mut self.write
+    }
+
+    fn maybe_rotate(&mut self) {
+        let elapsed = Instant::now().saturating_duration_since(self.last_rotation);
+        let rotations = (elapsed.as_nanos() / self.period.as_nanos()) as u32;
+        match rotations {
+            0 => return,
+            1 => {
+                std::mem::swap(&mut self.read, &mut self.write);
+                self.write.clear();
+            }
+            _ => {
+                // The latest period recorded nothing, so there's nothing to
+                // read.
+                self.read.clear();
+                self.write.clear();
+            }
+        }
+        self.last_rotation += self.period * rotations;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{retry, Logical, Version};
+    use futures::future;
+    use linkerd_app_core::svc::Layer;
+    use linkerd_app_core::Infallible;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn hedge<S>(inner: S, budget: Budget) -> Hedge<S> {
+        let route = profiles::http::Route::default();
+        let addr = profiles::LogicalAddr("foo.example.com:80".parse().unwrap());
+        let metrics = metrics::HttpRouteRetry::default()
+            .get_handle(metrics::RouteLabels::outbound(addr, &route));
+        Hedge {
+            inner,
+            policy: Some(Policy {
+                delay: Delay::Latency(Duration::from_millis(10)),
+                budget: Arc::new(budget),
+                metrics,
+            }),
+        }
+    }
+
+    /// Returns a service whose first call never completes.
+    fn stuck_once(
+        calls: Arc<AtomicUsize>,
+    ) -> impl svc::Service<
+        http::Request<http::BoxBody>,
+        Response = http::Response<http::BoxBody>,
+        Error = Infallible,
+        Future = future::BoxFuture<'static, Result<http::Response<http::BoxBody>, Infallible>>,
+    > + Clone {
+        svc::mk(move |req: http::Request<http::BoxBody>| {
+            if calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                // Hold the request, as a client does while it is outstanding.
+                return async move {
+                    let _req = req;
+                    future::pending().await
+                }
+                .boxed();
+            }
+            future::ok(http::Response::new(http::BoxBody::default())).boxed()
+        })
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn hedges_slow_request() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let svc = hedge(
+            stuck_once(calls.clone()),
+            Budget::new(Duration::from_secs(10), 10, 0.2),
+        );
+
+        let req = http::Request::get("http://foo.example.com")
+            .body(http::BoxBody::default())
+            .unwrap();
+        time::timeout(Duration::from_secs(10), svc.oneshot(req))
+            .await
+            .expect("request must complete")
+            .expect("request must succeed");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn does_not_hedge_without_budget() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let svc = hedge(
+            stuck_once(calls.clone()),
+            Budget::new(Duration::from_secs(10), 0, 0.0),
+        );
+
+        let req = http::Request::get("http://foo.example.com")
+            .body(http::BoxBody::default())
+            .unwrap();
+        time::timeout(Duration::from_millis(100), svc.oneshot(req))
+            .await
+            .expect_err("request must not be hedged");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn does_not_hedge_non_idempotent_request() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let svc = hedge(
+            stuck_once(calls.clone()),
+            Budget::new(Duration::from_secs(10), 10, 0.2),
+        );
+
+        let req = http::Request::post("http://foo.example.com")
+            .body(http::BoxBody::default())
+            .unwrap();
+        time::timeout(Duration::from_millis(100), svc.oneshot(req))
+            .await
+            .expect_err("request must not be hedged");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn configures_retryable_routes() {
+        let hedge = Some(profiles::http::Hedge::Latency(Duration::from_millis(10)));
+
+        let mut route = profiles::http::Route::default();
+        configure_route(hedge, &mut route);
+        assert!(
+            route.hedge().is_none(),
+            "routes without retries must not hedge"
+        );
+
+        route.set_retries(profiles::http::Retries::new(Arc::new(Budget::new(
+            Duration::from_secs(10),
+            10,
+            0.2,
+        ))));
+        configure_route(None, &mut route);
+        assert!(route.hedge().is_none());
+        configure_route(hedge, &mut route);
+        assert_eq!(route.hedge(), hedge);
+    }
+
+    /// Hedges must be dispatched even though the retry layer holds a clone of
+    /// the request body for as long as the original request is outstanding.
+    #[tokio::test(flavor = "current_thread")]
+    async fn hedges_with_retries() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let mut route = profiles::http::Route::default();
+        route.set_retries(profiles::http::Retries::new(Arc::new(Budget::new(
+            Duration::from_secs(10),
+            10,
+            0.2,
+        ))));
+        route.set_hedge(profiles::http::Hedge::Latency(Duration::from_millis(10)));
+        let logical_addr = profiles::LogicalAddr("foo.example.com:80".parse().unwrap());
+        let (_tx, rx) = tokio::sync::watch::channel(profiles::Profile {
+            addr: Some(logical_addr.clone()),
+            ..Default::default()
+        });
+        let route = Route {
+            logical: Logical {
+                profile: rx.into(),
+                logical_addr,
+                protocol: Version::H2,
+            },
+            route,
+        };
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let inner = http::BoxRequest::erased()
+            .layer(stuck_once(calls.clone()).map_err(|e| -> Error { match e {} }));
+        let metrics = metrics::HttpRouteRetry::default();
+        let svc = layer(metrics.clone())
+            .layer(retry::layer(metrics).layer(move |_: Route| inner.clone()))
+            .new_service(route);
+
+        let req = http::Request::get("http://foo.example.com")
+            .body(http::BoxBody::default())
+            .unwrap();
+        time::timeout(Duration::from_secs(10), svc.oneshot(req))
+            .await
+            .expect("request must complete")
+            .expect("request must succeed");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
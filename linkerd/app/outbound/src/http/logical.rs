@@ -1,14 +1,16 @@
-use super::{retry, CanonicalDstHeader, Concrete, Endpoint, Logical, Route};
-use crate::{endpoint, resolve, stack_labels, Outbound};
+use super::{
+    hedge, mirror, retry, trace_sampling, CanonicalDstHeader, Concrete, Endpoint, Logical, Route,
+};
+use crate::{endpoint, metrics::concurrency_limit::Key, resolve, stack_labels, Outbound};
 use linkerd_app_core::{
-    classify, config, profiles,
+    classify, concurrency_limit, config, failure_accrual, profiles,
     proxy::{
         api_resolve::{ConcreteAddr, Metadata},
         core::Resolve,
         http,
         resolve::map_endpoint,
     },
-    svc, Error, Infallible,
+    svc, trace_context, Error, Infallible,
 };
 use tracing::debug_span;
 
@@ -34,6 +36,13 @@ impl<E> Outbound<E> {
                 ..
             } = config.proxy;
             let watchdog = cache_max_idle_age * 2;
+            let concurrency_limits = rt.metrics.concurrency_limits.clone();
+            let logical_limit = config.http_logical_concurrency_limit;
+            let endpoint_limit = config.http_endpoint_concurrency_limit;
+            let route_mirrors = config.http_route_mirrors.clone();
+            let route_trace_sampling = config.http_route_trace_sampling.clone();
+            let route_retry = config.http_route_retry.clone();
+            let route_hedge = config.http_route_hedge;
 
             let endpoint =
                 endpoint.instrument(|e: &Endpoint| debug_span!("endpoint", server.addr = %e.addr));
@@ -49,6 +58,7 @@ impl<E> Outbound<E> {
                         inner,
                     )
                 }))
+                .push(rt.destinations.record_resolve())
                 .check_service::<Concrete>()
                 .into_inner();
 
@@ -64,6 +74,21 @@ impl<E> Outbound<E> {
                     ),
                 )
                 .check_new_service::<Endpoint, http::Request<_>>()
+                // Ejects endpoints that accrue failures so that the balancer
+                // avoids them until they recover.
+                .push(failure_accrual::NewGate::layer(
+                    config.failure_accrual,
+                    rt.metrics.failure_accrual.http_ejections.clone(),
+                    failure_accrual::http::NewReportClassified::<classify::Response>::new(),
+                ))
+                // Limits the number of requests in flight to each endpoint.
+                // Endpoint limits never shed requests: a saturated endpoint
+                // remains unready, so the balancer prefers other endpoints. If
+                // every endpoint is saturated, the balancer fails fast.
+                .push(concurrency_limit::NewAdaptiveLimit::layer_via({
+                    let limits = concurrency_limits.clone();
+                    move |e: &Endpoint| limits.limiter(endpoint_limit, Key::Endpoint(e.addr.into()))
+                }))
                 // Resolve the service to its endpoints and balance requests over them.
                 //
                 // If the balancer has been empty/unavailable, eagerly fail requests.
@@ -76,9 +101,10 @@ impl<E> Outbound<E> {
                 // endpoint layer spawns each _connection_ attempt on a background task, but the
                 // decision to attempt the connection must be driven by the balancer.
                 .push(resolve::layer(resolve, watchdog))
+                .push(rt.destinations.with_loads())
                 .push_on_service(
                     svc::layers()
-                        .push(http::balance::layer(
+                        .push(http::balance::layer_recorded(
                             crate::EWMA_DEFAULT_RTT,
                             crate::EWMA_DECAY,
                         ))
@@ -103,6 +129,23 @@ impl<E> Outbound<E> {
                 .push_map_target(Concrete::from)
                 .push(svc::ArcNewService::layer());
 
+            // Routes may mirror requests to another balancer. Mirrored
+            // requests are dispatched on a background task, so the balancer
+            // is buffered. Mirror balancers are cached so that they are
+            // shared by all of a logical destination's routes.
+            let mirror = concrete
+                .clone()
+                .push_on_service(
+                    svc::layers()
+                        .push(svc::layer::mk(svc::SpawnReady::new))
+                        .push(svc::FailFast::layer("HTTP Mirror", dispatch_timeout))
+                        .push_spawn_buffer::<http::Request<http::BoxBody>>(buffer_capacity),
+                )
+                .push_map_target(<(ConcreteAddr, Logical)>::from)
+                .push_cache(cache_max_idle_age)
+                .push(svc::ArcNewService::layer())
+                .into_inner();
+
             // Distribute requests over a distribution of balancers via a
             // traffic split.
             //
@@ -112,6 +155,13 @@ impl<E> Outbound<E> {
             let logical = concrete
                 .check_new_service::<(ConcreteAddr, Logical), _>()
                 .push(profiles::split::layer())
+                // Limits the number of requests in flight to each logical
+                // service, shedding requests when the limit is exhausted.
+                .push(concurrency_limit::NewAdaptiveLimit::layer_via(
+                    move |l: &Logical| {
+                        concurrency_limits.limiter(logical_limit, Key::Logical(l.logical_addr.clone()))
+                    },
+                ))
                 .push_on_service(
                     svc::layers()
                         .push(svc::layer::mk(svc::SpawnReady::new))
@@ -124,17 +174,23 @@ impl<E> Outbound<E> {
                         .push(svc::FailFast::layer("HTTP Logical", dispatch_timeout))
                         .push_spawn_buffer(buffer_capacity),
                 )
-                .push_cache(cache_max_idle_age);
+                .push_cache_inspect(cache_max_idle_age, rt.destinations.http_logicals());
 
             // If there's no route, use the logical service directly; otherwise
             // use the per-route stack.
             logical
                 .clone()
                 .push_switch(
-                    |(route, logical): (Option<profiles::http::Route>, Logical)| -> Result<_, Infallible> {
+                    move |(route, logical): (Option<profiles::http::Route>, Logical)| -> Result<_, Infallible> {
                         match route {
                             None => Ok(svc::Either::A(logical)),
-                            Some(route) => Ok(svc::Either::B(Route { route, logical })),
+                            Some(mut route) => {
+                                mirror::configure_route(&route_mirrors, &logical.logical_addr, &mut route);
+                                trace_sampling::configure_route(&route_trace_sampling, &logical.logical_addr, &mut route);
+                                retry::configure_route(&route_retry, &mut route);
+                                hedge::configure_route(route_hedge, &mut route);
+                                Ok(svc::Either::B(Route { route, logical }))
+                            }
                         }
                     },
                     logical
@@ -153,8 +209,15 @@ impl<E> Outbound<E> {
                         .push_http_insert_target::<profiles::http::Route>()
                         // Sets an optional retry policy.
                         .push(retry::layer(rt.metrics.proxy.http_route_retry.clone()))
+                        // Sets an optional hedging policy, drawing from the
+                        // retry budget.
+                        .push(hedge::layer(rt.metrics.proxy.http_route_retry.clone()))
                         // Sets an optional request timeout.
                         .push(http::NewTimeout::layer())
+                        // Mirrors a portion of requests to another balancer,
+                        // if the route is configured to do so.
+                        .push(mirror::layer(mirror, rt.metrics.http_mirror.clone()))
+                        .push_on_service(http::BoxRequest::layer())
                         // Records per-route metrics.
                         .push(
                             rt.metrics
@@ -165,6 +228,9 @@ impl<E> Outbound<E> {
                         // Sets the per-route response classifier as a request
                         // extension.
                         .push(classify::NewClassify::layer())
+                        // Applies the route's sampling ratio to traces started
+                        // by the proxy.
+                        .push(trace_context::NewRouteSampling::layer())
                         .push_on_service(
                             svc::layers()
                                 .push(http::BoxResponse::layer())
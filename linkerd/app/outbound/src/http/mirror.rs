@@ -0,0 +1,326 @@
+use super::{Logical, Route};
+use crate::metrics::mirror::{Counters, Mirror as Metrics};
+use linkerd_app_core::{
+    profiles::{self, LogicalAddr},
+    proxy::{
+        api_resolve::ConcreteAddr,
+        http::{self, HttpBody},
+    },
+    svc::{self, layer, NewService, Param, ServiceExt},
+    Error, NameAddr,
+};
+use linkerd_http_retry::ReplayBody;
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tracing::{debug, Instrument};
+
+/// Allow buffering mirrored request bodies up to 64 kb
+const MAX_BUFFERED_BYTES: usize = 64 * 1024;
+
+/// Configures the routes of a logical destination to mirror a ratio of their
+/// requests to another destination.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MirrorConfig {
+    pub logical: NameAddr,
+    pub mirror: NameAddr,
+    pub ratio: f32,
+}
+
+/// Identifies the balancer that mirrored requests are sent to, so that it may
+/// be shared by all of a logical destination's routes.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(super) struct MirrorTarget {
+    addr: NameAddr,
+    logical: Logical,
+}
+
+/// Sets a route's mirror if one is configured for its logical destination.
+pub(super) fn configure_route(
+    mirrors: &[MirrorConfig],
+    LogicalAddr(logical): &LogicalAddr,
+    route: &mut profiles::http::Route,
+) {
+    if let Some(config) = mirrors.iter().find(|m| m.logical == *logical) {
+        route.set_mirror(config.mirror.clone(), config.ratio);
+    }
+}
+
+pub fn layer<M: Clone, N>(
+    new_mirror: M,
+    metrics: Metrics,
+) -> impl layer::Layer<N, Service = NewMirror<M, N>> + Clone {
+    layer::mk(move |inner| NewMirror {
+        inner,
+        new_mirror: new_mirror.clone(),
+        metrics: metrics.clone(),
+    })
+}
+
+/// Builds route services that send a copy of a portion of requests to the
+/// route's mirror destination, if one is configured.
+#[derive(Clone, Debug)]
+pub struct NewMirror<M, N> {
+    inner: N,
+    new_mirror: M,
+    metrics: Metrics,
+}
+
+#[derive(Clone, Debug)]
+pub struct Mirror<M, S> {
+    inner: S,
+    mirror: Option<Mirrored<M>>,
+}
+
+#[derive(Clone, Debug)]
+struct Mirrored<M> {
+    service: M,
+    ratio: f32,
+    metrics: Arc<Counters>,
+}
+
+// === impl MirrorTarget ===
+
+impl From<MirrorTarget> for (ConcreteAddr, Logical) {
+    fn from(MirrorTarget { addr, logical }: MirrorTarget) -> Self {
+        (ConcreteAddr(addr), logical)
+    }
+}
+
+// === impl NewMirror ===
+
+impl<M, N> NewService<Route> for NewMirror<M, N>
+where
+    M: NewService<MirrorTarget>,
+    N: NewService<Route>,
+{
+    type Service = Mirror<M::Service, N::Service>;
+
+    fn new_service(&self, route: Route) -> Self::Service {
+        let mirror = route.route.mirror().map(|mirror| {
+            let target = MirrorTarget {
+                addr: mirror.addr().clone(),
+                logical: route.logical.clone(),
+            };
+            Mirrored {
+                service: self.new_mirror.new_service(target),
+                ratio: mirror.ratio(),
+                metrics: self.metrics.counters(route.param()),
+            }
+        });
+
+        Mirror {
+            inner: self.inner.new_service(route),
+            mirror,
+        }
+    }
+}
+
+// === impl Mirror ===
+
+impl<M, S> svc::Service<http::Request<http::BoxBody>> for Mirror<M, S>
+where
+    S: svc::Service<http::Request<http::BoxBody>>,
+    M: svc::Service<http::Request<http::BoxBody>, Response = http::Response<http::BoxBody>>,
+    M: Clone + Send + 'static,
+    M::Error: Into<Error>,
+    M::Future: Send,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<http::BoxBody>) -> Self::Future {
+        let mirror = match self.mirror.as_ref() {
+            Some(mirror) if rand::random::<f32>() < mirror.ratio => mirror,
+            _ => return self.inner.call(req),
+        };
+
+        let (head, body) = req.into_parts();
+        let body = match ReplayBody::try_new(body, MAX_BUFFERED_BYTES) {
+            Ok(body) => body,
+            Err(body) => {
+                debug!(
+                    size = body.size_hint().lower(),
+                    "Body is too large to mirror"
+                );
+                mirror.metrics.dropped.incr();
+                return self.inner.call(http::Request::from_parts(head, body));
+            }
+        };
+
+        let mut mirror_req = http::Request::new(());
+        *mirror_req.method_mut() = head.method.clone();
+        *mirror_req.uri_mut() = head.uri.clone();
+        *mirror_req.headers_mut() = head.headers.clone();
+        *mirror_req.version_mut() = head.version;
+
+        // The mirrored request is dispatched immediately. Its body replays
+        // the primary request's body as it is read.
+        let replay = body.clone();
+        let req = mirror_req.map(move |()| http::BoxBody::new(replay));
+        let service = mirror.service.clone();
+        let metrics = mirror.metrics.clone();
+        let capped = body.clone();
+        tokio::spawn(
+            async move {
+                match service.oneshot(req).await.map_err(Into::<Error>::into) {
+                    Ok(rsp) => {
+                        // Discard the mirrored response.
+                        let mut body = rsp.into_body();
+                        while let Some(Ok(_)) = body.data().await {}
+                    }
+                    Err(error) => {
+                        debug!(%error, "Mirrored request failed");
+                    }
+                }
+
+                if capped.is_capped() {
+                    debug!("Body exceeded the maximum buffer size; not mirrored");
+                    metrics.dropped.incr();
+                } else {
+                    metrics.mirrored.incr();
+                }
+            }
+            .in_current_span(),
+        );
+
+        self.inner
+            .call(http::Request::from_parts(head, http::BoxBody::new(body)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Buf;
+    use futures::future;
+    use linkerd_app_core::Infallible;
+    use tokio::sync::mpsc;
+
+    fn mirror<S, M>(inner: S, service: M, ratio: f32) -> (Mirror<M, S>, Arc<Counters>) {
+        let metrics = Arc::new(Counters::default());
+        let mirror = Mirror {
+            inner,
+            mirror: Some(Mirrored {
+                service,
+                ratio,
+                metrics: metrics.clone(),
+            }),
+        };
+        (mirror, metrics)
+    }
+
+    async fn read_request(
+        req: http::Request<http::BoxBody>,
+        tx: mpsc::UnboundedSender<(http::Method, bytes::Bytes)>,
+    ) -> Result<http::Response<http::BoxBody>, Error> {
+        let method = req.method().clone();
+        let body = hyper::body::to_bytes(req.into_body()).await?;
+        let _ = tx.send((method, body));
+        Ok(http::Response::new(http::BoxBody::default()))
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn mirrors_request_and_body() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let (primary_tx, mut primary_rx) = mpsc::unbounded_channel();
+        let (mirror_tx, mut mirror_rx) = mpsc::unbounded_channel();
+        let (svc, metrics) = mirror(
+            svc::mk(move |req| read_request(req, primary_tx.clone())),
+            svc::mk(move |req| read_request(req, mirror_tx.clone())),
+            1.0,
+        );
+
+        let req = http::Request::post("http://foo.example.com")
+            .body(http::BoxBody::new(hyper::Body::from("hello")))
+            .unwrap();
+        svc.oneshot(req).await.expect("request must succeed");
+
+        let (method, body) = primary_rx.recv().await.expect("primary request");
+        assert_eq!(method, http::Method::POST);
+        assert_eq!(body, "hello");
+
+        let (method, body) = mirror_rx.recv().await.expect("mirrored request");
+        assert_eq!(method, http::Method::POST);
+        assert_eq!(body, "hello");
+
+        // Metrics are recorded once the mirrored response completes.
+        while metrics.mirrored.value() == 0.0 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(metrics.mirrored.value(), 1.0);
+        assert_eq!(metrics.dropped.value(), 0.0);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn mirrors_while_primary_is_in_flight() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        // The primary request reads its body but never completes, so its body
+        // is never released.
+        let (primary_tx, mut primary_rx) = mpsc::unbounded_channel();
+        let primary = svc::mk(move |req: http::Request<http::BoxBody>| {
+            let tx = primary_tx.clone();
+            async move {
+                let mut body = req.into_body();
+                let mut buf = bytes::BytesMut::new();
+                while let Some(chunk) = body.data().await {
+                    buf.extend_from_slice(chunk?.chunk());
+                }
+                let _ = tx.send(buf.freeze());
+                future::pending::<()>().await;
+                drop(body);
+                Ok::<_, Error>(http::Response::new(http::BoxBody::default()))
+            }
+        });
+        let (mirror_tx, mut mirror_rx) = mpsc::unbounded_channel();
+        let (mut svc, _) = mirror(
+            primary,
+            svc::mk(move |req| read_request(req, mirror_tx.clone())),
+            1.0,
+        );
+
+        let req = http::Request::post("http://foo.example.com")
+            .body(http::BoxBody::new(hyper::Body::from("hello")))
+            .unwrap();
+        let rsp = svc::Service::call(svc.ready().await.unwrap(), req);
+        tokio::spawn(rsp);
+
+        let body = primary_rx.recv().await.expect("primary request");
+        assert_eq!(body, "hello");
+        let (_, body) = mirror_rx.recv().await.expect("mirrored request");
+        assert_eq!(body, "hello");
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn does_not_mirror_with_zero_ratio() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let (primary_tx, mut primary_rx) = mpsc::unbounded_channel();
+        let mirror_svc = svc::mk(|_: http::Request<http::BoxBody>| {
+            future::ok::<_, Infallible>(http::Response::new(http::BoxBody::default()))
+        });
+        let (svc, metrics) = mirror(
+            svc::mk(move |req| read_request(req, primary_tx.clone())),
+            mirror_svc,
+            0.0,
+        );
+
+        let req = http::Request::get("http://foo.example.com")
+            .body(http::BoxBody::default())
+            .unwrap();
+        svc.oneshot(req).await.expect("request must succeed");
+        primary_rx.recv().await.expect("primary request");
+
+        assert_eq!(metrics.mirrored.value(), 0.0);
+        assert_eq!(metrics.dropped.value(), 0.0);
+    }
+}
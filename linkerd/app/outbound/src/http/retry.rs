@@ -1,17 +1,43 @@
 use super::Route;
-use futures::future;
+use futures::{future, prelude::*};
 use linkerd_app_core::{
     classify,
+    exp_backoff::ExponentialBackoff,
     http_metrics::retries::Handle,
     metrics, profiles,
     proxy::http::{ClientHandle, HttpBody},
     svc::{layer, Either, Param},
-    Error,
+    trace_context, Error,
 };
 use linkerd_http_classify::{Classify, ClassifyEos, ClassifyResponse};
 use linkerd_http_retry::ReplayBody;
 use linkerd_retry as retry;
-use std::sync::Arc;
+use std::{pin::Pin, sync::Arc, time::SystemTime};
+use tokio::time::{self, Duration};
+
+/// Limits the attempts of requests on routes that may be retried and delays
+/// each retry.
+///
+/// The destination API does not describe these settings, so they apply to all
+/// of the proxy's retryable routes.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RetryConfig {
+    pub max_attempts: Option<usize>,
+    pub backoff: Option<ExponentialBackoff>,
+}
+
+/// Applies the retry configuration to a route, if the route may be retried.
+pub(super) fn configure_route(config: &RetryConfig, route: &mut profiles::http::Route) {
+    if let Some(mut retries) = route.retries().cloned() {
+        if let Some(max_attempts) = config.max_attempts {
+            retries = retries.with_max_attempts(max_attempts);
+        }
+        if let Some(backoff) = config.backoff {
+            retries = retries.with_backoff(backoff);
+        }
+        route.set_retries(retries);
+    }
+}
 
 pub fn layer<N>(
     metrics: metrics::HttpRouteRetry,
@@ -29,11 +55,19 @@ pub struct RetryPolicy {
     metrics: Handle,
     budget: Arc<retry::Budget>,
     response_classes: profiles::http::ResponseClasses,
+    max_attempts: Option<usize>,
+    backoff: Option<ExponentialBackoff>,
+    /// The number of times the current request has been sent.
+    attempts: usize,
 }
 
 /// Allow buffering requests up to 64 kb
 const MAX_BUFFERED_BYTES: usize = 64 * 1024;
 
+/// Responses that ask to be retried after a longer delay are returned to the
+/// client rather than holding the request.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(10);
+
 // === impl NewRetryPolicy ===
 
 impl NewRetryPolicy {
@@ -53,6 +87,9 @@ impl retry::NewPolicy<Route> for NewRetryPolicy {
             metrics,
             budget: retries.budget().clone(),
             response_classes: route.route.response_classes().clone(),
+            max_attempts: retries.max_attempts(),
+            backoff: retries.backoff().copied(),
+            attempts: 1,
         })
     }
 }
@@ -64,15 +101,15 @@ where
     A: HttpBody + Unpin,
     A::Error: Into<Error>,
 {
-    type Future = future::Ready<Self>;
+    type Future = Pin<Box<dyn Future<Output = Self> + Send + 'static>>;
 
     fn retry(
         &self,
         req: &http::Request<ReplayBody<A>>,
         result: Result<&http::Response<B>, &E>,
     ) -> Option<Self::Future> {
-        let retryable = match result {
-            Err(_) => false,
+        let (retryable, retry_after) = match result {
+            Err(_) => (false, None),
             Ok(rsp) => {
                 // is the request a failure?
                 let is_failure = classify::Request::from(self.response_classes.clone())
@@ -80,11 +117,13 @@ where
                     .start(rsp)
                     .eos(None)
                     .is_failure();
+                // did the server ask for the request to be retried later?
+                let retry_after = retry_after(rsp);
                 // did the body exceed the maximum length limit?
                 let exceeded_max_len = req.body().is_capped();
-                let retryable = is_failure && !exceeded_max_len;
-                tracing::trace!(is_failure, exceeded_max_len, retryable);
-                retryable
+                let retryable = (is_failure || retry_after.is_some()) && !exceeded_max_len;
+                tracing::trace!(is_failure, ?retry_after, exceeded_max_len, retryable);
+                (retryable, retry_after)
             }
         };
 
@@ -93,13 +132,44 @@ where
             return None;
         }
 
+        if let Some(max) = self.max_attempts {
+            if self.attempts >= max {
+                tracing::debug!(attempts = self.attempts, "Exceeded maximum attempts");
+                return None;
+            }
+        }
+
+        if let Some(retry_after) = retry_after {
+            if retry_after > MAX_RETRY_AFTER {
+                tracing::debug!(?retry_after, "Not retrying after a long delay");
+                return None;
+            }
+        }
+
         let withdrew = self.budget.withdraw().is_ok();
         self.metrics.incr_retryable(withdrew);
         if !withdrew {
             return None;
         }
 
-        Some(future::ready(self.clone()))
+        // Prefer the server's requested delay to the route's backoff.
+        let delay = retry_after.or_else(|| {
+            let backoff = self.backoff.as_ref()?;
+            let iterations = (self.attempts - 1).try_into().unwrap_or(u32::MAX);
+            Some(backoff.duration(iterations, &mut rand::thread_rng()))
+        });
+
+        let policy = Self {
+            attempts: self.attempts + 1,
+            ..self.clone()
+        };
+        match delay {
+            Some(delay) => {
+                tracing::trace!(?delay, "Delaying retry");
+                Some(Box::pin(time::sleep(delay).map(move |()| policy)))
+            }
+            None => Some(Box::pin(future::ready(policy))),
+        }
     }
 
     fn clone_request(
@@ -120,10 +190,46 @@ where
             clone.extensions_mut().insert(client_handle);
         }
 
+        if let Some(labels) = req.extensions().get::<trace_context::SpanLabels>() {
+            clone.extensions_mut().insert(labels.clone());
+        }
+        label_attempt(&mut clone, self.attempts + 1);
+
         Some(clone)
     }
 }
 
+/// If the request is traced, labels the spans of this attempt with its attempt
+/// number. The attempt's labels start with those of the parent span.
+fn label_attempt<B>(req: &mut http::Request<B>, attempt: usize) {
+    if let Some(parent) = req.extensions().get::<trace_context::SpanLabels>() {
+        let labels = parent.child();
+        labels.insert("retry.attempt", attempt.to_string());
+        req.extensions_mut().insert(labels);
+    }
+}
+
+/// Returns the delay requested by a 429 or 503 response's `Retry-After`
+/// header, if one is set.
+fn retry_after<B>(rsp: &http::Response<B>) -> Option<Duration> {
+    if rsp.status() != http::StatusCode::TOO_MANY_REQUESTS
+        && rsp.status() != http::StatusCode::SERVICE_UNAVAILABLE
+    {
+        return None;
+    }
+
+    let value = rsp
+        .headers()
+        .get(http::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?;
+    if let Ok(secs) = value.trim().parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(date.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
 impl<A, B, E> retry::PrepareRequest<http::Request<A>, http::Response<B>, E> for RetryPolicy
 where
     A: HttpBody + Unpin,
@@ -149,6 +255,146 @@ where
 
         // The body may still be too large to be buffered if the body's length was not known.
         // `ReplayBody` handles this gracefully.
-        Either::A(http::Request::from_parts(head, replay_body))
+        let mut req = http::Request::from_parts(head, replay_body);
+        label_attempt(&mut req, self.attempts);
+        Either::A(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::Infallible;
+    use retry::Policy;
+
+    fn policy(max_attempts: Option<usize>) -> RetryPolicy {
+        let route = profiles::http::Route::default();
+        let addr = profiles::LogicalAddr("foo.example.com:80".parse().unwrap());
+        let metrics = metrics::HttpRouteRetry::default()
+            .get_handle(metrics::RouteLabels::outbound(addr, &route));
+        RetryPolicy {
+            metrics,
+            budget: Arc::new(retry::Budget::new(Duration::from_secs(10), 10, 0.2)),
+            response_classes: route.response_classes().clone(),
+            max_attempts,
+            backoff: None,
+            attempts: 1,
+        }
+    }
+
+    fn request() -> http::Request<ReplayBody<hyper::Body>> {
+        let body = ReplayBody::try_new(hyper::Body::empty(), MAX_BUFFERED_BYTES).unwrap();
+        http::Request::get("http://foo.example.com")
+            .body(body)
+            .unwrap()
+    }
+
+    fn response(status: http::StatusCode) -> http::Response<()> {
+        http::Response::builder().status(status).body(()).unwrap()
+    }
+
+    #[test]
+    fn configures_retryable_routes() {
+        let config = RetryConfig {
+            max_attempts: Some(3),
+            backoff: None,
+        };
+
+        let mut route = profiles::http::Route::default();
+        configure_route(&config, &mut route);
+        assert!(
+            route.retries().is_none(),
+            "routes must not become retryable"
+        );
+
+        route.set_retries(profiles::http::Retries::new(Arc::new(retry::Budget::new(
+            Duration::from_secs(10),
+            10,
+            0.2,
+        ))));
+        configure_route(&config, &mut route);
+        assert_eq!(route.retries().unwrap().max_attempts(), Some(3));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn stops_after_max_attempts() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let req = request();
+        let rsp = response(http::StatusCode::INTERNAL_SERVER_ERROR);
+        let policy = policy(Some(2));
+        let policy = Policy::<_, _, Infallible>::retry(&policy, &req, Ok(&rsp))
+            .expect("first failure must be retried")
+            .await;
+        assert_eq!(policy.attempts, 2);
+        assert!(Policy::<_, _, Infallible>::retry(&policy, &req, Ok(&rsp)).is_none());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn retries_too_many_requests_after_delay() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let req = request();
+        let mut rsp = response(http::StatusCode::TOO_MANY_REQUESTS);
+        assert!(Policy::<_, _, Infallible>::retry(&policy(None), &req, Ok(&rsp)).is_none());
+
+        rsp.headers_mut().insert(
+            http::header::RETRY_AFTER,
+            http::HeaderValue::from_static("1"),
+        );
+        assert_eq!(retry_after(&rsp), Some(Duration::from_secs(1)));
+        assert!(Policy::<_, _, Infallible>::retry(&policy(None), &req, Ok(&rsp)).is_some());
+
+        rsp.headers_mut().insert(
+            http::header::RETRY_AFTER,
+            http::HeaderValue::from_static("3600"),
+        );
+        assert!(Policy::<_, _, Infallible>::retry(&policy(None), &req, Ok(&rsp)).is_none());
+    }
+
+    #[test]
+    fn retry_after_http_date() {
+        let date = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(60));
+        let mut rsp = response(http::StatusCode::SERVICE_UNAVAILABLE);
+        rsp.headers_mut()
+            .insert(http::header::RETRY_AFTER, date.parse().unwrap());
+        let delay = retry_after(&rsp).expect("must parse date");
+        assert!(delay > Duration::from_secs(50) && delay <= Duration::from_secs(60));
+
+        *rsp.status_mut() = http::StatusCode::OK;
+        assert_eq!(retry_after(&rsp), None);
+    }
+
+    #[test]
+    fn labels_attempts_of_traced_requests() {
+        let parent = trace_context::SpanLabels::default();
+        parent.insert("server.id", "web");
+        let mut req = http::Request::get("http://foo.example.com")
+            .body(hyper::Body::empty())
+            .unwrap();
+        req.extensions_mut().insert(parent.clone());
+
+        let policy = policy(None);
+        let req = match retry::PrepareRequest::<_, http::Response<()>, Infallible>::prepare_request(
+            &policy, req,
+        ) {
+            Either::A(req) => req,
+            Either::B(_) => panic!("request must be retryable"),
+        };
+        let labels = req.extensions().get::<trace_context::SpanLabels>().unwrap();
+        assert_eq!(labels.get("server.id").as_deref(), Some("web"));
+        assert_eq!(labels.get("retry.attempt").as_deref(), Some("1"));
+
+        let retry = Policy::<_, http::Response<()>, Infallible>::clone_request(&policy, &req)
+            .expect("request must be cloned");
+        let labels = retry
+            .extensions()
+            .get::<trace_context::SpanLabels>()
+            .unwrap();
+        assert_eq!(labels.get("server.id").as_deref(), Some("web"));
+        assert_eq!(labels.get("retry.attempt").as_deref(), Some("2"));
+
+        // The parent span is not labeled with the attempt number.
+        assert_eq!(parent.get("retry.attempt"), None);
     }
 }
@@ -1,7 +1,7 @@
 use super::{IdentityRequired, ProxyConnectionClose};
 use crate::{http, trace_labels, Outbound};
 use linkerd_app_core::{
-    config, errors, http_tracing,
+    concurrency_limit, config, errors, http_tracing,
     svc::{self, ExtractParam},
     Error, Result,
 };
@@ -35,6 +35,7 @@ impl<N> Outbound<N> {
     {
         self.map_stack(|config, rt, http| {
             let config::ProxyConfig {
+                server: config::ServerConfig { request_limits, .. },
                 dispatch_timeout,
                 max_in_flight_requests,
                 buffer_capacity,
@@ -54,14 +55,17 @@ impl<N> Outbound<N> {
                         .push(svc::ConcurrencyLimitLayer::new(max_in_flight_requests))
                         .push(svc::FailFast::layer("HTTP Server", dispatch_timeout))
                         .push_spawn_buffer(buffer_capacity)
+                        // Reject requests that exceed the server's size limits
+                        // before they are dispatched.
+                        .push(http::LimitRequests::layer(request_limits))
                         .push(rt.metrics.http_errors.to_layer())
                         // Tear down server connections when a peer proxy generates an error.
                         .push(ProxyConnectionClose::layer()),
                 )
                 // Synthesizes responses for proxy errors.
-                .check_new_service::<T, http::Request<_>>()
+                .check_new_service::<T, http::Request<http::BoxBody>>()
                 .push(ServerRescue::layer(config.emit_headers))
-                .check_new_service::<T, http::Request<_>>()
+                .check_new_service::<T, http::Request<http::BoxBody>>()
                 .push_on_service(
                     svc::layers()
                         // Initiates OpenCensus tracing.
@@ -114,6 +118,17 @@ impl errors::HttpRescue<Error> for ServerRescue {
         if cause.is::<errors::FailFastError>() {
             return Ok(errors::SyntheticHttpResponse::gateway_timeout(cause));
         }
+        if cause.is::<concurrency_limit::ConcurrencyLimited>() {
+            return Ok(errors::SyntheticHttpResponse::unavailable(cause));
+        }
+        if cause.is::<http::limits::HeadersTooLarge>() {
+            return Ok(errors::SyntheticHttpResponse::header_fields_too_large(
+                cause,
+            ));
+        }
+        if cause.is::<http::limits::BodyTooLarge>() {
+            return Ok(errors::SyntheticHttpResponse::payload_too_large(cause));
+        }
 
         if cause.is::<errors::H2Error>() {
             return Err(error);
@@ -0,0 +1,24 @@
+use linkerd_app_core::{
+    profiles::{self, LogicalAddr},
+    NameAddr,
+};
+
+/// Configures the routes of a logical destination to sample a ratio of the
+/// traces that the proxy starts for their requests.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceSamplingConfig {
+    pub logical: NameAddr,
+    pub ratio: f32,
+}
+
+/// Sets a route's trace sampling ratio if one is configured for its logical
+/// destination.
+pub(super) fn configure_route(
+    samplings: &[TraceSamplingConfig],
+    LogicalAddr(logical): &LogicalAddr,
+    route: &mut profiles::http::Route,
+) {
+    if let Some(config) = samplings.iter().find(|s| s.logical == *logical) {
+        route.set_trace_sampling(config.ratio);
+    }
+}
@@ -75,7 +75,12 @@ impl Outbound<svc::ArcNewHttp<http::Endpoint>> {
             allow_discovery,
             proxy:
                 ProxyConfig {
-                    server: ServerConfig { h2_settings, .. },
+                    server:
+                        ServerConfig {
+                            h2_settings,
+                            request_limits,
+                            ..
+                        },
                     dispatch_timeout,
                     max_in_flight_requests,
                     buffer_capacity,
@@ -203,12 +208,14 @@ impl Outbound<svc::ArcNewHttp<http::Endpoint>> {
             .push(http::NewNormalizeUri::layer())
             .push_on_service(
                 svc::layers()
+                    .push(http::BoxRequest::layer())
                     .push(http::MarkAbsoluteForm::layer())
                     // The concurrency-limit can force the service into fail-fast, but it need not
                     // be driven to readiness on a background task (i.e., by `SpawnReady`).
                     // Otherwise, the inner service is always ready (because it's a router).
                     .push(svc::ConcurrencyLimitLayer::new(max_in_flight_requests))
                     .push(svc::FailFast::layer("Ingress server", dispatch_timeout))
+                    .push(http::LimitRequests::layer(request_limits))
                     .push(rt.metrics.http_errors.to_layer()),
             )
             .push(http::ServerRescue::layer(config.emit_headers))
@@ -219,7 +226,11 @@ impl Outbound<svc::ArcNewHttp<http::Endpoint>> {
                     .push(http::BoxRequest::layer()),
             )
             .instrument(|a: &http::Accept| debug_span!("http", v = %a.protocol))
-            .push(http::NewServeHttp::layer(h2_settings, rt.drain))
+            .push(http::NewServeHttp::layer(
+                h2_settings,
+                request_limits,
+                rt.drain,
+            ))
             .push_request_filter(|(http, accept): (Option<http::Version>, _)| {
                 http.map(|h| http::Accept::from((h, accept)))
                     .ok_or(IngressHttpOnly)
@@ -0,0 +1,489 @@
+//! Records the outbound proxy's current view of its destinations so that it
+//! may be inspected via the admin server.
+
+use crate::{
+    endpoint::Endpoint,
+    http,
+    logical::{Concrete, Logical},
+    tcp,
+};
+use futures::{prelude::*, ready};
+use linkerd_app_core::{
+    cache,
+    profiles::{self, LogicalAddr},
+    proxy::{api_resolve::Metadata, core::Update, discover::Loads},
+    svc, tls, NameAddr,
+};
+use parking_lot::Mutex;
+use pin_project::pin_project;
+use serde_json::{json, Value};
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::SocketAddr,
+    pin::Pin,
+    sync::{Arc, Weak},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+/// Tracks the logical targets cached by the outbound stacks, along with the
+/// endpoints that each of their balancers has discovered.
+#[derive(Clone, Debug, Default)]
+pub struct Registry {
+    http: cache::Inspect<http::Logical>,
+    tcp: cache::Inspect<tcp::Logical>,
+    concretes: Arc<Mutex<HashMap<ConcreteKey, Weak<Balancer>>>>,
+}
+
+/// Describes a logical target's protocol.
+pub trait Protocol {
+    fn name(&self) -> &'static str;
+}
+
+/// Identifies a balancer by its logical and concrete addresses.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct ConcreteKey {
+    logical: LogicalAddr,
+    protocol: &'static str,
+    concrete: NameAddr,
+}
+
+/// The state of a single balancer, shared by its resolution and discovery
+/// streams.
+#[derive(Debug, Default)]
+struct Balancer {
+    endpoints: Mutex<Option<BTreeMap<SocketAddr, (Metadata, tls::ConditionalClientTls)>>>,
+    loads: Loads<SocketAddr>,
+}
+
+/// Records each concrete target's resolved endpoints.
+#[derive(Clone, Debug)]
+pub struct RecordResolve<R> {
+    inner: R,
+    registry: Registry,
+}
+
+#[pin_project]
+#[derive(Debug)]
+pub struct RecordResolveFuture<F> {
+    #[pin]
+    inner: F,
+    balancer: Option<Arc<Balancer>>,
+}
+
+#[pin_project]
+#[derive(Debug)]
+pub struct RecordResolution<R> {
+    #[pin]
+    inner: R,
+    balancer: Arc<Balancer>,
+}
+
+/// Pairs each concrete target's discovery stream with the `Loads` into which
+/// its balancer records endpoint loads.
+#[derive(Clone, Debug)]
+pub struct WithLoads<M> {
+    inner: M,
+    registry: Registry,
+}
+
+#[pin_project]
+#[derive(Debug)]
+pub struct WithLoadsFuture<F> {
+    #[pin]
+    inner: F,
+    balancer: Option<Arc<Balancer>>,
+}
+
+// === impl Registry ===
+
+impl Registry {
+    pub(crate) fn http_logicals(&self) -> cache::Inspect<http::Logical> {
+        self.http.clone()
+    }
+
+    pub(crate) fn tcp_logicals(&self) -> cache::Inspect<tcp::Logical> {
+        self.tcp.clone()
+    }
+
+    pub(crate) fn record_resolve<R>(
+        &self,
+    ) -> impl svc::Layer<R, Service = RecordResolve<R>> + Clone {
+        let registry = self.clone();
+        svc::layer::mk(move |inner| RecordResolve {
+            inner,
+            registry: registry.clone(),
+        })
+    }
+
+    pub(crate) fn with_loads<M>(&self) -> impl svc::Layer<M, Service = WithLoads<M>> + Clone {
+        let registry = self.clone();
+        svc::layer::mk(move |inner| WithLoads {
+            inner,
+            registry: registry.clone(),
+        })
+    }
+
+    /// Returns the balancer state for the given target, creating it if no
+    /// balancer is active.
+    fn balancer<P: Protocol>(&self, concrete: &Concrete<P>) -> Arc<Balancer> {
+        let key = ConcreteKey {
+            logical: concrete.logical.logical_addr.clone(),
+            protocol: concrete.logical.protocol.name(),
+            concrete: concrete.resolve.0.clone(),
+        };
+        let mut concretes = self.concretes.lock();
+        if let Some(balancer) = concretes.get(&key).and_then(Weak::upgrade) {
+            return balancer;
+        }
+        concretes.retain(|_, b| b.strong_count() > 0);
+        let balancer = Arc::new(Balancer::default());
+        concretes.insert(key, Arc::downgrade(&balancer));
+        balancer
+    }
+
+    /// Describes each logical target that is currently cached, as JSON.
+    pub fn to_json(&self) -> Value {
+        let mut logicals = self
+            .http
+            .idle_ages()
+            .into_iter()
+            .map(describe)
+            .chain(self.tcp.idle_ages().into_iter().map(describe))
+            .collect::<Vec<_>>();
+        logicals.sort_by(|(a, ap, ..), (b, bp, ..)| (a.to_string(), ap).cmp(&(b.to_string(), bp)));
+
+        let concretes = self
+            .concretes
+            .lock()
+            .iter()
+            .filter_map(|(k, b)| Some((k.clone(), b.upgrade()?)))
+            .collect::<Vec<_>>();
+
+        let logicals = logicals
+            .into_iter()
+            .map(|(addr, protocol, profile, idle)| {
+                let mut balancers = concretes
+                    .iter()
+                    .filter(|(k, _)| k.logical == addr && k.protocol == protocol)
+                    .map(|(k, b)| {
+                        json!({
+                            "addr": k.concrete.to_string(),
+                            "endpoints": b.to_json(),
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                balancers.sort_by_key(|b| b["addr"].as_str().map(String::from));
+                json!({
+                    "addr": addr.to_string(),
+                    "protocol": protocol,
+                    "idle_ms": idle.map(|d| d.as_millis() as u64),
+                    "profile": profile_to_json(profile.profile()),
+                    "concretes": balancers,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        json!({ "logicals": logicals })
+    }
+}
+
+// === impl Protocol ===
+
+impl Protocol for () {
+    fn name(&self) -> &'static str {
+        "opaque"
+    }
+}
+
+impl Protocol for http::Version {
+    fn name(&self) -> &'static str {
+        match self {
+            http::Version::Http1 => "http/1",
+            http::Version::H2 => "h2",
+        }
+    }
+}
+
+// === impl Balancer ===
+
+impl Balancer {
+    fn update<P>(&self, update: &Update<Endpoint<P>>) {
+        let mut endpoints = self.endpoints.lock();
+        match update {
+            Update::Reset(eps) => {
+                *endpoints = Some(
+                    eps.iter()
+                        .map(|(a, ep)| (*a, (ep.metadata.clone(), ep.tls.clone())))
+                        .collect(),
+                );
+            }
+            Update::Add(eps) => {
+                let endpoints = endpoints.get_or_insert_with(Default::default);
+                for (addr, ep) in eps {
+                    endpoints.insert(*addr, (ep.metadata.clone(), ep.tls.clone()));
+                }
+            }
+            Update::Remove(addrs) => {
+                let endpoints = endpoints.get_or_insert_with(Default::default);
+                for addr in addrs {
+                    endpoints.remove(addr);
+                }
+            }
+            Update::DoesNotExist => {
+                *endpoints = None;
+            }
+        }
+    }
+
+    /// Describes the balancer's endpoints, or `null` if the target does not
+    /// exist.
+    fn to_json(&self) -> Value {
+        let endpoints = self.endpoints.lock();
+        let endpoints = match &*endpoints {
+            Some(endpoints) => endpoints,
+            None => return Value::Null,
+        };
+        endpoints
+            .iter()
+            .map(|(addr, (metadata, tls))| {
+                json!({
+                    "addr": addr.to_string(),
+                    "labels": &*metadata.labels(),
+                    "protocol_hint": format!("{:?}", metadata.protocol_hint()),
+                    "identity": match tls {
+                        tls::ConditionalClientTls::Some(tls) => Value::from(tls.server_id.to_string()),
+                        tls::ConditionalClientTls::None(_) => Value::Null,
+                    },
+                    "opaque_transport_port": metadata.opaque_transport_port(),
+                    "authority_override": metadata.authority_override().map(|a| a.to_string()),
+                    "load": self.loads.get(addr),
+                })
+            })
+            .collect()
+    }
+}
+
+fn describe<P: Protocol>(
+    (logical, idle): (Logical<P>, Option<Duration>),
+) -> (
+    LogicalAddr,
+    &'static str,
+    profiles::Receiver,
+    Option<Duration>,
+) {
+    let protocol = logical.protocol.name();
+    (logical.logical_addr, protocol, logical.profile, idle)
+}
+
+fn profile_to_json(profile: profiles::Profile) -> Value {
+    let routes = profile
+        .http_routes
+        .iter()
+        .map(|(m, route)| {
+            json!({
+                "match": format!("{:?}", m),
+                "labels": &**route.labels(),
+                "timeout_ms": route.timeout().map(|t| t.as_millis() as u64),
+                "retries": route.retries().map(|r| json!({
+                    "max_attempts": r.max_attempts(),
+                    "backoff": r.backoff().map(|b| format!("{:?}", b)),
+                })),
+                "mirror": route.mirror().map(|m| json!({
+                    "addr": m.addr().to_string(),
+                    "ratio": m.ratio(),
+                })),
+                "hedge": route.hedge().map(|h| format!("{:?}", h)),
+                "trace_sampling": route.trace_sampling().map(|t| t.ratio()),
+            })
+        })
+        .collect::<Vec<_>>();
+    let targets = profile
+        .targets
+        .iter()
+        .map(|t| json!({ "addr": t.addr.to_string(), "weight": t.weight }))
+        .collect::<Vec<_>>();
+    json!({
+        "opaque_protocol": profile.opaque_protocol,
+        "routes": routes,
+        "targets": targets,
+        "endpoint": profile.endpoint.map(|(addr, _)| addr.to_string()),
+    })
+}
+
+// === impl RecordResolve ===
+
+impl<P, R> svc::Service<Concrete<P>> for RecordResolve<R>
+where
+    P: Protocol,
+    R: svc::Service<Concrete<P>>,
+{
+    type Response = RecordResolution<R::Response>;
+    type Error = R::Error;
+    type Future = RecordResolveFuture<R::Future>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, concrete: Concrete<P>) -> Self::Future {
+        let balancer = self.registry.balancer(&concrete);
+        RecordResolveFuture {
+            inner: self.inner.call(concrete),
+            balancer: Some(balancer),
+        }
+    }
+}
+
+impl<F, R, E> Future for RecordResolveFuture<F>
+where
+    F: TryFuture<Ok = R, Error = E>,
+{
+    type Output = Result<RecordResolution<R>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let inner = ready!(this.inner.try_poll(cx))?;
+        let balancer = this.balancer.take().expect("polled after ready");
+        Poll::Ready(Ok(RecordResolution { inner, balancer }))
+    }
+}
+
+impl<R, P, E> Stream for RecordResolution<R>
+where
+    R: TryStream<Ok = Update<Endpoint<P>>, Error = E>,
+{
+    type Item = Result<Update<Endpoint<P>>, E>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let update = ready!(this.inner.try_poll_next(cx));
+        if let Some(Ok(update)) = update.as_ref() {
+            this.balancer.update(update);
+        }
+        Poll::Ready(update)
+    }
+}
+
+// === impl WithLoads ===
+
+impl<P, M> svc::Service<Concrete<P>> for WithLoads<M>
+where
+    P: Protocol,
+    M: svc::Service<Concrete<P>>,
+{
+    type Response = (M::Response, Loads<SocketAddr>);
+    type Error = M::Error;
+    type Future = WithLoadsFuture<M::Future>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, concrete: Concrete<P>) -> Self::Future {
+        // The balancer is held until discovery starts so that it is shared
+        // with the resolution.
+        let balancer = self.registry.balancer(&concrete);
+        WithLoadsFuture {
+            inner: self.inner.call(concrete),
+            balancer: Some(balancer),
+        }
+    }
+}
+
+impl<F, D, E> Future for WithLoadsFuture<F>
+where
+    F: TryFuture<Ok = D, Error = E>,
+{
+    type Output = Result<(D, Loads<SocketAddr>), E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let discover = ready!(this.inner.try_poll(cx))?;
+        let balancer = this.balancer.take().expect("polled after ready");
+        Poll::Ready(Ok((discover, balancer.loads.clone())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::{
+        proxy::api_resolve::ConcreteAddr,
+        svc::{Layer, Service},
+    };
+    use std::str::FromStr;
+
+    fn concrete() -> tcp::Concrete {
+        let logical = LogicalAddr(NameAddr::from_str("foo.ns.svc.cluster.local:8080").unwrap());
+        let (_, profile) = tokio::sync::watch::channel(profiles::Profile {
+            addr: Some(logical.clone()),
+            ..Default::default()
+        });
+        Concrete {
+            resolve: ConcreteAddr(logical.0.clone()),
+            logical: Logical::new(logical, profile.into()),
+        }
+    }
+
+    fn endpoint(addr: SocketAddr) -> tcp::Endpoint {
+        Endpoint::from_metadata(
+            addr,
+            Metadata::default(),
+            tls::NoClientTls::NotProvidedByServiceDiscovery,
+            false,
+            &Default::default(),
+        )
+    }
+
+    #[tokio::test]
+    async fn records_endpoints() {
+        let registry = Registry::default();
+        let ep0 = SocketAddr::from(([10, 0, 0, 1], 8080));
+        let ep1 = SocketAddr::from(([10, 0, 0, 2], 8080));
+        let updates = vec![
+            Ok::<_, ()>(Update::Reset(vec![(ep0, endpoint(ep0))])),
+            Ok(Update::Add(vec![(ep1, endpoint(ep1))])),
+            Ok(Update::Remove(vec![ep0])),
+        ];
+        let mut resolve = registry
+            .record_resolve()
+            .layer(svc::mk(move |_: tcp::Concrete| {
+                future::ok::<_, ()>(futures::stream::iter(updates.clone()))
+            }));
+        let mut resolution = resolve.call(concrete()).await.unwrap();
+
+        // The balancer's state is shared by the resolution and discovery for
+        // the same target.
+        let (_, loads) = registry
+            .with_loads()
+            .layer(svc::mk(|_: tcp::Concrete| future::ok::<_, ()>(())))
+            .call(concrete())
+            .await
+            .unwrap();
+        assert_eq!(registry.concretes.lock().len(), 1);
+        let balancer = registry.balancer(&concrete());
+
+        resolution.next().await.unwrap().unwrap();
+        resolution.next().await.unwrap().unwrap();
+        let json = balancer.to_json();
+        assert_eq!(json[0]["addr"], "10.0.0.1:8080");
+        assert_eq!(json[1]["addr"], "10.0.0.2:8080");
+        assert_eq!(json[1]["load"], Value::Null);
+
+        resolution.next().await.unwrap().unwrap();
+        let json = balancer.to_json();
+        assert_eq!(json.as_array().unwrap().len(), 1);
+        assert_eq!(json[0]["addr"], "10.0.0.2:8080");
+
+        // Balancers are forgotten once their streams are dropped.
+        drop((resolution, loads, balancer));
+        assert!(registry
+            .concretes
+            .lock()
+            .values()
+            .all(|b| b.upgrade().is_none()));
+    }
+}
@@ -14,6 +14,7 @@ mod discover;
 pub mod endpoint;
 pub mod http;
 mod ingress;
+pub mod inspect;
 pub mod logical;
 mod metrics;
 mod resolve;
@@ -25,10 +26,9 @@ pub(crate) mod test_util;
 pub use self::metrics::Metrics;
 use futures::Stream;
 use linkerd_app_core::{
+    concurrency_limit,
     config::ProxyConfig,
-    drain,
-    http_tracing::OpenCensusSink,
-    identity, io, profiles,
+    drain, failure_accrual, http_tracing, identity, io, profiles,
     proxy::{
         api_resolve::{ConcreteAddr, Metadata},
         core::Resolve,
@@ -65,6 +65,30 @@ pub struct Config {
 
     // Whether the proxy may include informational headers on HTTP responses.
     pub emit_headers: bool,
+
+    // Configures when balanced endpoints are ejected after accruing failures.
+    // Endpoints are never ejected when unset.
+    pub failure_accrual: Option<failure_accrual::Config>,
+
+    // Configures adaptive concurrency limits for each HTTP logical service and
+    // for each balanced HTTP endpoint. Requests are not limited when unset.
+    pub http_logical_concurrency_limit: Option<concurrency_limit::Config>,
+    pub http_endpoint_concurrency_limit: Option<concurrency_limit::Config>,
+
+    // Configures logical destinations whose routes mirror a portion of their
+    // requests to another destination.
+    pub http_route_mirrors: Arc<[http::MirrorConfig]>,
+
+    // Configures logical destinations whose routes sample a ratio of the
+    // traces that the proxy starts.
+    pub http_route_trace_sampling: Arc<[http::TraceSamplingConfig]>,
+
+    // Configures the attempt limit and backoff of retryable HTTP routes.
+    pub http_route_retry: http::RetryConfig,
+
+    // Configures when requests on retryable HTTP routes are hedged. Requests
+    // are not hedged when unset.
+    pub http_route_hedge: Option<profiles::http::Hedge>,
 }
 
 #[derive(Clone, Debug)]
@@ -79,8 +103,10 @@ struct Runtime {
     metrics: Metrics,
     identity: identity::NewClient,
     tap: tap::Registry,
-    span_sink: OpenCensusSink,
+    span_sink: Option<http_tracing::SpanSink>,
     drain: drain::Watch,
+    connections: serve::Connections,
+    destinations: inspect::Registry,
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
@@ -101,6 +127,8 @@ impl Outbound<()> {
             tap: runtime.tap,
             span_sink: runtime.span_sink,
             drain: runtime.drain,
+            connections: runtime.connections,
+            destinations: inspect::Registry::default(),
         };
         Self {
             config,
@@ -113,6 +141,11 @@ impl Outbound<()> {
         self.runtime.metrics.clone()
     }
 
+    /// Returns a registry describing the outbound proxy's destinations.
+    pub fn destinations(&self) -> inspect::Registry {
+        self.runtime.destinations.clone()
+    }
+
     pub fn with_stack<S>(self, stack: S) -> Outbound<S> {
         self.map_stack(move |_, _, _| svc::stack(stack))
     }
@@ -179,7 +212,7 @@ impl Outbound<()> {
                 .push_http_endpoint()
                 .into_ingress(profiles, resolve);
             let shutdown = self.runtime.drain.signaled();
-            serve::serve(listen, stack, shutdown).await;
+            serve::serve(listen, stack, shutdown, self.runtime.connections).await;
         } else {
             let logical = self.to_tcp_connect().push_logical(resolve);
             let endpoint = self.to_tcp_connect().push_endpoint();
@@ -188,7 +221,7 @@ impl Outbound<()> {
                 .push_discover(profiles)
                 .into_inner();
             let shutdown = self.runtime.drain.signaled();
-            serve::serve(listen, server, shutdown).await;
+            serve::serve(listen, server, shutdown, self.runtime.connections).await;
         }
     }
 }
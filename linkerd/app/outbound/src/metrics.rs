@@ -8,7 +8,10 @@
 //! to be updated frequently or in a performance-critical area. We should probably look to use
 //! `DashMap` as we migrate other metrics registries.
 
+pub(crate) mod concurrency_limit;
 pub(crate) mod error;
+pub(crate) mod failure_accrual;
+pub(crate) mod mirror;
 
 pub use linkerd_app_core::metrics::*;
 
@@ -17,6 +20,9 @@ pub use linkerd_app_core::metrics::*;
 pub struct Metrics {
     pub(crate) http_errors: error::Http,
     pub(crate) tcp_errors: error::Tcp,
+    pub(crate) http_mirror: mirror::Mirror,
+    pub(crate) failure_accrual: failure_accrual::FailureAccrual,
+    pub(crate) concurrency_limits: concurrency_limit::ConcurrencyLimits,
 
     /// Holds metrics that are common to both inbound and outbound proxies. These metrics are
     /// reported separately
@@ -28,15 +34,21 @@ impl Metrics {
         Self {
             http_errors: error::Http::default(),
             tcp_errors: error::Tcp::default(),
+            http_mirror: mirror::Mirror::default(),
+            failure_accrual: failure_accrual::FailureAccrual::default(),
+            concurrency_limits: concurrency_limit::ConcurrencyLimits::default(),
             proxy,
         }
     }
 }
 
 impl FmtMetrics for Metrics {
-    fn fmt_metrics(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt_metrics(&self, f: &mut Encoder<'_>) -> std::fmt::Result {
         self.http_errors.fmt_metrics(f)?;
         self.tcp_errors.fmt_metrics(f)?;
+        self.http_mirror.fmt_metrics(f)?;
+        self.failure_accrual.fmt_metrics(f)?;
+        self.concurrency_limits.fmt_metrics(f)?;
 
         // XXX: Proxy metrics are reported elsewhere.
 
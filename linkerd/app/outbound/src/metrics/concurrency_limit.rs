@@ -0,0 +1,93 @@
+use linkerd_app_core::{
+    concurrency_limit::{Config, Limiter, WeakLimiter},
+    metrics::{metrics, Counter, Encoder, FmtLabels, FmtMetrics, Gauge},
+    profiles::LogicalAddr,
+};
+use parking_lot::Mutex;
+use std::{collections::HashMap, fmt, net::SocketAddr, sync::Arc};
+
+metrics! {
+    outbound_http_concurrency_limit: Gauge {
+        "The current concurrency limit of an outbound HTTP logical service or endpoint"
+    },
+    outbound_http_concurrency_limit_rejections_total: Counter {
+        "The total number of outbound HTTP requests that were shed because a logical service or endpoint was at its concurrency limit"
+    }
+}
+
+/// Tracks the adaptive concurrency limits of outbound HTTP logical services
+/// and endpoints.
+///
+/// Limiters are shared by all stacks that target the same logical address or
+/// endpoint address, and they are discarded once no stack references them.
+#[derive(Clone, Debug, Default)]
+pub struct ConcurrencyLimits(Arc<Mutex<HashMap<Key, WeakLimiter>>>);
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub(crate) enum Key {
+    Logical(LogicalAddr),
+    Endpoint(SocketAddr),
+}
+
+// === impl ConcurrencyLimits ===
+
+impl ConcurrencyLimits {
+    /// Obtains the limiter for `key`, creating it if necessary. Returns `None`
+    /// if limits are not configured.
+    pub(crate) fn limiter(&self, config: Option<Config>, key: Key) -> Option<Limiter> {
+        let config = config?;
+        let mut limiters = self.0.lock();
+        if let Some(limiter) = limiters.get(&key).and_then(WeakLimiter::upgrade) {
+            return Some(limiter);
+        }
+        limiters.retain(|_, l| l.upgrade().is_some());
+        let limiter = Limiter::new(config);
+        limiters.insert(key, limiter.downgrade());
+        Some(limiter)
+    }
+}
+
+impl FmtMetrics for ConcurrencyLimits {
+    fn fmt_metrics(&self, f: &mut Encoder<'_>) -> fmt::Result {
+        let limiters = self
+            .0
+            .lock()
+            .iter()
+            .filter_map(|(k, l)| Some((k.clone(), l.upgrade()?)))
+            .collect::<Vec<_>>();
+        if limiters.is_empty() {
+            return Ok(());
+        }
+
+        let limits = limiters
+            .iter()
+            .map(|(k, l)| (k, Gauge::from(l.limit() as u64)))
+            .collect::<Vec<_>>();
+        outbound_http_concurrency_limit.fmt_help(f)?;
+        outbound_http_concurrency_limit.fmt_scopes(
+            f,
+            limits.iter().map(|(k, g)| (*k, g)),
+            |g| g,
+        )?;
+
+        outbound_http_concurrency_limit_rejections_total.fmt_help(f)?;
+        outbound_http_concurrency_limit_rejections_total.fmt_scopes(
+            f,
+            limiters.iter().map(|(k, l)| (k, l)),
+            |l| l.rejections(),
+        )?;
+
+        Ok(())
+    }
+}
+
+// === impl Key ===
+
+impl FmtLabels for Key {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Logical(LogicalAddr(addr)) => write!(f, "scope=\"logical\",dst=\"{}\"", addr),
+            Self::Endpoint(addr) => write!(f, "scope=\"endpoint\",target_addr=\"{}\"", addr),
+        }
+    }
+}
@@ -4,14 +4,23 @@ mod tcp;
 pub(crate) use self::{http::Http, tcp::Tcp};
 use crate::http::IdentityRequired;
 use linkerd_app_core::{
-    errors::FailFastError, metrics::FmtLabels, proxy::http::ResponseTimeoutError,
+    concurrency_limit::ConcurrencyLimited,
+    errors::FailFastError,
+    metrics::FmtLabels,
+    proxy::http::{
+        limits::{BodyTooLarge, HeadersTooLarge},
+        ResponseTimeoutError,
+    },
 };
 use std::fmt;
 
 /// Outbound proxy error types.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 enum ErrorKind {
+    BodyTooLarge,
+    ConcurrencyLimited,
     FailFast,
+    HeadersTooLarge,
     IdentityRequired,
     Io,
     ResponseTimeout,
@@ -28,8 +37,14 @@ impl ErrorKind {
             ErrorKind::IdentityRequired
         } else if err.is::<FailFastError>() {
             ErrorKind::FailFast
+        } else if err.is::<ConcurrencyLimited>() {
+            ErrorKind::ConcurrencyLimited
         } else if err.is::<ResponseTimeoutError>() {
             ErrorKind::ResponseTimeout
+        } else if err.is::<HeadersTooLarge>() {
+            ErrorKind::HeadersTooLarge
+        } else if err.is::<BodyTooLarge>() {
+            ErrorKind::BodyTooLarge
         } else if let Some(e) = err.source() {
             Self::mk(e)
         } else {
@@ -44,7 +59,10 @@ impl FmtLabels for ErrorKind {
             f,
             "error=\"{}\"",
             match self {
+                ErrorKind::BodyTooLarge => "request body too large",
+                ErrorKind::ConcurrencyLimited => "concurrency limit",
                 ErrorKind::FailFast => "failfast",
+                ErrorKind::HeadersTooLarge => "request headers too large",
                 ErrorKind::IdentityRequired => "identity required",
                 ErrorKind::Io => "i/o",
                 ErrorKind::ResponseTimeout => "response timeout",
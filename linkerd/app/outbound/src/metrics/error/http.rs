@@ -1,6 +1,6 @@
 use super::ErrorKind;
 use linkerd_app_core::{
-    metrics::{metrics, Counter, FmtMetrics},
+    metrics::{metrics, Counter, Encoder, FmtMetrics},
     svc, Error,
 };
 use parking_lot::RwLock;
@@ -43,7 +43,7 @@ impl svc::stack::MonitorError<Error> for Http {
 }
 
 impl FmtMetrics for Http {
-    fn fmt_metrics(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt_metrics(&self, f: &mut Encoder<'_>) -> std::fmt::Result {
         let metrics = self.0.read();
         if metrics.is_empty() {
             return Ok(());
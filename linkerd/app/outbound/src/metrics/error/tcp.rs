@@ -1,6 +1,6 @@
 use super::ErrorKind;
 use linkerd_app_core::{
-    metrics::{metrics, Counter, FmtMetrics},
+    metrics::{metrics, Counter, Encoder, FmtMetrics},
     svc,
     transport::{labels::TargetAddr, OrigDstAddr},
     Error,
@@ -46,7 +46,7 @@ impl<T: svc::Param<OrigDstAddr>> svc::stack::MonitorNewService<T> for Tcp {
 }
 
 impl FmtMetrics for Tcp {
-    fn fmt_metrics(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt_metrics(&self, f: &mut Encoder<'_>) -> std::fmt::Result {
         let metrics = self.0.read();
         if metrics.is_empty() {
             return Ok(());
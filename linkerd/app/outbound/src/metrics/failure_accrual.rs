@@ -0,0 +1,29 @@
+use linkerd_app_core::metrics::{metrics, Counter, Encoder, FmtMetrics};
+use std::sync::Arc;
+
+metrics! {
+    outbound_http_balancer_endpoint_ejections_total: Counter {
+        "The total number of times an HTTP endpoint has been ejected from a balancer after accruing failures."
+    },
+    outbound_tcp_balancer_endpoint_ejections_total: Counter {
+        "The total number of times a TCP endpoint has been ejected from a balancer after accruing failures."
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct FailureAccrual {
+    pub(crate) http_ejections: Arc<Counter>,
+    pub(crate) tcp_ejections: Arc<Counter>,
+}
+
+// === impl FailureAccrual ===
+
+impl FmtMetrics for FailureAccrual {
+    fn fmt_metrics(&self, f: &mut Encoder<'_>) -> std::fmt::Result {
+        outbound_http_balancer_endpoint_ejections_total.fmt_help(f)?;
+        outbound_http_balancer_endpoint_ejections_total.fmt_metric(f, &*self.http_ejections)?;
+
+        outbound_tcp_balancer_endpoint_ejections_total.fmt_help(f)?;
+        outbound_tcp_balancer_endpoint_ejections_total.fmt_metric(f, &*self.tcp_ejections)
+    }
+}
@@ -0,0 +1,44 @@
+use linkerd_app_core::metrics::{metrics, Counter, Encoder, FmtMetrics, RouteLabels};
+use parking_lot::RwLock;
+use std::{collections::HashMap, sync::Arc};
+
+metrics! {
+    outbound_http_mirror_requests_total: Counter {
+        "The total number of outbound HTTP requests mirrored to another destination."
+    },
+    outbound_http_mirror_dropped_total: Counter {
+        "The total number of outbound HTTP requests that were selected for mirroring but could not be mirrored."
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Mirror(Arc<RwLock<HashMap<RouteLabels, Arc<Counters>>>>);
+
+#[derive(Debug, Default)]
+pub struct Counters {
+    pub(crate) mirrored: Counter,
+    pub(crate) dropped: Counter,
+}
+
+// === impl Mirror ===
+
+impl Mirror {
+    pub(crate) fn counters(&self, labels: RouteLabels) -> Arc<Counters> {
+        self.0.write().entry(labels).or_default().clone()
+    }
+}
+
+impl FmtMetrics for Mirror {
+    fn fmt_metrics(&self, f: &mut Encoder<'_>) -> std::fmt::Result {
+        let metrics = self.0.read();
+        if metrics.is_empty() {
+            return Ok(());
+        }
+
+        outbound_http_mirror_requests_total.fmt_help(f)?;
+        outbound_http_mirror_requests_total.fmt_scopes(f, metrics.iter(), |c| &c.mirrored)?;
+
+        outbound_http_mirror_dropped_total.fmt_help(f)?;
+        outbound_http_mirror_dropped_total.fmt_scopes(f, metrics.iter(), |c| &c.dropped)
+    }
+}
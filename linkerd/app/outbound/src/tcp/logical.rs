@@ -1,7 +1,7 @@
 use super::{Concrete, Endpoint, Logical};
 use crate::{endpoint, resolve, Outbound};
 use linkerd_app_core::{
-    config, drain, io, profiles,
+    config, drain, errors, failure_accrual, io, profiles,
     proxy::{
         api_resolve::{ConcreteAddr, Metadata},
         core::Resolve,
@@ -57,6 +57,7 @@ impl<C> Outbound<C> {
                         inner,
                     )
                 }))
+                .push(rt.destinations.record_resolve())
                 .check_service::<Concrete>()
                 .into_inner();
 
@@ -70,10 +71,18 @@ impl<C> Outbound<C> {
                         server.id = t.tls.value().map(|tls| tracing::field::display(&tls.server_id)),
                     )
                 })
+                // Ejects endpoints that fail to connect so that the balancer
+                // avoids them until they recover.
+                .push(failure_accrual::NewGate::layer(
+                    config.failure_accrual,
+                    rt.metrics.failure_accrual.tcp_ejections.clone(),
+                    failure_accrual::NewReportResult::new(is_connect_failure),
+                ))
                 .push(resolve::layer(resolve, config.proxy.cache_max_idle_age * 2))
+                .push(rt.destinations.with_loads())
                 .push_on_service(
                     svc::layers()
-                        .push(tcp::balance::layer(
+                        .push(tcp::balance::layer_recorded(
                             crate::EWMA_DEFAULT_RTT,
                             crate::EWMA_DECAY,
                         ))
@@ -103,7 +112,7 @@ impl<C> Outbound<C> {
                         .push(svc::FailFast::layer("TCP Logical", dispatch_timeout))
                         .push_spawn_buffer(buffer_capacity),
                 )
-                .push_cache(cache_max_idle_age)
+                .push_cache_inspect(cache_max_idle_age, rt.destinations.tcp_logicals())
                 .check_new_service::<Logical, I>()
                 .instrument(|_: &Logical| debug_span!("tcp"))
                 .check_new_service::<Logical, I>()
@@ -112,6 +121,33 @@ impl<C> Outbound<C> {
     }
 }
 
+/// Only failures to establish a connection count against an endpoint. Errors
+/// that are local to the proxy, e.g. when it has exhausted its file
+/// descriptors, are ignored.
+fn is_connect_failure(error: &Error) -> bool {
+    let cause = errors::root_cause(&**error);
+    if cause.is::<errors::ConnectTimeout>() {
+        return true;
+    }
+    cause
+        .downcast_ref::<std::io::Error>()
+        .map(|e| {
+            matches!(
+                e.kind(),
+                std::io::ErrorKind::ConnectionRefused
+                    | std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+                    | std::io::ErrorKind::NotConnected
+                    | std::io::ErrorKind::BrokenPipe
+                    | std::io::ErrorKind::TimedOut
+                    | std::io::ErrorKind::UnexpectedEof
+                    // TLS handshake failures.
+                    | std::io::ErrorKind::InvalidData
+            )
+        })
+        .unwrap_or(false)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -128,6 +164,21 @@ mod tests {
     use tokio::time;
 
     /// Tests that the logical stack forwards connections to services with a single endpoint.
+    #[test]
+    fn counts_connect_failures() {
+        let io_error =
+            |kind: std::io::ErrorKind| -> Error { std::io::Error::new(kind, "connect").into() };
+        assert!(is_connect_failure(&io_error(
+            std::io::ErrorKind::ConnectionRefused
+        )));
+        assert!(is_connect_failure(&io_error(std::io::ErrorKind::TimedOut)));
+        assert!(!is_connect_failure(&io_error(
+            std::io::ErrorKind::AddrNotAvailable
+        )));
+        assert!(!is_connect_failure(&io_error(std::io::ErrorKind::Other)));
+        assert!(!is_connect_failure(&"no identity".into()));
+    }
+
     #[tokio::test]
     async fn forward() {
         let _trace = linkerd_tracing::test::trace_init();
@@ -10,7 +10,7 @@ use linkerd_app_core::{
     IpMatch, IpNet, ProxyRuntime,
 };
 pub use linkerd_app_test as support;
-use std::{str::FromStr, time::Duration};
+use std::{str::FromStr, sync::Arc, time::Duration};
 
 pub(crate) fn default_config() -> Config {
     Config {
@@ -22,6 +22,7 @@ pub(crate) fn default_config() -> Config {
                 addr: ListenAddr(([0, 0, 0, 0], 0).into()),
                 keepalive: Keepalive(None),
                 h2_settings: h2::Settings::default(),
+                request_limits: Default::default(),
             },
             connect: config::ConnectConfig {
                 keepalive: Keepalive(None),
@@ -45,19 +46,30 @@ pub(crate) fn default_config() -> Config {
             detect_protocol_timeout: Duration::from_secs(3),
         },
         inbound_ips: Default::default(),
+        failure_accrual: None,
+        http_logical_concurrency_limit: None,
+        http_endpoint_concurrency_limit: None,
+        http_route_mirrors: Arc::new([]),
+        http_route_trace_sampling: Arc::new([]),
+        http_route_retry: Default::default(),
+        http_route_hedge: None,
     }
 }
 
 pub(crate) fn runtime() -> (ProxyRuntime, drain::Signal) {
     let (drain_tx, drain) = drain::channel();
     let (tap, _) = tap::new();
-    let (metrics, _) = metrics::Metrics::new(std::time::Duration::from_secs(10));
+    let (metrics, _) = metrics::Metrics::new(
+        std::time::Duration::from_secs(10),
+        metrics::latency::DEFAULT_SCHEMA,
+    );
     let runtime = ProxyRuntime {
         identity: linkerd_meshtls_rustls::creds::default_for_test().1.into(),
         metrics: metrics.proxy,
         tap,
         span_sink: None,
         drain,
+        connections: Default::default(),
     };
     (runtime, drain_tx)
 }
@@ -0,0 +1,376 @@
+//! Loads proxy settings from a configuration file that may be reloaded while
+//! the proxy runs.
+//!
+//! The file holds TOML key-value pairs named for the proxy's environment
+//! variables, so it can express any setting that the environment can. Values
+//! may be strings, numbers, booleans, or arrays of these, which are joined
+//! with commas. For example:
+//!
+//! ```toml
+//! LINKERD2_PROXY_LOG = "warn,linkerd=debug"
+//! LINKERD2_PROXY_INBOUND_DETECT_TIMEOUT = "5s"
+//! LINKERD2_PROXY_INBOUND_PORTS_REQUIRE_IDENTITY = [8080, 9090]
+//! ```
+//!
+//! Settings in the file take precedence over the environment. The file is
+//! re-read when it changes and when the proxy receives a `SIGHUP`. Only the
+//! log level and the inbound policies (the default policy, the ports that
+//! require identity or TLS, and the protocol detection timeout) are applied
+//! while the proxy runs. Changes to other settings, including the outbound
+//! connect and dispatch timeouts, take effect when the proxy restarts. A log
+//! level that is removed from the file reverts to the environment's level, or
+//! to the default level. Updates that do not form a valid configuration are
+//! ignored.
+
+use crate::env::{self, EnvError, Strings};
+use linkerd_app_core::{trace, Error};
+use linkerd_app_inbound::policy;
+use std::{
+    collections::HashMap,
+    fs,
+    future::Future,
+    io,
+    path::{Path, PathBuf},
+    pin::Pin,
+};
+use thiserror::Error;
+use tokio::time::{self, Duration};
+use tracing::{debug, info, warn};
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub path: PathBuf,
+    /// How often the file is checked for changes.
+    pub poll_interval: Duration,
+}
+
+pub type Task = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+/// Settings read from a configuration file, keyed by environment variable.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct File(HashMap<String, String>);
+
+/// Reads settings from a file, falling back to another source of settings.
+#[derive(Debug)]
+pub struct Layered<'a, S> {
+    file: &'a File,
+    fallback: &'a S,
+}
+
+#[derive(Debug, Error)]
+pub enum FileError {
+    #[error("failed to read configuration file: {0}")]
+    Io(#[from] io::Error),
+    #[error("invalid configuration file: {0}")]
+    Invalid(#[from] toml::de::Error),
+    #[error("invalid configuration file setting {key}: {reason}")]
+    Unsupported { key: String, reason: &'static str },
+}
+
+/// Applies reloaded settings to the running proxy.
+struct Reload {
+    policies: policy::Store,
+    log_level: trace::Handle,
+}
+
+// === impl Config ===
+
+impl Config {
+    /// Builds a task that watches the file and applies its settings.
+    pub fn build(self, policies: policy::Store, log_level: trace::Handle) -> Task {
+        let reload = Reload {
+            policies,
+            log_level,
+        };
+        Box::pin(reload.run(self))
+    }
+}
+
+// === impl File ===
+
+impl File {
+    /// Reads the file synchronously, i.e. while the proxy is starting.
+    pub fn read(path: &Path) -> Result<Self, FileError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(contents: &str) -> Result<Self, FileError> {
+        // Parsing a `Value`, unlike a map, rejects duplicate keys.
+        let table = match contents.parse::<toml::Value>()? {
+            toml::Value::Table(table) => table,
+            _ => unreachable!("TOML documents are tables"),
+        };
+        let settings = table
+            .into_iter()
+            .map(|(key, value)| match to_setting(&value) {
+                Ok(value) => Ok((key, value)),
+                Err(reason) => Err(FileError::Unsupported { key, reason }),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self(settings))
+    }
+
+    /// Returns settings that are read from this file or, if a setting is not
+    /// in the file, from `fallback`.
+    pub fn layer<'a, S: Strings>(&'a self, fallback: &'a S) -> Layered<'a, S> {
+        Layered {
+            file: self,
+            fallback,
+        }
+    }
+}
+
+/// Formats a TOML value as an environment variable would express it.
+fn to_setting(value: &toml::Value) -> Result<String, &'static str> {
+    use toml::Value;
+
+    match value {
+        Value::Array(items) => {
+            let items = items
+                .iter()
+                .map(|item| match item {
+                    Value::Array(_) => Err("nested arrays are not supported"),
+                    item => to_setting(item),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(items.join(","))
+        }
+        Value::String(s) => Ok(s.clone()),
+        Value::Integer(i) => Ok(i.to_string()),
+        Value::Float(f) => Ok(f.to_string()),
+        Value::Boolean(b) => Ok(b.to_string()),
+        Value::Datetime(_) => Err("datetimes are not supported"),
+        Value::Table(_) => Err("tables are not supported"),
+    }
+}
+
+// === impl Layered ===
+
+impl<S: Strings> Strings for Layered<'_, S> {
+    fn get(&self, key: &str) -> Result<Option<String>, EnvError> {
+        match self.file.0.get(key) {
+            Some(value) => Ok(Some(value.clone())),
+            None => self.fallback.get(key),
+        }
+    }
+}
+
+// === impl Reload ===
+
+impl Reload {
+    async fn run(self, config: Config) {
+        let mut signal = linkerd_signal::Reload::new();
+        let mut interval = time::interval(config.poll_interval);
+        interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+
+        // The first tick completes immediately so that settings that are not
+        // read at startup, like the log level, are applied.
+        let mut last = None;
+        loop {
+            let requested = tokio::select! {
+                _ = interval.tick() => false,
+                () = signal.recv() => true,
+            };
+
+            let contents = match tokio::fs::read_to_string(&config.path).await {
+                Ok(contents) => contents,
+                Err(error) => {
+                    // Avoid warning on every poll while the file is missing.
+                    if requested || last.is_some() {
+                        warn!(%error, path = %config.path.display(), "Failed to read configuration file");
+                    }
+                    last = None;
+                    continue;
+                }
+            };
+            if !requested && last.as_ref() == Some(&contents) {
+                continue;
+            }
+            debug!(path = %config.path.display(), "Reloading configuration");
+
+            match self.apply(&contents, &env::Env) {
+                Ok(()) => info!(path = %config.path.display(), "Applied configuration file"),
+                Err(error) => warn!(%error, "Ignoring invalid configuration"),
+            }
+            last = Some(contents);
+        }
+    }
+
+    /// Applies the live settings of a valid configuration.
+    ///
+    /// The entire configuration is validated before any setting is applied.
+    fn apply(&self, contents: &str, fallback: &impl Strings) -> Result<(), Error> {
+        let file = File::parse(contents)?;
+        let strings = file.layer(fallback);
+        let config = env::parse_config(&strings)?;
+
+        if let Some(handle) = self.log_level.level() {
+            let level = strings
+                .get(env::ENV_LOG)?
+                .unwrap_or_else(|| trace::DEFAULT_LOG_LEVEL.to_string());
+            handle.set_level(level)?;
+        }
+
+        config.inbound.policy.reload(&self.policies);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct NoEnv;
+
+    impl Strings for NoEnv {
+        fn get(&self, _: &str) -> Result<Option<String>, EnvError> {
+            Ok(None)
+        }
+    }
+
+    #[test]
+    fn parses_settings() {
+        let file = File::parse(
+            r#"
+            # A comment.
+            LINKERD2_PROXY_LOG = "warn,linkerd=debug" # A trailing comment.
+            LINKERD2_PROXY_INBOUND_DETECT_TIMEOUT = '5s'
+            LINKERD2_PROXY_INBOUND_PORTS_REQUIRE_IDENTITY = [8080, 9_090]
+            LINKERD2_PROXY_DESTINATION_PROFILE_SUFFIXES = ["svc.cluster.local.", "a\"b#"]
+            LINKERD2_PROXY_TAP_DISABLED = true
+            "#,
+        )
+        .expect("file must parse");
+
+        let strings = file.layer(&NoEnv);
+        let get = |key| strings.get(key).unwrap();
+        assert_eq!(get("LINKERD2_PROXY_LOG").unwrap(), "warn,linkerd=debug");
+        assert_eq!(get("LINKERD2_PROXY_INBOUND_DETECT_TIMEOUT").unwrap(), "5s");
+        assert_eq!(
+            get("LINKERD2_PROXY_INBOUND_PORTS_REQUIRE_IDENTITY").unwrap(),
+            "8080,9090"
+        );
+        assert_eq!(
+            get("LINKERD2_PROXY_DESTINATION_PROFILE_SUFFIXES").unwrap(),
+            "svc.cluster.local.,a\"b#"
+        );
+        assert_eq!(get("LINKERD2_PROXY_TAP_DISABLED").unwrap(), "true");
+        assert_eq!(get("LINKERD2_PROXY_BUFFER_CAPACITY"), None);
+    }
+
+    #[test]
+    fn rejects_invalid_files() {
+        for contents in [
+            "A = unquoted",
+            "A = 1\nA = 2",
+            "A",
+            "A = \"unterminated",
+            "A = [1, 2",
+            "A = 1 2",
+            "A B = 1",
+        ] {
+            match File::parse(contents) {
+                Err(FileError::Invalid(_)) => {}
+                res => panic!("{:?} must be invalid: {:?}", contents, res),
+            }
+        }
+
+        for (contents, key) in [
+            ("\n[table]", "table"),
+            ("A = [[1], [2]]", "A"),
+            ("A = 1979-05-27T07:32:00Z", "A"),
+        ] {
+            match File::parse(contents) {
+                Err(FileError::Unsupported { key: k, .. }) => assert_eq!(k, key, "{:?}", contents),
+                res => panic!("{:?} must be unsupported: {:?}", contents, res),
+            }
+        }
+    }
+
+    #[test]
+    fn reloads_settings() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let policies = policy::Store::fixed(
+            policy::DefaultPolicy::Allow(policy::defaults::all_unauthenticated(
+                Duration::from_secs(10),
+            )),
+            None,
+        );
+        let (_dispatch, log_level) = linkerd_tracing::Settings::default().build();
+        let reload = Reload {
+            policies: policies.clone(),
+            log_level: log_level.clone(),
+        };
+        let level = || log_level.level().unwrap().current().unwrap();
+        let default_level = level();
+
+        // Identity settings must refer to files that exist.
+        let dir = std::env::temp_dir().join(format!("linkerd-config-file-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for file in ["key.p8", "csr.der", "token"] {
+            fs::write(dir.join(file), "test").unwrap();
+        }
+        let required = format!(
+            r#"
+            LINKERD2_PROXY_DESTINATION_SVC_ADDR = "dst.linkerd.svc.cluster.local:8086"
+            LINKERD2_PROXY_DESTINATION_SVC_NAME = "dst.linkerd.serviceaccount.identity.linkerd.cluster.local"
+            LINKERD2_PROXY_IDENTITY_SVC_ADDR = "identity.linkerd.svc.cluster.local:8080"
+            LINKERD2_PROXY_IDENTITY_SVC_NAME = "identity.linkerd.serviceaccount.identity.linkerd.cluster.local"
+            LINKERD2_PROXY_IDENTITY_TRUST_ANCHORS = "test"
+            LINKERD2_PROXY_IDENTITY_DIR = '{dir}'
+            LINKERD2_PROXY_IDENTITY_TOKEN_FILE = '{dir}/token'
+            LINKERD2_PROXY_IDENTITY_LOCAL_NAME = "foo.ns.serviceaccount.identity.linkerd.cluster.local"
+            LINKERD2_PROXY_INBOUND_PORTS = "8080"
+            "#,
+            dir = dir.display()
+        );
+
+        let port = |port: u16| {
+            let json = policies.to_json();
+            json["ports"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|p| p["port"] == port)
+                .map(|p| p["policy"].clone())
+        };
+
+        reload
+            .apply(
+                &format!(
+                    "{}\nLINKERD2_PROXY_INBOUND_PORTS_REQUIRE_IDENTITY = [8080]\nLINKERD2_PROXY_LOG = \"debug\"",
+                    required
+                ),
+                &NoEnv,
+            )
+            .expect("configuration must be valid");
+        assert_eq!(port(8080).unwrap()["name"], "all-authenticated");
+        assert_eq!(level(), "debug");
+
+        // Invalid updates are not applied.
+        reload
+            .apply(
+                &format!(
+                    "{}\nLINKERD2_PROXY_INBOUND_PORTS_REQUIRE_IDENTITY = [\"http\"]",
+                    required
+                ),
+                &NoEnv,
+            )
+            .expect_err("configuration must be invalid");
+        assert_eq!(port(8080).unwrap()["name"], "all-authenticated");
+        assert_eq!(level(), "debug");
+
+        reload
+            .apply(&required, &NoEnv)
+            .expect("configuration must be valid");
+        assert_eq!(port(8080), None);
+        assert_eq!(
+            level(),
+            default_level,
+            "a removed log level must revert to the default"
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
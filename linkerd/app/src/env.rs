@@ -1,13 +1,16 @@
 use crate::core::{
-    addr,
+    addr, concurrency_limit,
     config::*,
     control::{Config as ControlConfig, ControlAddr},
-    proxy::http::{h1, h2},
-    tls,
+    failure_accrual, metrics, profiles,
+    proxy::http::{self, h1, h2},
+    tls, trace_context,
     transport::{Keepalive, ListenAddr},
-    Addr, AddrMatch, Conditional, IpNet,
+    Addr, AddrMatch, Conditional, IpNet, NameAddr,
+};
+use crate::{
+    config_file, dns, gateway, identity, inbound, metrics_export, outbound, trace_collector,
 };
-use crate::{dns, gateway, identity, inbound, oc_collector, outbound};
 use inbound::policy;
 use std::{
     collections::{HashMap, HashSet},
@@ -84,6 +87,28 @@ pub enum ParseError {
     InvalidTrustAnchors,
     #[error("not a valid port policy: {0}")]
     InvalidPortPolicy(String),
+    #[error("not a valid header name: {0}")]
+    InvalidHeaderName(String),
+    #[error("not a valid HTTP URL: {0}")]
+    InvalidHttpUrl(String),
+    #[error("not a valid label: {0}")]
+    InvalidLabel(String),
+    #[error("not a valid HTTP route: {0}")]
+    InvalidHttpRoute(String),
+    #[error("not a valid port JWT authorization: {0}")]
+    InvalidPortJwt(String),
+    #[error("not a valid rate limit: {0}")]
+    InvalidRateLimit(String),
+    #[error("not a valid route mirror: {0}")]
+    InvalidRouteMirror(String),
+    #[error("not a valid route trace sampling ratio: {0}")]
+    InvalidRouteTraceSampling(String),
+    #[error("not a valid native histogram schema")]
+    InvalidNativeHistogramSchema,
+    #[error(transparent)]
+    InvalidTracePropagation(#[from] trace_context::InvalidPropagation),
+    #[error(transparent)]
+    InvalidTraceProtocol(#[from] trace_collector::InvalidProtocol),
 }
 
 // Environment variables to look at when loading the configuration
@@ -94,8 +119,45 @@ pub const ENV_ADMIN_LISTEN_ADDR: &str = "LINKERD2_PROXY_ADMIN_LISTEN_ADDR";
 
 pub const ENV_METRICS_RETAIN_IDLE: &str = "LINKERD2_PROXY_METRICS_RETAIN_IDLE";
 
+/// Configures the initial resolution of native latency histograms, as a
+/// Prometheus schema between -4 and 4. Each increment of the schema doubles the
+/// number of buckets per power of two.
+pub const ENV_METRICS_NATIVE_HISTOGRAM_SCHEMA: &str =
+    "LINKERD2_PROXY_METRICS_NATIVE_HISTOGRAM_SCHEMA";
+
+/// Limits how long a drain waits for the proxy's connections to close before
+/// the proxy shuts down.
+pub const ENV_SHUTDOWN_GRACE_PERIOD: &str = "LINKERD2_PROXY_SHUTDOWN_GRACE_PERIOD";
+
 const ENV_INGRESS_MODE: &str = "LINKERD2_PROXY_INGRESS_MODE";
 
+/// Configures logical destinations whose routes mirror a ratio of their
+/// requests to another destination, as a comma-separated list of
+/// `<logical>=<mirror>@<ratio>` entries.
+const ENV_OUTBOUND_HTTP_ROUTE_MIRRORS: &str = "LINKERD2_PROXY_OUTBOUND_HTTP_ROUTE_MIRRORS";
+
+/// Configures logical destinations whose routes sample a ratio of the traces
+/// that the proxy starts, as a comma-separated list of `<logical>=<ratio>`
+/// entries.
+const ENV_OUTBOUND_HTTP_ROUTE_TRACE_SAMPLING: &str =
+    "LINKERD2_PROXY_OUTBOUND_HTTP_ROUTE_TRACE_SAMPLING";
+
+/// Limits the number of times a request on a retryable route may be sent,
+/// including the original request.
+const ENV_OUTBOUND_HTTP_ROUTE_RETRY_MAX_ATTEMPTS: &str =
+    "LINKERD2_PROXY_OUTBOUND_HTTP_ROUTE_RETRY_MAX_ATTEMPTS";
+
+/// Hedges requests on retryable routes that have not received a response
+/// within a fixed latency.
+const ENV_OUTBOUND_HTTP_ROUTE_HEDGE_LATENCY: &str =
+    "LINKERD2_PROXY_OUTBOUND_HTTP_ROUTE_HEDGE_LATENCY";
+
+/// Hedges requests on retryable routes that have not received a response
+/// within the given percentile (between 0.0 and 1.0) of recently observed
+/// latencies.
+const ENV_OUTBOUND_HTTP_ROUTE_HEDGE_PERCENTILE: &str =
+    "LINKERD2_PROXY_OUTBOUND_HTTP_ROUTE_HEDGE_PERCENTILE";
+
 const ENV_INBOUND_DISPATCH_TIMEOUT: &str = "LINKERD2_PROXY_INBOUND_DISPATCH_TIMEOUT";
 const ENV_OUTBOUND_DISPATCH_TIMEOUT: &str = "LINKERD2_PROXY_OUTBOUND_DISPATCH_TIMEOUT";
 
@@ -126,8 +188,49 @@ pub const ENV_OUTBOUND_MAX_IN_FLIGHT: &str = "LINKERD2_PROXY_OUTBOUND_MAX_IN_FLI
 const ENV_OUTBOUND_DISABLE_INFORMATIONAL_HEADERS: &str =
     "LINKERD2_PROXY_OUTBOUND_DISABLE_INFORMATIONAL_HEADERS";
 
+/// Configures balanced endpoints to be ejected after this many consecutive
+/// failures.
+///
+/// May not be set along with `LINKERD2_PROXY_OUTBOUND_FAILURE_ACCRUAL_MAX_FAILURE_RATE`.
+/// If neither is set, endpoints are never ejected.
+const ENV_OUTBOUND_FAILURE_ACCRUAL_CONSECUTIVE_FAILURES: &str =
+    "LINKERD2_PROXY_OUTBOUND_FAILURE_ACCRUAL_CONSECUTIVE_FAILURES";
+
+/// Configures balanced endpoints to be ejected when the ratio of failed
+/// requests (between 0.0 and 1.0) reaches this value.
+const ENV_OUTBOUND_FAILURE_ACCRUAL_MAX_FAILURE_RATE: &str =
+    "LINKERD2_PROXY_OUTBOUND_FAILURE_ACCRUAL_MAX_FAILURE_RATE";
+const ENV_OUTBOUND_FAILURE_ACCRUAL_MIN_REQUESTS: &str =
+    "LINKERD2_PROXY_OUTBOUND_FAILURE_ACCRUAL_MIN_REQUESTS";
+const ENV_OUTBOUND_FAILURE_ACCRUAL_WINDOW: &str = "LINKERD2_PROXY_OUTBOUND_FAILURE_ACCRUAL_WINDOW";
+
 pub const ENV_TRACE_ATTRIBUTES_PATH: &str = "LINKERD2_PROXY_TRACE_ATTRIBUTES_PATH";
 
+/// Configures the trace context propagation formats read from requests, in
+/// order of preference.
+///
+/// This is a comma-separated list of `b3`, `w3c`, and `grpc`. By default, all
+/// formats are read, preferring `grpc`, then `b3`, then `w3c`.
+pub const ENV_TRACE_PROPAGATION: &str = "LINKERD2_PROXY_TRACE_PROPAGATION";
+
+/// Configures the protocol used to export spans to the trace collector.
+///
+/// Either `opencensus` (the default) or `opentelemetry`.
+pub const ENV_TRACE_PROTOCOL: &str = "LINKERD2_PROXY_TRACE_PROTOCOL";
+
+/// Enables head sampling: the proxy starts a trace for each request that has
+/// no trace context, sampling the given ratio (between 0.0 and 1.0) of them.
+///
+/// Requests with a trace context keep their parent's sampling decision. When
+/// head sampling is disabled (the default), requests without a trace context
+/// are not traced.
+pub const ENV_TRACE_SAMPLE_RATIO: &str = "LINKERD2_PROXY_TRACE_SAMPLE_RATIO";
+
+/// Limits the number of traces started by the proxy that are sampled each
+/// second. If set without `LINKERD2_PROXY_TRACE_SAMPLE_RATIO`, all traces are
+/// sampled up to this limit.
+pub const ENV_TRACE_SAMPLE_MAX_PER_SECOND: &str = "LINKERD2_PROXY_TRACE_SAMPLE_MAX_PER_SECOND";
+
 /// Constrains which destination names may be used for profile/route discovery.
 ///
 /// The value is a comma-separated list of domain name suffixes that may be
@@ -164,6 +267,34 @@ pub const ENV_INBOUND_PORTS_REQUIRE_IDENTITY: &str =
 
 pub const ENV_INBOUND_PORTS_REQUIRE_TLS: &str = "LINKERD2_PROXY_INBOUND_PORTS_REQUIRE_TLS";
 
+/// Configures HTTP routes whose requests are authorized by a different policy
+/// than the rest of the server's requests, as a comma-separated list of
+/// `<path-prefix>=<policy>` entries. The policy must be a valid default
+/// policy (e.g. `deny` or `all-authenticated`).
+///
+/// These routes apply to every inbound server.
+pub const ENV_INBOUND_HTTP_ROUTES: &str = "LINKERD2_PROXY_INBOUND_HTTP_ROUTES";
+
+/// Configures inbound servers that authorize HTTP requests with JWT bearer
+/// tokens, as a comma-separated list of `<port>=<jwks-path>` entries. Each
+/// entry may be followed by `;issuer=<issuer>` and any number of
+/// `;audience=<audience>` settings that tokens must match.
+///
+/// Requests with a valid token are authorized from all networks, in addition
+/// to the server's other authorizations.
+pub const ENV_INBOUND_PORTS_JWT: &str = "LINKERD2_PROXY_INBOUND_PORTS_JWT";
+
+/// Limits the rate at which each inbound server admits HTTP requests, as
+/// `<requests-per-second>[/<burst>]`. The burst defaults to the rate.
+///
+/// This applies to servers whose policies do not configure a rate limit.
+pub const ENV_INBOUND_HTTP_RATE_LIMIT_TOTAL: &str = "LINKERD2_PROXY_INBOUND_HTTP_RATE_LIMIT_TOTAL";
+
+/// Limits the rate at which each inbound server admits HTTP requests from
+/// each client identity, as `<requests-per-second>[/<burst>]`.
+pub const ENV_INBOUND_HTTP_RATE_LIMIT_IDENTITY: &str =
+    "LINKERD2_PROXY_INBOUND_HTTP_RATE_LIMIT_IDENTITY";
+
 /// Configures the default port policy for inbound connections.
 ///
 /// This must parse to a valid port policy (one of: `deny`, `authenticated`,
@@ -179,6 +310,12 @@ pub const ENV_POLICY_CLUSTER_NETWORKS: &str = "LINKERD2_PROXY_POLICY_CLUSTER_NET
 
 pub const ENV_INBOUND_IPS: &str = "LINKERD2_PROXY_INBOUND_IPS";
 
+/// Constrains which request headers are recorded in the inbound access log.
+///
+/// This is a comma-separated list of header names. By default, no headers
+/// are recorded.
+pub const ENV_ACCESS_LOG_HEADERS: &str = "LINKERD2_PROXY_ACCESS_LOG_HEADERS";
+
 pub const ENV_IDENTITY_DISABLED: &str = "LINKERD2_PROXY_IDENTITY_DISABLED";
 pub const ENV_IDENTITY_DIR: &str = "LINKERD2_PROXY_IDENTITY_DIR";
 pub const ENV_IDENTITY_TRUST_ANCHORS: &str = "LINKERD2_PROXY_IDENTITY_TRUST_ANCHORS";
@@ -195,6 +332,28 @@ pub const ENV_HOSTNAME: &str = "HOSTNAME";
 
 pub const ENV_TRACE_COLLECTOR_SVC_BASE: &str = "LINKERD2_PROXY_TRACE_COLLECTOR_SVC";
 
+/// Configures a Prometheus remote-write URL to which metrics are periodically
+/// pushed. Metrics are not exported unless this is set.
+pub const ENV_METRICS_EXPORT_URL: &str = "LINKERD2_PROXY_METRICS_EXPORT_URL";
+const ENV_METRICS_EXPORT_INTERVAL: &str = "LINKERD2_PROXY_METRICS_EXPORT_INTERVAL";
+const ENV_METRICS_EXPORT_TIMEOUT: &str = "LINKERD2_PROXY_METRICS_EXPORT_TIMEOUT";
+/// Configures the number of snapshots retained while the remote-write
+/// endpoint is unavailable.
+const ENV_METRICS_EXPORT_BUFFER_CAPACITY: &str = "LINKERD2_PROXY_METRICS_EXPORT_BUFFER_CAPACITY";
+/// A comma-separated list of `name=value` labels added to exported metrics.
+///
+/// If no `instance` label is configured, the proxy's hostname is used.
+const ENV_METRICS_EXPORT_LABELS: &str = "LINKERD2_PROXY_METRICS_EXPORT_LABELS";
+
+/// The tracing filter. This is read by `linkerd_tracing` at startup, and is
+/// applied again when it is set in the configuration file.
+pub const ENV_LOG: &str = "LINKERD2_PROXY_LOG";
+
+/// Configures a file of settings that take precedence over the environment,
+/// some of which may be reloaded while the proxy runs.
+pub const ENV_CONFIG_FILE: &str = "LINKERD2_PROXY_CONFIG_FILE";
+const ENV_CONFIG_FILE_POLL_INTERVAL: &str = "LINKERD2_PROXY_CONFIG_FILE_POLL_INTERVAL";
+
 pub const ENV_DESTINATION_CONTEXT: &str = "LINKERD2_PROXY_DESTINATION_CONTEXT";
 pub const ENV_DESTINATION_PROFILE_INITIAL_TIMEOUT: &str =
     "LINKERD2_PROXY_DESTINATION_PROFILE_INITIAL_TIMEOUT";
@@ -224,6 +383,7 @@ pub const DEFAULT_INBOUND_LISTEN_ADDR: &str = "0.0.0.0:4143";
 pub const DEFAULT_CONTROL_LISTEN_ADDR: &str = "0.0.0.0:4190";
 const DEFAULT_ADMIN_LISTEN_ADDR: &str = "127.0.0.1:4191";
 const DEFAULT_METRICS_RETAIN_IDLE: Duration = Duration::from_secs(10 * 60);
+const DEFAULT_SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(2 * 60);
 const DEFAULT_INBOUND_DISPATCH_TIMEOUT: Duration = Duration::from_secs(1);
 const DEFAULT_INBOUND_DETECT_TIMEOUT: Duration = Duration::from_secs(10);
 const DEFAULT_INBOUND_CONNECT_TIMEOUT: Duration = Duration::from_millis(300);
@@ -234,7 +394,26 @@ const DEFAULT_OUTBOUND_DETECT_TIMEOUT: Duration = Duration::from_secs(10);
 const DEFAULT_OUTBOUND_CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
 const DEFAULT_OUTBOUND_CONNECT_BACKOFF: ExponentialBackoff =
     ExponentialBackoff::new_unchecked(Duration::from_millis(100), Duration::from_millis(500), 0.1);
+const DEFAULT_OUTBOUND_FAILURE_ACCRUAL_BACKOFF: ExponentialBackoff =
+    ExponentialBackoff::new_unchecked(Duration::from_secs(1), Duration::from_secs(60), 0.5);
+const DEFAULT_OUTBOUND_FAILURE_ACCRUAL_MIN_REQUESTS: usize = 10;
+const DEFAULT_OUTBOUND_FAILURE_ACCRUAL_WINDOW: Duration = Duration::from_secs(10);
+const DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_INITIAL: usize = 20;
+const DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_MIN: usize = 1;
+const DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_MAX: usize = 1_000;
+const DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_FAILFAST_TIMEOUT: Duration = Duration::from_millis(100);
+const DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_AIMD_BACKOFF_RATIO: f64 = 0.9;
+const DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_AIMD_MAX_LATENCY: Duration = Duration::from_secs(5);
+const DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_GRADIENT_TOLERANCE: f64 = 1.5;
+const DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_GRADIENT_SMOOTHING: f64 = 0.2;
 const DEFAULT_RESOLV_CONF: &str = "/etc/resolv.conf";
+const DEFAULT_CONFIG_FILE_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+const DEFAULT_METRICS_EXPORT_INTERVAL: Duration = Duration::from_secs(30);
+const DEFAULT_METRICS_EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_METRICS_EXPORT_BUFFER_CAPACITY: usize = 10;
+const DEFAULT_METRICS_EXPORT_BACKOFF: ExponentialBackoff =
+    ExponentialBackoff::new_unchecked(Duration::from_secs(1), Duration::from_secs(60), 0.5);
 
 const DEFAULT_INITIAL_STREAM_WINDOW_SIZE: u32 = 65_535; // Protocol default
 const DEFAULT_INITIAL_CONNECTION_WINDOW_SIZE: u32 = 1048576; // 1MB ~ 16 streams at capacity
@@ -284,6 +463,14 @@ const DEFAULT_IDENTITY_MAX_REFRESH: Duration = Duration::from_secs(60 * 60 * 24)
 
 const INBOUND_CONNECT_BASE: &str = "INBOUND_CONNECT";
 const OUTBOUND_CONNECT_BASE: &str = "OUTBOUND_CONNECT";
+const INBOUND_SERVER_BASE: &str = "INBOUND_SERVER";
+const OUTBOUND_SERVER_BASE: &str = "OUTBOUND_SERVER";
+const OUTBOUND_FAILURE_ACCRUAL_BASE: &str = "OUTBOUND_FAILURE_ACCRUAL";
+const OUTBOUND_HTTP_ROUTE_RETRY_BASE: &str = "OUTBOUND_HTTP_ROUTE_RETRY";
+const OUTBOUND_HTTP_LOGICAL_CONCURRENCY_LIMIT_BASE: &str =
+    "OUTBOUND_HTTP_LOGICAL_CONCURRENCY_LIMIT";
+const OUTBOUND_HTTP_ENDPOINT_CONCURRENCY_LIMIT_BASE: &str =
+    "OUTBOUND_HTTP_ENDPOINT_CONCURRENCY_LIMIT";
 
 /// Load a `App` by reading ENV variables.
 pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError> {
@@ -299,6 +486,16 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
 
     let outbound_detect_timeout = parse(strings, ENV_OUTBOUND_DETECT_TIMEOUT, parse_duration);
     let outbound_dispatch_timeout = parse(strings, ENV_OUTBOUND_DISPATCH_TIMEOUT, parse_duration);
+    let outbound_route_mirrors = parse(
+        strings,
+        ENV_OUTBOUND_HTTP_ROUTE_MIRRORS,
+        parse_route_mirrors,
+    );
+    let outbound_route_trace_sampling = parse(
+        strings,
+        ENV_OUTBOUND_HTTP_ROUTE_TRACE_SAMPLING,
+        parse_route_trace_sampling,
+    );
     let outbound_connect_timeout = parse(strings, ENV_OUTBOUND_CONNECT_TIMEOUT, parse_duration);
 
     let inbound_accept_keepalive = parse(strings, ENV_INBOUND_ACCEPT_KEEPALIVE, parse_duration);
@@ -335,6 +532,12 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
     let outbound_max_in_flight = parse(strings, ENV_OUTBOUND_MAX_IN_FLIGHT, parse_number);
 
     let metrics_retain_idle = parse(strings, ENV_METRICS_RETAIN_IDLE, parse_duration);
+    let metrics_native_histogram_schema = parse(
+        strings,
+        ENV_METRICS_NATIVE_HISTOGRAM_SCHEMA,
+        parse_native_histogram_schema,
+    );
+    let shutdown_grace_period = parse(strings, ENV_SHUTDOWN_GRACE_PERIOD, parse_duration);
 
     // DNS
 
@@ -349,8 +552,18 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
 
     let oc_attributes_file_path = strings.get(ENV_TRACE_ATTRIBUTES_PATH);
 
+    let trace_propagation = parse(strings, ENV_TRACE_PROPAGATION, parse_trace_propagation);
+
+    let trace_protocol = parse(strings, ENV_TRACE_PROTOCOL, parse_trace_protocol);
+
+    let trace_sampler = parse_trace_sampler(strings);
+
     let trace_collector_addr = parse_control_addr(strings, ENV_TRACE_COLLECTOR_SVC_BASE);
 
+    let metrics_export = parse_metrics_export_config(strings, hostname.clone());
+
+    let config_file = parse_config_file_config(strings);
+
     let gateway_suffixes = parse(strings, ENV_INBOUND_GATEWAY_SUFFIXES, parse_dns_suffixes);
 
     let dst_addr = parse_control_addr(strings, ENV_DESTINATION_SVC_BASE);
@@ -402,6 +615,10 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
         std::sync::Arc::new(ips)
     };
 
+    let access_log_headers = parse(strings, ENV_ACCESS_LOG_HEADERS, parse_header_names)?
+        .unwrap_or_default()
+        .into();
+
     let outbound = {
         let ingress_mode = parse(strings, ENV_INGRESS_MODE, parse_bool)?.unwrap_or(false);
 
@@ -422,7 +639,8 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
         let server = ServerConfig {
             addr,
             keepalive,
-            h2_settings,
+            h2_settings: parse_h2_server_settings(strings, OUTBOUND_SERVER_BASE, h2_settings)?,
+            request_limits: parse_request_limits(strings, OUTBOUND_SERVER_BASE)?,
         };
         let cache_max_idle_age =
             outbound_cache_max_idle_age?.unwrap_or(DEFAULT_OUTBOUND_ROUTER_MAX_IDLE_AGE);
@@ -464,6 +682,23 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
                 detect_protocol_timeout,
             },
             inbound_ips: inbound_ips.clone(),
+            failure_accrual: parse_failure_accrual(strings)?,
+            http_logical_concurrency_limit: parse_concurrency_limit(
+                strings,
+                OUTBOUND_HTTP_LOGICAL_CONCURRENCY_LIMIT_BASE,
+                true,
+            )?,
+            // Saturated endpoints remain unready so that the balancer prefers
+            // other endpoints.
+            http_endpoint_concurrency_limit: parse_concurrency_limit(
+                strings,
+                OUTBOUND_HTTP_ENDPOINT_CONCURRENCY_LIMIT_BASE,
+                false,
+            )?,
+            http_route_mirrors: outbound_route_mirrors?.unwrap_or_default().into(),
+            http_route_trace_sampling: outbound_route_trace_sampling?.unwrap_or_default().into(),
+            http_route_retry: parse_route_retry(strings)?,
+            http_route_hedge: parse_route_hedge(strings)?,
         }
     };
 
@@ -483,7 +718,8 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
         let server = ServerConfig {
             addr,
             keepalive,
-            h2_settings,
+            h2_settings: parse_h2_server_settings(strings, INBOUND_SERVER_BASE, h2_settings)?,
+            request_limits: parse_request_limits(strings, INBOUND_SERVER_BASE)?,
         };
         let cache_max_idle_age =
             inbound_cache_max_idle_age?.unwrap_or(DEFAULT_INBOUND_ROUTER_MAX_IDLE_AGE);
@@ -527,7 +763,7 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
             // We always configure a default policy. This policy applies when no other policy is
             // configured, especially when the port is not documented in via `ENV_INBOUND_PORTS`.
             let default = parse(strings, ENV_INBOUND_DEFAULT_POLICY, |s| {
-                parse_default_policy(s, cluster_nets, detect_protocol_timeout)
+                parse_default_policy(s, cluster_nets.clone(), detect_protocol_timeout)
             })?
             .unwrap_or_else(|| {
                 warn!(
@@ -537,6 +773,16 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
                 policy::defaults::all_unauthenticated(detect_protocol_timeout).into()
             });
 
+            let local = inbound::policy::LocalPolicy {
+                rate_limit: parse_rate_limit(strings)?,
+                http_routes: parse(strings, ENV_INBOUND_HTTP_ROUTES, |s| {
+                    parse_http_routes(s, &cluster_nets, detect_protocol_timeout)
+                })?
+                .unwrap_or_default(),
+                port_authorizations: parse(strings, ENV_INBOUND_PORTS_JWT, parse_port_jwts)?
+                    .unwrap_or_default(),
+            };
+
             match parse_control_addr(strings, ENV_POLICY_SVC_BASE)? {
                 Some(addr) => {
                     // If the inbound is proxy is configured to discover policies, then load the set
@@ -587,6 +833,7 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
                         ports,
                         workload,
                         control,
+                        local,
                     }
                 }
 
@@ -669,6 +916,7 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
                             .chain(require_tls_ports)
                             .chain(opaque_ports)
                             .collect(),
+                        local,
                     }
                 }
             }
@@ -690,6 +938,7 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
             profile_idle_timeout: dst_profile_idle_timeout?
                 .unwrap_or(DEFAULT_DESTINATION_PROFILE_IDLE_TIMEOUT),
             allowed_ips: inbound_ips.into(),
+            access_log_headers,
         }
     };
 
@@ -712,10 +961,14 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
 
     let admin = super::admin::Config {
         metrics_retain_idle: metrics_retain_idle?.unwrap_or(DEFAULT_METRICS_RETAIN_IDLE),
+        metrics_native_histogram_schema: metrics_native_histogram_schema?
+            .unwrap_or(metrics::latency::DEFAULT_SCHEMA),
+        shutdown_grace_period: shutdown_grace_period?.unwrap_or(DEFAULT_SHUTDOWN_GRACE_PERIOD),
         server: ServerConfig {
             addr: ListenAddr(admin_listener_addr),
             keepalive: inbound.proxy.server.keepalive,
             h2_settings,
+            request_limits: Default::default(),
         },
     };
 
@@ -727,8 +980,8 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
             .into(),
     };
 
-    let oc_collector = match trace_collector_addr? {
-        None => oc_collector::Config::Disabled,
+    let trace_collector = match trace_collector_addr? {
+        None => trace_collector::Config::Disabled,
         Some(addr) => {
             let connect = if addr.addr.is_loopback() {
                 inbound.proxy.connect.clone()
@@ -743,9 +996,12 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
                 })
                 .unwrap_or_default();
 
-            oc_collector::Config::Enabled(Box::new(oc_collector::EnabledConfig {
+            trace_collector::Config::Enabled(Box::new(trace_collector::EnabledConfig {
                 attributes,
                 hostname: hostname?,
+                propagations: trace_propagation?.unwrap_or_default(),
+                sampler: trace_sampler?,
+                protocol: trace_protocol?.unwrap_or_default(),
                 control: ControlConfig {
                     addr,
                     connect,
@@ -762,6 +1018,7 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
                 addr: ListenAddr(addr),
                 keepalive: inbound.proxy.server.keepalive,
                 h2_settings,
+                request_limits: Default::default(),
             },
         })
         .unwrap_or(super::tap::Config::Disabled);
@@ -790,7 +1047,9 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
         dns,
         dst,
         tap,
-        oc_collector,
+        trace_collector,
+        metrics_export: metrics_export?,
+        config_file: config_file?,
         identity,
         outbound,
         gateway,
@@ -843,8 +1102,19 @@ impl Strings for Env {
 }
 
 impl Env {
+    /// Parses the configuration from the environment and, if one is
+    /// configured, a configuration file.
     pub fn try_config(&self) -> Result<super::Config, EnvError> {
-        parse_config(self)
+        match parse(self, ENV_CONFIG_FILE, |s| Ok(PathBuf::from(s)))? {
+            None => parse_config(self),
+            Some(path) => {
+                let file = config_file::File::read(&path).map_err(|error| {
+                    error!(%error, path = %path.display(), "Invalid {}", ENV_CONFIG_FILE);
+                    EnvError::InvalidEnvVar
+                })?;
+                parse_config(&file.layer(self))
+            }
+        }
     }
 }
 
@@ -885,6 +1155,14 @@ where
     s.parse().map_err(Into::into)
 }
 
+fn parse_native_histogram_schema(s: &str) -> Result<i32, ParseError> {
+    let schema = parse_number(s)?;
+    if !metrics::latency::SCHEMAS.contains(&schema) {
+        return Err(ParseError::InvalidNativeHistogramSchema);
+    }
+    Ok(schema)
+}
+
 fn parse_duration(s: &str) -> Result<Duration, ParseError> {
     use regex::Regex;
 
@@ -927,6 +1205,66 @@ fn parse_addr(s: &str) -> Result<Addr, ParseError> {
     })
 }
 
+fn parse_trace_propagation(s: &str) -> Result<trace_context::Propagations, ParseError> {
+    s.parse().map_err(Into::into)
+}
+
+fn parse_trace_protocol(s: &str) -> Result<trace_collector::Protocol, ParseError> {
+    s.parse().map_err(Into::into)
+}
+
+fn parse_header_names(s: &str) -> Result<Vec<http::HeaderName>, ParseError> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            http::HeaderName::from_str(s).map_err(|_| ParseError::InvalidHeaderName(s.to_string()))
+        })
+        .collect()
+}
+
+fn parse_route_mirrors(s: &str) -> Result<Vec<outbound::http::MirrorConfig>, ParseError> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|entry| {
+            let invalid = || ParseError::InvalidRouteMirror(entry.to_string());
+            let (logical, mirror) = entry.split_once('=').ok_or_else(invalid)?;
+            let (mirror, ratio) = mirror.rsplit_once('@').ok_or_else(invalid)?;
+            let ratio = parse_number::<f32>(ratio.trim())?;
+            if !(0.0..=1.0).contains(&ratio) {
+                return Err(invalid());
+            }
+            Ok(outbound::http::MirrorConfig {
+                logical: NameAddr::from_str(logical.trim()).map_err(ParseError::AddrError)?,
+                mirror: NameAddr::from_str(mirror.trim()).map_err(ParseError::AddrError)?,
+                ratio,
+            })
+        })
+        .collect()
+}
+
+fn parse_route_trace_sampling(
+    s: &str,
+) -> Result<Vec<outbound::http::TraceSamplingConfig>, ParseError> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|entry| {
+            let invalid = || ParseError::InvalidRouteTraceSampling(entry.to_string());
+            let (logical, ratio) = entry.split_once('=').ok_or_else(invalid)?;
+            let ratio = parse_number::<f32>(ratio.trim())?;
+            if !(0.0..=1.0).contains(&ratio) {
+                return Err(invalid());
+            }
+            Ok(outbound::http::TraceSamplingConfig {
+                logical: NameAddr::from_str(logical.trim()).map_err(ParseError::AddrError)?,
+                ratio,
+            })
+        })
+        .collect()
+}
+
 fn parse_port_set(s: &str) -> Result<HashSet<u16>, ParseError> {
     let mut set = HashSet::new();
     if !s.is_empty() {
@@ -1045,11 +1383,92 @@ fn parse_default_policy(
         name => Err(ParseError::InvalidPortPolicy(name.to_string())),
     }
 }
+
+/// Parses routes that authorize requests with the path prefix `<path-prefix>`
+/// by the authorizations of the default policy `<policy>`.
+fn parse_http_routes(
+    s: &str,
+    cluster_nets: &HashSet<IpNet>,
+    detect_timeout: Duration,
+) -> Result<Vec<policy::HttpRoute>, ParseError> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|entry| {
+            let invalid = || ParseError::InvalidHttpRoute(entry.to_string());
+            let (prefix, name) = entry.split_once('=').ok_or_else(invalid)?;
+            let prefix = prefix.trim();
+            if !prefix.starts_with('/') {
+                return Err(invalid());
+            }
+            let authorizations =
+                match parse_default_policy(name.trim(), cluster_nets.clone(), detect_timeout)? {
+                    policy::DefaultPolicy::Allow(policy) => policy.authorizations,
+                    policy::DefaultPolicy::Deny => vec![],
+                };
+            Ok(policy::HttpRoute {
+                matches: vec![policy::HttpRouteMatch {
+                    path: Some(policy::PathMatch::Prefix(prefix.to_string())),
+                    ..Default::default()
+                }],
+                authorizations,
+                kind: "default".into(),
+                name: prefix.into(),
+            })
+        })
+        .collect()
+}
+
+/// Parses JWT authorizations for the servers on specific ports.
+fn parse_port_jwts(s: &str) -> Result<HashMap<u16, Vec<policy::Authorization>>, ParseError> {
+    let mut ports = HashMap::<u16, Vec<policy::Authorization>>::new();
+    for entry in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let invalid = || ParseError::InvalidPortJwt(entry.to_string());
+        let mut settings = entry.split(';').map(str::trim);
+        let (port, jwks_path) = settings
+            .next()
+            .and_then(|s| s.split_once('='))
+            .ok_or_else(invalid)?;
+        let jwks_path = jwks_path.trim();
+        if jwks_path.is_empty() {
+            return Err(invalid());
+        }
+
+        let mut issuer = None;
+        let mut audiences = vec![];
+        for setting in settings {
+            match setting.split_once('=') {
+                Some(("issuer", iss)) if issuer.is_none() => issuer = Some(iss.to_string()),
+                Some(("audience", aud)) => audiences.push(aud.to_string()),
+                _ => return Err(invalid()),
+            }
+        }
+
+        ports
+            .entry(parse_number::<u16>(port.trim())?)
+            .or_default()
+            .push(policy::defaults::jwt_authorization(
+                jwks_path.into(),
+                issuer,
+                audiences,
+            ));
+    }
+    Ok(ports)
+}
+
 pub fn parse_backoff<S: Strings>(
     strings: &S,
     base: &str,
     default: ExponentialBackoff,
 ) -> Result<ExponentialBackoff, EnvError> {
+    parse_optional_backoff(strings, base).map(|backoff| backoff.unwrap_or(default))
+}
+
+/// Parses a backoff, returning `None` if none of its settings are set.
+fn parse_optional_backoff<S: Strings>(
+    strings: &S,
+    base: &str,
+) -> Result<Option<ExponentialBackoff>, EnvError> {
     let min_env = format!("LINKERD2_PROXY_{}_EXP_BACKOFF_MIN", base);
     let min = parse(strings, &min_env, parse_duration);
     let max_env = format!("LINKERD2_PROXY_{}_EXP_BACKOFF_MAX", base);
@@ -1058,9 +1477,9 @@ pub fn parse_backoff<S: Strings>(
     let jitter = parse(strings, &jitter_env, parse_number::<f64>);
 
     match (min?, max?, jitter?) {
-        (None, None, None) => Ok(default),
+        (None, None, None) => Ok(None),
         (Some(min), Some(max), jitter) => {
-            ExponentialBackoff::try_new(min, max, jitter.unwrap_or_default()).map_err(|error| {
+            ExponentialBackoff::try_new(min, max, jitter.unwrap_or_default()).map(Some).map_err(|error| {
                 error!(message="Invalid backoff", %error, %min_env, ?min, %max_env, ?max, %jitter_env, ?jitter);
                 EnvError::InvalidEnvVar
             })
@@ -1072,6 +1491,434 @@ pub fn parse_backoff<S: Strings>(
     }
 }
 
+/// Parses the HTTP/2 settings of a proxy server, which extend the HTTP/2
+/// settings shared by all of the proxy's clients and servers.
+fn parse_h2_server_settings<S: Strings>(
+    strings: &S,
+    base: &str,
+    settings: h2::Settings,
+) -> Result<h2::Settings, EnvError> {
+    let keepalive_interval = parse(
+        strings,
+        &format!("LINKERD2_PROXY_{}_HTTP2_KEEP_ALIVE_INTERVAL", base),
+        parse_duration,
+    );
+    let keepalive_timeout = parse(
+        strings,
+        &format!("LINKERD2_PROXY_{}_HTTP2_KEEP_ALIVE_TIMEOUT", base),
+        parse_duration,
+    );
+    let max_concurrent_streams = parse(
+        strings,
+        &format!("LINKERD2_PROXY_{}_HTTP2_MAX_CONCURRENT_STREAMS", base),
+        parse_number,
+    );
+    let max_header_list_size = parse(
+        strings,
+        &format!("LINKERD2_PROXY_{}_HTTP2_MAX_HEADER_LIST_SIZE", base),
+        parse_number,
+    );
+    let max_connection_age = parse(
+        strings,
+        &format!("LINKERD2_PROXY_{}_HTTP2_MAX_CONNECTION_AGE", base),
+        parse_duration,
+    );
+    let max_connection_age_grace = parse(
+        strings,
+        &format!("LINKERD2_PROXY_{}_HTTP2_MAX_CONNECTION_AGE_GRACE", base),
+        parse_duration,
+    );
+
+    Ok(h2::Settings {
+        keepalive_interval: keepalive_interval?,
+        keepalive_timeout: keepalive_timeout?,
+        max_concurrent_streams: max_concurrent_streams?,
+        max_header_list_size: max_header_list_size?,
+        max_connection_age: max_connection_age?,
+        max_connection_age_grace: max_connection_age_grace?,
+        ..settings
+    })
+}
+
+fn parse_request_limits<S: Strings>(
+    strings: &S,
+    base: &str,
+) -> Result<http::RequestLimits, EnvError> {
+    let max_header_bytes = parse(
+        strings,
+        &format!("LINKERD2_PROXY_{}_HTTP_MAX_REQUEST_HEADER_BYTES", base),
+        parse_number,
+    );
+    let max_body_bytes = parse(
+        strings,
+        &format!("LINKERD2_PROXY_{}_HTTP_MAX_REQUEST_BODY_BYTES", base),
+        parse_number,
+    );
+
+    Ok(http::RequestLimits {
+        max_header_bytes: max_header_bytes?,
+        max_body_bytes: max_body_bytes?,
+    })
+}
+
+fn parse_failure_accrual<S: Strings>(
+    strings: &S,
+) -> Result<Option<failure_accrual::Config>, EnvError> {
+    let consecutive = parse(
+        strings,
+        ENV_OUTBOUND_FAILURE_ACCRUAL_CONSECUTIVE_FAILURES,
+        parse_number::<usize>,
+    );
+    let max_rate = parse(
+        strings,
+        ENV_OUTBOUND_FAILURE_ACCRUAL_MAX_FAILURE_RATE,
+        parse_number::<f64>,
+    );
+    let min_requests = parse(
+        strings,
+        ENV_OUTBOUND_FAILURE_ACCRUAL_MIN_REQUESTS,
+        parse_number::<usize>,
+    );
+    let window = parse(strings, ENV_OUTBOUND_FAILURE_ACCRUAL_WINDOW, parse_duration);
+
+    let accrual = match (consecutive?, max_rate?) {
+        (None, None) => return Ok(None),
+        (Some(n), None) if n > 0 => failure_accrual::Accrual::ConsecutiveFailures(n),
+        (None, Some(max_rate)) if max_rate > 0.0 && max_rate <= 1.0 => {
+            failure_accrual::Accrual::FailureRate {
+                max_rate,
+                min_requests: min_requests?
+                    .unwrap_or(DEFAULT_OUTBOUND_FAILURE_ACCRUAL_MIN_REQUESTS),
+                window: window?.unwrap_or(DEFAULT_OUTBOUND_FAILURE_ACCRUAL_WINDOW),
+            }
+        }
+        (consecutive, max_rate) => {
+            error!(
+                ?consecutive,
+                ?max_rate,
+                "Only one of {} (greater than zero) or {} (between 0.0 and 1.0) may be set",
+                ENV_OUTBOUND_FAILURE_ACCRUAL_CONSECUTIVE_FAILURES,
+                ENV_OUTBOUND_FAILURE_ACCRUAL_MAX_FAILURE_RATE,
+            );
+            return Err(EnvError::InvalidEnvVar);
+        }
+    };
+
+    let backoff = parse_backoff(
+        strings,
+        OUTBOUND_FAILURE_ACCRUAL_BASE,
+        DEFAULT_OUTBOUND_FAILURE_ACCRUAL_BACKOFF,
+    )?;
+    Ok(Some(failure_accrual::Config { accrual, backoff }))
+}
+
+/// Parses the attempt limit and backoff of retryable routes. Retries are
+/// neither limited nor delayed by default.
+fn parse_route_retry<S: Strings>(strings: &S) -> Result<outbound::http::RetryConfig, EnvError> {
+    let max_attempts = parse(
+        strings,
+        ENV_OUTBOUND_HTTP_ROUTE_RETRY_MAX_ATTEMPTS,
+        parse_number::<usize>,
+    );
+    let backoff = parse_optional_backoff(strings, OUTBOUND_HTTP_ROUTE_RETRY_BASE);
+
+    let max_attempts = max_attempts?;
+    if max_attempts == Some(0) {
+        error!(
+            "{} must be greater than zero",
+            ENV_OUTBOUND_HTTP_ROUTE_RETRY_MAX_ATTEMPTS
+        );
+        return Err(EnvError::InvalidEnvVar);
+    }
+    Ok(outbound::http::RetryConfig {
+        max_attempts,
+        backoff: backoff?,
+    })
+}
+
+/// Parses the rate limit applied to inbound servers whose policies do not
+/// configure one.
+fn parse_rate_limit(strings: &dyn Strings) -> Result<Option<policy::RateLimit>, EnvError> {
+    let total = parse(strings, ENV_INBOUND_HTTP_RATE_LIMIT_TOTAL, parse_limit)?;
+    let identity = parse(strings, ENV_INBOUND_HTTP_RATE_LIMIT_IDENTITY, parse_limit)?;
+    if total.is_none() && identity.is_none() {
+        return Ok(None);
+    }
+    Ok(Some(policy::RateLimit { total, identity }))
+}
+
+fn parse_limit(s: &str) -> Result<policy::Limit, ParseError> {
+    let (rps, burst) = match s.split_once('/') {
+        Some((rps, burst)) => (rps, Some(burst)),
+        None => (s, None),
+    };
+    let requests_per_second = parse_number::<u32>(rps.trim())?;
+    let burst = match burst {
+        Some(burst) => parse_number::<u32>(burst.trim())?,
+        None => requests_per_second,
+    };
+    if requests_per_second == 0 || burst == 0 {
+        return Err(ParseError::InvalidRateLimit(s.to_string()));
+    }
+    Ok(policy::Limit {
+        requests_per_second,
+        burst,
+    })
+}
+
+/// Parses the hedging policy of retryable routes. Requests are not hedged by
+/// default.
+fn parse_route_hedge<S: Strings>(strings: &S) -> Result<Option<profiles::http::Hedge>, EnvError> {
+    let latency = parse(
+        strings,
+        ENV_OUTBOUND_HTTP_ROUTE_HEDGE_LATENCY,
+        parse_duration,
+    );
+    let percentile = parse(
+        strings,
+        ENV_OUTBOUND_HTTP_ROUTE_HEDGE_PERCENTILE,
+        parse_number::<f32>,
+    );
+
+    match (latency?, percentile?) {
+        (None, None) => Ok(None),
+        (Some(latency), None) => Ok(Some(profiles::http::Hedge::Latency(latency))),
+        (None, Some(percentile)) if (0.0..=1.0).contains(&percentile) => {
+            Ok(Some(profiles::http::Hedge::Percentile(percentile)))
+        }
+        (None, Some(percentile)) => {
+            error!(
+                "{} must be between 0.0 and 1.0: {}",
+                ENV_OUTBOUND_HTTP_ROUTE_HEDGE_PERCENTILE, percentile
+            );
+            Err(EnvError::InvalidEnvVar)
+        }
+        (Some(_), Some(_)) => {
+            error!(
+                "{} and {} must not both be set",
+                ENV_OUTBOUND_HTTP_ROUTE_HEDGE_LATENCY, ENV_OUTBOUND_HTTP_ROUTE_HEDGE_PERCENTILE
+            );
+            Err(EnvError::InvalidEnvVar)
+        }
+    }
+}
+
+/// Parses an adaptive concurrency limit. Limits are only enabled when
+/// `LINKERD2_PROXY_{base}_ALGORITHM` is set to `aimd` or `gradient`. Limits
+/// only shed requests, after a failfast timeout, when `sheds` is set.
+fn parse_concurrency_limit<S: Strings>(
+    strings: &S,
+    base: &str,
+    sheds: bool,
+) -> Result<Option<concurrency_limit::Config>, EnvError> {
+    let env = |name: &str| format!("LINKERD2_PROXY_{}_{}", base, name);
+    let algorithm = parse(strings, &env("ALGORITHM"), |s| Ok(s.to_ascii_lowercase()));
+    let initial_limit = parse(strings, &env("INITIAL"), parse_number::<usize>);
+    let min_limit = parse(strings, &env("MIN"), parse_number::<usize>);
+    let max_limit = parse(strings, &env("MAX"), parse_number::<usize>);
+    let failfast_timeout = parse(strings, &env("FAILFAST_TIMEOUT"), parse_duration);
+    let backoff_ratio = parse(strings, &env("AIMD_BACKOFF_RATIO"), parse_number::<f64>);
+    let max_latency = parse(strings, &env("AIMD_MAX_LATENCY"), parse_duration);
+    let tolerance = parse(strings, &env("GRADIENT_TOLERANCE"), parse_number::<f64>);
+    let smoothing = parse(strings, &env("GRADIENT_SMOOTHING"), parse_number::<f64>);
+
+    let algorithm = match algorithm?.as_deref() {
+        None => return Ok(None),
+        Some("aimd") => {
+            let backoff_ratio =
+                backoff_ratio?.unwrap_or(DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_AIMD_BACKOFF_RATIO);
+            if !(backoff_ratio > 0.0 && backoff_ratio < 1.0) {
+                error!(
+                    backoff_ratio,
+                    "{} must be between 0.0 and 1.0",
+                    env("AIMD_BACKOFF_RATIO")
+                );
+                return Err(EnvError::InvalidEnvVar);
+            }
+            concurrency_limit::Algorithm::Aimd {
+                backoff_ratio,
+                max_latency: max_latency?
+                    .unwrap_or(DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_AIMD_MAX_LATENCY),
+            }
+        }
+        Some("gradient") => {
+            let smoothing =
+                smoothing?.unwrap_or(DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_GRADIENT_SMOOTHING);
+            if !(smoothing > 0.0 && smoothing <= 1.0) {
+                error!(
+                    smoothing,
+                    "{} must be greater than 0.0 and at most 1.0",
+                    env("GRADIENT_SMOOTHING")
+                );
+                return Err(EnvError::InvalidEnvVar);
+            }
+            concurrency_limit::Algorithm::Gradient {
+                tolerance: tolerance?
+                    .unwrap_or(DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_GRADIENT_TOLERANCE),
+                smoothing,
+            }
+        }
+        Some(algorithm) => {
+            error!(
+                %algorithm,
+                "{} must be one of `aimd` or `gradient`",
+                env("ALGORITHM")
+            );
+            return Err(EnvError::InvalidEnvVar);
+        }
+    };
+
+    let failfast_timeout = match (failfast_timeout?, sheds) {
+        (timeout, true) => {
+            Some(timeout.unwrap_or(DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_FAILFAST_TIMEOUT))
+        }
+        (Some(_), false) => {
+            warn!(
+                "{} is ignored, since these limits never shed requests",
+                env("FAILFAST_TIMEOUT")
+            );
+            None
+        }
+        (None, false) => None,
+    };
+
+    let min_limit = min_limit?.unwrap_or(DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_MIN);
+    let max_limit = max_limit?.unwrap_or(DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_MAX);
+    if min_limit == 0 || min_limit > max_limit {
+        error!(
+            min_limit,
+            max_limit,
+            "{} must be greater than zero and no more than {}",
+            env("MIN"),
+            env("MAX")
+        );
+        return Err(EnvError::InvalidEnvVar);
+    }
+
+    Ok(Some(concurrency_limit::Config {
+        algorithm,
+        initial_limit: initial_limit?.unwrap_or(DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_INITIAL),
+        min_limit,
+        max_limit,
+        failfast_timeout,
+    }))
+}
+
+fn parse_trace_sampler<S: Strings>(strings: &S) -> Result<trace_context::Sampler, EnvError> {
+    let ratio = parse(strings, ENV_TRACE_SAMPLE_RATIO, parse_number::<f32>);
+    let max_per_second = parse(
+        strings,
+        ENV_TRACE_SAMPLE_MAX_PER_SECOND,
+        parse_number::<u32>,
+    );
+    match (ratio?, max_per_second?) {
+        (None, None) => Ok(trace_context::Sampler::default()),
+        (Some(ratio), _) if !(0.0..=1.0).contains(&ratio) => {
+            error!(
+                ratio,
+                "{} must be between 0.0 and 1.0", ENV_TRACE_SAMPLE_RATIO
+            );
+            Err(EnvError::InvalidEnvVar)
+        }
+        (ratio, max_per_second) => Ok(trace_context::Sampler::new(
+            ratio.unwrap_or(1.0),
+            max_per_second,
+        )),
+    }
+}
+
+fn parse_config_file_config<S: Strings>(
+    strings: &S,
+) -> Result<Option<config_file::Config>, EnvError> {
+    let path = match parse(strings, ENV_CONFIG_FILE, |s| Ok(PathBuf::from(s)))? {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+    let poll_interval = parse(strings, ENV_CONFIG_FILE_POLL_INTERVAL, parse_duration)?
+        .unwrap_or(DEFAULT_CONFIG_FILE_POLL_INTERVAL);
+    Ok(Some(config_file::Config {
+        path,
+        poll_interval,
+    }))
+}
+
+fn parse_metrics_export_config<S: Strings>(
+    strings: &S,
+    hostname: Result<Option<String>, EnvError>,
+) -> Result<metrics_export::Config, EnvError> {
+    let url = match parse(strings, ENV_METRICS_EXPORT_URL, parse_http_url)? {
+        Some(url) => url,
+        None => return Ok(metrics_export::Config::Disabled),
+    };
+    let interval = parse(strings, ENV_METRICS_EXPORT_INTERVAL, parse_duration);
+    let timeout = parse(strings, ENV_METRICS_EXPORT_TIMEOUT, parse_duration);
+    let buffer_capacity = parse(
+        strings,
+        ENV_METRICS_EXPORT_BUFFER_CAPACITY,
+        parse_number::<usize>,
+    );
+    let labels = parse(strings, ENV_METRICS_EXPORT_LABELS, parse_labels);
+    let backoff = parse_backoff(strings, "METRICS_EXPORT", DEFAULT_METRICS_EXPORT_BACKOFF);
+
+    let interval = interval?.unwrap_or(DEFAULT_METRICS_EXPORT_INTERVAL);
+    if interval == Duration::ZERO {
+        error!("{} must be greater than zero", ENV_METRICS_EXPORT_INTERVAL);
+        return Err(EnvError::InvalidEnvVar);
+    }
+    let buffer_capacity = buffer_capacity?.unwrap_or(DEFAULT_METRICS_EXPORT_BUFFER_CAPACITY);
+    if buffer_capacity == 0 {
+        error!("{} must be at least 1", ENV_METRICS_EXPORT_BUFFER_CAPACITY);
+        return Err(EnvError::InvalidEnvVar);
+    }
+
+    let mut labels = labels?.unwrap_or_default();
+    if !labels.iter().any(|(name, _)| name == "instance") {
+        if let Some(hostname) = hostname? {
+            labels.push(("instance".to_string(), hostname));
+        }
+    }
+
+    Ok(metrics_export::Config::Enabled(Box::new(
+        metrics_export::EnabledConfig {
+            url,
+            interval,
+            timeout: timeout?.unwrap_or(DEFAULT_METRICS_EXPORT_TIMEOUT),
+            backoff: backoff?,
+            buffer_capacity,
+            labels,
+        },
+    )))
+}
+
+fn parse_http_url(s: &str) -> Result<http::uri::Uri, ParseError> {
+    let url = s
+        .parse::<http::uri::Uri>()
+        .map_err(|_| ParseError::InvalidHttpUrl(s.to_string()))?;
+    if url.scheme_str() != Some("http") || url.authority().is_none() {
+        return Err(ParseError::InvalidHttpUrl(s.to_string()));
+    }
+    Ok(url)
+}
+
+fn parse_labels(s: &str) -> Result<Vec<(String, String)>, ParseError> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(|l| {
+            let (name, value) = l
+                .split_once('=')
+                .ok_or_else(|| ParseError::InvalidLabel(l.to_string()))?;
+            let name = name.trim();
+            let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid || name.starts_with("__") {
+                return Err(ParseError::InvalidLabel(l.to_string()));
+            }
+            Ok((name.to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
 pub fn parse_control_addr<S: Strings>(
     strings: &S,
     base: &str,
@@ -1212,6 +2059,14 @@ pub fn parse_identity_config<S: Strings>(
 mod tests {
     use super::*;
 
+    struct TestEnv(HashMap<&'static str, &'static str>);
+
+    impl Strings for TestEnv {
+        fn get(&self, key: &str) -> Result<Option<String>, EnvError> {
+            Ok(self.0.get(key).map(|v| v.to_string()))
+        }
+    }
+
     fn test_unit<F: Fn(u64) -> Duration>(unit: &str, to_duration: F) {
         for v in &[0, 1, 23, 456_789] {
             let d = to_duration(*v);
@@ -1393,4 +2248,405 @@ mod tests {
         assert!(parse_ip_set("10.4.0.3,foobar,192.168.0.69").is_err());
         assert!(parse_ip_set("10.0.1.1/24").is_err());
     }
+
+    #[test]
+    fn route_mirrors() {
+        let mirrors = parse_route_mirrors(
+            "web.ns.svc.cluster.local:8080=web-next.ns.svc.cluster.local:8080@0.1, \
+             api.ns.svc.cluster.local:80 = api-next.ns.svc.cluster.local:80 @ 1",
+        )
+        .expect("mirrors must parse");
+        assert_eq!(
+            mirrors,
+            vec![
+                outbound::http::MirrorConfig {
+                    logical: "web.ns.svc.cluster.local:8080".parse().unwrap(),
+                    mirror: "web-next.ns.svc.cluster.local:8080".parse().unwrap(),
+                    ratio: 0.1,
+                },
+                outbound::http::MirrorConfig {
+                    logical: "api.ns.svc.cluster.local:80".parse().unwrap(),
+                    mirror: "api-next.ns.svc.cluster.local:80".parse().unwrap(),
+                    ratio: 1.0,
+                },
+            ]
+        );
+        assert_eq!(parse_route_mirrors("").unwrap(), vec![]);
+        assert!(parse_route_mirrors("web.ns.svc.cluster.local:8080").is_err());
+        assert!(parse_route_mirrors("web.ns.svc.cluster.local:8080=web-next:8080").is_err());
+        assert!(parse_route_mirrors("web.ns.svc.cluster.local:8080=web-next:8080@1.5").is_err());
+        assert!(parse_route_mirrors("web.ns.svc.cluster.local=web-next:8080@0.5").is_err());
+    }
+
+    #[test]
+    fn route_trace_sampling() {
+        let samplings = parse_route_trace_sampling(
+            "web.ns.svc.cluster.local:8080=0.1, api.ns.svc.cluster.local:80 = 1",
+        )
+        .expect("trace sampling must parse");
+        assert_eq!(
+            samplings,
+            vec![
+                outbound::http::TraceSamplingConfig {
+                    logical: "web.ns.svc.cluster.local:8080".parse().unwrap(),
+                    ratio: 0.1,
+                },
+                outbound::http::TraceSamplingConfig {
+                    logical: "api.ns.svc.cluster.local:80".parse().unwrap(),
+                    ratio: 1.0,
+                },
+            ]
+        );
+        assert_eq!(parse_route_trace_sampling("").unwrap(), vec![]);
+        assert!(parse_route_trace_sampling("web.ns.svc.cluster.local:8080").is_err());
+        assert!(parse_route_trace_sampling("web.ns.svc.cluster.local:8080=1.5").is_err());
+        assert!(parse_route_trace_sampling("web.ns.svc.cluster.local=0.5").is_err());
+    }
+
+    #[test]
+    fn metrics_export_urls() {
+        assert!(parse_http_url("http://prometheus:9090/api/v1/write").is_ok());
+        assert!(parse_http_url("https://prometheus:9090/api/v1/write").is_err());
+        assert!(parse_http_url("prometheus:9090").is_err());
+        assert!(parse_http_url("/api/v1/write").is_err());
+    }
+
+    #[test]
+    fn metrics_export_labels() {
+        assert_eq!(
+            parse_labels("cluster=east, env = prod,"),
+            Ok(vec![
+                ("cluster".to_string(), "east".to_string()),
+                ("env".to_string(), "prod".to_string()),
+            ])
+        );
+        assert_eq!(parse_labels(""), Ok(vec![]));
+        assert!(parse_labels("cluster").is_err());
+        assert!(parse_labels("0cluster=east").is_err());
+        assert!(parse_labels("__name__=foo").is_err());
+        assert!(parse_labels("clu-ster=east").is_err());
+    }
+
+    #[test]
+    fn h2_server_settings() {
+        let shared = h2::Settings {
+            initial_stream_window_size: Some(65_535),
+            ..Default::default()
+        };
+        let env = TestEnv(
+            vec![
+                (
+                    "LINKERD2_PROXY_INBOUND_SERVER_HTTP2_KEEP_ALIVE_INTERVAL",
+                    "10s",
+                ),
+                (
+                    "LINKERD2_PROXY_INBOUND_SERVER_HTTP2_KEEP_ALIVE_TIMEOUT",
+                    "3s",
+                ),
+                (
+                    "LINKERD2_PROXY_INBOUND_SERVER_HTTP2_MAX_CONCURRENT_STREAMS",
+                    "100",
+                ),
+                (
+                    "LINKERD2_PROXY_INBOUND_SERVER_HTTP2_MAX_HEADER_LIST_SIZE",
+                    "16384",
+                ),
+                (
+                    "LINKERD2_PROXY_INBOUND_SERVER_HTTP2_MAX_CONNECTION_AGE",
+                    "30m",
+                ),
+                (
+                    "LINKERD2_PROXY_INBOUND_SERVER_HTTP2_MAX_CONNECTION_AGE_GRACE",
+                    "10s",
+                ),
+                (
+                    "LINKERD2_PROXY_OUTBOUND_SERVER_HTTP2_MAX_CONCURRENT_STREAMS",
+                    "nope",
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        );
+
+        let inbound = parse_h2_server_settings(&env, INBOUND_SERVER_BASE, shared).unwrap();
+        assert_eq!(inbound.initial_stream_window_size, Some(65_535));
+        assert_eq!(inbound.keepalive_interval, Some(Duration::from_secs(10)));
+        assert_eq!(inbound.keepalive_timeout, Some(Duration::from_secs(3)));
+        assert_eq!(inbound.max_concurrent_streams, Some(100));
+        assert_eq!(inbound.max_header_list_size, Some(16384));
+        assert_eq!(
+            inbound.max_connection_age,
+            Some(Duration::from_secs(30 * 60))
+        );
+        assert_eq!(
+            inbound.max_connection_age_grace,
+            Some(Duration::from_secs(10))
+        );
+
+        assert!(parse_h2_server_settings(&env, OUTBOUND_SERVER_BASE, shared).is_err());
+    }
+
+    #[test]
+    fn request_limits() {
+        let env = TestEnv(
+            vec![
+                (
+                    "LINKERD2_PROXY_INBOUND_SERVER_HTTP_MAX_REQUEST_HEADER_BYTES",
+                    "8192",
+                ),
+                (
+                    "LINKERD2_PROXY_INBOUND_SERVER_HTTP_MAX_REQUEST_BODY_BYTES",
+                    "1048576",
+                ),
+                (
+                    "LINKERD2_PROXY_OUTBOUND_SERVER_HTTP_MAX_REQUEST_BODY_BYTES",
+                    "-1",
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        );
+
+        let inbound = parse_request_limits(&env, INBOUND_SERVER_BASE).unwrap();
+        assert_eq!(inbound.max_header_bytes, Some(8192));
+        assert_eq!(inbound.max_body_bytes, Some(1024 * 1024));
+
+        assert!(parse_request_limits(&env, OUTBOUND_SERVER_BASE).is_err());
+        assert_eq!(
+            parse_request_limits(&TestEnv(Default::default()), OUTBOUND_SERVER_BASE).unwrap(),
+            http::RequestLimits::default()
+        );
+    }
+
+    #[test]
+    fn native_histogram_schema() {
+        assert_eq!(parse_native_histogram_schema("-4").unwrap(), -4);
+        assert_eq!(parse_native_histogram_schema("4").unwrap(), 4);
+        assert!(parse_native_histogram_schema("5").is_err());
+        assert!(parse_native_histogram_schema("fine").is_err());
+    }
+
+    #[test]
+    fn route_retry() {
+        let env = TestEnv(
+            vec![
+                ("LINKERD2_PROXY_OUTBOUND_HTTP_ROUTE_RETRY_MAX_ATTEMPTS", "3"),
+                (
+                    "LINKERD2_PROXY_OUTBOUND_HTTP_ROUTE_RETRY_EXP_BACKOFF_MIN",
+                    "25ms",
+                ),
+                (
+                    "LINKERD2_PROXY_OUTBOUND_HTTP_ROUTE_RETRY_EXP_BACKOFF_MAX",
+                    "1s",
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let retry = parse_route_retry(&env).unwrap();
+        assert_eq!(retry.max_attempts, Some(3));
+        assert_eq!(
+            retry.backoff,
+            Some(
+                ExponentialBackoff::try_new(Duration::from_millis(25), Duration::from_secs(1), 0.0)
+                    .unwrap()
+            )
+        );
+
+        let retry = parse_route_retry(&TestEnv(HashMap::new())).unwrap();
+        assert_eq!(retry, outbound::http::RetryConfig::default());
+
+        let env = TestEnv(
+            vec![("LINKERD2_PROXY_OUTBOUND_HTTP_ROUTE_RETRY_MAX_ATTEMPTS", "0")]
+                .into_iter()
+                .collect(),
+        );
+        assert!(parse_route_retry(&env).is_err());
+    }
+
+    #[test]
+    fn route_hedge() {
+        let env = |vars: Vec<(&'static str, &'static str)>| TestEnv(vars.into_iter().collect());
+
+        assert_eq!(parse_route_hedge(&TestEnv(HashMap::new())).unwrap(), None);
+        assert_eq!(
+            parse_route_hedge(&env(vec![(
+                "LINKERD2_PROXY_OUTBOUND_HTTP_ROUTE_HEDGE_LATENCY",
+                "50ms"
+            )]))
+            .unwrap(),
+            Some(profiles::http::Hedge::Latency(Duration::from_millis(50)))
+        );
+        assert_eq!(
+            parse_route_hedge(&env(vec![(
+                "LINKERD2_PROXY_OUTBOUND_HTTP_ROUTE_HEDGE_PERCENTILE",
+                "0.9"
+            )]))
+            .unwrap(),
+            Some(profiles::http::Hedge::Percentile(0.9))
+        );
+        assert!(parse_route_hedge(&env(vec![(
+            "LINKERD2_PROXY_OUTBOUND_HTTP_ROUTE_HEDGE_PERCENTILE",
+            "90"
+        )]))
+        .is_err());
+        assert!(parse_route_hedge(&env(vec![
+            ("LINKERD2_PROXY_OUTBOUND_HTTP_ROUTE_HEDGE_LATENCY", "50ms"),
+            ("LINKERD2_PROXY_OUTBOUND_HTTP_ROUTE_HEDGE_PERCENTILE", "0.9"),
+        ]))
+        .is_err());
+    }
+
+    #[test]
+    fn http_routes() {
+        let timeout = Duration::from_secs(10);
+        let routes = parse_http_routes(
+            "/admin=all-authenticated, /debug=deny",
+            &HashSet::new(),
+            timeout,
+        )
+        .unwrap();
+        assert_eq!(routes.len(), 2);
+        assert_eq!(
+            routes[0].matches,
+            vec![policy::HttpRouteMatch {
+                path: Some(policy::PathMatch::Prefix("/admin".into())),
+                ..Default::default()
+            }]
+        );
+        assert_eq!(
+            routes[0].authorizations,
+            policy::defaults::all_authenticated(timeout).authorizations
+        );
+        assert_eq!(routes[1].name.as_ref(), "/debug");
+        assert!(routes[1].authorizations.is_empty());
+
+        assert!(parse_http_routes("admin=deny", &HashSet::new(), timeout).is_err());
+        assert!(parse_http_routes("/admin", &HashSet::new(), timeout).is_err());
+        assert!(
+            parse_http_routes("/admin=cluster-authenticated", &HashSet::new(), timeout).is_err()
+        );
+    }
+
+    #[test]
+    fn port_jwts() {
+        let ports = parse_port_jwts(
+            "8080=/var/run/jwks.json;issuer=https://issuer.example.com;audience=a;audience=b, \
+             9090=/var/run/other.json",
+        )
+        .unwrap();
+        assert_eq!(
+            ports.get(&8080),
+            Some(&vec![policy::defaults::jwt_authorization(
+                "/var/run/jwks.json".into(),
+                Some("https://issuer.example.com".into()),
+                vec!["a".into(), "b".into()],
+            )])
+        );
+        assert_eq!(
+            ports.get(&9090),
+            Some(&vec![policy::defaults::jwt_authorization(
+                "/var/run/other.json".into(),
+                None,
+                vec![],
+            )])
+        );
+
+        assert!(parse_port_jwts("8080").is_err());
+        assert!(parse_port_jwts("8080=").is_err());
+        assert!(parse_port_jwts("http=/var/run/jwks.json").is_err());
+        assert!(parse_port_jwts("8080=/var/run/jwks.json;iss=foo").is_err());
+    }
+
+    #[test]
+    fn rate_limit() {
+        let env = |vars: Vec<(&'static str, &'static str)>| TestEnv(vars.into_iter().collect());
+
+        assert_eq!(parse_rate_limit(&TestEnv(HashMap::new())).unwrap(), None);
+        assert_eq!(
+            parse_rate_limit(&env(vec![
+                ("LINKERD2_PROXY_INBOUND_HTTP_RATE_LIMIT_TOTAL", "100"),
+                ("LINKERD2_PROXY_INBOUND_HTTP_RATE_LIMIT_IDENTITY", "10/20"),
+            ]))
+            .unwrap(),
+            Some(policy::RateLimit {
+                total: Some(policy::Limit {
+                    requests_per_second: 100,
+                    burst: 100,
+                }),
+                identity: Some(policy::Limit {
+                    requests_per_second: 10,
+                    burst: 20,
+                }),
+            })
+        );
+        assert!(parse_rate_limit(&env(vec![(
+            "LINKERD2_PROXY_INBOUND_HTTP_RATE_LIMIT_TOTAL",
+            "0"
+        )]))
+        .is_err());
+        assert!(parse_rate_limit(&env(vec![(
+            "LINKERD2_PROXY_INBOUND_HTTP_RATE_LIMIT_IDENTITY",
+            "10/"
+        )]))
+        .is_err());
+    }
+
+    #[test]
+    fn concurrency_limit() {
+        let env = TestEnv(
+            vec![
+                (
+                    "LINKERD2_PROXY_OUTBOUND_HTTP_LOGICAL_CONCURRENCY_LIMIT_ALGORITHM",
+                    "gradient",
+                ),
+                (
+                    "LINKERD2_PROXY_OUTBOUND_HTTP_LOGICAL_CONCURRENCY_LIMIT_MAX",
+                    "200",
+                ),
+                (
+                    "LINKERD2_PROXY_OUTBOUND_HTTP_ENDPOINT_CONCURRENCY_LIMIT_ALGORITHM",
+                    "aimd",
+                ),
+                (
+                    "LINKERD2_PROXY_OUTBOUND_HTTP_ENDPOINT_CONCURRENCY_LIMIT_AIMD_BACKOFF_RATIO",
+                    "1.5",
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        );
+
+        let logical =
+            parse_concurrency_limit(&env, OUTBOUND_HTTP_LOGICAL_CONCURRENCY_LIMIT_BASE, true)
+                .unwrap()
+                .expect("limit must be configured");
+        assert!(matches!(
+            logical.algorithm,
+            concurrency_limit::Algorithm::Gradient { .. }
+        ));
+        assert_eq!(logical.max_limit, 200);
+        assert_eq!(
+            logical.initial_limit,
+            DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_INITIAL
+        );
+        assert_eq!(
+            logical.failfast_timeout,
+            Some(DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_FAILFAST_TIMEOUT)
+        );
+
+        assert!(parse_concurrency_limit(
+            &env,
+            OUTBOUND_HTTP_ENDPOINT_CONCURRENCY_LIMIT_BASE,
+            false
+        )
+        .is_err());
+        assert!(parse_concurrency_limit(
+            &TestEnv(Default::default()),
+            OUTBOUND_HTTP_ENDPOINT_CONCURRENCY_LIMIT_BASE,
+            false,
+        )
+        .unwrap()
+        .is_none());
+    }
 }
@@ -8,11 +8,13 @@
 )]
 #![forbid(unsafe_code)]
 
+pub mod config_file;
 pub mod dst;
 pub mod env;
 pub mod identity;
-pub mod oc_collector;
+pub mod metrics_export;
 pub mod tap;
+pub mod trace_collector;
 
 pub use self::metrics::Metrics;
 use futures::{future, Future, FutureExt};
@@ -23,6 +25,7 @@ use linkerd_app_core::{
     control::ControlAddr,
     dns, drain,
     metrics::FmtMetrics,
+    serve,
     svc::Param,
     transport::{listen::Bind, ClientAddr, Local, OrigDstAddr, Remote, ServerAddr},
     Error, ProxyRuntime,
@@ -60,16 +63,22 @@ pub struct Config {
     pub dst: dst::Config,
     pub admin: admin::Config,
     pub tap: tap::Config,
-    pub oc_collector: oc_collector::Config,
+    pub trace_collector: trace_collector::Config,
+    pub metrics_export: metrics_export::Config,
+    pub config_file: Option<config_file::Config>,
 }
 
 pub struct App {
     admin: admin::Task,
-    drain: drain::Signal,
+    // Drains the admin and tap servers, which continue serving while the
+    // proxy's connections are drained.
+    admin_drain: drain::Signal,
     dst: ControlAddr,
     identity: identity::Identity,
     inbound_addr: Local<ServerAddr>,
-    oc_collector: oc_collector::OcCollector,
+    trace_collector: trace_collector::TraceCollector,
+    metrics_export: Option<metrics_export::Task>,
+    config_file: Option<config_file::Task>,
     outbound_addr: Local<ServerAddr>,
     start_proxy: Pin<Box<dyn std::future::Future<Output = ()> + Send + 'static>>,
     tap: tap::Tap,
@@ -108,13 +117,18 @@ impl Config {
             dst,
             identity,
             inbound,
-            oc_collector,
+            trace_collector,
+            metrics_export,
+            config_file,
             outbound,
             gateway,
             tap,
         } = self;
         debug!("building app");
-        let (metrics, report) = Metrics::new(admin.metrics_retain_idle);
+        let (metrics, report) = Metrics::new(
+            admin.metrics_retain_idle,
+            admin.metrics_native_histogram_schema,
+        );
 
         let dns = dns.build();
 
@@ -125,11 +139,13 @@ impl Config {
         let report = identity.metrics().and_report(report);
 
         let (drain_tx, drain_rx) = drain::channel();
+        let (admin_drain_tx, admin_drain_rx) = drain::channel();
+        let connections = serve::Connections::default();
 
         let tap = {
             let bind = bind_admin.clone();
-            info_span!("tap")
-                .in_scope(|| tap.build(bind, identity.receiver().server(), drain_rx.clone()))?
+            let drain = admin_drain_rx.clone();
+            info_span!("tap").in_scope(|| tap.build(bind, identity.receiver().server(), drain))?
         };
 
         let dst = {
@@ -138,21 +154,24 @@ impl Config {
             info_span!("dst").in_scope(|| dst.build(dns, metrics, identity.receiver().new_client()))
         }?;
 
-        let oc_collector = {
+        let trace_collector = {
             let identity = identity.receiver().new_client();
             let dns = dns.resolver.clone();
             let client_metrics = metrics.control.clone();
-            let metrics = metrics.opencensus;
-            info_span!("opencensus")
-                .in_scope(|| oc_collector.build(identity, dns, metrics, client_metrics))
+            let oc_metrics = metrics.opencensus;
+            let otel_metrics = metrics.opentelemetry;
+            info_span!("tracing").in_scope(|| {
+                trace_collector.build(identity, dns, oc_metrics, otel_metrics, client_metrics)
+            })
         }?;
 
         let runtime = ProxyRuntime {
             identity: identity.receiver(),
             metrics: metrics.proxy.clone(),
             tap: tap.registry(),
-            span_sink: oc_collector.span_sink(),
-            drain: drain_rx.clone(),
+            span_sink: trace_collector.span_sink(),
+            drain: drain_rx,
+            connections: connections.clone(),
         };
         let inbound = Inbound::new(inbound, runtime.clone());
         let outbound = Outbound::new(outbound, runtime);
@@ -163,14 +182,21 @@ impl Config {
             info_span!("policy").in_scope(|| inbound.build_policies(dns, metrics))
         };
 
+        let report = inbound
+            .metrics()
+            .and_report(outbound.metrics())
+            .and_report(report);
+
+        let metrics_export = metrics_export.build(report.clone());
+
+        let config_file =
+            config_file.map(|config| config.build(inbound_policies.clone(), log_level.clone()));
+
         let admin = {
             let identity = identity.receiver().server();
             let metrics = inbound.metrics();
             let policy = inbound_policies.clone();
-            let report = inbound
-                .metrics()
-                .and_report(outbound.metrics())
-                .and_report(report);
+            let destinations = outbound.destinations();
             info_span!("admin").in_scope(move || {
                 admin.build(
                     bind_admin,
@@ -179,8 +205,11 @@ impl Config {
                     report,
                     metrics,
                     log_level,
-                    drain_rx,
+                    admin_drain_rx,
                     shutdown_tx,
+                    destinations,
+                    drain_tx,
+                    connections,
                 )
             })?
         };
@@ -237,10 +266,12 @@ impl Config {
         Ok(App {
             admin,
             dst: dst_addr,
-            drain: drain_tx,
+            admin_drain: admin_drain_tx,
             identity,
             inbound_addr,
-            oc_collector,
+            trace_collector,
+            metrics_export,
+            config_file,
             outbound_addr,
             start_proxy,
             tap,
@@ -295,24 +326,32 @@ impl App {
         self.identity.addr()
     }
 
-    pub fn opencensus_addr(&self) -> Option<&ControlAddr> {
-        match self.oc_collector {
-            oc_collector::OcCollector::Disabled { .. } => None,
-            oc_collector::OcCollector::Enabled(ref oc) => Some(&oc.addr),
+    pub fn tracing_addr(&self) -> Option<(trace_collector::Protocol, &ControlAddr)> {
+        match self.trace_collector {
+            trace_collector::TraceCollector::Disabled { .. } => None,
+            trace_collector::TraceCollector::Enabled(ref collector) => {
+                Some((collector.protocol, &collector.addr))
+            }
         }
     }
 
-    pub fn spawn(self) -> drain::Signal {
+    /// Spawns the proxy's tasks, returning a handle that drains the proxy's
+    /// connections before it shuts down.
+    pub fn spawn(self) -> admin::Drain {
         let App {
             admin,
-            drain,
+            admin_drain,
             identity,
-            oc_collector,
+            trace_collector,
+            metrics_export,
+            config_file,
             start_proxy,
             tap,
             ..
         } = self;
 
+        let drain = admin.drain.clone();
+
         // Run a daemon thread for all administrative tasks.
         //
         // The main reactor holds `admin_shutdown_tx` until the reactor drops
@@ -372,13 +411,29 @@ impl App {
                             tokio::spawn(serve.instrument(info_span!("tap").or_current()));
                         }
 
-                        if let oc_collector::OcCollector::Enabled(oc) = oc_collector {
-                            tokio::spawn(oc.task.instrument(info_span!("opencensus").or_current()));
+                        if let trace_collector::TraceCollector::Enabled(collector) = trace_collector
+                        {
+                            tokio::spawn(
+                                collector
+                                    .task
+                                    .instrument(info_span!("tracing").or_current()),
+                            );
+                        }
+
+                        if let Some(task) = metrics_export {
+                            tokio::spawn(
+                                task.instrument(info_span!("metrics_export").or_current()),
+                            );
+                        }
+
+                        if let Some(task) = config_file {
+                            tokio::spawn(task.instrument(info_span!("config_file").or_current()));
                         }
 
                         // we don't care if the admin shutdown channel is
                         // dropped or actually triggered.
                         let _ = admin_shutdown_rx.await;
+                        drop(admin_drain);
                     }
                     .instrument(info_span!("daemon")),
                 )
@@ -0,0 +1,340 @@
+//! Periodically pushes metrics to a Prometheus remote-write endpoint, for
+//! deployments in which proxies cannot be scraped.
+//!
+//! Snapshots are buffered while the endpoint is unavailable. When the buffer is
+//! full, the oldest snapshot is dropped.
+
+use bytes::Bytes;
+use futures::future;
+use hyper::client::HttpConnector;
+use linkerd_app_core::{
+    exp_backoff::ExponentialBackoff,
+    metrics::{remote_write, Encoder, FmtMetrics},
+    Error,
+};
+use std::{collections::VecDeque, future::Future, pin::Pin, time::SystemTime};
+use thiserror::Error;
+use tokio::time::{self, Duration, Instant};
+use tracing::{debug, warn};
+
+#[derive(Clone, Debug)]
+pub enum Config {
+    Disabled,
+    Enabled(Box<EnabledConfig>),
+}
+
+#[derive(Clone, Debug)]
+pub struct EnabledConfig {
+    /// The remote-write endpoint, which must be a plaintext HTTP URL.
+    pub url: http::Uri,
+    pub interval: Duration,
+    pub timeout: Duration,
+    pub backoff: ExponentialBackoff,
+    /// The maximum number of snapshots buffered while the endpoint is
+    /// unavailable.
+    pub buffer_capacity: usize,
+    /// Labels added to each exported time series.
+    pub labels: Vec<(String, String)>,
+}
+
+pub type Task = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+struct Exporter<R> {
+    config: EnabledConfig,
+    report: R,
+    client: hyper::Client<HttpConnector>,
+    buffer: VecDeque<Bytes>,
+}
+
+#[derive(Debug, Error)]
+#[error("remote-write endpoint responded with {0}")]
+struct UnexpectedStatus(http::StatusCode);
+
+enum PushError {
+    /// The request may succeed if it is retried.
+    Retryable(Error),
+    /// The endpoint will never accept the request.
+    Rejected(http::StatusCode),
+}
+
+// === impl Config ===
+
+impl Config {
+    /// Builds a task that exports the given metrics, if export is enabled.
+    pub fn build<R>(self, report: R) -> Option<Task>
+    where
+        R: FmtMetrics + Send + Sync + 'static,
+    {
+        match self {
+            Config::Disabled => None,
+            Config::Enabled(config) => {
+                let exporter = Exporter {
+                    buffer: VecDeque::with_capacity(config.buffer_capacity),
+                    config: *config,
+                    report,
+                    client: hyper::Client::new(),
+                };
+                Some(Box::pin(exporter.run()))
+            }
+        }
+    }
+}
+
+// === impl Exporter ===
+
+impl<R: FmtMetrics> Exporter<R> {
+    async fn run(mut self) {
+        let mut interval =
+            time::interval_at(Instant::now() + self.config.interval, self.config.interval);
+        interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+
+        // While the endpoint is failing, snapshots are buffered until the
+        // backoff elapses.
+        let mut failures = 0;
+        let mut retry_at = None::<Instant>;
+        loop {
+            let retry = async move {
+                match retry_at {
+                    Some(at) => time::sleep_until(at).await,
+                    None => future::pending().await,
+                }
+            };
+            tokio::select! {
+                _ = interval.tick() => self.snapshot(),
+                () = retry => {}
+            }
+            if retry_at.map(|at| at > Instant::now()).unwrap_or(false) {
+                continue;
+            }
+
+            match self.flush().await {
+                Ok(()) => {
+                    failures = 0;
+                    retry_at = None;
+                }
+                Err(error) => {
+                    let backoff = self
+                        .config
+                        .backoff
+                        .duration(failures, &mut rand::thread_rng());
+                    failures = failures.saturating_add(1);
+                    warn!(
+                        %error,
+                        buffered = self.buffer.len(),
+                        ?backoff,
+                        "Failed to export metrics",
+                    );
+                    retry_at = Some(Instant::now() + backoff);
+                }
+            }
+        }
+    }
+
+    fn snapshot(&mut self) {
+        let body = remote_write::encode(&self.report, &self.config.labels, SystemTime::now());
+        if self.buffer.len() >= self.config.buffer_capacity {
+            warn!("Dropping the oldest unexported metrics");
+            self.buffer.pop_front();
+        }
+        self.buffer.push_back(body.into());
+    }
+
+    /// Sends buffered snapshots, oldest first, until the buffer is empty or a
+    /// request fails.
+    async fn flush(&mut self) -> Result<(), Error> {
+        while let Some(body) = self.buffer.front().cloned() {
+            match self.push(body).await {
+                Ok(()) => {}
+                Err(PushError::Rejected(status)) => {
+                    warn!(%status, "Dropping metrics rejected by the remote-write endpoint");
+                }
+                Err(PushError::Retryable(error)) => return Err(error),
+            }
+            self.buffer.pop_front();
+        }
+        Ok(())
+    }
+
+    async fn push(&self, body: Bytes) -> Result<(), PushError> {
+        let req = http::Request::post(self.config.url.clone())
+            .header(http::header::CONTENT_TYPE, remote_write::CONTENT_TYPE)
+            .header(
+                http::header::CONTENT_ENCODING,
+                remote_write::CONTENT_ENCODING,
+            )
+            .header("x-prometheus-remote-write-version", remote_write::VERSION)
+            .header(http::header::USER_AGENT, "linkerd-proxy")
+            .body(hyper::Body::from(body))
+            .map_err(|e| PushError::Retryable(e.into()))?;
+
+        let rsp = match time::timeout(self.config.timeout, self.client.request(req)).await {
+            Ok(Ok(rsp)) => rsp,
+            Ok(Err(error)) => return Err(PushError::Retryable(error.into())),
+            Err(elapsed) => return Err(PushError::Retryable(elapsed.into())),
+        };
+
+        let status = rsp.status();
+        debug!(%status, "Exported metrics");
+        if status.is_success() {
+            Ok(())
+        } else if status.is_server_error() || status == http::StatusCode::TOO_MANY_REQUESTS {
+            Err(PushError::Retryable(UnexpectedStatus(status).into()))
+        } else {
+            Err(PushError::Rejected(status))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::service::{make_service_fn, service_fn};
+    use linkerd_app_core::metrics::{metrics, Counter};
+    use std::{convert::Infallible, fmt, net::SocketAddr};
+    use tokio::sync::mpsc;
+
+    metrics! {
+        request_total: Counter { "Total requests" }
+    }
+
+    struct Metrics;
+    impl FmtMetrics for Metrics {
+        fn fmt_metrics(&self, f: &mut Encoder<'_>) -> fmt::Result {
+            request_total.fmt_help(f)?;
+            request_total.fmt_metric(f, &Counter::from(1))
+        }
+    }
+
+    /// Serves a stub remote-write endpoint that responds with each of the
+    /// given statuses in turn, sending each request it receives on a channel.
+    fn stub(
+        mut statuses: Vec<http::StatusCode>,
+    ) -> (SocketAddr, mpsc::UnboundedReceiver<http::Request<Bytes>>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        statuses.reverse();
+        let statuses = std::sync::Arc::new(parking_lot::Mutex::new(statuses));
+        let make = make_service_fn(move |_| {
+            let tx = tx.clone();
+            let statuses = statuses.clone();
+            future::ok::<_, Infallible>(service_fn(move |req: http::Request<hyper::Body>| {
+                let tx = tx.clone();
+                let status = statuses
+                    .lock()
+                    .pop()
+                    .unwrap_or(http::StatusCode::NO_CONTENT);
+                async move {
+                    let (head, body) = req.into_parts();
+                    let body = hyper::body::to_bytes(body).await.unwrap();
+                    let _ = tx.send(http::Request::from_parts(head, body));
+                    let rsp = http::Response::builder()
+                        .status(status)
+                        .body(hyper::Body::empty())
+                        .unwrap();
+                    Ok::<_, Infallible>(rsp)
+                }
+            }))
+        });
+        let server = hyper::Server::bind(&([127, 0, 0, 1], 0).into()).serve(make);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+        (addr, rx)
+    }
+
+    fn config(addr: SocketAddr, buffer_capacity: usize) -> Config {
+        Config::Enabled(Box::new(EnabledConfig {
+            url: format!("http://{}/api/v1/write", addr).parse().unwrap(),
+            interval: Duration::from_millis(10),
+            timeout: Duration::from_secs(1),
+            backoff: ExponentialBackoff::try_new(
+                Duration::from_millis(50),
+                Duration::from_millis(50),
+                0.0,
+            )
+            .unwrap(),
+            buffer_capacity,
+            labels: vec![("instance".to_string(), "proxy-1".to_string())],
+        }))
+    }
+
+    #[tokio::test]
+    async fn pushes_snapshots() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let (addr, mut reqs) = stub(vec![]);
+        let task = config(addr, 10).build(Metrics).expect("must be enabled");
+        let task = tokio::spawn(task);
+
+        let req = reqs.recv().await.unwrap();
+        assert_eq!(req.method(), http::Method::POST);
+        assert_eq!(req.uri().path(), "/api/v1/write");
+        assert_eq!(
+            req.headers()[http::header::CONTENT_TYPE],
+            remote_write::CONTENT_TYPE
+        );
+        assert_eq!(req.headers()[http::header::CONTENT_ENCODING], "snappy");
+        assert_eq!(
+            req.headers()["x-prometheus-remote-write-version"],
+            remote_write::VERSION
+        );
+        assert!(!req.body().is_empty());
+
+        // Snapshots continue to be pushed.
+        reqs.recv().await.unwrap();
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn retries_failed_pushes() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let (addr, mut reqs) = stub(vec![
+            http::StatusCode::SERVICE_UNAVAILABLE,
+            http::StatusCode::SERVICE_UNAVAILABLE,
+        ]);
+        let task = config(addr, 100).build(Metrics).expect("must be enabled");
+        let task = tokio::spawn(task);
+
+        // The first snapshot is retried after each failure.
+        let first = reqs.recv().await.unwrap().into_body();
+        let retried = reqs.recv().await.unwrap().into_body();
+        assert_eq!(first, retried);
+        let succeeded = reqs.recv().await.unwrap().into_body();
+        assert_eq!(first, succeeded);
+
+        // Snapshots taken while backing off are then sent in order.
+        let next = reqs.recv().await.unwrap().into_body();
+        assert_ne!(first, next);
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn drops_rejected_and_oldest_snapshots() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let (addr, _reqs) = stub(vec![]);
+        let exporter = Exporter {
+            config: match config(addr, 2) {
+                Config::Enabled(c) => *c,
+                Config::Disabled => unreachable!(),
+            },
+            report: Metrics,
+            client: hyper::Client::new(),
+            buffer: VecDeque::new(),
+        };
+        let mut exporter = exporter;
+        for _ in 0..3 {
+            exporter.snapshot();
+            // Snapshots are timestamped in milliseconds.
+            time::sleep(Duration::from_millis(2)).await;
+        }
+        assert_eq!(exporter.buffer.len(), 2);
+
+        let (addr, mut reqs) = stub(vec![http::StatusCode::BAD_REQUEST]);
+        exporter.config.url = format!("http://{}/api/v1/write", addr).parse().unwrap();
+        let buffered = exporter.buffer.clone();
+        exporter.flush().await.expect("rejections must not fail");
+        assert!(exporter.buffer.is_empty());
+        assert_eq!(reqs.recv().await.unwrap().into_body(), buffered[0]);
+        assert_eq!(reqs.recv().await.unwrap().into_body(), buffered[1]);
+    }
+}
@@ -84,7 +84,12 @@ impl Config {
                     .check_new_service::<B::Addrs, _>()
                     .into_inner();
 
-                let serve = Box::pin(serve::serve(listen, accept, drain.signaled()));
+                let serve = Box::pin(serve::serve(
+                    listen,
+                    accept,
+                    drain.signaled(),
+                    Default::default(),
+                ));
 
                 Ok(Tap::Enabled {
                     listen_addr,
@@ -0,0 +1,183 @@
+use linkerd_app_core::{
+    control, dns, http_tracing, identity, metrics::ControlHttp as HttpMetrics, opencensus,
+    opentelemetry, svc::NewService, trace_context, Error,
+};
+use std::{collections::HashMap, future::Future, pin::Pin};
+use thiserror::Error;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+pub mod oc_collector;
+pub mod otel_collector;
+
+const SPAN_BUFFER_CAPACITY: usize = 100;
+const SERVICE_NAME: &str = "linkerd-proxy";
+
+#[derive(Clone, Debug)]
+pub enum Config {
+    Disabled,
+    Enabled(Box<EnabledConfig>),
+}
+
+#[derive(Clone, Debug)]
+pub struct EnabledConfig {
+    pub control: control::Config,
+    pub attributes: HashMap<String, String>,
+    pub hostname: Option<String>,
+    pub propagations: trace_context::Propagations,
+    pub sampler: trace_context::Sampler,
+    pub protocol: Protocol,
+}
+
+/// The protocol used to export spans to the collector.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Protocol {
+    OpenCensus,
+    OpenTelemetry,
+}
+
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+#[error("unknown trace protocol {0:?}; expected 'opencensus' or 'opentelemetry'")]
+pub struct InvalidProtocol(String);
+
+pub type Task = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+pub enum TraceCollector {
+    Disabled,
+    Enabled(Box<EnabledCollector>),
+}
+
+pub struct EnabledCollector {
+    pub addr: control::ControlAddr,
+    pub protocol: Protocol,
+    pub span_sink: http_tracing::SpanSink,
+    pub task: Task,
+}
+
+#[derive(Debug, Error)]
+#[error("ID '{:?} should have {} bytes, but it has {}", self.id, self.expected_size, self.actual_size)]
+struct IdLengthError {
+    id: Vec<u8>,
+    expected_size: usize,
+    actual_size: usize,
+}
+
+// === impl Config ===
+
+impl Config {
+    pub fn build(
+        self,
+        identity: identity::NewClient,
+        dns: dns::Resolver,
+        oc_metrics: opencensus::metrics::Registry,
+        otel_metrics: opentelemetry::metrics::Registry,
+        client_metrics: HttpMetrics,
+    ) -> Result<TraceCollector, Error> {
+        match self {
+            Config::Disabled => Ok(TraceCollector::Disabled),
+            Config::Enabled(inner) => {
+                let addr = inner.control.addr.clone();
+                let svc = inner
+                    .control
+                    .build(dns, client_metrics, identity)
+                    .new_service(());
+
+                let (spans_tx, spans_rx) = mpsc::channel(SPAN_BUFFER_CAPACITY);
+                let span_sink = http_tracing::SpanSink {
+                    spans: spans_tx,
+                    propagations: inner.propagations,
+                    sampler: inner.sampler,
+                };
+                let spans_rx = ReceiverStream::new(spans_rx);
+
+                let task = match inner.protocol {
+                    Protocol::OpenCensus => oc_collector::create_collector(
+                        addr.clone(),
+                        inner.hostname,
+                        inner.attributes,
+                        svc,
+                        spans_rx,
+                        oc_metrics,
+                    ),
+                    Protocol::OpenTelemetry => otel_collector::create_collector(
+                        addr.clone(),
+                        inner.hostname,
+                        inner.attributes,
+                        svc,
+                        spans_rx,
+                        otel_metrics,
+                    ),
+                };
+
+                Ok(TraceCollector::Enabled(Box::new(EnabledCollector {
+                    addr,
+                    protocol: inner.protocol,
+                    task,
+                    span_sink,
+                })))
+            }
+        }
+    }
+}
+
+// === impl Protocol ===
+
+impl Default for Protocol {
+    fn default() -> Self {
+        Self::OpenCensus
+    }
+}
+
+impl std::str::FromStr for Protocol {
+    type Err = InvalidProtocol;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            s if s.eq_ignore_ascii_case("opencensus") => Ok(Self::OpenCensus),
+            s if s.eq_ignore_ascii_case("opentelemetry") => Ok(Self::OpenTelemetry),
+            s => Err(InvalidProtocol(s.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for Protocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OpenCensus => "OpenCensus".fmt(f),
+            Self::OpenTelemetry => "OpenTelemetry".fmt(f),
+        }
+    }
+}
+
+// === impl TraceCollector ===
+
+impl TraceCollector {
+    pub fn span_sink(&self) -> Option<http_tracing::SpanSink> {
+        match self {
+            TraceCollector::Disabled => None,
+            TraceCollector::Enabled(inner) => Some(inner.span_sink.clone()),
+        }
+    }
+}
+
+/// Spans in traces started by the proxy have no parent.
+fn parent_into_bytes(id: trace_context::Id) -> Result<Vec<u8>, IdLengthError> {
+    if id.is_empty() {
+        return Ok(Vec::new());
+    }
+    into_bytes(id, 8)
+}
+
+fn into_bytes(id: trace_context::Id, size: usize) -> Result<Vec<u8>, IdLengthError> {
+    let bytes: Vec<u8> = id.into();
+    if bytes.len() == size {
+        Ok(bytes)
+    } else {
+        let actual_size = bytes.len();
+        Err(IdLengthError {
+            id: bytes,
+            expected_size: size,
+            actual_size,
+        })
+    }
+}
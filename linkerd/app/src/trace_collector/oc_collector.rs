@@ -0,0 +1,120 @@
+use super::{into_bytes, parent_into_bytes, IdLengthError, Task, SERVICE_NAME};
+use bytes::Bytes;
+use linkerd_app_core::{
+    control::ControlAddr,
+    http_tracing::{ExportSpan, SpanKind},
+    proxy::http::HttpBody,
+    Error,
+};
+use linkerd_opencensus::{
+    self as opencensus, metrics,
+    proto::{self, trace::v1 as oc},
+};
+use std::{collections::HashMap, time::SystemTime};
+use tokio_stream::{wrappers::ReceiverStream, StreamExt};
+use tonic::{body::BoxBody, client::GrpcService};
+use tracing::Instrument;
+
+pub(super) fn create_collector<S>(
+    addr: ControlAddr,
+    hostname: Option<String>,
+    attributes: HashMap<String, String>,
+    svc: S,
+    spans: ReceiverStream<ExportSpan>,
+    metrics: metrics::Registry,
+) -> Task
+where
+    S: GrpcService<BoxBody> + Clone + Send + 'static,
+    S::Error: Into<Error>,
+    S::Future: Send,
+    S::ResponseBody: Default + HttpBody<Data = Bytes> + Send + 'static,
+    <S::ResponseBody as HttpBody>::Error: Into<Error> + Send,
+{
+    use self::proto::agent::common::v1 as oc;
+
+    let node = oc::Node {
+        identifier: Some(oc::ProcessIdentifier {
+            host_name: hostname.unwrap_or_default(),
+            pid: std::process::id(),
+            start_timestamp: Some(SystemTime::now().into()),
+        }),
+        service_info: Some(oc::ServiceInfo {
+            name: SERVICE_NAME.to_string(),
+        }),
+        attributes,
+        ..oc::Node::default()
+    };
+
+    let spans = spans.filter_map(|span| match convert_span(span) {
+        Ok(span) => Some(span),
+        Err(error) => {
+            tracing::debug!(%error, "Dropping invalid span");
+            None
+        }
+    });
+
+    Box::pin(
+        opencensus::export_spans(svc, node, spans, metrics)
+            .instrument(tracing::debug_span!("opencensus", peer.addr = %addr).or_current()),
+    )
+}
+
+fn convert_span(
+    ExportSpan {
+        mut span,
+        kind,
+        labels,
+    }: ExportSpan,
+) -> Result<oc::Span, IdLengthError> {
+    let mut attributes = HashMap::<String, oc::AttributeValue>::new();
+    for (k, v) in labels.iter() {
+        attributes.insert(
+            k.clone(),
+            oc::AttributeValue {
+                value: Some(oc::attribute_value::Value::StringValue(truncatable(
+                    v.clone(),
+                ))),
+            },
+        );
+    }
+    for (k, v) in span.labels.drain() {
+        attributes.insert(
+            k.into_owned(),
+            oc::AttributeValue {
+                value: Some(oc::attribute_value::Value::StringValue(truncatable(v))),
+            },
+        );
+    }
+    let kind = match kind {
+        SpanKind::Server => oc::span::SpanKind::Server,
+        SpanKind::Client => oc::span::SpanKind::Client,
+    };
+    Ok(oc::Span {
+        trace_id: into_bytes(span.trace_id, 16)?,
+        span_id: into_bytes(span.span_id, 8)?,
+        tracestate: None,
+        parent_span_id: parent_into_bytes(span.parent_id)?,
+        name: Some(truncatable(span.span_name)),
+        kind: kind as i32,
+        start_time: Some(span.start.into()),
+        end_time: Some(span.end.into()),
+        attributes: Some(oc::span::Attributes {
+            attribute_map: attributes,
+            dropped_attributes_count: 0,
+        }),
+        stack_trace: None,
+        time_events: None,
+        links: None,
+        status: None, // TODO: this is gRPC status; we must read response trailers to populate this
+        resource: None,
+        same_process_as_parent_span: Some(kind == oc::span::SpanKind::Client),
+        child_span_count: None,
+    })
+}
+
+fn truncatable(value: String) -> oc::TruncatableString {
+    oc::TruncatableString {
+        value,
+        truncated_byte_count: 0,
+    }
+}
@@ -0,0 +1,192 @@
+use super::{into_bytes, parent_into_bytes, IdLengthError, Task, SERVICE_NAME};
+use bytes::Bytes;
+use linkerd_app_core::{
+    control::ControlAddr,
+    http_tracing::{ExportSpan, SpanKind},
+    proxy::http::HttpBody,
+    Error,
+};
+use linkerd_opentelemetry::{
+    self as opentelemetry, metrics,
+    proto::{
+        common::v1::{any_value, AnyValue, KeyValue},
+        resource::v1::Resource,
+        trace::v1 as otel,
+    },
+};
+use std::{collections::HashMap, time::SystemTime};
+use tokio_stream::{wrappers::ReceiverStream, StreamExt};
+use tonic::{body::BoxBody, client::GrpcService};
+use tracing::Instrument;
+
+pub(super) fn create_collector<S>(
+    addr: ControlAddr,
+    hostname: Option<String>,
+    attributes: HashMap<String, String>,
+    svc: S,
+    spans: ReceiverStream<ExportSpan>,
+    metrics: metrics::Registry,
+) -> Task
+where
+    S: GrpcService<BoxBody> + Clone + Send + 'static,
+    S::Error: Into<Error>,
+    S::Future: Send,
+    S::ResponseBody: Default + HttpBody<Data = Bytes> + Send + 'static,
+    <S::ResponseBody as HttpBody>::Error: Into<Error> + Send,
+{
+    // Attributes from the configured attributes file may not override the
+    // process attributes set by the proxy.
+    let mut attributes = attributes
+        .into_iter()
+        .map(|(k, v)| string_kv(k, v))
+        .collect::<Vec<_>>();
+    attributes.retain(|kv| !matches!(&*kv.key, "service.name" | "host.name" | "process.pid"));
+    attributes.push(string_kv(
+        "service.name".to_string(),
+        SERVICE_NAME.to_string(),
+    ));
+    if let Some(hostname) = hostname {
+        attributes.push(string_kv("host.name".to_string(), hostname));
+    }
+    attributes.push(KeyValue {
+        key: "process.pid".to_string(),
+        value: Some(AnyValue {
+            value: Some(any_value::Value::IntValue(std::process::id().into())),
+        }),
+    });
+    let resource = Resource {
+        attributes,
+        dropped_attributes_count: 0,
+    };
+
+    let spans = spans.filter_map(|span| match convert_span(span) {
+        Ok(span) => Some(span),
+        Err(error) => {
+            tracing::debug!(%error, "Dropping invalid span");
+            None
+        }
+    });
+
+    Box::pin(
+        opentelemetry::export_spans(svc, resource, spans, metrics)
+            .instrument(tracing::debug_span!("opentelemetry", peer.addr = %addr).or_current()),
+    )
+}
+
+fn convert_span(
+    ExportSpan {
+        mut span,
+        kind,
+        labels,
+    }: ExportSpan,
+) -> Result<otel::Span, IdLengthError> {
+    // Span labels take precedence over the stack's labels.
+    let mut attributes = (*labels).clone();
+    for (k, v) in span.labels.drain() {
+        attributes.insert(k.into_owned(), v);
+    }
+    let kind = match kind {
+        SpanKind::Server => otel::span::SpanKind::Server,
+        SpanKind::Client => otel::span::SpanKind::Client,
+    };
+    Ok(otel::Span {
+        trace_id: into_bytes(span.trace_id, 16)?,
+        span_id: into_bytes(span.span_id, 8)?,
+        parent_span_id: parent_into_bytes(span.parent_id)?,
+        name: span.span_name,
+        kind: kind as i32,
+        start_time_unix_nano: unix_nanos(span.start),
+        end_time_unix_nano: unix_nanos(span.end),
+        attributes: attributes
+            .into_iter()
+            .map(|(k, v)| string_kv(k, v))
+            .collect(),
+        // TODO: this is gRPC status; we must read response trailers to populate this
+        status: None,
+        ..Default::default()
+    })
+}
+
+fn string_kv(key: String, value: String) -> KeyValue {
+    KeyValue {
+        key,
+        value: Some(AnyValue {
+            value: Some(any_value::Value::StringValue(value)),
+        }),
+    }
+}
+
+fn unix_nanos(t: SystemTime) -> u64 {
+    linkerd_app_core::metrics::unix_time(t).as_nanos() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::trace_context;
+    use std::{
+        sync::Arc,
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    #[test]
+    fn converts_span() {
+        let start = UNIX_EPOCH + Duration::from_secs(1);
+        let span = ExportSpan {
+            span: trace_context::Span {
+                trace_id: trace_context::Id::from(vec![1; 16]),
+                span_id: trace_context::Id::from(vec![2; 8]),
+                parent_id: trace_context::Id::from(vec![3; 8]),
+                span_name: "GET /".to_string(),
+                start,
+                end: start + Duration::from_millis(5),
+                labels: Some(("http.status_code".into(), "200".to_string()))
+                    .into_iter()
+                    .collect(),
+            },
+            kind: SpanKind::Server,
+            labels: Arc::new(
+                Some(("direction".to_string(), "inbound".to_string()))
+                    .into_iter()
+                    .collect(),
+            ),
+        };
+
+        let span = convert_span(span).expect("span must convert");
+        assert_eq!(span.trace_id, vec![1; 16]);
+        assert_eq!(span.parent_span_id, vec![3; 8]);
+        assert_eq!(span.kind, otel::span::SpanKind::Server as i32);
+        assert_eq!(span.start_time_unix_nano, 1_000_000_000);
+        assert_eq!(span.end_time_unix_nano, 1_005_000_000);
+        let mut attributes = span
+            .attributes
+            .iter()
+            .map(
+                |kv| match kv.value.as_ref().and_then(|v| v.value.as_ref()) {
+                    Some(any_value::Value::StringValue(v)) => (kv.key.as_str(), v.as_str()),
+                    v => panic!("unexpected attribute value: {:?}", v),
+                },
+            )
+            .collect::<Vec<_>>();
+        attributes.sort_unstable();
+        assert_eq!(
+            attributes,
+            [("direction", "inbound"), ("http.status_code", "200")]
+        );
+
+        let invalid = ExportSpan {
+            span: trace_context::Span {
+                trace_id: trace_context::Id::from(vec![1; 8]),
+                span_id: trace_context::Id::from(vec![2; 8]),
+                parent_id: trace_context::Id::from(vec![3; 8]),
+                span_name: "GET /".to_string(),
+                start,
+                end: start,
+                labels: Default::default(),
+            },
+            kind: SpanKind::Client,
+            labels: Default::default(),
+        };
+        assert!(convert_span(invalid).is_err());
+    }
+}
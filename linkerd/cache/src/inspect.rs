@@ -0,0 +1,129 @@
+use super::Services;
+use parking_lot::Mutex;
+use std::{
+    hash::Hash,
+    sync::{Arc, Weak},
+};
+use tokio::time;
+
+/// Observes the entries of one or more caches.
+///
+/// Caches are registered when they are built and are forgotten once they are
+/// dropped.
+pub struct Inspect<T> {
+    caches: Arc<Mutex<Vec<Weak<dyn Entries<T> + Send + Sync>>>>,
+}
+
+trait Entries<T> {
+    fn idle_ages(&self, now: time::Instant, ages: &mut Vec<(T, Option<time::Duration>)>);
+}
+
+// === impl Inspect ===
+
+impl<T> Inspect<T> {
+    pub(crate) fn register<S>(&self, services: &Arc<Services<T, S>>)
+    where
+        T: Clone + Eq + Hash + Send + Sync + 'static,
+        S: Send + Sync + 'static,
+    {
+        let services = Arc::downgrade(services) as Weak<dyn Entries<T> + Send + Sync>;
+        let mut caches = self.caches.lock();
+        caches.retain(|c| c.strong_count() > 0);
+        caches.push(services);
+    }
+
+    /// Returns each cached target with the time since its service was last
+    /// used, or `None` if the service is currently in use.
+    pub fn idle_ages(&self) -> Vec<(T, Option<time::Duration>)> {
+        let now = time::Instant::now();
+        let mut ages = Vec::new();
+        for cache in self.caches.lock().iter() {
+            if let Some(cache) = cache.upgrade() {
+                cache.idle_ages(now, &mut ages);
+            }
+        }
+        ages
+    }
+}
+
+impl<T> Clone for Inspect<T> {
+    fn clone(&self) -> Self {
+        Self {
+            caches: self.caches.clone(),
+        }
+    }
+}
+
+impl<T> Default for Inspect<T> {
+    fn default() -> Self {
+        Self {
+            caches: Default::default(),
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for Inspect<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Inspect")
+            .field("caches", &self.caches.lock().len())
+            .finish()
+    }
+}
+
+// === impl Entries ===
+
+impl<T: Clone, S> Entries<T> for Services<T, S> {
+    fn idle_ages(&self, now: time::Instant, ages: &mut Vec<(T, Option<time::Duration>)>) {
+        for (target, (_, handle)) in self.read().iter() {
+            if let Some(handle) = handle.upgrade() {
+                // The eviction task and this function each hold a reference to
+                // the handle. Any other references are held by `Cached`
+                // services that are in use.
+                let age = if Arc::strong_count(&handle) > 2 {
+                    None
+                } else {
+                    Some(now.saturating_duration_since(*handle.released_at.lock()))
+                };
+                ages.push((target.clone(), age));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Cache;
+    use linkerd_stack::{layer::Layer, NewService};
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn reports_idle_ages() {
+        time::pause();
+
+        let idle = time::Duration::from_secs(10);
+        let inspect = Inspect::default();
+        let cache = Cache::layer_inspect(idle, inspect.clone()).layer(|_: &'static str| ());
+
+        let a = cache.new_service("a");
+        let b = cache.new_service("b");
+        drop(b);
+        time::advance(time::Duration::from_secs(3)).await;
+
+        let mut ages = inspect.idle_ages();
+        ages.sort_by_key(|(t, _)| *t);
+        assert_eq!(
+            ages,
+            vec![("a", None), ("b", Some(time::Duration::from_secs(3)))]
+        );
+
+        // Entries are no longer reported once they are evicted.
+        drop(a);
+        time::sleep(idle * 2).await;
+        assert_eq!(inspect.idle_ages(), vec![]);
+
+        // Dropped caches are no longer observed.
+        let _ = cache.new_service("c");
+        drop(cache);
+        assert_eq!(inspect.idle_ages(), vec![]);
+    }
+}
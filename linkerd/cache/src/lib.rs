@@ -7,7 +7,7 @@
 #![forbid(unsafe_code)]
 
 use linkerd_stack::{layer, NewService};
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use std::{
     collections::{hash_map::Entry, HashMap},
     hash::Hash,
@@ -17,6 +17,10 @@ use std::{
 use tokio::{sync::Notify, time};
 use tracing::{debug, instrument, trace};
 
+mod inspect;
+
+pub use self::inspect::Inspect;
+
 #[derive(Clone)]
 pub struct Cache<T, N>
 where
@@ -35,10 +39,17 @@ where
 {
     inner: S,
     // Notifies entry's eviction task that a drop has occurred.
-    handle: Arc<Notify>,
+    handle: Arc<Handle>,
+}
+
+#[derive(Debug)]
+struct Handle {
+    notify: Notify,
+    // The last time a `Cached` instance was dropped.
+    released_at: Mutex<time::Instant>,
 }
 
-type Services<T, S> = RwLock<HashMap<T, (S, Weak<Notify>)>>;
+type Services<T, S> = RwLock<HashMap<T, (S, Weak<Handle>)>>;
 
 // === impl Cache ===
 
@@ -52,6 +63,18 @@ where
         layer::mk(move |inner| Self::new(idle, inner))
     }
 
+    /// Like `layer`, but each cache's entries may be observed via `inspect`.
+    pub fn layer_inspect(
+        idle: time::Duration,
+        inspect: Inspect<T>,
+    ) -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(move |inner| {
+            let cache = Self::new(idle, inner);
+            inspect.register(&cache.services);
+            cache
+        })
+    }
+
     fn new(idle: time::Duration, inner: N) -> Self {
         let services = Arc::new(Services::default());
         Self {
@@ -65,12 +88,15 @@ where
         target: T,
         idle: time::Duration,
         cache: &Arc<Services<T, N::Service>>,
-    ) -> Arc<Notify> {
+    ) -> Arc<Handle> {
         // Spawn a background task that holds the handle. Every time the handle
         // is notified, it resets the idle timeout. Every time teh idle timeout
         // expires, the handle is checked and the service is dropped if there
         // are no active handles.
-        let handle = Arc::new(Notify::new());
+        let handle = Arc::new(Handle {
+            notify: Notify::new(),
+            released_at: Mutex::new(time::Instant::now()),
+        });
         tokio::spawn(Self::evict(
             target,
             idle,
@@ -84,11 +110,11 @@ where
     async fn evict(
         target: T,
         idle: time::Duration,
-        mut reset: Arc<Notify>,
+        mut reset: Arc<Handle>,
         cache: Weak<Services<T, N::Service>>,
     ) {
         // Wait for the handle to be notified before starting to track idleness.
-        reset.notified().await;
+        reset.notify.notified().await;
         debug!("Awaiting idleness");
 
         // Wait for either the reset to be notified or the idle timeout to
@@ -98,7 +124,7 @@ where
                 biased;
 
                 // If the reset was notified, restart the timer.
-                _ = reset.notified() => {
+                _ = reset.notify.notified() => {
                     trace!("Reset");
                 }
                 _ = time::sleep(idle) => match cache.upgrade() {
@@ -209,7 +235,8 @@ where
     S: Send + Sync + 'static,
 {
     fn drop(&mut self) {
-        self.handle.notify_one();
+        *self.handle.released_at.lock() = time::Instant::now();
+        self.handle.notify.notify_one();
     }
 }
 
@@ -0,0 +1,623 @@
+//! Limits the number of requests in flight to a target, adapting the limit to
+//! the latency that the target exhibits.
+//!
+//! Each target's [`Limiter`] starts with an initial limit. As responses
+//! complete, the limit is adjusted according to the configured [`Algorithm`]:
+//! it grows while the target keeps up with its load and shrinks when latency
+//! rises or requests fail. While the limit is reached, the service is not
+//! ready. If a failfast timeout is configured and the service stays unready for
+//! that long, requests are shed via [`FailFast`] and fail with a
+//! [`ConcurrencyLimited`] error. Otherwise, the service remains unready until a
+//! request completes (e.g. so that a balancer prefers other endpoints).
+
+#![deny(
+    warnings,
+    rust_2018_idioms,
+    clippy::disallowed_methods,
+    clippy::disallowed_types
+)]
+#![forbid(unsafe_code)]
+
+use futures::{future, prelude::*, ready};
+use linkerd_error::Error;
+use linkerd_metrics::Counter;
+use linkerd_stack::{
+    layer::{self, Layer},
+    ExtractParam, FailFast, NewService, Service,
+};
+use parking_lot::Mutex;
+use pin_project::pin_project;
+use std::{
+    convert::Infallible,
+    pin::Pin,
+    sync::{Arc, Weak},
+    task::{Context, Poll, Waker},
+};
+use thiserror::Error;
+use tokio::time::{Duration, Instant};
+use tracing::{debug, trace};
+
+/// The number of samples over which the gradient algorithm averages its
+/// long-term latency.
+const LONG_WINDOW: f64 = 600.0;
+
+/// Configures how a target's concurrency limit is adjusted.
+#[derive(Copy, Clone, Debug)]
+pub struct Config {
+    pub algorithm: Algorithm,
+    pub initial_limit: usize,
+    pub min_limit: usize,
+    pub max_limit: usize,
+
+    /// The amount of time that requests may wait for the limit before they
+    /// are shed. Requests are never shed when unset.
+    pub failfast_timeout: Option<Duration>,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum Algorithm {
+    /// Increases the limit by one when a request completes while at least
+    /// half of the limit is in use. The limit is multiplied by `backoff_ratio`
+    /// when a request fails or takes longer than `max_latency`.
+    Aimd {
+        backoff_ratio: f64,
+        max_latency: Duration,
+    },
+
+    /// Scales the limit by the ratio of the target's long-term average latency
+    /// to the latency of each request, so that the limit shrinks as requests
+    /// queue at the target. `tolerance` is the degree to which latency may
+    /// rise before the limit shrinks, and `smoothing` determines how quickly
+    /// the limit moves toward each new estimate.
+    Gradient { tolerance: f64, smoothing: f64 },
+}
+
+/// The request was shed because its target was at its concurrency limit.
+#[derive(Clone, Debug, Error)]
+#[error("concurrency limit of {limit} requests exceeded")]
+pub struct ConcurrencyLimited {
+    limit: usize,
+}
+
+/// Tracks a target's concurrency limit and its in-flight requests.
+#[derive(Clone, Debug)]
+pub struct Limiter(Arc<Shared>);
+
+#[derive(Clone, Debug)]
+pub struct WeakLimiter(Weak<Shared>);
+
+/// Builds services that are limited by the `Limiter` obtained via `X`. When no
+/// limiter is provided, requests are not limited.
+#[derive(Clone, Debug)]
+pub struct NewAdaptiveLimit<X, N> {
+    extract: X,
+    inner: N,
+}
+
+#[derive(Debug)]
+pub struct AdaptiveLimit<S> {
+    inner: S,
+    limit: Option<Limit>,
+}
+
+#[pin_project(project = ResponseFutureProj)]
+#[derive(Debug)]
+pub enum ResponseFuture<F> {
+    Inner {
+        #[pin]
+        future: F,
+        permit: Option<Permit>,
+        start: Instant,
+    },
+    Shed(Option<ConcurrencyLimited>),
+}
+
+/// Reserves a slot within a limit until the request completes.
+#[derive(Debug)]
+pub struct Permit(Option<Limiter>);
+
+#[derive(Debug)]
+struct Shared {
+    config: Config,
+    state: Mutex<State>,
+    rejections: Counter,
+}
+
+#[derive(Debug)]
+struct State {
+    limit: f64,
+    in_flight: usize,
+    /// The gradient algorithm's long-term average latency, in seconds.
+    long_rtt: Option<f64>,
+    waiters: Vec<Waker>,
+}
+
+#[derive(Debug)]
+struct Limit {
+    limiter: Limiter,
+    /// Sheds requests once the limit has been exhausted for the failfast
+    /// timeout, if one is configured.
+    failfast: Option<FailFast<Acquire>>,
+    acquired: Option<Acquired>,
+}
+
+#[derive(Debug)]
+enum Acquired {
+    Permit(Permit),
+    Shed,
+}
+
+/// A service that is ready when a permit is available and returns the permit
+/// when called, so that `FailFast` may shed requests while the limit is
+/// exhausted.
+#[derive(Debug)]
+struct Acquire {
+    limiter: Limiter,
+    permit: Option<Permit>,
+}
+
+// === impl Limiter ===
+
+impl Limiter {
+    pub fn new(config: Config) -> Self {
+        let min = config.min_limit.max(1);
+        let max = config.max_limit.max(min);
+        let config = Config {
+            min_limit: min,
+            max_limit: max,
+            initial_limit: config.initial_limit.max(min).min(max),
+            ..config
+        };
+        Self(Arc::new(Shared {
+            state: Mutex::new(State {
+                limit: config.initial_limit as f64,
+                in_flight: 0,
+                long_rtt: None,
+                waiters: Vec::new(),
+            }),
+            config,
+            rejections: Counter::new(),
+        }))
+    }
+
+    /// Returns the current limit.
+    pub fn limit(&self) -> usize {
+        self.0.state.lock().limit as usize
+    }
+
+    /// Returns the number of requests that are in flight.
+    pub fn in_flight(&self) -> usize {
+        self.0.state.lock().in_flight
+    }
+
+    /// Counts requests that were shed because the limit was exhausted.
+    pub fn rejections(&self) -> &Counter {
+        &self.0.rejections
+    }
+
+    pub fn downgrade(&self) -> WeakLimiter {
+        WeakLimiter(Arc::downgrade(&self.0))
+    }
+
+    /// Obtains a permit if the limit has not been reached. Otherwise, the task
+    /// is notified when a permit may be available.
+    pub fn poll_acquire(&self, cx: &mut Context<'_>) -> Poll<Permit> {
+        let mut state = self.0.state.lock();
+        if state.in_flight < state.limit as usize {
+            state.in_flight += 1;
+            return Poll::Ready(Permit(Some(self.clone())));
+        }
+
+        trace!(limit = state.limit as usize, "Concurrency limit reached");
+        if !state.waiters.iter().any(|w| w.will_wake(cx.waker())) {
+            state.waiters.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+
+    fn release(&self, sample: Option<(Duration, bool)>) {
+        let mut state = self.0.state.lock();
+        if let Some((rtt, dropped)) = sample {
+            state.update(&self.0.config, rtt, dropped);
+        }
+        state.in_flight -= 1;
+        if state.in_flight < state.limit as usize {
+            for waker in state.waiters.drain(..) {
+                waker.wake();
+            }
+        }
+    }
+}
+
+// === impl WeakLimiter ===
+
+impl WeakLimiter {
+    pub fn upgrade(&self) -> Option<Limiter> {
+        self.0.upgrade().map(Limiter)
+    }
+}
+
+// === impl Permit ===
+
+impl Permit {
+    /// Releases the permit, updating the limit with the request's outcome.
+    pub fn complete(mut self, rtt: Duration, dropped: bool) {
+        if let Some(limiter) = self.0.take() {
+            limiter.release(Some((rtt, dropped)));
+        }
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        // Requests that are canceled don't inform the limit.
+        if let Some(limiter) = self.0.take() {
+            limiter.release(None);
+        }
+    }
+}
+
+// === impl State ===
+
+impl State {
+    fn update(&mut self, config: &Config, rtt: Duration, dropped: bool) {
+        let limit = match config.algorithm {
+            Algorithm::Aimd {
+                backoff_ratio,
+                max_latency,
+            } => {
+                if dropped || rtt > max_latency {
+                    self.limit * backoff_ratio
+                } else if self.in_flight * 2 >= self.limit as usize {
+                    self.limit + 1.0
+                } else {
+                    return;
+                }
+            }
+
+            Algorithm::Gradient {
+                tolerance,
+                smoothing,
+            } => {
+                // Failed requests don't reflect the target's latency.
+                if dropped {
+                    return;
+                }
+
+                let rtt = rtt.as_secs_f64().max(f64::EPSILON);
+                let long_rtt = match self.long_rtt {
+                    Some(long) => long + (rtt - long) / LONG_WINDOW,
+                    None => rtt,
+                };
+                self.long_rtt = Some(long_rtt);
+
+                // Don't grow the limit while the target is underutilized.
+                let gradient = (tolerance * long_rtt / rtt).clamp(0.5, 1.0);
+                if gradient >= 1.0 && self.in_flight * 2 < self.limit as usize {
+                    return;
+                }
+
+                let estimate = self.limit * gradient + self.limit.sqrt();
+                self.limit * (1.0 - smoothing) + estimate * smoothing
+            }
+        };
+
+        let limit = limit
+            .max(config.min_limit as f64)
+            .min(config.max_limit as f64);
+        if limit as usize != self.limit as usize {
+            debug!(
+                limit = limit as usize,
+                in_flight = self.in_flight,
+                ?rtt,
+                dropped,
+                "Updated concurrency limit"
+            );
+        }
+        self.limit = limit;
+    }
+}
+
+// === impl NewAdaptiveLimit ===
+
+impl<X: Clone, N> NewAdaptiveLimit<X, N> {
+    pub fn layer_via(extract: X) -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(move |inner| Self {
+            extract: extract.clone(),
+            inner,
+        })
+    }
+}
+
+impl<T, X, N> NewService<T> for NewAdaptiveLimit<X, N>
+where
+    X: ExtractParam<Option<Limiter>, T>,
+    N: NewService<T>,
+{
+    type Service = AdaptiveLimit<N::Service>;
+
+    fn new_service(&self, target: T) -> Self::Service {
+        let limiter = self.extract.extract_param(&target);
+        AdaptiveLimit::new(limiter, self.inner.new_service(target))
+    }
+}
+
+// === impl AdaptiveLimit ===
+
+impl<S> AdaptiveLimit<S> {
+    pub fn new(limiter: Option<Limiter>, inner: S) -> Self {
+        let limit = limiter.map(|limiter| {
+            let failfast = limiter.0.config.failfast_timeout.map(|timeout| {
+                let acquire = Acquire {
+                    limiter: limiter.clone(),
+                    permit: None,
+                };
+                FailFast::layer("Concurrency limit", timeout).layer(acquire)
+            });
+            Limit {
+                limiter,
+                failfast,
+                acquired: None,
+            }
+        });
+        Self { inner, limit }
+    }
+}
+
+impl<Req, S> Service<Req> for AdaptiveLimit<S>
+where
+    S: Service<Req>,
+    S::Error: Into<Error>,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if let Some(limit) = self.limit.as_mut() {
+            if limit.acquired.is_none() {
+                limit.acquired = Some(match limit.failfast.as_mut() {
+                    // Without a failfast timeout, the service remains unready
+                    // until a permit is available.
+                    None => Acquired::Permit(ready!(limit.limiter.poll_acquire(cx))),
+                    Some(failfast) => {
+                        ready!(failfast.poll_ready(cx))?;
+                        // `Acquire` returns its permit immediately, so the call
+                        // never needs to be polled again.
+                        let acquired = failfast
+                            .call(())
+                            .now_or_never()
+                            .expect("permits must be returned immediately");
+                        match acquired {
+                            Ok(permit) => Acquired::Permit(permit),
+                            Err(_) => Acquired::Shed,
+                        }
+                    }
+                });
+            }
+
+            // Shed requests are failed without being dispatched.
+            if let Some(Acquired::Shed) = limit.acquired {
+                return Poll::Ready(Ok(()));
+            }
+        }
+
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        let permit = match self.limit.as_mut() {
+            None => None,
+            Some(limit) => match limit.acquired.take().expect("poll_ready must be called") {
+                Acquired::Permit(permit) => Some(permit),
+                Acquired::Shed => {
+                    limit.limiter.rejections().incr();
+                    let limit = limit.limiter.limit();
+                    debug!(limit, "Shedding request");
+                    return ResponseFuture::Shed(Some(ConcurrencyLimited { limit }));
+                }
+            },
+        };
+
+        ResponseFuture::Inner {
+            future: self.inner.call(req),
+            permit,
+            start: Instant::now(),
+        }
+    }
+}
+
+impl<F> Future for ResponseFuture<F>
+where
+    F: TryFuture,
+    F::Error: Into<Error>,
+{
+    type Output = Result<F::Ok, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            ResponseFutureProj::Inner {
+                future,
+                permit,
+                start,
+            } => {
+                let res = ready!(future.try_poll(cx));
+                if let Some(permit) = permit.take() {
+                    permit.complete(
+                        Instant::now().saturating_duration_since(*start),
+                        res.is_err(),
+                    );
+                }
+                Poll::Ready(res.map_err(Into::into))
+            }
+            ResponseFutureProj::Shed(error) => Poll::Ready(Err(error
+                .take()
+                .expect("future polled after completion")
+                .into())),
+        }
+    }
+}
+
+// === impl Acquire ===
+
+impl Service<()> for Acquire {
+    type Response = Permit;
+    type Error = Infallible;
+    type Future = future::Ready<Result<Permit, Infallible>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.permit.is_none() {
+            self.permit = Some(ready!(self.limiter.poll_acquire(cx)));
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, (): ()) -> Self::Future {
+        future::ok(self.permit.take().expect("poll_ready must be called"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_test::{assert_pending, assert_ready_ok};
+    use tower_test::mock;
+
+    fn config(algorithm: Algorithm) -> Config {
+        Config {
+            algorithm,
+            initial_limit: 10,
+            min_limit: 2,
+            max_limit: 20,
+            failfast_timeout: Some(Duration::from_millis(100)),
+        }
+    }
+
+    fn state(limit: f64, in_flight: usize) -> State {
+        State {
+            limit,
+            in_flight,
+            long_rtt: None,
+            waiters: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn aimd() {
+        let config = config(Algorithm::Aimd {
+            backoff_ratio: 0.5,
+            max_latency: Duration::from_secs(1),
+        });
+        let fast = Duration::from_millis(10);
+
+        // The limit doesn't grow while it's underutilized.
+        let mut s = state(10.0, 4);
+        s.update(&config, fast, false);
+        assert_eq!(s.limit, 10.0);
+
+        s.in_flight = 5;
+        s.update(&config, fast, false);
+        assert_eq!(s.limit, 11.0);
+
+        s.update(&config, Duration::from_secs(2), false);
+        assert_eq!(s.limit, 5.5);
+
+        s.update(&config, fast, true);
+        assert_eq!(s.limit, 2.75);
+
+        s.update(&config, fast, true);
+        assert_eq!(s.limit, 2.0, "the limit must not fall below the minimum");
+
+        let mut s = state(20.0, 20);
+        s.update(&config, fast, false);
+        assert_eq!(s.limit, 20.0, "the limit must not exceed the maximum");
+    }
+
+    #[test]
+    fn gradient() {
+        let config = config(Algorithm::Gradient {
+            tolerance: 1.5,
+            smoothing: 1.0,
+        });
+        let mut s = state(16.0, 16);
+
+        // While latency is steady, the limit grows by its square root.
+        s.update(&config, Duration::from_millis(10), false);
+        assert_eq!(s.limit, 20.0);
+
+        // When latency spikes, the limit shrinks.
+        s.update(&config, Duration::from_millis(100), false);
+        assert!(s.limit < 20.0, "{}", s.limit);
+
+        // Failures are ignored.
+        let limit = s.limit;
+        s.update(&config, Duration::from_secs(10), true);
+        assert_eq!(s.limit, limit);
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn sheds_when_limit_exhausted() {
+        let limiter = Limiter::new(Config {
+            initial_limit: 1,
+            min_limit: 1,
+            ..config(Algorithm::Aimd {
+                backoff_ratio: 0.9,
+                max_latency: Duration::from_secs(1),
+            })
+        });
+        let (inner, mut handle) = mock::pair::<(), ()>();
+        let mut svc = mock::Spawn::new(AdaptiveLimit::new(Some(limiter.clone()), inner));
+        handle.allow(2);
+
+        assert_ready_ok!(svc.poll_ready());
+        let in_flight = svc.call(());
+        assert_eq!(limiter.in_flight(), 1);
+
+        // The limit is exhausted, so the service is unready until it fails
+        // fast.
+        assert_pending!(svc.poll_ready());
+        tokio::time::sleep(Duration::from_millis(101)).await;
+        assert_ready_ok!(svc.poll_ready());
+        let err = svc.call(()).await.expect_err("request must be shed");
+        assert!(err.is::<ConcurrencyLimited>(), "{}", err);
+        assert_eq!(limiter.rejections().value(), 1.0);
+
+        // Once the in-flight request completes, requests are admitted again.
+        let ((), rsp) = handle.next_request().await.expect("request must be sent");
+        rsp.send_response(());
+        in_flight.await.expect("request must succeed");
+        assert_eq!(limiter.in_flight(), 0);
+        assert_ready_ok!(svc.poll_ready());
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn unready_without_failfast() {
+        let limiter = Limiter::new(Config {
+            initial_limit: 1,
+            min_limit: 1,
+            failfast_timeout: None,
+            ..config(Algorithm::Aimd {
+                backoff_ratio: 0.9,
+                max_latency: Duration::from_secs(1),
+            })
+        });
+        let (inner, mut handle) = mock::pair::<(), ()>();
+        let mut svc = mock::Spawn::new(AdaptiveLimit::new(Some(limiter.clone()), inner));
+        handle.allow(2);
+
+        assert_ready_ok!(svc.poll_ready());
+        let in_flight = svc.call(());
+
+        // The limit is exhausted, so the service remains unready rather than
+        // shedding requests.
+        assert_pending!(svc.poll_ready());
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        assert_pending!(svc.poll_ready());
+        assert_eq!(limiter.rejections().value(), 0.0);
+
+        let ((), rsp) = handle.next_request().await.expect("request must be sent");
+        rsp.send_response(());
+        in_flight.await.expect("request must succeed");
+        assert_ready_ok!(svc.poll_ready());
+    }
+}
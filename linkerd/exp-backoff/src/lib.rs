@@ -16,7 +16,7 @@ use thiserror::Error;
 use tokio::time;
 
 /// A jittered exponential backoff strategy.
-#[derive(Copy, Clone, Debug, Default)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
 pub struct ExponentialBackoff {
     /// The minimum amount of time to wait before resuming an operation.
     min: time::Duration,
@@ -83,6 +83,13 @@ impl ExponentialBackoff {
         }
     }
 
+    /// Returns the jittered duration to wait after the given number of
+    /// consecutive backoffs.
+    pub fn duration<R: rand::Rng>(&self, iterations: u32, rng: &mut R) -> time::Duration {
+        let base = self.base(iterations);
+        base + self.jitter(base, rng)
+    }
+
     fn base(&self, iterations: u32) -> time::Duration {
         debug_assert!(
             self.min <= self.max,
@@ -137,10 +144,7 @@ impl Stream for ExponentialBackoffStream {
                 return Poll::Ready(None);
             }
 
-            let backoff = {
-                let base = this.backoff.base(*this.iterations);
-                base + this.backoff.jitter(base, &mut this.rng)
-            };
+            let backoff = this.backoff.duration(*this.iterations, &mut this.rng);
             this.sleep.as_mut().reset(time::Instant::now() + backoff);
             *this.sleeping = true;
         }
@@ -0,0 +1,252 @@
+//! Records HTTP outcomes using the response classifier set on each request.
+
+use super::{Attempt, Health, NewReport};
+use futures::{prelude::*, ready};
+use http_body::Body;
+use linkerd_error::Error;
+use linkerd_http_classify::{ClassifyEos, ClassifyResponse};
+use linkerd_stack::Service;
+use pin_project::{pin_project, pinned_drop};
+use std::{
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Determines whether a response classification is a failure.
+pub trait IsFailure {
+    fn is_failure(&self) -> bool;
+}
+
+/// Records HTTP responses according to their `C`-typed classification.
+///
+/// If a request does not have a `C`-typed classifier extension, its response
+/// is classified with `C::default()`.
+#[derive(Debug)]
+pub struct NewReportClassified<C>(PhantomData<fn() -> C>);
+
+#[derive(Debug)]
+pub struct ReportClassified<C, S> {
+    inner: S,
+    health: Health,
+    _marker: PhantomData<fn() -> C>,
+}
+
+#[pin_project(PinnedDrop)]
+#[derive(Debug)]
+pub struct ResponseFuture<F, C> {
+    #[pin]
+    inner: F,
+    classify: Option<C>,
+    health: Health,
+    attempt: Attempt,
+}
+
+#[pin_project(PinnedDrop)]
+#[derive(Debug)]
+pub struct ReportBody<B, C>
+where
+    C: ClassifyEos,
+    C::Class: IsFailure,
+{
+    #[pin]
+    inner: B,
+    classify: Option<C>,
+    health: Health,
+    attempt: Attempt,
+}
+
+// === impl NewReportClassified ===
+
+impl<C> NewReportClassified<C> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<C> Default for NewReportClassified<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C> Clone for NewReportClassified<C> {
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
+
+impl<C, S> NewReport<S> for NewReportClassified<C> {
+    type Service = ReportClassified<C, S>;
+
+    fn new_report(&self, inner: S, health: Health) -> Self::Service {
+        ReportClassified {
+            inner,
+            health,
+            _marker: PhantomData,
+        }
+    }
+}
+
+// === impl ReportClassified ===
+
+impl<C, S: Clone> Clone for ReportClassified<C, S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            health: self.health.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<C, S, A, B> Service<http::Request<A>> for ReportClassified<C, S>
+where
+    S: Service<http::Request<A>, Response = http::Response<B>>,
+    S::Error: Into<Error>,
+    C: ClassifyResponse + Clone + Default + Send + Sync + 'static,
+    C::Class: IsFailure,
+{
+    type Response = http::Response<ReportBody<B, C::ClassifyEos>>;
+    type Error = Error;
+    type Future = ResponseFuture<S::Future, C>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: http::Request<A>) -> Self::Future {
+        let classify = req.extensions().get::<C>().cloned().unwrap_or_default();
+        let attempt = self.health.dispatch();
+        ResponseFuture {
+            inner: self.inner.call(req),
+            classify: Some(classify),
+            health: self.health.clone(),
+            attempt,
+        }
+    }
+}
+
+// === impl ResponseFuture ===
+
+impl<F, C, B> Future for ResponseFuture<F, C>
+where
+    F: TryFuture<Ok = http::Response<B>>,
+    F::Error: Into<Error>,
+    C: ClassifyResponse,
+    C::Class: IsFailure,
+{
+    type Output = Result<http::Response<ReportBody<B, C::ClassifyEos>>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = ready!(this.inner.try_poll(cx)).map_err(Into::into);
+        let classify = this.classify.take().expect("polled after complete");
+        Poll::Ready(match res {
+            Ok(rsp) => {
+                let classify = classify.start(&rsp);
+                Ok(rsp.map(|inner| ReportBody {
+                    inner,
+                    classify: Some(classify),
+                    health: this.health.clone(),
+                    attempt: *this.attempt,
+                }))
+            }
+            Err(error) => {
+                let class = classify.error(&error);
+                this.health.record(*this.attempt, !class.is_failure());
+                Err(error)
+            }
+        })
+    }
+}
+
+#[pinned_drop]
+impl<F, C> PinnedDrop for ResponseFuture<F, C> {
+    fn drop(self: Pin<&mut Self>) {
+        // The request was canceled before a response was received.
+        if self.classify.is_some() {
+            self.health.cancel(self.attempt);
+        }
+    }
+}
+
+// === impl ReportBody ===
+
+impl<B, C> ReportBody<B, C>
+where
+    C: ClassifyEos,
+    C::Class: IsFailure,
+{
+    fn record(self: Pin<&mut Self>, classify: impl FnOnce(C) -> C::Class) {
+        let this = self.project();
+        if let Some(c) = this.classify.take() {
+            this.health.record(*this.attempt, !classify(c).is_failure());
+        }
+    }
+}
+
+impl<B, C> Body for ReportBody<B, C>
+where
+    B: Body,
+    B::Error: Into<Error>,
+    C: ClassifyEos,
+    C::Class: IsFailure,
+{
+    type Data = B::Data;
+    type Error = Error;
+
+    #[inline]
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let frame = ready!(self.as_mut().project().inner.poll_data(cx));
+        Poll::Ready(frame.map(|res| {
+            res.map_err(|e| {
+                let error = e.into();
+                self.as_mut().record(|c| c.error(&error));
+                error
+            })
+        }))
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        let res = ready!(self.as_mut().project().inner.poll_trailers(cx));
+        Poll::Ready(match res {
+            Ok(trailers) => {
+                self.record(|c| c.eos(trailers.as_ref()));
+                Ok(trailers)
+            }
+            Err(e) => {
+                let error = e.into();
+                self.record(|c| c.error(&error));
+                Err(error)
+            }
+        })
+    }
+
+    #[inline]
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[pinned_drop]
+impl<B, C> PinnedDrop for ReportBody<B, C>
+where
+    C: ClassifyEos,
+    C::Class: IsFailure,
+{
+    fn drop(self: Pin<&mut Self>) {
+        self.record(|c| c.eos(None));
+    }
+}
@@ -0,0 +1,800 @@
+//! Ejects balanced endpoints that accrue failures.
+//!
+//! Each endpoint's outcomes are recorded into a [`Health`]. When an endpoint
+//! fails too many consecutive requests, or too large a ratio of its recent
+//! requests, it is ejected: its [`Gate`] is unready for a backoff so that a
+//! balancer sends requests to other endpoints. Once the backoff elapses, a
+//! single probe request is permitted. If the probe succeeds, the endpoint is
+//! restored; otherwise it is ejected again with a longer backoff. Only the
+//! probe's own outcome ends probation: late responses to requests dispatched
+//! before the endpoint was ejected are ignored.
+
+#![deny(
+    warnings,
+    rust_2018_idioms,
+    clippy::disallowed_methods,
+    clippy::disallowed_types
+)]
+#![forbid(unsafe_code)]
+
+pub mod http;
+
+use futures::{prelude::*, ready};
+use linkerd_error::Error;
+use linkerd_exp_backoff::ExponentialBackoff;
+use linkerd_metrics::Counter;
+use linkerd_stack::{layer, NewService, Service};
+use parking_lot::Mutex;
+use pin_project::{pin_project, pinned_drop};
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, Waker},
+};
+use tokio::time::{self, Duration, Instant};
+use tracing::{debug, trace};
+
+/// The number of buckets used to track failure rates over a window.
+const RATE_BUCKETS: u32 = 10;
+
+/// Configures when endpoints are ejected.
+#[derive(Copy, Clone, Debug)]
+pub struct Config {
+    pub accrual: Accrual,
+
+    /// Determines how long an endpoint is ejected. The backoff grows each time
+    /// an endpoint is ejected without recovering.
+    pub backoff: ExponentialBackoff,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum Accrual {
+    /// Eject an endpoint after this many consecutive failures.
+    ConsecutiveFailures(usize),
+
+    /// Eject an endpoint when the ratio of failures to requests observed over
+    /// `window` reaches `max_rate`, once at least `min_requests` requests have
+    /// been observed.
+    FailureRate {
+        max_rate: f64,
+        min_requests: usize,
+        window: Duration,
+    },
+}
+
+/// Wraps an endpoint service so that its outcomes are recorded.
+///
+/// Reporters must call [`Health::dispatch`] as each request is dispatched and
+/// record the request's outcome with the returned [`Attempt`].
+pub trait NewReport<S> {
+    type Service;
+
+    fn new_report(&self, inner: S, health: Health) -> Self::Service;
+}
+
+/// Builds endpoint services that are gated on the endpoint's health.
+#[derive(Clone, Debug)]
+pub struct NewGate<R, N> {
+    config: Option<Config>,
+    ejections: Arc<Counter>,
+    report: R,
+    inner: N,
+}
+
+/// An endpoint service that is unready while its endpoint is ejected.
+#[derive(Debug)]
+pub struct Gate<S> {
+    inner: S,
+    health: Health,
+    sleep: Pin<Box<time::Sleep>>,
+}
+
+/// Tracks the outcomes of an endpoint's requests.
+#[derive(Clone, Debug)]
+pub struct Health(Option<Arc<Mutex<State>>>);
+
+/// Identifies a request dispatched to an endpoint, so that only the outcome of
+/// an endpoint's probe may end its probation.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Attempt(Option<u64>);
+
+/// Records responses as successes and errors as failures.
+///
+/// Errors that are not attributable to the endpoint may be ignored.
+#[derive(Copy, Clone, Debug)]
+pub struct NewReportResult {
+    is_failure: fn(&Error) -> bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct ReportResult<S> {
+    inner: S,
+    health: Health,
+    is_failure: fn(&Error) -> bool,
+}
+
+#[pin_project(PinnedDrop)]
+#[derive(Debug)]
+pub struct ReportResultFuture<F> {
+    #[pin]
+    inner: F,
+    health: Health,
+    attempt: Attempt,
+    is_failure: fn(&Error) -> bool,
+    recorded: bool,
+}
+
+#[derive(Debug)]
+struct State {
+    accrual: Accrued,
+    backoff: ExponentialBackoff,
+    /// The number of times the endpoint has been ejected since it was last
+    /// healthy.
+    ejections: u32,
+    status: Status,
+    /// Identifies the next probe.
+    next_probe: u64,
+    /// Notified when a probe completes.
+    waker: Option<Waker>,
+    ejections_total: Arc<Counter>,
+}
+
+#[derive(Debug)]
+enum Status {
+    Healthy,
+    Ejected {
+        until: Instant,
+    },
+    /// The endpoint may be sent a single probe. Once it has been dispatched,
+    /// `probe` identifies it.
+    Probation {
+        probe: Option<u64>,
+    },
+}
+
+#[derive(Debug)]
+enum Accrued {
+    Consecutive {
+        max_failures: usize,
+        failures: usize,
+    },
+    Rate {
+        max_rate: f64,
+        min_requests: usize,
+        window: Duration,
+        buckets: VecDeque<Bucket>,
+    },
+}
+
+#[derive(Debug)]
+struct Bucket {
+    start: Instant,
+    successes: usize,
+    failures: usize,
+}
+
+enum Readiness {
+    Ready,
+    Ejected(Instant),
+    Probing,
+}
+
+// === impl NewGate ===
+
+impl<R: Clone, N> NewGate<R, N> {
+    /// Gates endpoints according to `config`. If no configuration is
+    /// provided, endpoints are never ejected.
+    pub fn layer(
+        config: Option<Config>,
+        ejections: Arc<Counter>,
+        report: R,
+    ) -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(move |inner| Self {
+            config,
+            ejections: ejections.clone(),
+            report: report.clone(),
+            inner,
+        })
+    }
+}
+
+impl<T, R, N> NewService<T> for NewGate<R, N>
+where
+    N: NewService<T>,
+    R: NewReport<N::Service>,
+{
+    type Service = Gate<R::Service>;
+
+    fn new_service(&self, target: T) -> Self::Service {
+        let health = match self.config {
+            Some(config) => Health::new(config, self.ejections.clone()),
+            None => Health::disabled(),
+        };
+        let inner = self.inner.new_service(target);
+        Gate::new(self.report.new_report(inner, health.clone()), health)
+    }
+}
+
+// === impl Gate ===
+
+impl<S> Gate<S> {
+    pub fn new(inner: S, health: Health) -> Self {
+        Self {
+            inner,
+            health,
+            sleep: Box::pin(time::sleep(Duration::ZERO)),
+        }
+    }
+}
+
+impl<Req, S> Service<Req> for Gate<S>
+where
+    S: Service<Req>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        loop {
+            match self.health.readiness(cx) {
+                Readiness::Ready => break,
+                Readiness::Probing => return Poll::Pending,
+                Readiness::Ejected(until) => {
+                    if self.sleep.deadline() != until {
+                        self.sleep.as_mut().reset(until);
+                    }
+                    ready!(self.sleep.as_mut().poll(cx));
+                }
+            }
+        }
+
+        self.inner.poll_ready(cx)
+    }
+
+    #[inline]
+    fn call(&mut self, req: Req) -> Self::Future {
+        self.inner.call(req)
+    }
+}
+
+// === impl Health ===
+
+impl Health {
+    pub fn new(config: Config, ejections_total: Arc<Counter>) -> Self {
+        let accrual = match config.accrual {
+            Accrual::ConsecutiveFailures(max_failures) => Accrued::Consecutive {
+                max_failures: max_failures.max(1),
+                failures: 0,
+            },
+            Accrual::FailureRate {
+                max_rate,
+                min_requests,
+                window,
+            } => Accrued::Rate {
+                max_rate,
+                min_requests: min_requests.max(1),
+                window,
+                buckets: VecDeque::with_capacity(RATE_BUCKETS as usize),
+            },
+        };
+        Self(Some(Arc::new(Mutex::new(State {
+            accrual,
+            backoff: config.backoff,
+            ejections: 0,
+            status: Status::Healthy,
+            next_probe: 0,
+            waker: None,
+            ejections_total,
+        }))))
+    }
+
+    /// Returns a `Health` that never ejects its endpoint.
+    pub fn disabled() -> Self {
+        Self(None)
+    }
+
+    /// Notes that a request is being dispatched to the endpoint. If the
+    /// endpoint is on probation, the request becomes its probe.
+    pub fn dispatch(&self) -> Attempt {
+        let mut state = match self.0.as_ref() {
+            Some(state) => state.lock(),
+            None => return Attempt(None),
+        };
+        let state = &mut *state;
+        match state.status {
+            Status::Probation { ref mut probe } if probe.is_none() => {
+                let id = state.next_probe;
+                state.next_probe = state.next_probe.wrapping_add(1);
+                *probe = Some(id);
+                Attempt(Some(id))
+            }
+            _ => Attempt(None),
+        }
+    }
+
+    pub fn success(&self, attempt: Attempt) {
+        self.record(attempt, true)
+    }
+
+    pub fn failure(&self, attempt: Attempt) {
+        self.record(attempt, false)
+    }
+
+    fn record(&self, attempt: Attempt, success: bool) {
+        if let Some(state) = self.0.as_ref() {
+            state.lock().record(attempt, success);
+        }
+    }
+
+    /// Notes that a request completed without an outcome, e.g. because it was
+    /// canceled, so that a canceled probe does not leave the endpoint unready.
+    fn cancel(&self, attempt: Attempt) {
+        if let Some(state) = self.0.as_ref() {
+            state.lock().cancel(attempt);
+        }
+    }
+
+    fn readiness(&self, cx: &mut Context<'_>) -> Readiness {
+        let mut state = match self.0.as_ref() {
+            Some(state) => state.lock(),
+            None => return Readiness::Ready,
+        };
+        match state.status {
+            Status::Healthy | Status::Probation { probe: None } => Readiness::Ready,
+            Status::Probation { probe: Some(_) } => {
+                state.waker = Some(cx.waker().clone());
+                Readiness::Probing
+            }
+            Status::Ejected { until } => {
+                if Instant::now() < until {
+                    return Readiness::Ejected(until);
+                }
+                debug!("Probing ejected endpoint");
+                state.status = Status::Probation { probe: None };
+                Readiness::Ready
+            }
+        }
+    }
+}
+
+// === impl State ===
+
+impl State {
+    fn record(&mut self, Attempt(attempt): Attempt, success: bool) {
+        match self.status {
+            Status::Healthy => {
+                if self.accrual.record(success) {
+                    self.eject();
+                }
+            }
+            Status::Probation { probe: Some(probe) } if attempt == Some(probe) => {
+                if success {
+                    debug!("Endpoint recovered");
+                    self.status = Status::Healthy;
+                    self.ejections = 0;
+                } else {
+                    self.eject();
+                }
+                if let Some(waker) = self.waker.take() {
+                    waker.wake();
+                }
+            }
+            // Responses to requests that were dispatched before the endpoint
+            // was ejected are ignored.
+            Status::Ejected { .. } | Status::Probation { .. } => {}
+        }
+    }
+
+    fn cancel(&mut self, Attempt(attempt): Attempt) {
+        if let Status::Probation { probe: Some(probe) } = self.status {
+            if attempt != Some(probe) {
+                return;
+            }
+            debug!("Probe canceled");
+            self.status = Status::Probation { probe: None };
+            if let Some(waker) = self.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+
+    fn eject(&mut self) {
+        let backoff = self
+            .backoff
+            .duration(self.ejections, &mut rand::thread_rng());
+        debug!(?backoff, ejections = self.ejections, "Ejecting endpoint");
+        self.ejections = self.ejections.saturating_add(1);
+        self.status = Status::Ejected {
+            until: Instant::now() + backoff,
+        };
+        self.accrual.reset();
+        self.ejections_total.incr();
+    }
+}
+
+// === impl Accrued ===
+
+impl Accrued {
+    /// Records an outcome, returning true if the endpoint should be ejected.
+    fn record(&mut self, success: bool) -> bool {
+        match self {
+            Self::Consecutive {
+                max_failures,
+                failures,
+            } => {
+                if success {
+                    *failures = 0;
+                    return false;
+                }
+                *failures += 1;
+                trace!(failures, "Consecutive failures");
+                *failures >= *max_failures
+            }
+
+            Self::Rate {
+                max_rate,
+                min_requests,
+                window,
+                buckets,
+            } => {
+                let now = Instant::now();
+                while let Some(bucket) = buckets.front() {
+                    if now.saturating_duration_since(bucket.start) < *window {
+                        break;
+                    }
+                    buckets.pop_front();
+                }
+
+                let width = *window / RATE_BUCKETS;
+                let bucket = match buckets.back_mut() {
+                    Some(bucket) if now.saturating_duration_since(bucket.start) < width => bucket,
+                    _ => {
+                        buckets.push_back(Bucket {
+                            start: now,
+                            successes: 0,
+                            failures: 0,
+                        });
+                        buckets.back_mut().expect("bucket must have been added")
+                    }
+                };
+                if success {
+                    bucket.successes += 1;
+                } else {
+                    bucket.failures += 1;
+                }
+
+                let (successes, failures) = buckets
+                    .iter()
+                    .fold((0, 0), |(s, f), b| (s + b.successes, f + b.failures));
+                let requests = successes + failures;
+                let rate = failures as f64 / requests as f64;
+                trace!(requests, failures, rate, "Failure rate");
+                requests >= *min_requests && rate >= *max_rate
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        match self {
+            Self::Consecutive { failures, .. } => *failures = 0,
+            Self::Rate { buckets, .. } => buckets.clear(),
+        }
+    }
+}
+
+// === impl NewReportResult ===
+
+impl NewReportResult {
+    /// Records errors as failures only if `is_failure` returns true. Other
+    /// errors are ignored.
+    pub fn new(is_failure: fn(&Error) -> bool) -> Self {
+        Self { is_failure }
+    }
+}
+
+impl Default for NewReportResult {
+    fn default() -> Self {
+        Self::new(|_| true)
+    }
+}
+
+impl<S> NewReport<S> for NewReportResult {
+    type Service = ReportResult<S>;
+
+    fn new_report(&self, inner: S, health: Health) -> Self::Service {
+        ReportResult {
+            inner,
+            health,
+            is_failure: self.is_failure,
+        }
+    }
+}
+
+// === impl ReportResult ===
+
+impl<Req, S> Service<Req> for ReportResult<S>
+where
+    S: Service<Req>,
+    S::Error: Into<Error>,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = ReportResultFuture<S::Future>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        let attempt = self.health.dispatch();
+        ReportResultFuture {
+            inner: self.inner.call(req),
+            health: self.health.clone(),
+            attempt,
+            is_failure: self.is_failure,
+            recorded: false,
+        }
+    }
+}
+
+impl<F> Future for ReportResultFuture<F>
+where
+    F: TryFuture,
+    F::Error: Into<Error>,
+{
+    type Output = Result<F::Ok, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = ready!(this.inner.try_poll(cx)).map_err(Into::into);
+        match res {
+            Ok(_) => this.health.success(*this.attempt),
+            Err(ref error) if (this.is_failure)(error) => this.health.failure(*this.attempt),
+            Err(_) => this.health.cancel(*this.attempt),
+        }
+        *this.recorded = true;
+        Poll::Ready(res)
+    }
+}
+
+#[pinned_drop]
+impl<F> PinnedDrop for ReportResultFuture<F> {
+    fn drop(self: Pin<&mut Self>) {
+        if !self.recorded {
+            self.health.cancel(self.attempt);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_test::{assert_pending, assert_ready_ok};
+    use tower_test::mock;
+
+    fn config(accrual: Accrual) -> Config {
+        Config {
+            accrual,
+            backoff: ExponentialBackoff::try_new(
+                Duration::from_secs(1),
+                Duration::from_secs(10),
+                0.0,
+            )
+            .unwrap(),
+        }
+    }
+
+    fn gate(
+        config: Config,
+    ) -> (
+        mock::Spawn<Gate<mock::Mock<(), ()>>>,
+        mock::Handle<(), ()>,
+        Health,
+    ) {
+        let health = Health::new(config, Arc::new(Counter::default()));
+        let (inner, handle) = mock::pair();
+        let gate = mock::Spawn::new(Gate::new(inner, health.clone()));
+        (gate, handle, health)
+    }
+
+    fn report_gate(
+        config: Config,
+    ) -> (
+        mock::Spawn<Gate<ReportResult<mock::Mock<(), ()>>>>,
+        mock::Handle<(), ()>,
+        Health,
+    ) {
+        let health = Health::new(config, Arc::new(Counter::default()));
+        let (inner, handle) = mock::pair();
+        let report = NewReportResult::default().new_report(inner, health.clone());
+        let gate = mock::Spawn::new(Gate::new(report, health.clone()));
+        (gate, handle, health)
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn ejects_after_consecutive_failures() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let (mut gate, mut handle, health) = gate(config(Accrual::ConsecutiveFailures(2)));
+        handle.allow(10);
+        assert_ready_ok!(gate.poll_ready());
+
+        health.failure(health.dispatch());
+        health.success(health.dispatch());
+        health.failure(health.dispatch());
+        assert_ready_ok!(gate.poll_ready());
+
+        health.failure(health.dispatch());
+        assert_pending!(gate.poll_ready());
+
+        // After the backoff elapses, a single probe is permitted.
+        time::sleep(Duration::from_secs(1)).await;
+        assert_ready_ok!(gate.poll_ready());
+        let probe = health.dispatch();
+        assert_pending!(gate.poll_ready());
+
+        // The probe fails, so the endpoint is ejected with a longer backoff.
+        health.failure(probe);
+        assert_pending!(gate.poll_ready());
+        time::sleep(Duration::from_secs(1)).await;
+        assert_pending!(gate.poll_ready());
+        time::sleep(Duration::from_secs(1)).await;
+        assert_ready_ok!(gate.poll_ready());
+
+        // The probe succeeds, so the endpoint is restored.
+        let probe = health.dispatch();
+        health.success(probe);
+        assert_ready_ok!(gate.poll_ready());
+        assert_eq!(
+            health.dispatch(),
+            Attempt(None),
+            "healthy endpoints are not probed"
+        );
+        assert_ready_ok!(gate.poll_ready());
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn ejects_at_failure_rate() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let (mut gate, mut handle, health) = gate(config(Accrual::FailureRate {
+            max_rate: 0.5,
+            min_requests: 4,
+            window: Duration::from_secs(10),
+        }));
+        handle.allow(10);
+
+        health.failure(health.dispatch());
+        health.failure(health.dispatch());
+        health.failure(health.dispatch());
+        assert_ready_ok!(gate.poll_ready(), "too few requests to eject");
+
+        // Failures outside of the window are forgotten.
+        time::sleep(Duration::from_secs(10)).await;
+        health.success(health.dispatch());
+        health.success(health.dispatch());
+        health.failure(health.dispatch());
+        assert_ready_ok!(gate.poll_ready());
+
+        health.failure(health.dispatch());
+        assert_pending!(gate.poll_ready());
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn canceled_probe_permits_another() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let (mut gate, mut handle, health) = report_gate(config(Accrual::ConsecutiveFailures(1)));
+        handle.allow(10);
+
+        health.failure(health.dispatch());
+        assert_pending!(gate.poll_ready());
+        time::sleep(Duration::from_secs(1)).await;
+        assert_ready_ok!(gate.poll_ready());
+
+        // The probe is dropped before it completes, so another probe is
+        // permitted.
+        let probe = gate.call(());
+        assert_pending!(gate.poll_ready());
+        drop(probe);
+        drop(handle.next_request().await);
+        assert!(gate.is_woken());
+        assert_ready_ok!(gate.poll_ready());
+
+        // The second probe succeeds, so the endpoint is restored.
+        let probe = gate.call(());
+        let (_, rsp) = handle.next_request().await.expect("probe must be sent");
+        rsp.send_response(());
+        probe.await.expect("probe must succeed");
+        assert_ready_ok!(gate.poll_ready());
+        let _rsp = gate.call(());
+        assert_ready_ok!(gate.poll_ready());
+    }
+
+    /// Requests dispatched before an endpoint was ejected must neither end
+    /// nor release its probation.
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn ignores_requests_dispatched_before_probation() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let (mut gate, mut handle, health) = report_gate(config(Accrual::ConsecutiveFailures(1)));
+        handle.allow(10);
+
+        assert_ready_ok!(gate.poll_ready());
+        let early_ok = gate.call(());
+        let (_, early_rsp) = handle.next_request().await.expect("request must be sent");
+        assert_ready_ok!(gate.poll_ready());
+        let early_canceled = gate.call(());
+        drop(handle.next_request().await);
+
+        health.failure(health.dispatch());
+        assert_pending!(gate.poll_ready());
+        time::sleep(Duration::from_secs(1)).await;
+        assert_ready_ok!(gate.poll_ready());
+        let probe = gate.call(());
+        let (_, probe_rsp) = handle.next_request().await.expect("probe must be sent");
+        assert_pending!(gate.poll_ready());
+
+        early_rsp.send_response(());
+        early_ok.await.expect("request must succeed");
+        assert_pending!(gate.poll_ready(), "late responses must not end probation");
+        drop(early_canceled);
+        assert_pending!(
+            gate.poll_ready(),
+            "canceled requests must not release probation"
+        );
+
+        probe_rsp.send_response(());
+        probe.await.expect("probe must succeed");
+        assert_ready_ok!(gate.poll_ready());
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn ignored_errors_release_probation() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let health = Health::new(
+            config(Accrual::ConsecutiveFailures(1)),
+            Arc::new(Counter::default()),
+        );
+        let (inner, mut handle) = mock::pair::<(), ()>();
+        let report = NewReportResult::new(|_| false).new_report(inner, health.clone());
+        let mut gate = mock::Spawn::new(Gate::new(report, health.clone()));
+        handle.allow(10);
+
+        // Ignored errors do not eject the endpoint.
+        assert_ready_ok!(gate.poll_ready());
+        let rsp = gate.call(());
+        let (_, tx) = handle.next_request().await.expect("request must be sent");
+        tx.send_error("boom");
+        rsp.await.expect_err("request must fail");
+        assert_ready_ok!(gate.poll_ready());
+
+        // A probe that fails with an ignored error permits another probe.
+        health.failure(health.dispatch());
+        time::sleep(Duration::from_secs(1)).await;
+        assert_ready_ok!(gate.poll_ready());
+        let probe = gate.call(());
+        let (_, tx) = handle.next_request().await.expect("probe must be sent");
+        assert_pending!(gate.poll_ready());
+        tx.send_error("boom");
+        probe.await.expect_err("probe must fail");
+        assert_ready_ok!(gate.poll_ready());
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn disabled_never_ejects() {
+        let health = Health::disabled();
+        let (inner, mut handle) = mock::pair::<(), ()>();
+        let mut gate = mock::Spawn::new(Gate::new(inner, health.clone()));
+        handle.allow(1);
+
+        for _ in 0..100 {
+            health.failure(health.dispatch());
+        }
+        assert_ready_ok!(gate.poll_ready());
+    }
+}
@@ -8,15 +8,17 @@
 
 use futures_core::TryFuture;
 use linkerd_identity as identity;
-use linkerd_proxy_transport::{ClientAddr, Remote};
+use linkerd_proxy_transport::{ClientAddr, OrigDstAddr, Remote};
 use linkerd_stack as svc;
 use linkerd_tls as tls;
 use linkerd_tracing::access_log::TRACE_TARGET;
 use pin_project::pin_project;
 use std::{
+    fmt::Write,
     future::Future,
     net::SocketAddr,
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
     time::{Duration, SystemTime},
 };
@@ -26,6 +28,7 @@ use tracing::{field, span, Level, Span};
 
 #[derive(Clone, Debug)]
 pub struct NewAccessLog<N> {
+    headers: Arc<[http::HeaderName]>,
     inner: N,
 }
 
@@ -34,6 +37,19 @@ pub struct AccessLogContext<S> {
     inner: S,
     client_addr: SocketAddr,
     client_id: Option<identity::Name>,
+    upstream_addr: SocketAddr,
+    tls: tls::ConditionalServerTls,
+    headers: Arc<[http::HeaderName]>,
+}
+
+/// Identifies the route that handled a request.
+///
+/// Inner services may add this to a response's extensions so that the route
+/// is recorded in the access log.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RouteLabels {
+    pub kind: Arc<str>,
+    pub name: Arc<str>,
 }
 
 struct ResponseFutureInner {
@@ -63,21 +79,28 @@ impl<N> NewAccessLog<N> {
     /// Recording the access log will introduce additional overhead in the
     /// request path, but this is largely avoided when access logging is not
     /// enabled.
+    ///
+    /// The values of the given request `headers` are recorded in the
+    /// `request_headers` field.
     #[inline]
-    pub fn layer() -> impl svc::layer::Layer<N, Service = Self> {
-        svc::layer::mk(|inner| NewAccessLog { inner })
+    pub fn layer(headers: Arc<[http::HeaderName]>) -> impl svc::layer::Layer<N, Service = Self> {
+        svc::layer::mk(move |inner| NewAccessLog {
+            headers: headers.clone(),
+            inner,
+        })
     }
 }
 
 impl<N, T> NewService<T> for NewAccessLog<N>
 where
-    T: Param<tls::ConditionalServerTls> + Param<Remote<ClientAddr>>,
+    T: Param<tls::ConditionalServerTls> + Param<Remote<ClientAddr>> + Param<OrigDstAddr>,
     N: NewService<T>,
 {
     type Service = AccessLogContext<N::Service>;
 
     fn new_service(&self, target: T) -> Self::Service {
         let Remote(ClientAddr(client_addr)) = target.param();
+        let OrigDstAddr(upstream_addr) = target.param();
         let tls: tls::ConditionalServerTls = target.param();
         let client_id = tls
             .value()
@@ -87,6 +110,9 @@ where
             inner,
             client_addr,
             client_id,
+            upstream_addr,
+            tls,
+            headers: self.headers.clone(),
         }
     }
 }
@@ -136,8 +162,14 @@ where
             response_bytes = field::Empty,
             total_ns = field::Empty,
             processing_ns = field::Empty,
+            jwt.sub = field::Empty,
             user_agent = get_header(http::header::USER_AGENT),
             host = get_header(http::header::HOST),
+            tls = %TlsStatus(&self.tls),
+            upstream.addr = %self.upstream_addr,
+            route.kind = field::Empty,
+            route.name = field::Empty,
+            request_headers = field::Empty,
         );
 
         // The access log span is only enabled by the `tracing` subscriber if
@@ -150,6 +182,13 @@ where
             };
         }
 
+        if !self.headers.is_empty() {
+            span.record(
+                "request_headers",
+                &field::display(fmt_headers(&self.headers, request.headers())),
+            );
+        }
+
         AccessLogFuture {
             data: Some(ResponseFutureInner {
                 span,
@@ -204,6 +243,20 @@ where
         span.record("total_ns", &field::display(total_ns));
         span.record("processing_ns", &field::display(processing_ns));
 
+        // If the request was authenticated by a bearer token, record its subject.
+        if let Some(sub) = response
+            .extensions()
+            .get::<linkerd_jwt::Claims>()
+            .and_then(|claims| claims.sub.as_deref())
+        {
+            span.record("jwt.sub", &sub);
+        }
+
+        if let Some(route) = response.extensions().get::<RouteLabels>() {
+            span.record("route.kind", &&*route.kind);
+            span.record("route.name", &&*route.name);
+        }
+
         Poll::Ready(Ok(response))
     }
 }
@@ -212,3 +265,36 @@ where
 fn now() -> humantime::Rfc3339Timestamp {
     humantime::format_rfc3339(SystemTime::now())
 }
+
+/// Formats the values of the selected headers as space-separated
+/// `name="value"` pairs, omitting headers that are not present.
+fn fmt_headers(names: &[http::HeaderName], headers: &http::HeaderMap) -> String {
+    let mut out = String::new();
+    for name in names {
+        for value in headers.get_all(name) {
+            if !out.is_empty() {
+                out.push(' ');
+            }
+            let _ = write!(
+                &mut out,
+                "{}={:?}",
+                name,
+                String::from_utf8_lossy(value.as_bytes())
+            );
+        }
+    }
+    out
+}
+
+struct TlsStatus<'t>(&'t tls::ConditionalServerTls);
+
+impl std::fmt::Display for TlsStatus<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.0.value(), self.0.reason()) {
+            (Some(tls::ServerTls::Established { .. }), _) => write!(f, "true"),
+            (Some(tls::ServerTls::Passthru { .. }), _) => write!(f, "opaque"),
+            (None, Some(reason)) => reason.fmt(f),
+            (None, None) => unreachable!("a conditional has either a value or a reason"),
+        }
+    }
+}
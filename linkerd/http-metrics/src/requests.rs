@@ -4,18 +4,25 @@ mod service;
 pub use self::service::{NewHttpMetrics, ResponseBody};
 use super::Report;
 use linkerd_http_classify::ClassifyResponse;
-use linkerd_metrics::{latency, Counter, FmtMetrics, Histogram, LastUpdate, NewMetrics};
+use linkerd_metrics::{latency, Counter, FmtMetrics, LastUpdate, NativeHistogram, NewMetrics};
 use linkerd_stack::{self as svc, layer};
+use parking_lot::Mutex;
 use std::{collections::HashMap, fmt::Debug, hash::Hash};
 use tokio::time::{Duration, Instant};
 
 type Registry<T, C> = super::Registry<T, Metrics<C>>;
 
 #[derive(Debug)]
-pub struct Requests<T, C>(Registry<T, C>)
+pub struct Requests<T, C>
 where
     T: Hash + Eq,
-    C: Hash + Eq;
+    C: Hash + Eq,
+{
+    registry: Registry<T, C>,
+
+    /// The initial schema of native latency histograms.
+    native_schema: i32,
+}
 
 #[derive(Debug)]
 pub struct Metrics<C>
@@ -24,6 +31,7 @@ where
 {
     last_update: Instant,
     total: Counter,
+    native_schema: i32,
     by_status: HashMap<Option<http::StatusCode>, StatusMetrics<C>>,
 }
 
@@ -32,7 +40,7 @@ struct StatusMetrics<C>
 where
     C: Hash + Eq,
 {
-    latency: Histogram<latency::Ms>,
+    latency: NativeHistogram<latency::Ms>,
     by_class: HashMap<C, ClassMetrics>,
 }
 
@@ -45,16 +53,25 @@ pub struct ClassMetrics {
 
 impl<T: Hash + Eq, C: Hash + Eq> Default for Requests<T, C> {
     fn default() -> Self {
-        Requests(Registry::default())
+        Self::new(latency::DEFAULT_SCHEMA)
     }
 }
 
 impl<T: Hash + Eq, C: Hash + Eq> Requests<T, C> {
+    /// Creates a registry whose native latency histograms have the given
+    /// initial schema.
+    pub fn new(native_schema: i32) -> Self {
+        Self {
+            registry: Registry::default(),
+            native_schema,
+        }
+    }
+
     pub fn into_report(self, retain_idle: Duration) -> Report<T, Metrics<C>>
     where
         Report<T, Metrics<C>>: FmtMetrics,
     {
-        Report::new(retain_idle, self.0)
+        Report::new(retain_idle, self.registry)
     }
 
     pub fn to_layer<L, N, Tgt>(
@@ -64,42 +81,53 @@ impl<T: Hash + Eq, C: Hash + Eq> Requests<T, C> {
         L: ClassifyResponse<Class = C> + Send + Sync + 'static,
         N: svc::NewService<Tgt>,
     {
-        let reg = self.0.clone();
-        NewMetrics::layer(reg)
+        let reg = self.registry.clone();
+        let native_schema = self.native_schema;
+        NewMetrics::layer(reg, move || Mutex::new(Metrics::new(native_schema)))
     }
 }
 
 impl<T: Hash + Eq, C: Hash + Eq> Clone for Requests<T, C> {
     fn clone(&self) -> Self {
-        Requests(self.0.clone())
+        Self {
+            registry: self.registry.clone(),
+            native_schema: self.native_schema,
+        }
     }
 }
 
 // === impl Metrics ===
 
-impl<C: Hash + Eq> Default for Metrics<C> {
-    fn default() -> Self {
+impl<C: Hash + Eq> Metrics<C> {
+    fn new(native_schema: i32) -> Self {
         Self {
             last_update: Instant::now(),
             total: Counter::default(),
+            native_schema,
             by_status: HashMap::default(),
         }
     }
 }
 
+impl<C: Hash + Eq> Default for Metrics<C> {
+    fn default() -> Self {
+        Self::new(latency::DEFAULT_SCHEMA)
+    }
+}
+
 impl<C: Hash + Eq> LastUpdate for Metrics<C> {
     fn last_update(&self) -> Instant {
         self.last_update
     }
 }
 
-impl<C> Default for StatusMetrics<C>
+impl<C> StatusMetrics<C>
 where
     C: Hash + Eq,
 {
-    fn default() -> Self {
+    fn new(native_schema: i32) -> Self {
         Self {
-            latency: Histogram::default(),
+            latency: NativeHistogram::latency(native_schema),
             by_class: HashMap::default(),
         }
     }
@@ -141,7 +169,7 @@ mod tests {
         let retain_idle_for = Duration::from_secs(1);
         let r = super::Requests::<Target, Class>::default();
         let report = r.clone().into_report(retain_idle_for);
-        let mut registry = r.0.lock();
+        let mut registry = r.registry.lock();
 
         let before_update = Instant::now();
         let metrics = registry
@@ -1,7 +1,7 @@
 use super::{ClassMetrics, Metrics, StatusMetrics};
 use crate::{Prefixed, Report};
 use linkerd_metrics::{
-    latency, Counter, FmtLabels, FmtMetric, FmtMetrics, Histogram, Metric, Store,
+    latency, Counter, Encoder, FmtLabels, FmtMetric, FmtMetrics, Metric, NativeHistogram, Store,
 };
 use parking_lot::Mutex;
 use std::{fmt, hash::Hash};
@@ -32,7 +32,7 @@ where
 
     fn response_latency_ms(
         &self,
-    ) -> Metric<'_, Prefixed<'_, &'static str>, Histogram<latency::Ms>> {
+    ) -> Metric<'_, Prefixed<'_, &'static str>, NativeHistogram<latency::Ms>> {
         Metric::new(
             self.prefix_key("response_latency_ms"),
             "Elapsed times between a request's headers being received \
@@ -48,7 +48,7 @@ where
 {
     fn fmt_by_target<N, M>(
         registry: &Store<T, Mutex<Metrics<C>>>,
-        f: &mut fmt::Formatter<'_>,
+        f: &mut Encoder<'_>,
         metric: Metric<'_, N, M>,
         get_metric: impl Fn(&Metrics<C>) -> &M,
     ) -> fmt::Result
@@ -61,7 +61,7 @@ where
 
     fn fmt_by_status<N, M>(
         registry: &Store<T, Mutex<Metrics<C>>>,
-        f: &mut fmt::Formatter<'_>,
+        f: &mut Encoder<'_>,
         metric: Metric<'_, N, M>,
         get_metric: impl Fn(&StatusMetrics<C>) -> &M,
     ) -> fmt::Result
@@ -83,7 +83,7 @@ where
 
     fn fmt_by_class<N, M>(
         registry: &Store<T, Mutex<Metrics<C>>>,
-        f: &mut fmt::Formatter<'_>,
+        f: &mut Encoder<'_>,
         metric: Metric<'_, N, M>,
         get_metric: impl Fn(&ClassMetrics) -> &M,
     ) -> fmt::Result
@@ -111,7 +111,7 @@ where
     T: FmtLabels + Hash + Eq,
     C: FmtLabels + Hash + Eq,
 {
-    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fn fmt_metrics(&self, f: &mut Encoder<'_>) -> fmt::Result {
         let mut registry = self.registry.lock();
         trace!(
             prefix = self.prefix,
@@ -5,6 +5,7 @@ use linkerd_error::Error;
 use linkerd_http_classify::{ClassifyEos, ClassifyResponse};
 use linkerd_metrics::NewMetrics;
 use linkerd_stack::Proxy;
+use linkerd_trace_context::SpanContext;
 use parking_lot::Mutex;
 use pin_project::{pin_project, pinned_drop};
 use std::{
@@ -44,6 +45,7 @@ where
     classify: Option<C>,
     metrics: Option<Arc<Mutex<Metrics<C::Class>>>>,
     stream_open_at: Instant,
+    span: Option<SpanContext>,
     #[pin]
     inner: F,
 }
@@ -73,6 +75,8 @@ where
     metrics: Option<Arc<Mutex<Metrics<C::Class>>>>,
     stream_open_at: Instant,
     latency_recorded: bool,
+    /// The traced span of the request, recorded as the latency's exemplar.
+    span: Option<SpanContext>,
     #[pin]
     inner: B,
 }
@@ -144,11 +148,13 @@ where
         };
 
         let classify = req.extensions().get::<C>().cloned().unwrap_or_default();
+        let span = req.extensions().get::<SpanContext>().cloned();
 
         ResponseFuture {
             classify: Some(classify),
             metrics: self.metrics.clone(),
             stream_open_at: Instant::now(),
+            span,
             inner: self.inner.proxy(svc, req),
         }
     }
@@ -193,11 +199,13 @@ where
         };
 
         let classify = req.extensions().get::<C>().cloned().unwrap_or_default();
+        let span = req.extensions().get::<SpanContext>().cloned();
 
         ResponseFuture {
             classify: Some(classify),
             metrics: self.metrics.clone(),
             stream_open_at: Instant::now(),
+            span,
             inner: self.inner.call(req),
         }
     }
@@ -229,6 +237,7 @@ where
                     metrics,
                     stream_open_at: *this.stream_open_at,
                     latency_recorded: false,
+                    span: this.span.take(),
                     inner,
                 };
                 Ok(http::Response::from_parts(head, body))
@@ -316,6 +325,7 @@ where
             classify: None,
             metrics: None,
             latency_recorded: false,
+            span: None,
         }
     }
 }
@@ -338,13 +348,19 @@ where
 
         (*metrics).last_update = now;
 
+        let native_schema = metrics.native_schema;
         let status_metrics = metrics
             .by_status
             .entry(Some(*this.status))
-            .or_insert_with(StatusMetrics::default);
+            .or_insert_with(|| StatusMetrics::new(native_schema));
 
         let elapsed = now.saturating_duration_since(*this.stream_open_at);
-        status_metrics.latency.add(elapsed);
+        match this.span.take().filter(SpanContext::is_sampled) {
+            Some(span) => status_metrics
+                .latency
+                .add_with_trace_id(elapsed, span.trace_id()),
+            None => status_metrics.latency.add(elapsed),
+        }
 
         *this.latency_recorded = true;
     }
@@ -380,10 +396,11 @@ fn measure_class<C: Hash + Eq>(
 
     (*metrics).last_update = now;
 
+    let native_schema = metrics.native_schema;
     let status_metrics = metrics
         .by_status
         .entry(status)
-        .or_insert_with(StatusMetrics::default);
+        .or_insert_with(|| StatusMetrics::new(native_schema));
 
     let class_metrics = status_metrics
         .by_class
@@ -1,5 +1,5 @@
 use super::{Prefixed, Registry, Report};
-use linkerd_metrics::{Counter, FmtLabels, FmtMetric, FmtMetrics, LastUpdate, Metric};
+use linkerd_metrics::{Counter, Encoder, FmtLabels, FmtMetric, FmtMetrics, LastUpdate, Metric};
 use parking_lot::Mutex;
 use std::{fmt, hash::Hash, sync::Arc};
 use tokio::time::{Duration, Instant};
@@ -18,6 +18,8 @@ pub struct Metrics {
     last_update: Instant,
     retryable: Counter,
     no_budget: Counter,
+    hedged: Counter,
+    hedge_no_budget: Counter,
 }
 
 struct NoBudgetLabel;
@@ -58,6 +60,15 @@ impl Handle {
             m.no_budget.incr();
         }
     }
+
+    pub fn incr_hedged(&self, has_budget: bool) {
+        let mut m = self.0.lock();
+        m.last_update = Instant::now();
+        m.hedged.incr();
+        if !has_budget {
+            m.hedge_no_budget.incr();
+        }
+    }
 }
 
 // === impl Metrics ===
@@ -68,6 +79,8 @@ impl Default for Metrics {
             last_update: Instant::now(),
             retryable: Counter::default(),
             no_budget: Counter::default(),
+            hedged: Counter::default(),
+            hedge_no_budget: Counter::default(),
         }
     }
 }
@@ -90,13 +103,20 @@ where
             "Total count of retryable HTTP responses.",
         )
     }
+
+    fn hedged_total(&self) -> Metric<'_, Prefixed<'_, &'static str>, Counter> {
+        Metric::new(
+            self.prefix_key("hedged_total"),
+            "Total count of HTTP requests that exceeded their hedging delay.",
+        )
+    }
 }
 
 impl<T> FmtMetrics for Report<T, Metrics>
 where
     T: FmtLabels + Hash + Eq,
 {
-    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fn fmt_metrics(&self, f: &mut Encoder<'_>) -> fmt::Result {
         let mut registry = self.registry.lock();
         trace!(
             prfefix = %self.prefix,
@@ -117,6 +137,15 @@ where
                 .fmt_metric_labeled(f, &metric.name, (tgt, NoBudgetLabel))?;
         }
 
+        let metric = self.hedged_total();
+        metric.fmt_help(f)?;
+        for (tgt, tm) in registry.iter() {
+            let m = tm.lock();
+            m.hedged.fmt_metric_labeled(f, &metric.name, tgt)?;
+            m.hedge_no_budget
+                .fmt_metric_labeled(f, &metric.name, (tgt, NoBudgetLabel))?;
+        }
+
         registry.retain_since(Instant::now() - self.retain_idle);
 
         Ok(())
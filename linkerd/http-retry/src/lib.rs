@@ -11,7 +11,13 @@ use http::HeaderMap;
 use http_body::{Body, SizeHint};
 use linkerd_error::Error;
 use parking_lot::Mutex;
-use std::{collections::VecDeque, io::IoSlice, pin::Pin, sync::Arc, task::Context, task::Poll};
+use std::{
+    collections::VecDeque,
+    io::IoSlice,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, Waker},
+};
 use thiserror::Error;
 
 /// Wraps an HTTP body type and lazily buffers data as it is read from the inner
@@ -25,6 +31,12 @@ use thiserror::Error;
 ///
 /// The buffered data can then be used to retry the request if the original
 /// request fails.
+///
+/// A clone that is polled while another clone holds the data waits until
+/// either that clone is dropped or the initial body has been read in full. In
+/// the latter case, the clone replays a copy of the completed body, so that a
+/// request may be sent again (i.e. hedged or mirrored) while the original
+/// request is still in flight.
 #[derive(Debug)]
 pub struct ReplayBody<B> {
     /// Buffered state owned by this body if it is actively being polled. If
@@ -43,6 +55,10 @@ pub struct ReplayBody<B> {
 
     /// Should this clone replay trailers from the shared state?
     replay_trailers: bool,
+
+    /// A copy of the completed body, if this clone replays it without owning
+    /// the state.
+    completed: Option<Completed>,
 }
 
 #[derive(Debug, Error)]
@@ -67,6 +83,12 @@ pub struct BufList {
 #[derive(Debug)]
 struct SharedState<B> {
     body: Mutex<Option<BodyState<B>>>,
+
+    /// Allows clones to wait for the body while another clone owns the state.
+    ///
+    /// When both locks are held, this one must be acquired first.
+    replay: Mutex<Replay>,
+
     /// Did the initial body return `true` from `is_end_stream` before it was
     /// ever polled? If so, always return `true`; the body is completely empty.
     ///
@@ -78,6 +100,26 @@ struct SharedState<B> {
     orig_size_hint: SizeHint,
 }
 
+#[derive(Debug, Default)]
+struct Replay {
+    /// Set once the initial body has been read in full, unless it exceeded
+    /// the maximum buffer size.
+    completed: Option<Completed>,
+
+    /// Set once the initial body has exceeded the maximum buffer size.
+    capped: bool,
+
+    /// Clones waiting for the state to be released or for the body to
+    /// complete.
+    waiters: Vec<Waker>,
+}
+
+#[derive(Clone, Debug)]
+struct Completed {
+    buf: BufList,
+    trailers: Option<HeaderMap>,
+}
+
 #[derive(Debug)]
 struct BodyState<B> {
     buf: BufList,
@@ -109,11 +151,25 @@ impl<B: Body> ReplayBody<B> {
             return Err(body);
         }
 
+        let was_empty = body.is_end_stream();
+        let replay = Replay {
+            // An empty body may be replayed by any clone.
+            completed: if was_empty {
+                Some(Completed {
+                    buf: Default::default(),
+                    trailers: None,
+                })
+            } else {
+                None
+            },
+            ..Default::default()
+        };
         Ok(Self {
             shared: Arc::new(SharedState {
                 body: Mutex::new(None),
+                replay: Mutex::new(replay),
                 orig_size_hint,
-                was_empty: body.is_end_stream(),
+                was_empty,
             }),
             state: Some(BodyState {
                 buf: Default::default(),
@@ -125,22 +181,38 @@ impl<B: Body> ReplayBody<B> {
             // The initial `ReplayBody` has nothing to replay
             replay_body: false,
             replay_trailers: false,
+            completed: None,
         })
     }
 
-    /// Mutably borrows the body state if this clone currently owns it,
-    /// or else tries to acquire it from the shared state.
-    ///
-    /// # Panics
+    /// Ensures that this clone either owns the body state or holds a copy of
+    /// the completed body.
     ///
-    /// This panics if another clone has currently acquired the state, based on
-    /// the assumption that a retry body will not be polled until the previous
-    /// request has been dropped.
-    fn acquire_state<'a>(
-        state: &'a mut Option<BodyState<B>>,
-        shared: &Mutex<Option<BodyState<B>>>,
-    ) -> &'a mut BodyState<B> {
-        state.get_or_insert_with(|| shared.lock().take().expect("missing body state"))
+    /// If another clone owns the state, this waits until that clone is dropped
+    /// or until it has read the initial body in full. Fails if the body has
+    /// exceeded the maximum buffer size while this clone was waiting.
+    fn poll_acquire(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Capped>> {
+        if self.state.is_some() || self.completed.is_some() {
+            return Poll::Ready(Ok(()));
+        }
+
+        let mut replay = self.shared.replay.lock();
+        if let Some(state) = self.shared.body.lock().take() {
+            self.state = Some(state);
+            return Poll::Ready(Ok(()));
+        }
+        if let Some(completed) = replay.completed.clone() {
+            tracing::trace!("Replaying completed body while another clone is active");
+            self.completed = Some(completed);
+            return Poll::Ready(Ok(()));
+        }
+        if replay.capped {
+            return Poll::Ready(Err(Capped));
+        }
+
+        tracing::trace!("Waiting for another clone to release the body");
+        replay.waiters.push(cx.waker().clone());
+        Poll::Pending
     }
 
     /// Returns `true` if the body previously exceeded the configured maximum
@@ -149,17 +221,54 @@ impl<B: Body> ReplayBody<B> {
     /// If this is true, the body is now empty, and the request should *not* be
     /// retried with this body.
     pub fn is_capped(&self) -> bool {
-        self.state
+        if let Some(state) = self.state.as_ref() {
+            return state.is_capped();
+        }
+        if self.completed.is_some() {
+            return false;
+        }
+
+        // The replay lock must be acquired before the body lock.
+        let replay = self.shared.replay.lock();
+        self.shared
+            .body
+            .lock()
             .as_ref()
             .map(BodyState::is_capped)
-            .unwrap_or_else(|| {
-                self.shared
-                    .body
-                    .lock()
-                    .as_ref()
-                    .expect("if our `state` was `None`, the shared state must be `Some`")
-                    .is_capped()
-            })
+            // Another clone owns the state.
+            .unwrap_or(replay.capped)
+    }
+}
+
+impl<B> SharedState<B> {
+    /// Publishes a copy of the body once its data has been read in full, so
+    /// that clones may replay it while the active clone is still in use.
+    ///
+    /// Not all clients read a request's trailers, so the copy is published
+    /// when the data completes and is updated if trailers are read later.
+    /// Clones that began replaying the copy before then do not replay the
+    /// trailers.
+    fn complete(&self, state: &BodyState<B>) {
+        if !state.is_completed || state.is_capped() {
+            return;
+        }
+
+        let mut replay = self.replay.lock();
+        replay.completed = Some(Completed {
+            buf: state.buf.clone(),
+            trailers: state.trailers.clone(),
+        });
+        replay.waiters.drain(..).for_each(Waker::wake);
+    }
+
+    /// Fails any clones waiting for the body once it has exceeded the maximum
+    /// buffer size.
+    fn cap(&self) {
+        let mut replay = self.replay.lock();
+        if !replay.capped {
+            replay.capped = true;
+            replay.waiters.drain(..).for_each(Waker::wake);
+        }
     }
 }
 
@@ -176,7 +285,21 @@ where
         cx: &mut Context<'_>,
     ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
         let this = self.get_mut();
-        let state = Self::acquire_state(&mut this.state, &this.shared.body);
+        if let Err(capped) = futures::ready!(this.poll_acquire(cx)) {
+            return Poll::Ready(Some(Err(capped.into())));
+        }
+
+        // If another clone owns the state, replay the completed body.
+        if let Some(completed) = this.completed.as_ref() {
+            let replay_body = std::mem::take(&mut this.replay_body);
+            if replay_body && completed.buf.has_remaining() {
+                tracing::trace!("Replaying completed body");
+                return Poll::Ready(Some(Ok(Data::Replay(completed.buf.clone()))));
+            }
+            return Poll::Ready(None);
+        }
+
+        let state = this.state.as_mut().expect("state must have been acquired");
         // Move these out to avoid mutable borrow issues in the `map` closure
         // when polling the inner body.
         tracing::trace!(
@@ -231,6 +354,7 @@ where
                 None => {
                     tracing::trace!("Initial body completed");
                     state.is_completed = true;
+                    this.shared.complete(state);
                     return Poll::Ready(None);
                 }
             }
@@ -250,6 +374,7 @@ where
                 );
                 state.buf = Default::default();
             }
+            this.shared.cap();
             data.copy_to_bytes(length)
         } else {
             // Buffer and return the bytes.
@@ -264,12 +389,24 @@ where
         cx: &mut Context<'_>,
     ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
         let this = self.get_mut();
-        let state = Self::acquire_state(&mut this.state, &this.shared.body);
+        if let Err(capped) = futures::ready!(this.poll_acquire(cx)) {
+            return Poll::Ready(Err(capped.into()));
+        }
         tracing::trace!(
             replay_trailers = this.replay_trailers,
             "Replay::poll_trailers"
         );
 
+        // If another clone owns the state, replay the completed body.
+        if let Some(completed) = this.completed.as_ref() {
+            if std::mem::take(&mut this.replay_trailers) {
+                return Poll::Ready(Ok(completed.trailers.clone()));
+            }
+            return Poll::Ready(Ok(None));
+        }
+
+        let state = this.state.as_mut().expect("state must have been acquired");
+
         if this.replay_trailers {
             this.replay_trailers = false;
             if let Some(ref trailers) = state.trailers {
@@ -287,6 +424,9 @@ where
                     }
                     tlrs
                 });
+                if res.is_ok() {
+                    this.shared.complete(state);
+                }
                 return Poll::Ready(res.map_err(Into::into));
             }
         }
@@ -301,6 +441,10 @@ where
             return true;
         }
 
+        if let Some(completed) = self.completed.as_ref() {
+            return !self.replay_body && (!self.replay_trailers || completed.trailers.is_none());
+        }
+
         let is_inner_eos = self
             .state
             .as_ref()
@@ -317,6 +461,10 @@ where
 
     #[inline]
     fn size_hint(&self) -> SizeHint {
+        if let Some(completed) = self.completed.as_ref() {
+            return SizeHint::with_exact(completed.buf.remaining() as u64);
+        }
+
         // If this clone isn't holding the body, return the original size hint.
         let state = match self.state.as_ref() {
             Some(state) => state,
@@ -352,15 +500,19 @@ impl<B> Clone for ReplayBody<B> {
             // reading any additional data from the initial body.
             replay_body: true,
             replay_trailers: true,
+            completed: None,
         }
     }
 }
 
 impl<B> Drop for ReplayBody<B> {
     fn drop(&mut self) {
-        // If this clone owned the shared state, put it back.
+        // If this clone owned the shared state, put it back and notify any
+        // clones that are waiting for it.
         if let Some(state) = self.state.take() {
+            let mut replay = self.shared.replay.lock();
             *self.shared.body.lock() = Some(state);
+            replay.waiters.drain(..).for_each(Waker::wake);
         }
     }
 }
@@ -864,6 +1016,84 @@ mod tests {
         assert!(err.is::<Capped>())
     }
 
+    #[tokio::test(flavor = "current_thread")]
+    async fn replays_while_initial_is_active() {
+        let Test {
+            mut tx,
+            mut initial,
+            mut replay,
+            _trace,
+        } = Test::new();
+
+        let mut tlrs = HeaderMap::new();
+        tlrs.insert("x-hello", HeaderValue::from_str("world").unwrap());
+
+        let tlrs2 = tlrs.clone();
+        tokio::spawn(async move {
+            tx.send_data("hello").await;
+            tx.send_data(" world").await;
+            tx.send_trailers(tlrs2).await;
+        });
+
+        assert_eq!(body_to_string(&mut initial).await, "hello world");
+        let initial_tlrs = initial.trailers().await.expect("trailers should not error");
+        assert_eq!(initial_tlrs.as_ref(), Some(&tlrs));
+
+        // The initial body has not been dropped.
+        assert_eq!(body_to_string(&mut replay).await, "hello world");
+        let replay_tlrs = replay.trailers().await.expect("trailers should not error");
+        assert_eq!(replay_tlrs.as_ref(), Some(&tlrs));
+        drop(initial);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn waits_for_initial_to_complete() {
+        let Test {
+            mut tx,
+            mut initial,
+            replay,
+            _trace,
+        } = Test::new();
+
+        // The replay waits for the initial body to complete.
+        let replay = tokio::spawn(body_to_string(replay));
+        tokio::task::yield_now().await;
+
+        tokio::spawn(async move {
+            tx.send_data("hello").await;
+            tx.send_data(" world").await;
+        });
+        assert_eq!(body_to_string(&mut initial).await, "hello world");
+
+        // The initial body has not been dropped.
+        let body = replay.await.expect("replay task must not fail");
+        assert_eq!(body, "hello world");
+        drop(initial);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn caps_while_initial_is_active() {
+        let _trace = linkerd_tracing::test::with_default_filter("linkerd_http_retry=trace");
+
+        let (mut tx, body) = hyper::Body::channel();
+        let mut initial = ReplayBody::try_new(body, 8).expect("channel body must not be too large");
+        let mut replay = initial.clone();
+
+        // The replay waits for the initial body until it exceeds the cap.
+        let replay = tokio::spawn(async move { replay.data().await });
+
+        tx.send_data(Bytes::from("aaaaaaaaa")).await.unwrap();
+        assert_eq!(chunk(&mut initial).await, Some("aaaaaaaaa".to_string()));
+
+        let err = replay
+            .await
+            .expect("replay task must not fail")
+            .expect("replay must yield Some(Err(..)) when capped")
+            .expect_err("replay must error when capped");
+        assert!(err.is::<Capped>());
+        drop(initial);
+    }
+
     #[tokio::test(flavor = "current_thread")]
     async fn caps_across_replays() {
         // Test that, when the initial body is longer than the preconfigured
@@ -897,6 +1127,37 @@ mod tests {
         assert!(err.is::<Capped>())
     }
 
+    #[test]
+    fn is_capped_while_sibling_drops() {
+        // Checking a clone while the clone that owns the state is dropped on
+        // another thread must not deadlock.
+        let (done_tx, done_rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            for _ in 0..10_000 {
+                let (_tx, body) = hyper::Body::channel();
+                let initial = ReplayBody::try_new(body, 8).expect("body must not be too large");
+                let replay = initial.clone();
+                let barrier = Arc::new(std::sync::Barrier::new(2));
+                let dropper = std::thread::spawn({
+                    let barrier = barrier.clone();
+                    move || {
+                        barrier.wait();
+                        drop(initial);
+                    }
+                });
+                barrier.wait();
+                for _ in 0..10 {
+                    assert!(!replay.is_capped());
+                }
+                dropper.join().expect("drop must not panic");
+            }
+            let _ = done_tx.send(());
+        });
+        done_rx
+            .recv_timeout(std::time::Duration::from_secs(30))
+            .expect("is_capped must not deadlock");
+    }
+
     #[test]
     fn body_too_big() {
         let max_size = 8;
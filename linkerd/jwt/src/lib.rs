@@ -0,0 +1,585 @@
+//! Validates JSON Web Tokens (JWTs) against a JSON Web Key Set (JWKS).
+//!
+//! Only asymmetric signatures are supported: `RS256`, `RS384`, `RS512`,
+//! `ES256`, and `ES384`. Unsigned tokens (`alg: none`) and HMAC-signed tokens
+//! are always rejected.
+
+#![deny(
+    warnings,
+    rust_2018_idioms,
+    clippy::disallowed_methods,
+    clippy::disallowed_types
+)]
+#![forbid(unsafe_code)]
+
+mod watch;
+
+pub use self::watch::watch_file;
+use ring::signature;
+use serde_json::Value;
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use thiserror::Error;
+
+/// Tokens are considered valid for this long before their `nbf` and after
+/// their `exp` to tolerate clock skew.
+const LEEWAY: Duration = Duration::from_secs(60);
+
+/// A set of keys that may sign tokens.
+#[derive(Clone, Debug, Default)]
+pub struct Jwks {
+    keys: Vec<Key>,
+}
+
+/// Constrains the claims of a validated token.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Validation<'a> {
+    /// If set, a token's `iss` claim must be this value.
+    pub issuer: Option<&'a str>,
+
+    /// If not empty, a token's `aud` claim must include one of these values.
+    pub audiences: &'a [String],
+}
+
+/// The registered claims of a validated token.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Claims {
+    pub sub: Option<Arc<str>>,
+    pub iss: Option<Arc<str>>,
+    pub aud: Vec<Arc<str>>,
+    pub exp: SystemTime,
+}
+
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum InvalidToken {
+    #[error("malformed token")]
+    Malformed,
+
+    #[error("unsupported signing algorithm: {0}")]
+    UnsupportedAlgorithm(String),
+
+    #[error("no key matches the token")]
+    UnknownKey,
+
+    #[error("invalid signature")]
+    InvalidSignature,
+
+    #[error("token has expired")]
+    Expired,
+
+    #[error("token is not yet valid")]
+    NotYetValid,
+
+    #[error("token has an unexpected issuer")]
+    InvalidIssuer,
+
+    #[error("token has an unexpected audience")]
+    InvalidAudience,
+}
+
+#[derive(Debug, Error)]
+pub enum InvalidJwks {
+    #[error("invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("missing `keys`")]
+    MissingKeys,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Alg {
+    Rs256,
+    Rs384,
+    Rs512,
+    Es256,
+    Es384,
+}
+
+#[derive(Clone, Debug)]
+struct Key {
+    kid: Option<String>,
+    alg: Option<Alg>,
+    material: Material,
+}
+
+#[derive(Clone, Debug)]
+enum Material {
+    Rsa { n: Vec<u8>, e: Vec<u8> },
+    P256(Vec<u8>),
+    P384(Vec<u8>),
+}
+
+/// Returns the bearer token from a request's `authorization` header, if one
+/// is present.
+pub fn bearer_token(headers: &http::HeaderMap) -> Option<&str> {
+    let value = headers.get(http::header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("bearer") {
+        return None;
+    }
+    let token = token.trim();
+    if token.is_empty() {
+        return None;
+    }
+    Some(token)
+}
+
+// === impl Jwks ===
+
+impl Jwks {
+    /// Parses a JWKS document.
+    ///
+    /// Keys with unsupported types, curves, or algorithms and keys that are not
+    /// used for signatures are ignored.
+    pub fn from_json(json: &[u8]) -> Result<Self, InvalidJwks> {
+        let doc = serde_json::from_slice::<Value>(json)?;
+        let keys = doc
+            .get("keys")
+            .and_then(Value::as_array)
+            .ok_or(InvalidJwks::MissingKeys)?
+            .iter()
+            .filter_map(|jwk| {
+                let key = Key::from_jwk(jwk);
+                if key.is_none() {
+                    tracing::debug!(kid = ?jwk.get("kid"), "Ignoring unsupported key");
+                }
+                key
+            })
+            .collect();
+        Ok(Self { keys })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Validates a compact-serialized token, returning its claims if it was
+    /// signed by one of the set's keys and satisfies `validation` at `now`.
+    pub fn validate(
+        &self,
+        token: &str,
+        validation: Validation<'_>,
+        now: SystemTime,
+    ) -> Result<Claims, InvalidToken> {
+        let mut parts = token.split('.');
+        let (header, payload, sig) = match (parts.next(), parts.next(), parts.next(), parts.next())
+        {
+            (Some(h), Some(p), Some(s), None) => (h, p, s),
+            _ => return Err(InvalidToken::Malformed),
+        };
+
+        let header = decode_json(header)?;
+        let alg = match header.get("alg").and_then(Value::as_str) {
+            Some(alg) => Alg::from_name(alg)
+                .ok_or_else(|| InvalidToken::UnsupportedAlgorithm(alg.to_string()))?,
+            None => return Err(InvalidToken::Malformed),
+        };
+        let kid = header.get("kid").and_then(Value::as_str);
+
+        let sig = decode(sig)?;
+        let msg = &token.as_bytes()[..header_and_payload_len(token)];
+        let mut candidates = self
+            .keys
+            .iter()
+            .filter(|k| k.can_verify(alg, kid))
+            .peekable();
+        if candidates.peek().is_none() {
+            return Err(InvalidToken::UnknownKey);
+        }
+        if !candidates.any(|k| k.verify(alg, msg, &sig)) {
+            return Err(InvalidToken::InvalidSignature);
+        }
+
+        let claims = decode_json(payload)?;
+        Claims::validate(&claims, validation, now)
+    }
+}
+
+fn header_and_payload_len(token: &str) -> usize {
+    token.rfind('.').expect("token must have three parts")
+}
+
+fn decode(part: &str) -> Result<Vec<u8>, InvalidToken> {
+    base64::decode_config(part, base64::URL_SAFE_NO_PAD).map_err(|_| InvalidToken::Malformed)
+}
+
+fn decode_json(part: &str) -> Result<Value, InvalidToken> {
+    let json = decode(part)?;
+    match serde_json::from_slice(&json) {
+        Ok(v @ Value::Object(_)) => Ok(v),
+        _ => Err(InvalidToken::Malformed),
+    }
+}
+
+// === impl Claims ===
+
+impl Claims {
+    fn validate(
+        claims: &Value,
+        validation: Validation<'_>,
+        now: SystemTime,
+    ) -> Result<Self, InvalidToken> {
+        let time = |name: &str| -> Result<Option<SystemTime>, InvalidToken> {
+            match claims.get(name) {
+                None => Ok(None),
+                Some(v) => {
+                    let secs = v.as_u64().ok_or(InvalidToken::Malformed)?;
+                    // Times that can't be represented are malformed.
+                    let time = UNIX_EPOCH
+                        .checked_add(Duration::from_secs(secs))
+                        .ok_or(InvalidToken::Malformed)?;
+                    Ok(Some(time))
+                }
+            }
+        };
+        let string = |name: &str| -> Result<Option<Arc<str>>, InvalidToken> {
+            match claims.get(name) {
+                None => Ok(None),
+                Some(Value::String(s)) => Ok(Some(s.as_str().into())),
+                Some(_) => Err(InvalidToken::Malformed),
+            }
+        };
+
+        // Tokens without an expiration are never accepted.
+        let exp = time("exp")?.ok_or(InvalidToken::Malformed)?;
+        if exp.checked_add(LEEWAY).ok_or(InvalidToken::Malformed)? <= now {
+            return Err(InvalidToken::Expired);
+        }
+        if let Some(nbf) = time("nbf")? {
+            if now + LEEWAY < nbf {
+                return Err(InvalidToken::NotYetValid);
+            }
+        }
+
+        let iss = string("iss")?;
+        if let Some(issuer) = validation.issuer {
+            if iss.as_deref() != Some(issuer) {
+                return Err(InvalidToken::InvalidIssuer);
+            }
+        }
+
+        let aud = match claims.get("aud") {
+            None => vec![],
+            Some(Value::String(s)) => vec![s.as_str().into()],
+            Some(Value::Array(auds)) => auds
+                .iter()
+                .map(|a| a.as_str().map(Into::into).ok_or(InvalidToken::Malformed))
+                .collect::<Result<Vec<Arc<str>>, _>>()?,
+            Some(_) => return Err(InvalidToken::Malformed),
+        };
+        if !validation.audiences.is_empty()
+            && !aud
+                .iter()
+                .any(|a| validation.audiences.iter().any(|v| **a == **v))
+        {
+            return Err(InvalidToken::InvalidAudience);
+        }
+
+        Ok(Self {
+            sub: string("sub")?,
+            iss,
+            aud,
+            exp,
+        })
+    }
+}
+
+// === impl Alg ===
+
+impl Alg {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "RS256" => Some(Self::Rs256),
+            "RS384" => Some(Self::Rs384),
+            "RS512" => Some(Self::Rs512),
+            "ES256" => Some(Self::Es256),
+            "ES384" => Some(Self::Es384),
+            _ => None,
+        }
+    }
+}
+
+// === impl Key ===
+
+impl Key {
+    fn from_jwk(jwk: &Value) -> Option<Self> {
+        let field = |name: &str| jwk.get(name).and_then(Value::as_str);
+        let bytes = |name: &str| {
+            let v = field(name)?;
+            base64::decode_config(v, base64::URL_SAFE_NO_PAD).ok()
+        };
+
+        if let Some(u) = field("use") {
+            if u != "sig" {
+                return None;
+            }
+        }
+
+        let alg = match field("alg") {
+            Some(alg) => Some(Alg::from_name(alg)?),
+            None => None,
+        };
+
+        let material = match field("kty")? {
+            "RSA" => Material::Rsa {
+                n: bytes("n")?,
+                e: bytes("e")?,
+            },
+            "EC" => {
+                // Public keys are encoded as uncompressed points.
+                let mut point = vec![0x04];
+                point.extend(bytes("x")?);
+                point.extend(bytes("y")?);
+                match field("crv")? {
+                    "P-256" => Material::P256(point),
+                    "P-384" => Material::P384(point),
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        };
+
+        Some(Self {
+            kid: field("kid").map(ToString::to_string),
+            alg,
+            material,
+        })
+    }
+
+    fn can_verify(&self, alg: Alg, kid: Option<&str>) -> bool {
+        if let Some(kid) = kid {
+            if self.kid.as_deref() != Some(kid) {
+                return false;
+            }
+        }
+        if let Some(a) = self.alg {
+            if a != alg {
+                return false;
+            }
+        }
+        matches!(
+            (&self.material, alg),
+            (Material::Rsa { .. }, Alg::Rs256 | Alg::Rs384 | Alg::Rs512)
+                | (Material::P256(_), Alg::Es256)
+                | (Material::P384(_), Alg::Es384)
+        )
+    }
+
+    fn verify(&self, alg: Alg, msg: &[u8], sig: &[u8]) -> bool {
+        match self.material {
+            Material::Rsa { ref n, ref e } => {
+                let params = match alg {
+                    Alg::Rs256 => &signature::RSA_PKCS1_2048_8192_SHA256,
+                    Alg::Rs384 => &signature::RSA_PKCS1_2048_8192_SHA384,
+                    Alg::Rs512 => &signature::RSA_PKCS1_2048_8192_SHA512,
+                    _ => return false,
+                };
+                signature::RsaPublicKeyComponents { n, e }
+                    .verify(params, msg, sig)
+                    .is_ok()
+            }
+            Material::P256(ref point) => {
+                signature::UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_FIXED, point)
+                    .verify(msg, sig)
+                    .is_ok()
+            }
+            Material::P384(ref point) => {
+                signature::UnparsedPublicKey::new(&signature::ECDSA_P384_SHA384_FIXED, point)
+                    .verify(msg, sig)
+                    .is_ok()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::{rand::SystemRandom, signature::KeyPair};
+
+    struct Signer {
+        key: signature::EcdsaKeyPair,
+        rng: SystemRandom,
+    }
+
+    impl Signer {
+        fn new() -> Self {
+            let rng = SystemRandom::new();
+            let alg = &signature::ECDSA_P256_SHA256_FIXED_SIGNING;
+            let pkcs8 = signature::EcdsaKeyPair::generate_pkcs8(alg, &rng).unwrap();
+            let key = signature::EcdsaKeyPair::from_pkcs8(alg, pkcs8.as_ref()).unwrap();
+            Self { key, rng }
+        }
+
+        fn jwks(&self, kid: &str) -> Jwks {
+            let point = self.key.public_key().as_ref();
+            let (x, y) = point[1..].split_at(32);
+            let json = serde_json::json!({
+                "keys": [{
+                    "kty": "EC",
+                    "crv": "P-256",
+                    "use": "sig",
+                    "kid": kid,
+                    "x": base64::encode_config(x, base64::URL_SAFE_NO_PAD),
+                    "y": base64::encode_config(y, base64::URL_SAFE_NO_PAD),
+                }]
+            });
+            Jwks::from_json(json.to_string().as_bytes()).unwrap()
+        }
+
+        fn sign(&self, kid: &str, claims: Value) -> String {
+            let enc = |v: Value| base64::encode_config(v.to_string(), base64::URL_SAFE_NO_PAD);
+            let msg = format!(
+                "{}.{}",
+                enc(serde_json::json!({ "alg": "ES256", "typ": "JWT", "kid": kid })),
+                enc(claims)
+            );
+            let sig = self.key.sign(&self.rng, msg.as_bytes()).unwrap();
+            format!(
+                "{}.{}",
+                msg,
+                base64::encode_config(sig.as_ref(), base64::URL_SAFE_NO_PAD)
+            )
+        }
+    }
+
+    fn now() -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(1_600_000_000)
+    }
+
+    fn claims(exp: u64) -> Value {
+        serde_json::json!({
+            "sub": "alice",
+            "iss": "https://issuer.example.com",
+            "aud": ["api", "web"],
+            "exp": exp,
+        })
+    }
+
+    #[test]
+    fn validates_signed_token() {
+        let signer = Signer::new();
+        let jwks = signer.jwks("k1");
+        let token = signer.sign("k1", claims(1_600_000_100));
+        let audiences = vec!["api".to_string()];
+        let validation = Validation {
+            issuer: Some("https://issuer.example.com"),
+            audiences: &audiences,
+        };
+
+        let claims = jwks
+            .validate(&token, validation, now())
+            .expect("token must be valid");
+        assert_eq!(claims.sub.as_deref(), Some("alice"));
+        assert_eq!(claims.aud.len(), 2);
+
+        assert_eq!(
+            jwks.validate(
+                &token,
+                Validation {
+                    issuer: Some("https://other.example.com"),
+                    audiences: &[],
+                },
+                now()
+            ),
+            Err(InvalidToken::InvalidIssuer)
+        );
+        assert_eq!(
+            jwks.validate(
+                &token,
+                Validation {
+                    issuer: None,
+                    audiences: &["admin".to_string()],
+                },
+                now()
+            ),
+            Err(InvalidToken::InvalidAudience)
+        );
+    }
+
+    #[test]
+    fn rejects_expired_token() {
+        let signer = Signer::new();
+        let jwks = signer.jwks("k1");
+        let token = signer.sign("k1", claims(1_600_000_000 - 120));
+        assert_eq!(
+            jwks.validate(&token, Validation::default(), now()),
+            Err(InvalidToken::Expired)
+        );
+
+        // Expiration is subject to leeway.
+        let token = signer.sign("k1", claims(1_600_000_000 - 30));
+        assert!(jwks.validate(&token, Validation::default(), now()).is_ok());
+    }
+
+    #[test]
+    fn rejects_unrepresentable_times() {
+        let signer = Signer::new();
+        let jwks = signer.jwks("k1");
+
+        let token = signer.sign("k1", claims(u64::MAX));
+        assert_eq!(
+            jwks.validate(&token, Validation::default(), now()),
+            Err(InvalidToken::Malformed)
+        );
+
+        let mut claims = claims(1_600_000_100);
+        claims["nbf"] = u64::MAX.into();
+        let token = signer.sign("k1", claims);
+        assert_eq!(
+            jwks.validate(&token, Validation::default(), now()),
+            Err(InvalidToken::Malformed)
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_bad_signatures() {
+        let signer = Signer::new();
+        let token = signer.sign("k1", claims(1_600_000_100));
+
+        assert_eq!(
+            signer
+                .jwks("k2")
+                .validate(&token, Validation::default(), now()),
+            Err(InvalidToken::UnknownKey)
+        );
+        assert_eq!(
+            Signer::new()
+                .jwks("k1")
+                .validate(&token, Validation::default(), now()),
+            Err(InvalidToken::InvalidSignature)
+        );
+
+        let unsigned = format!(
+            "{}.{}.",
+            base64::encode_config(r#"{"alg":"none"}"#, base64::URL_SAFE_NO_PAD),
+            base64::encode_config(claims(1_600_000_100).to_string(), base64::URL_SAFE_NO_PAD),
+        );
+        assert_eq!(
+            signer
+                .jwks("k1")
+                .validate(&unsigned, Validation::default(), now()),
+            Err(InvalidToken::UnsupportedAlgorithm("none".to_string()))
+        );
+    }
+
+    #[test]
+    fn parses_bearer_token() {
+        let mut headers = http::HeaderMap::new();
+        assert_eq!(bearer_token(&headers), None);
+
+        headers.insert(
+            http::header::AUTHORIZATION,
+            http::HeaderValue::from_static("Bearer abc.def.ghi"),
+        );
+        assert_eq!(bearer_token(&headers), Some("abc.def.ghi"));
+
+        headers.insert(
+            http::header::AUTHORIZATION,
+            http::HeaderValue::from_static("Basic dXNlcjpwYXNz"),
+        );
+        assert_eq!(bearer_token(&headers), None);
+    }
+}
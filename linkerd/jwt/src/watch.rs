@@ -0,0 +1,87 @@
+use super::Jwks;
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use tokio::{fs, sync::watch, time};
+use tracing::{debug, warn, Instrument};
+
+/// Loads a JWKS file and watches it for changes.
+///
+/// If a Tokio runtime is available, a task reads the file and then polls the
+/// file's modification time every `interval`, reloading it when it changes.
+/// The task completes once all receivers are dropped. Otherwise, the file is
+/// read immediately and is not reloaded.
+///
+/// The receiver holds `None` until the file has been read successfully. If
+/// the file later becomes unreadable or invalid, the last valid key set is
+/// retained.
+pub fn watch_file(path: PathBuf, interval: Duration) -> watch::Receiver<Option<Arc<Jwks>>> {
+    let handle = match tokio::runtime::Handle::try_current() {
+        Ok(handle) => handle,
+        Err(_) => {
+            debug!(path = %path.display(), "No runtime; JWKS will not be reloaded");
+            let jwks = match load_blocking(&path) {
+                Ok(jwks) => Some(Arc::new(jwks)),
+                Err(error) => {
+                    warn!(path = %path.display(), %error, "Failed to load JWKS");
+                    None
+                }
+            };
+            return watch::channel(jwks).1;
+        }
+    };
+
+    let (tx, rx) = watch::channel(None);
+    let span = tracing::debug_span!("jwks", path = %path.display());
+    handle.spawn(
+        async move {
+            // The modification time of the last file that was loaded, if any.
+            let mut loaded: Option<Option<SystemTime>> = None;
+            let mut warn_unreadable = true;
+            loop {
+                match fs::metadata(&path).await {
+                    Ok(meta) => {
+                        let modified = meta.modified().ok();
+                        if loaded != Some(modified) {
+                            match load(&path).await {
+                                Ok(jwks) => {
+                                    debug!("Loaded JWKS");
+                                    loaded = Some(modified);
+                                    let _ = tx.send(Some(Arc::new(jwks)));
+                                }
+                                Err(error) => warn!(%error, "Failed to load JWKS"),
+                            }
+                        }
+                    }
+                    Err(error) if warn_unreadable => {
+                        warn!(%error, "Failed to load JWKS");
+                        warn_unreadable = false;
+                    }
+                    Err(error) => debug!(%error, "Failed to stat JWKS"),
+                }
+
+                tokio::select! {
+                    _ = tx.closed() => return,
+                    _ = time::sleep(interval) => {}
+                }
+            }
+        }
+        .instrument(span),
+    );
+
+    rx
+}
+
+async fn load(path: &Path) -> Result<Jwks, linkerd_error::Error> {
+    let json = fs::read(path).await?;
+    let jwks = Jwks::from_json(&json)?;
+    Ok(jwks)
+}
+
+fn load_blocking(path: &Path) -> Result<Jwks, linkerd_error::Error> {
+    let json = std::fs::read(path)?;
+    let jwks = Jwks::from_json(&json)?;
+    Ok(jwks)
+}
@@ -1,9 +1,11 @@
 use super::{
-    prom::{FmtLabels, FmtMetric},
+    prom::{self, Encoder, FmtLabels, FmtMetric, Format},
+    protobuf::proto,
     Factor,
 };
 use std::fmt::{self, Display};
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
 
 /// A Prometheus counter is represented by a `Wrapping` unsigned 52-bit integer.
 ///
@@ -17,14 +19,22 @@ use std::sync::atomic::{AtomicU64, Ordering};
 /// [`rate()`]: https://prometheus.io/docs/prometheus/latest/querying/functions/#rate()
 /// [`irate()`]: https://prometheus.io/docs/prometheus/latest/querying/functions/#irate()
 /// [`resets()`]: https://prometheus.io/docs/prometheus/latest/querying/functions/#resets
+///
+/// Counters record when they were created, which is exposed as the
+/// `_created` sample in the OpenMetrics format. Counters that are built from a
+/// snapshot of a value (i.e. with `From<u64>`) have no creation time.
 #[derive(Debug)]
-pub struct Counter<F = ()>(AtomicU64, std::marker::PhantomData<F>);
+pub struct Counter<F = ()>(AtomicU64, Option<SystemTime>, std::marker::PhantomData<F>);
 
 // ===== impl Counter =====
 
 impl<F> Default for Counter<F> {
     fn default() -> Self {
-        Self(AtomicU64::default(), std::marker::PhantomData)
+        Self(
+            AtomicU64::default(),
+            Some(SystemTime::now()),
+            std::marker::PhantomData,
+        )
     }
 }
 
@@ -48,6 +58,35 @@ impl<F: Factor> Counter<F> {
         let n = self.0.load(Ordering::Acquire);
         F::factor(n)
     }
+
+    fn fmt_counter<N, L>(&self, f: &mut Encoder<'_>, name: N, labels: Option<&L>) -> fmt::Result
+    where
+        N: Display,
+        L: FmtLabels,
+    {
+        if !f.start_metric(Self::KIND, &name) {
+            return Ok(());
+        }
+
+        if let Some(metric) = f.proto_metric(Self::KIND, &name, labels) {
+            metric.counter = Some(proto::Counter {
+                value: Some(self.value()),
+            });
+            return Ok(());
+        }
+
+        if f.format() != Format::OpenMetrics {
+            return f.fmt_sample(name, labels, self.value());
+        }
+        let name = name.to_string();
+        let family = prom::counter_family(&name);
+        f.fmt_sample(format_args!("{}_total", family), labels, self.value())?;
+        if let Some(created) = self.1 {
+            let created = crate::unix_time(created).as_secs_f64();
+            f.fmt_sample(format_args!("{}_created", family), labels, created)?;
+        }
+        Ok(())
+    }
 }
 
 impl<F: Factor> From<&Counter<F>> for f64 {
@@ -57,37 +96,30 @@ impl<F: Factor> From<&Counter<F>> for f64 {
 }
 
 impl<F> From<&Counter<F>> for u64 {
-    fn from(Counter(ref counter, _): &Counter<F>) -> u64 {
+    fn from(Counter(ref counter, _, _): &Counter<F>) -> u64 {
         counter.load(Ordering::Acquire)
     }
 }
 
 impl<F> From<u64> for Counter<F> {
     fn from(value: u64) -> Self {
-        Counter(value.into(), std::marker::PhantomData)
+        Counter(value.into(), None, std::marker::PhantomData)
     }
 }
 
 impl<F: Factor> FmtMetric for Counter<F> {
     const KIND: &'static str = "counter";
 
-    fn fmt_metric<N: Display>(&self, f: &mut fmt::Formatter<'_>, name: N) -> fmt::Result {
-        writeln!(f, "{} {}", name, self.value())
+    fn fmt_metric<N: Display>(&self, f: &mut Encoder<'_>, name: N) -> fmt::Result {
+        self.fmt_counter(f, name, None::<&prom::NoLabels>)
     }
 
-    fn fmt_metric_labeled<N, L>(
-        &self,
-        f: &mut fmt::Formatter<'_>,
-        name: N,
-        labels: L,
-    ) -> fmt::Result
+    fn fmt_metric_labeled<N, L>(&self, f: &mut Encoder<'_>, name: N, labels: L) -> fmt::Result
     where
         L: FmtLabels,
         N: Display,
     {
-        write!(f, "{}{{", name)?;
-        labels.fmt_labels(f)?;
-        writeln!(f, "}} {}", self.value())
+        self.fmt_counter(f, name, Some(&labels))
     }
 }
 
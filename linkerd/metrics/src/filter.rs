@@ -0,0 +1,403 @@
+use super::{
+    native::Sparse,
+    protobuf::{proto, Labels},
+};
+use std::{collections::HashMap, fmt};
+
+/// Selects and aggregates the metrics that are served for a scrape.
+///
+/// Metric families are selected by name prefix. Dropped labels are aggregated
+/// away by summing the values of samples that are otherwise identical.
+/// Summaries' quantiles cannot be summed, so summaries retain all of their
+/// labels.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Filter {
+    prefixes: Vec<String>,
+    drop_labels: Vec<String>,
+}
+
+/// Indicates that a label that distinguishes the samples of a single metric
+/// (i.e. a histogram's `le` label) cannot be dropped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReservedLabel(String);
+
+/// Labels that describe a histogram's buckets or a summary's quantiles.
+const RESERVED_LABELS: &[&str] = &["le", "quantile"];
+
+/// The aggregated value of samples that share a name and labels.
+struct Aggregate {
+    value: f64,
+    /// Text following the value, e.g. an exemplar, of the first sample.
+    trailer: Option<String>,
+}
+
+/// Accumulates the text samples of a single metric family.
+#[derive(Default)]
+pub(crate) struct Family {
+    samples: Vec<((String, Labels), Aggregate)>,
+    sample_idx: HashMap<(String, Labels), usize>,
+}
+
+// === impl Filter ===
+
+impl Filter {
+    /// Only serves metric families whose names start with the given prefix (or
+    /// with any other selected prefix).
+    pub fn select_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefixes.push(prefix.into());
+        self
+    }
+
+    /// Aggregates the given label away.
+    ///
+    /// Fails if the label describes histogram buckets or summary quantiles.
+    pub fn drop_label(mut self, label: impl Into<String>) -> Result<Self, ReservedLabel> {
+        let label = label.into();
+        if RESERVED_LABELS.contains(&&*label) {
+            return Err(ReservedLabel(label));
+        }
+        self.drop_labels.push(label);
+        Ok(self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prefixes.is_empty() && self.drop_labels.is_empty()
+    }
+
+    pub(crate) fn selects_all(&self) -> bool {
+        self.prefixes.is_empty()
+    }
+
+    pub(crate) fn selects(&self, family: &str) -> bool {
+        self.prefixes.is_empty() || self.prefixes.iter().any(|p| family.starts_with(&**p))
+    }
+
+    pub(crate) fn drops_labels(&self) -> bool {
+        !self.drop_labels.is_empty()
+    }
+
+    pub(crate) fn retain_labels(&self, mut labels: Labels) -> Labels {
+        labels.retain(|(name, _)| !self.drop_labels.contains(name));
+        labels
+    }
+
+    /// Aggregates away the dropped labels of protobuf-encoded metric families.
+    ///
+    /// Families must already have been selected by the encoder.
+    pub(crate) fn aggregate_protobuf(&self, families: &mut [proto::MetricFamily]) {
+        if self.drop_labels.is_empty() {
+            return;
+        }
+
+        for family in families.iter_mut() {
+            if family.r#type() == proto::MetricType::Summary {
+                continue;
+            }
+            let mut metrics = Vec::<proto::Metric>::with_capacity(family.metric.len());
+            for mut metric in family.metric.drain(..) {
+                metric
+                    .label
+                    .retain(|l| !self.drop_labels.iter().any(|d| d == l.name()));
+                match metrics.iter_mut().find(|m| m.label == metric.label) {
+                    Some(agg) => merge_metric(agg, metric),
+                    None => metrics.push(metric),
+                }
+            }
+            family.metric = metrics;
+        }
+    }
+}
+
+// === impl ReservedLabel ===
+
+impl fmt::Display for ReservedLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the {:?} label cannot be dropped", self.0)
+    }
+}
+
+impl std::error::Error for ReservedLabel {}
+
+// === impl Family ===
+
+impl Family {
+    pub(crate) fn add(
+        &mut self,
+        name: String,
+        labels: Labels,
+        value: f64,
+        trailer: Option<String>,
+    ) {
+        let created = name.ends_with("_created");
+        let key = (name, labels);
+        match self.sample_idx.get(&key) {
+            Some(&idx) => {
+                let agg = &mut self.samples[idx].1;
+                if created {
+                    // Aggregates were created when their first member was.
+                    agg.value = agg.value.min(value);
+                } else {
+                    agg.value += value;
+                }
+                if agg.trailer.is_none() {
+                    agg.trailer = trailer;
+                }
+            }
+            None => {
+                self.sample_idx.insert(key.clone(), self.samples.len());
+                self.samples.push((key, Aggregate { value, trailer }));
+            }
+        }
+    }
+
+    /// Writes the family's aggregated samples, in the order in which they were
+    /// first observed.
+    pub(crate) fn flush(&mut self, w: &mut dyn fmt::Write) -> fmt::Result {
+        self.sample_idx.clear();
+        for ((name, labels), agg) in self.samples.drain(..) {
+            w.write_str(&name)?;
+            if !labels.is_empty() {
+                w.write_char('{')?;
+                for (i, (k, v)) in labels.iter().enumerate() {
+                    if i > 0 {
+                        w.write_char(',')?;
+                    }
+                    write!(w, "{}=\"", k)?;
+                    for c in v.chars() {
+                        match c {
+                            '\\' => w.write_str("\\\\")?,
+                            '"' => w.write_str("\\\"")?,
+                            '\n' => w.write_str("\\n")?,
+                            c => w.write_char(c)?,
+                        }
+                    }
+                    w.write_char('"')?;
+                }
+                w.write_char('}')?;
+            }
+            write!(w, " {}", agg.value)?;
+            if let Some(trailer) = agg.trailer {
+                write!(w, " {}", trailer)?;
+            }
+            writeln!(w)?;
+        }
+        Ok(())
+    }
+}
+
+/// Sums the values of a metric into another that shares its labels.
+fn merge_metric(agg: &mut proto::Metric, metric: proto::Metric) {
+    fn add(a: &mut Option<f64>, b: Option<f64>) {
+        *a = Some(a.unwrap_or_default() + b.unwrap_or_default());
+    }
+
+    if let (Some(a), Some(b)) = (agg.counter.as_mut(), metric.counter) {
+        add(&mut a.value, b.value);
+    }
+    if let (Some(a), Some(b)) = (agg.gauge.as_mut(), metric.gauge) {
+        add(&mut a.value, b.value);
+    }
+    if let (Some(a), Some(b)) = (agg.untyped.as_mut(), metric.untyped) {
+        add(&mut a.value, b.value);
+    }
+    if let (Some(a), Some(b)) = (agg.histogram.as_mut(), metric.histogram) {
+        let native = Sparse::from_proto(a).zip(Sparse::from_proto(&b));
+        a.sample_count = Some(a.sample_count() + b.sample_count());
+        add(&mut a.sample_sum, b.sample_sum);
+        for (a, b) in a.bucket.iter_mut().zip(b.bucket) {
+            a.cumulative_count = Some(a.cumulative_count() + b.cumulative_count());
+        }
+        if let Some((mut native, other)) = native {
+            native.merge(other);
+            native.set_proto(a);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        histogram::{Bounds, Bucket},
+        prom::{Encoder, Format},
+        Counter, FmtLabels, FmtMetric, Gauge, Histogram, NativeHistogram,
+    };
+
+    crate::metrics! {
+        request_total: Counter { "Total requests" },
+        response_latency_ms: Histogram<u32> { "Latency" },
+        tcp_open_total: Counter { "Opened connections" }
+    }
+
+    static BOUNDS: &Bounds = &Bounds(&[Bucket::Le(10.0), Bucket::Inf]);
+
+    struct DstPod(&'static str);
+
+    impl FmtLabels for DstPod {
+        fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "dst_pod=\"{}\",status=\"200\"", self.0)
+        }
+    }
+
+    /// Formats a set of metrics in the given format.
+    fn encode(filter: &Filter, format: Format) -> String {
+        let a = Histogram::<u32>::new(BOUNDS);
+        a.add(2u32);
+        a.add(20u32);
+        let b = Histogram::<u32>::new(BOUNDS);
+        b.add_with_trace_id(5u32, "abc");
+
+        let mut text = String::new();
+        let mut f = Encoder::text(&mut text, format).with_filter(filter);
+        request_total.fmt_help(&mut f).unwrap();
+        request_total
+            .fmt_metric_labeled(&mut f, &Counter::new(), &DstPod("a"))
+            .unwrap();
+        request_total
+            .fmt_metric_labeled(&mut f, &Counter::from(2), &DstPod("b"))
+            .unwrap();
+        response_latency_ms.fmt_help(&mut f).unwrap();
+        response_latency_ms
+            .fmt_metric_labeled(&mut f, &a, &DstPod("a"))
+            .unwrap();
+        response_latency_ms
+            .fmt_metric_labeled(&mut f, &b, &DstPod("b"))
+            .unwrap();
+        tcp_open_total.fmt_help(&mut f).unwrap();
+        tcp_open_total
+            .fmt_metric_labeled(&mut f, &Counter::from(5), &DstPod("a"))
+            .unwrap();
+        // Metrics without help text form their own family.
+        Gauge::from(100)
+            .fmt_metric(&mut f, "process_start_time_seconds")
+            .unwrap();
+        f.finish().unwrap();
+        text
+    }
+
+    #[test]
+    fn selects_prefixes() {
+        let filter = Filter::default()
+            .select_prefix("tcp_")
+            .select_prefix("process_");
+        assert_eq!(
+            encode(&filter, Format::Prometheus),
+            "\
+# HELP tcp_open_total Opened connections
+# TYPE tcp_open_total counter
+tcp_open_total{dst_pod=\"a\",status=\"200\"} 5
+process_start_time_seconds 100
+"
+        );
+    }
+
+    #[test]
+    fn drops_labels() {
+        let filter = Filter::default()
+            .select_prefix("re")
+            .drop_label("dst_pod")
+            .unwrap();
+        assert_eq!(
+            encode(&filter, Format::Prometheus),
+            "\
+# HELP request_total Total requests
+# TYPE request_total counter
+request_total{status=\"200\"} 2
+# HELP response_latency_ms Latency
+# TYPE response_latency_ms histogram
+response_latency_ms_bucket{status=\"200\",le=\"10\"} 2
+response_latency_ms_bucket{status=\"200\",le=\"+Inf\"} 3
+response_latency_ms_count{status=\"200\"} 3
+response_latency_ms_sum{status=\"200\"} 27
+"
+        );
+    }
+
+    #[test]
+    fn retains_exemplars_and_created() {
+        let filter = Filter::default()
+            .select_prefix("response_")
+            .drop_label("dst_pod")
+            .unwrap();
+        let text = encode(&filter, Format::OpenMetrics);
+        let lines = text.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 7, "{}", text);
+        assert!(
+            lines[2].starts_with(
+                "response_latency_ms_bucket{status=\"200\",le=\"10\"} 2 # {trace_id=\"abc\"} 5 "
+            ),
+            "{}",
+            lines[2]
+        );
+        assert!(
+            lines[6].starts_with("response_latency_ms_created{status=\"200\"} "),
+            "{}",
+            lines[6]
+        );
+    }
+
+    #[test]
+    fn rejects_reserved_labels() {
+        for label in RESERVED_LABELS {
+            assert_eq!(
+                Filter::default().drop_label(*label),
+                Err(ReservedLabel(label.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn aggregates_protobuf() {
+        crate::metrics! {
+            latency_ms: NativeHistogram<u32> { "Latency" }
+        }
+
+        let a = NativeHistogram::<u32>::new(BOUNDS, 1);
+        a.add(3u32);
+        a.add(20u32);
+        let b = NativeHistogram::<u32>::new(BOUNDS, 0);
+        b.add(0u32);
+        b.add(3u32);
+
+        let filter = Filter::default()
+            .select_prefix("latency_")
+            .drop_label("dst_pod")
+            .unwrap();
+        let mut families = vec![];
+        {
+            let f = &mut Encoder::protobuf(&mut families).with_filter(&filter);
+            latency_ms.fmt_help(f).unwrap();
+            latency_ms.fmt_metric_labeled(f, &a, &DstPod("a")).unwrap();
+            latency_ms.fmt_metric_labeled(f, &b, &DstPod("b")).unwrap();
+            tcp_open_total.fmt_help(f).unwrap();
+            tcp_open_total
+                .fmt_metric_labeled(f, &Counter::from(1), &DstPod("a"))
+                .unwrap();
+        }
+        filter.aggregate_protobuf(&mut families);
+
+        assert_eq!(families.len(), 1);
+        assert_eq!(families[0].metric.len(), 1);
+        let metric = &families[0].metric[0];
+        assert_eq!(metric.label.len(), 1);
+        assert_eq!(metric.label[0].name(), "status");
+
+        let hist = metric.histogram.as_ref().unwrap();
+        assert_eq!(hist.sample_count(), 4);
+        assert_eq!(hist.sample_sum(), 26.0);
+        assert_eq!(
+            hist.bucket
+                .iter()
+                .map(|b| b.cumulative_count())
+                .collect::<Vec<_>>(),
+            vec![3, 4]
+        );
+        // Both histograms are reduced to schema 0, in which 3 falls in bucket 2
+        // and 20 falls in bucket 5.
+        let native = Sparse::from_proto(hist).unwrap();
+        assert_eq!(hist.schema(), 0);
+        assert_eq!(hist.zero_count(), 1);
+        assert_eq!(native.buckets().collect::<Vec<_>>(), vec![(2, 2), (5, 1)]);
+    }
+}
@@ -1,4 +1,7 @@
-use super::prom::{FmtLabels, FmtMetric};
+use super::{
+    prom::{Encoder, FmtLabels, FmtMetric, NoLabels},
+    protobuf::proto,
+};
 use std::fmt::{self, Display};
 use std::sync::atomic::{AtomicU64, Ordering};
 
@@ -36,25 +39,40 @@ impl From<Gauge> for u64 {
     }
 }
 
+impl Gauge {
+    fn fmt_gauge<N, L>(&self, f: &mut Encoder<'_>, name: N, labels: Option<&L>) -> fmt::Result
+    where
+        N: Display,
+        L: FmtLabels,
+    {
+        if !f.start_metric(Self::KIND, &name) {
+            return Ok(());
+        }
+
+        match f.proto_metric(Self::KIND, &name, labels) {
+            Some(metric) => {
+                metric.gauge = Some(proto::Gauge {
+                    value: Some(self.value() as f64),
+                });
+                Ok(())
+            }
+            None => f.fmt_sample(name, labels, self.value() as f64),
+        }
+    }
+}
+
 impl FmtMetric for Gauge {
     const KIND: &'static str = "gauge";
 
-    fn fmt_metric<N: Display>(&self, f: &mut fmt::Formatter<'_>, name: N) -> fmt::Result {
-        writeln!(f, "{} {}", name, self.value())
+    fn fmt_metric<N: Display>(&self, f: &mut Encoder<'_>, name: N) -> fmt::Result {
+        self.fmt_gauge(f, name, None::<&NoLabels>)
     }
 
-    fn fmt_metric_labeled<N, L>(
-        &self,
-        f: &mut fmt::Formatter<'_>,
-        name: N,
-        labels: L,
-    ) -> fmt::Result
+    fn fmt_metric_labeled<N, L>(&self, f: &mut Encoder<'_>, name: N, labels: L) -> fmt::Result
     where
         L: FmtLabels,
         N: Display,
     {
-        write!(f, "{}{{", name)?;
-        labels.fmt_labels(f)?;
-        writeln!(f, "}} {}", self.value())
+        self.fmt_gauge(f, name, Some(&labels))
     }
 }
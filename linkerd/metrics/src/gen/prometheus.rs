@@ -0,0 +1,56 @@
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WriteRequest {
+    #[prost(message, repeated, tag="1")]
+    pub timeseries: ::prost::alloc::vec::Vec<TimeSeries>,
+    #[prost(message, repeated, tag="3")]
+    pub metadata: ::prost::alloc::vec::Vec<MetricMetadata>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MetricMetadata {
+    #[prost(enumeration="metric_metadata::MetricType", tag="1")]
+    pub r#type: i32,
+    #[prost(string, tag="2")]
+    pub metric_family_name: ::prost::alloc::string::String,
+    #[prost(string, tag="4")]
+    pub help: ::prost::alloc::string::String,
+    #[prost(string, tag="5")]
+    pub unit: ::prost::alloc::string::String,
+}
+/// Nested message and enum types in `MetricMetadata`.
+pub mod metric_metadata {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+    #[repr(i32)]
+    pub enum MetricType {
+        Unknown = 0,
+        Counter = 1,
+        Gauge = 2,
+        Histogram = 3,
+        Gaugehistogram = 4,
+        Summary = 5,
+        Info = 6,
+        Stateset = 7,
+    }
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Sample {
+    #[prost(double, tag="1")]
+    pub value: f64,
+    /// Milliseconds since the Unix epoch.
+    #[prost(int64, tag="2")]
+    pub timestamp: i64,
+}
+/// Labels must be sorted by name and include the `__name__` label.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TimeSeries {
+    #[prost(message, repeated, tag="1")]
+    pub labels: ::prost::alloc::vec::Vec<Label>,
+    #[prost(message, repeated, tag="2")]
+    pub samples: ::prost::alloc::vec::Vec<Sample>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Label {
+    #[prost(string, tag="1")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(string, tag="2")]
+    pub value: ::prost::alloc::string::String,
+}
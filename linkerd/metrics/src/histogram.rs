@@ -1,15 +1,30 @@
+use parking_lot::Mutex;
 use std::fmt;
 use std::marker::PhantomData;
+use std::time::SystemTime;
 use std::{cmp, iter, slice};
 
-use super::{Counter, Factor, FmtLabels, FmtMetric};
+use super::{
+    prom::{Encoder, Format, NoLabels},
+    protobuf::proto,
+    Counter, Factor, FmtLabels, FmtMetric,
+};
 
 /// A series of latency values and counts.
+///
+/// In the OpenMetrics format, each bucket is annotated with an exemplar: the
+/// most recent observation in the bucket that was recorded with a trace ID.
 #[derive(Debug)]
 pub struct Histogram<V: Into<u64>, F = ()> {
     bounds: &'static Bounds,
     buckets: Box<[Counter<F>]>,
 
+    /// The latest traced observation in each bucket. Allocated when the first
+    /// traced observation is recorded.
+    exemplars: Mutex<Option<Box<[Option<Exemplar>]>>>,
+
+    created: SystemTime,
+
     /// The total sum of all observed latency values.
     ///
     /// Histogram sums always explicitly wrap on overflows rather than
@@ -45,6 +60,16 @@ pub enum Bucket {
 #[derive(Debug)]
 pub struct Bounds(pub &'static [Bucket]);
 
+#[derive(Clone, Debug)]
+struct Exemplar {
+    trace_id: String,
+    value: u64,
+    timestamp: SystemTime,
+}
+
+/// Helper that formats an exemplar following a bucket's sample.
+struct FmtExemplar<'e, F>(&'e Exemplar, PhantomData<F>);
+
 /// Helper that lazily formats an `{K}="{V}"`" label.
 struct Label<K: fmt::Display, V: fmt::Display>(K, V);
 
@@ -63,6 +88,8 @@ impl<V: Into<u64>, F: Factor> Histogram<V, F> {
         Self {
             bounds,
             buckets: buckets.into_boxed_slice(),
+            exemplars: Mutex::new(None),
+            created: SystemTime::now(),
             sum: Counter::default(),
             _p: PhantomData,
         }
@@ -72,21 +99,118 @@ impl<V: Into<u64>, F: Factor> Histogram<V, F> {
         let v: V = u.into();
         let value: u64 = v.into();
 
-        let idx = self
-            .bounds
+        let idx = self.bucket_index(value);
+        self.buckets[idx].incr();
+        self.sum.add(value);
+    }
+
+    /// Records an observation that was made in the given trace, keeping it as
+    /// its bucket's exemplar.
+    pub fn add_with_trace_id<U: Into<V>>(&self, u: U, trace_id: impl fmt::Display) {
+        let v: V = u.into();
+        let value: u64 = v.into();
+
+        let idx = self.bucket_index(value);
+        self.buckets[idx].incr();
+        self.sum.add(value);
+
+        let mut exemplars = self.exemplars.lock();
+        let exemplars =
+            exemplars.get_or_insert_with(|| vec![None; self.buckets.len()].into_boxed_slice());
+        exemplars[idx] = Some(Exemplar {
+            trace_id: trace_id.to_string(),
+            value,
+            timestamp: SystemTime::now(),
+        });
+    }
+
+    fn bucket_index(&self, value: u64) -> usize {
+        self.bounds
             .0
             .iter()
             .position(|b| match *b {
                 Bucket::Le(ceiling) => F::factor(value) <= ceiling,
                 Bucket::Inf => true,
             })
-            .expect("all values must fit into a bucket");
+            .expect("all values must fit into a bucket")
+    }
 
-        self.buckets[idx].incr();
-        self.sum.add(value);
+    /// Describes the histogram's classic buckets as a protobuf message.
+    pub(crate) fn to_proto(&self) -> proto::Histogram {
+        let mut total = 0;
+        let bucket = self
+            .into_iter()
+            .map(|(le, count)| {
+                total += u64::from(count);
+                proto::Bucket {
+                    cumulative_count: Some(F::factor(total) as u64),
+                    upper_bound: Some(match *le {
+                        Bucket::Le(v) => v,
+                        Bucket::Inf => f64::INFINITY,
+                    }),
+                }
+            })
+            .collect();
+        proto::Histogram {
+            sample_count: Some(F::factor(total) as u64),
+            sample_sum: Some(self.sum.value()),
+            bucket,
+            ..Default::default()
+        }
+    }
+
+    fn fmt_histogram<N, L>(&self, f: &mut Encoder<'_>, name: N, labels: Option<&L>) -> fmt::Result
+    where
+        N: fmt::Display,
+        L: FmtLabels,
+    {
+        if !f.start_metric(Self::KIND, &name) {
+            return Ok(());
+        }
+
+        if let Some(metric) = f.proto_metric(Self::KIND, &name, labels) {
+            metric.histogram = Some(self.to_proto());
+            return Ok(());
+        }
+
+        let openmetrics = f.format() == Format::OpenMetrics;
+        let exemplars = if openmetrics {
+            self.exemplars.lock().clone()
+        } else {
+            None
+        };
+
+        let mut total = 0;
+        for (i, (le, count)) in self.into_iter().enumerate() {
+            total += u64::from(count);
+            let exemplar = exemplars.as_ref().and_then(|e| e[i].as_ref());
+            f.fmt_sample_with(
+                format_args!("{}_bucket", &name),
+                Some(&(labels, Label("le", le))),
+                F::factor(total),
+                exemplar
+                    .map(FmtExemplar::<F>::new)
+                    .as_ref()
+                    .map(|e| e as &dyn fmt::Display),
+            )?;
+        }
+        f.fmt_sample(format_args!("{}_count", &name), labels, F::factor(total))?;
+        f.fmt_sample(format_args!("{}_sum", &name), labels, self.sum.value())?;
+        if openmetrics {
+            f.fmt_sample(
+                format_args!("{}_created", &name),
+                labels,
+                unix_secs(self.created),
+            )?;
+        }
+        Ok(())
     }
 }
 
+fn unix_secs(t: SystemTime) -> f64 {
+    crate::unix_time(t).as_secs_f64()
+}
+
 #[cfg(any(test, feature = "test_util"))]
 #[allow(clippy::float_cmp)]
 impl<V: Into<u64>, F: Factor + std::fmt::Debug> Histogram<V, F> {
@@ -183,40 +307,36 @@ impl<'a, V: Into<u64>, F> IntoIterator for &'a Histogram<V, F> {
 impl<V: Into<u64>, F: Factor> FmtMetric for Histogram<V, F> {
     const KIND: &'static str = "histogram";
 
-    fn fmt_metric<N: fmt::Display>(&self, f: &mut fmt::Formatter<'_>, name: N) -> fmt::Result {
-        let total = Counter::<F>::new();
-        for (le, count) in self {
-            total.add(count.into());
-            total.fmt_metric_labeled(f, format_args!("{}_bucket", &name), Label("le", le))?;
-        }
-        total.fmt_metric(f, format_args!("{}_count", &name))?;
-        self.sum.fmt_metric(f, format_args!("{}_sum", &name))?;
-        Ok(())
+    fn fmt_metric<N: fmt::Display>(&self, f: &mut Encoder<'_>, name: N) -> fmt::Result {
+        self.fmt_histogram(f, name, None::<&NoLabels>)
     }
 
-    fn fmt_metric_labeled<N, L>(
-        &self,
-        f: &mut fmt::Formatter<'_>,
-        name: N,
-        labels: L,
-    ) -> fmt::Result
+    fn fmt_metric_labeled<N, L>(&self, f: &mut Encoder<'_>, name: N, labels: L) -> fmt::Result
     where
         N: fmt::Display,
         L: FmtLabels,
     {
-        let total = Counter::<F>::new();
-        for (le, count) in self {
-            total.add(count.into());
-            total.fmt_metric_labeled(
-                f,
-                format_args!("{}_bucket", &name),
-                (&labels, Label("le", le)),
-            )?;
-        }
-        total.fmt_metric_labeled(f, format_args!("{}_count", &name), &labels)?;
-        self.sum
-            .fmt_metric_labeled(f, format_args!("{}_sum", &name), &labels)?;
-        Ok(())
+        self.fmt_histogram(f, name, Some(&labels))
+    }
+}
+
+// ===== impl FmtExemplar =====
+
+impl<'e, F> FmtExemplar<'e, F> {
+    fn new(exemplar: &'e Exemplar) -> Self {
+        Self(exemplar, PhantomData)
+    }
+}
+
+impl<F: Factor> fmt::Display for FmtExemplar<'_, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "# {{trace_id=\"{}\"}} {} {}",
+            self.0.trace_id,
+            F::factor(self.0.value),
+            unix_secs(self.0.timestamp),
+        )
     }
 }
 
@@ -1,6 +1,9 @@
-use std::time::Duration;
+use std::{ops::RangeInclusive, time::Duration};
 
-use super::histogram::{Bounds, Bucket, Histogram};
+use super::{
+    histogram::{Bounds, Bucket, Histogram},
+    native, NativeHistogram,
+};
 
 /// The maximum value (inclusive) for each latency bucket in
 /// milliseconds.
@@ -34,6 +37,14 @@ pub const BOUNDS: &Bounds = &Bounds(&[
     Bucket::Inf,
 ]);
 
+/// The default initial schema of native latency histograms, in which each
+/// bucket is about 9% wider than the prior.
+pub const DEFAULT_SCHEMA: i32 = 3;
+
+/// The schemas that native histograms support, from the coarsest to the
+/// finest resolution.
+pub const SCHEMAS: RangeInclusive<i32> = native::SCHEMAS;
+
 /// A duration in milliseconds.
 #[derive(Debug, Default, Clone)]
 pub struct Ms(Duration);
@@ -72,6 +83,13 @@ impl From<Ms> for u64 {
     }
 }
 
+/// Native histograms observe fractional milliseconds.
+impl From<Ms> for f64 {
+    fn from(Ms(ms): Ms) -> f64 {
+        ms.as_secs_f64() * 1_000.0
+    }
+}
+
 impl From<Duration> for Ms {
     fn from(d: Duration) -> Self {
         Ms(d)
@@ -83,3 +101,10 @@ impl Default for Histogram<Ms> {
         Histogram::new(BOUNDS)
     }
 }
+
+impl NativeHistogram<Ms> {
+    /// Creates a latency histogram with the given initial schema.
+    pub fn latency(schema: i32) -> Self {
+        NativeHistogram::new(BOUNDS, schema)
+    }
+}
@@ -9,12 +9,16 @@
 //! Utilities for exposing metrics to Prometheus.
 
 mod counter;
+mod filter;
 mod gauge;
 mod histogram;
 pub mod latency;
+mod native;
 #[cfg(feature = "linkerd-stack")]
 mod new_metrics;
 mod prom;
+mod protobuf;
+pub mod remote_write;
 mod scopes;
 mod serve;
 mod store;
@@ -27,13 +31,16 @@ pub use self::new_metrics::NewMetrics;
 pub use self::summary::Summary;
 pub use self::{
     counter::Counter,
+    filter::{Filter, ReservedLabel},
     gauge::Gauge,
     histogram::Histogram,
-    prom::{FmtLabels, FmtMetric, FmtMetrics, Metric},
+    native::NativeHistogram,
+    prom::{Encoder, FmtLabels, FmtMetric, FmtMetrics, Format, Metric},
     scopes::Scopes,
     serve::Serve,
     store::{LastUpdate, SharedStore, Store},
 };
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 #[macro_export]
 macro_rules! metrics {
@@ -50,6 +57,12 @@ macro_rules! metrics {
     }
 }
 
+/// Returns the time elapsed between the Unix epoch and `t`, or zero if `t`
+/// precedes the epoch.
+pub fn unix_time(t: SystemTime) -> Duration {
+    t.duration_since(UNIX_EPOCH).unwrap_or_default()
+}
+
 pub trait Factor {
     fn factor(n: u64) -> f64;
 }
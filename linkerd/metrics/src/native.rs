@@ -0,0 +1,441 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    ops::RangeInclusive,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use super::{
+    histogram::Bounds,
+    prom::{Encoder, NoLabels},
+    protobuf::proto,
+    FmtLabels, FmtMetric, Histogram,
+};
+
+/// A histogram with exponentially-sized buckets that are allocated as values
+/// are observed.
+///
+/// Bucket boundaries are powers of `2^(2^-schema)`, so each increment of the
+/// schema doubles the histogram's resolution. When more than `MAX_BUCKETS`
+/// buckets are populated, the resolution is halved until they fit.
+///
+/// Native buckets are only exposed in the Prometheus protobuf format. The text
+/// formats report observations in the classic buckets described by `Bounds`.
+#[derive(Debug)]
+pub struct NativeHistogram<V: Into<u64>> {
+    classic: Histogram<V>,
+    native: Dense,
+}
+
+/// Counts observations in a fixed range of buckets at the histogram's initial
+/// schema, so that observations never take a lock.
+///
+/// Values below the range are counted in its first bucket and values above it
+/// in its last bucket. The populated buckets are reduced to `MAX_BUCKETS` when
+/// the histogram is read.
+#[derive(Debug)]
+struct Dense {
+    schema: i32,
+
+    /// The index of the first bucket in `counts`.
+    offset: i32,
+    counts: Box<[AtomicU64]>,
+
+    /// The number of observations that are not greater than zero.
+    zero_count: AtomicU64,
+
+    /// The bits of the exact sum of all observations.
+    sum: AtomicU64,
+}
+
+/// The sparse buckets of a native histogram.
+#[derive(Debug)]
+pub(crate) struct Sparse {
+    schema: i32,
+
+    /// The number of observations that are not greater than zero.
+    zero_count: u64,
+
+    /// The exact sum of all observations, which may be more precise than the
+    /// classic histogram's integral sum.
+    sum: f64,
+
+    /// Counts of observations by bucket index. Bucket `i` holds values in
+    /// `(2^((i-1)/2^schema), 2^(i/2^schema)]`.
+    buckets: BTreeMap<i32, u64>,
+}
+
+/// The lowest resolution supported by Prometheus, in which each bucket is
+/// 65536 times wider than the prior.
+const MIN_SCHEMA: i32 = -4;
+
+/// The highest supported resolution, in which each bucket is about 4.4% wider
+/// than the prior. Prometheus supports finer schemas, but their buckets are
+/// too numerous to allocate up front.
+const MAX_SCHEMA: i32 = 4;
+
+/// The schemas supported by native histograms.
+pub(crate) const SCHEMAS: RangeInclusive<i32> = MIN_SCHEMA..=MAX_SCHEMA;
+
+/// The maximum number of populated buckets per histogram.
+const MAX_BUCKETS: usize = 160;
+
+/// Observations are counted in distinct buckets between `2^MIN_EXPONENT` and
+/// `2^MAX_EXPONENT`, i.e. from about 1µs to 70 minutes when observing
+/// milliseconds.
+const MIN_EXPONENT: i32 = -10;
+const MAX_EXPONENT: i32 = 22;
+
+// ===== impl NativeHistogram =====
+
+impl<V: Into<u64> + Into<f64> + Clone> NativeHistogram<V> {
+    /// Creates a histogram with the given initial schema, falling back to the
+    /// given classic bounds for text formats.
+    pub fn new(bounds: &'static Bounds, schema: i32) -> Self {
+        assert!(
+            SCHEMAS.contains(&schema),
+            "schema must be between {} and {}",
+            MIN_SCHEMA,
+            MAX_SCHEMA
+        );
+        Self {
+            classic: Histogram::new(bounds),
+            native: Dense::new(schema),
+        }
+    }
+
+    pub fn add<U: Into<V>>(&self, u: U) {
+        let v: V = u.into();
+        self.native.observe(v.clone().into());
+        self.classic.add(v);
+    }
+
+    /// Records an observation that was made in the given trace, keeping it as
+    /// its classic bucket's exemplar.
+    pub fn add_with_trace_id<U: Into<V>>(&self, u: U, trace_id: impl fmt::Display) {
+        let v: V = u.into();
+        self.native.observe(v.clone().into());
+        self.classic.add_with_trace_id(v, trace_id);
+    }
+
+    /// Returns the histogram's current schema, which decreases as values
+    /// spread across more than `MAX_BUCKETS` buckets.
+    pub fn schema(&self) -> i32 {
+        self.native.to_sparse().schema
+    }
+
+    pub fn classic(&self) -> &Histogram<V> {
+        &self.classic
+    }
+
+    fn fmt_native<N, L>(&self, f: &mut Encoder<'_>, name: N, labels: Option<&L>) -> fmt::Result
+    where
+        N: fmt::Display,
+        L: FmtLabels,
+    {
+        if !f.start_metric(Self::KIND, &name) {
+            return Ok(());
+        }
+
+        match f.proto_metric(Self::KIND, &name, labels) {
+            Some(metric) => {
+                let mut hist = self.classic.to_proto();
+                self.native.to_sparse().set_proto(&mut hist);
+                metric.histogram = Some(hist);
+                Ok(())
+            }
+            // Text formats only include the classic buckets.
+            None => match labels {
+                Some(labels) => self.classic.fmt_metric_labeled(f, name, labels),
+                None => self.classic.fmt_metric(f, name),
+            },
+        }
+    }
+}
+
+impl<V: Into<u64> + Into<f64> + Clone> FmtMetric for NativeHistogram<V> {
+    const KIND: &'static str = "histogram";
+
+    fn fmt_metric<N: fmt::Display>(&self, f: &mut Encoder<'_>, name: N) -> fmt::Result {
+        self.fmt_native(f, name, None::<&NoLabels>)
+    }
+
+    fn fmt_metric_labeled<N, L>(&self, f: &mut Encoder<'_>, name: N, labels: L) -> fmt::Result
+    where
+        N: fmt::Display,
+        L: FmtLabels,
+    {
+        self.fmt_native(f, name, Some(&labels))
+    }
+}
+
+// ===== impl Dense =====
+
+impl Dense {
+    fn new(schema: i32) -> Self {
+        let offset = bucket_index(2f64.powi(MIN_EXPONENT), schema);
+        let len = bucket_index(2f64.powi(MAX_EXPONENT), schema) - offset + 1;
+        Self {
+            schema,
+            offset,
+            counts: (0..len).map(|_| AtomicU64::new(0)).collect(),
+            zero_count: AtomicU64::new(0),
+            sum: AtomicU64::new(0f64.to_bits()),
+        }
+    }
+
+    fn observe(&self, value: f64) {
+        let _ = self
+            .sum
+            .fetch_update(Ordering::Release, Ordering::Relaxed, |sum| {
+                Some((f64::from_bits(sum) + value).to_bits())
+            });
+
+        // Latencies and durations are never negative, so zero and NaN are
+        // counted together in the zero bucket.
+        #[allow(clippy::neg_cmp_op_on_partial_ord)]
+        if !(value > 0.0) {
+            self.zero_count.fetch_add(1, Ordering::Release);
+            return;
+        }
+
+        let last = self.counts.len() as i32 - 1;
+        let i = bucket_index(value, self.schema)
+            .saturating_sub(self.offset)
+            .clamp(0, last);
+        self.counts[i as usize].fetch_add(1, Ordering::Release);
+    }
+
+    /// Reads the populated buckets, reduced to at most `MAX_BUCKETS`.
+    fn to_sparse(&self) -> Sparse {
+        let buckets = self
+            .counts
+            .iter()
+            .zip(self.offset..)
+            .filter_map(|(count, idx)| match count.load(Ordering::Acquire) {
+                0 => None,
+                n => Some((idx, n)),
+            })
+            .collect();
+        let mut sparse = Sparse {
+            schema: self.schema,
+            zero_count: self.zero_count.load(Ordering::Acquire),
+            sum: f64::from_bits(self.sum.load(Ordering::Acquire)),
+            buckets,
+        };
+        while sparse.buckets.len() > MAX_BUCKETS && sparse.schema > MIN_SCHEMA {
+            sparse.downscale();
+        }
+        sparse
+    }
+}
+
+// ===== impl Sparse =====
+
+impl Sparse {
+    /// Reads the native buckets of a protobuf histogram, if it has any.
+    pub(crate) fn from_proto(hist: &proto::Histogram) -> Option<Self> {
+        let mut buckets = BTreeMap::new();
+        let deltas = hist.positive_delta.iter();
+        let mut idxs = hist.positive_span.iter().scan(0, |next, span| {
+            let start = *next + span.offset();
+            *next = start + span.length() as i32;
+            Some(start..*next)
+        });
+        let mut count = 0;
+        let mut idx = idxs.next().unwrap_or(0..0);
+        for delta in deltas {
+            let i = loop {
+                match idx.next() {
+                    Some(i) => break i,
+                    None => idx = idxs.next()?,
+                }
+            };
+            count += delta;
+            buckets.insert(i, count as u64);
+        }
+
+        Some(Self {
+            schema: hist.schema?,
+            zero_count: hist.zero_count(),
+            sum: hist.sample_sum(),
+            buckets,
+        })
+    }
+
+    /// Returns the counts of the histogram's populated buckets by index.
+    #[cfg(test)]
+    pub(crate) fn buckets(&self) -> impl Iterator<Item = (i32, u64)> + '_ {
+        self.buckets.iter().map(|(i, n)| (*i, *n))
+    }
+
+    /// Merges another histogram's buckets into this one, reducing both to the
+    /// lower of their schemas.
+    pub(crate) fn merge(&mut self, mut other: Self) {
+        while self.schema > other.schema {
+            self.downscale();
+        }
+        while other.schema > self.schema {
+            other.downscale();
+        }
+        self.zero_count += other.zero_count;
+        self.sum += other.sum;
+        for (idx, count) in other.buckets {
+            *self.buckets.entry(idx).or_default() += count;
+        }
+        while self.buckets.len() > MAX_BUCKETS && self.schema > MIN_SCHEMA {
+            self.downscale();
+        }
+    }
+
+    /// Describes the native buckets in a protobuf histogram, whose sum is
+    /// replaced by the more precise native sum.
+    pub(crate) fn set_proto(&self, hist: &mut proto::Histogram) {
+        hist.schema = Some(self.schema);
+        hist.zero_threshold = Some(0.0);
+        hist.zero_count = Some(self.zero_count);
+        hist.sample_sum = Some(self.sum);
+        hist.positive_span.clear();
+        hist.positive_delta.clear();
+
+        // Buckets are described by spans of consecutive indices and the
+        // differences between the counts of consecutive buckets.
+        let mut prior = None;
+        let mut prior_count = 0;
+        for (&idx, &count) in self.buckets.iter() {
+            match prior {
+                Some(p) if idx == p + 1 => {
+                    if let Some(span) = hist.positive_span.last_mut() {
+                        span.length = Some(span.length() + 1);
+                    }
+                }
+                _ => hist.positive_span.push(proto::BucketSpan {
+                    offset: Some(prior.map(|p| idx - p - 1).unwrap_or(idx)),
+                    length: Some(1),
+                }),
+            }
+            hist.positive_delta.push(count as i64 - prior_count);
+            prior = Some(idx);
+            prior_count = count as i64;
+        }
+
+        // Prometheus identifies native histograms by their spans, so histograms
+        // without any buckets are given an empty one.
+        if hist.positive_span.is_empty() {
+            hist.positive_span.push(proto::BucketSpan {
+                offset: Some(0),
+                length: Some(0),
+            });
+        }
+    }
+
+    /// Halves the histogram's resolution, merging each pair of adjacent
+    /// buckets.
+    fn downscale(&mut self) {
+        // Bucket `i` falls into bucket `ceil(i / 2)` of the next lower schema.
+        for (idx, count) in std::mem::take(&mut self.buckets) {
+            *self
+                .buckets
+                .entry(idx.div_euclid(2) + idx.rem_euclid(2))
+                .or_default() += count;
+        }
+        self.schema -= 1;
+    }
+}
+
+fn bucket_index(value: f64, schema: i32) -> i32 {
+    // Float-to-int casts saturate, so infinite values land in the last bucket.
+    (value.log2() * 2f64.powi(schema)).ceil() as i32
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::*;
+    use crate::histogram::Bucket;
+
+    static BOUNDS: &Bounds = &Bounds(&[Bucket::Le(1.0), Bucket::Le(10.0), Bucket::Inf]);
+
+    #[test]
+    fn bucket_boundaries() {
+        // Bucket 0 is (0.5, 1]; bucket 1 is (1, 2].
+        assert_eq!(bucket_index(1.0, 0), 0);
+        assert_eq!(bucket_index(1.5, 0), 1);
+        assert_eq!(bucket_index(2.0, 0), 1);
+        assert_eq!(bucket_index(0.25, 0), -2);
+        // With schema 3, each power of two is split into 8 buckets.
+        assert_eq!(bucket_index(2.0, 3), 8);
+        assert_eq!(bucket_index(2.1, 3), 9);
+        assert_eq!(bucket_index(0.5, 3), -8);
+        // Sub-unit values are resolved more finely than whole numbers.
+        assert_ne!(bucket_index(0.1, 3), bucket_index(0.2, 3));
+    }
+
+    #[test]
+    fn records_native_and_classic() {
+        let hist = NativeHistogram::<u32>::new(BOUNDS, 3);
+        hist.add(0u32);
+        hist.add(5u32);
+        hist.add(5u32);
+        hist.add(100u32);
+
+        let native = hist.native.to_sparse();
+        assert_eq!(native.zero_count, 1);
+        assert_eq!(native.sum, 110.0);
+        assert_eq!(
+            native
+                .buckets
+                .iter()
+                .map(|(i, n)| (*i, *n))
+                .collect::<Vec<_>>(),
+            vec![(bucket_index(5.0, 3), 2), (bucket_index(100.0, 3), 1)],
+        );
+
+        hist.classic()
+            .assert_bucket_exactly(1.0, 1.0)
+            .assert_bucket_exactly(10.0, 2.0)
+            .assert_bucket_exactly(f64::INFINITY, 1.0);
+    }
+
+    #[test]
+    fn downscales_to_fit() {
+        let hist = NativeHistogram::<u32>::new(BOUNDS, MAX_SCHEMA);
+        for v in 1..=100_000u32 {
+            hist.add(v);
+        }
+        let native = hist.native.to_sparse();
+        assert!(native.buckets.len() <= MAX_BUCKETS);
+        assert!(native.schema < MAX_SCHEMA);
+        assert_eq!(native.buckets.values().sum::<u64>(), 100_000);
+        // Every value must still fall within its bucket at the reduced
+        // schema.
+        let expected = (1..=100_000u32).fold(BTreeMap::<i32, u64>::new(), |mut b, v| {
+            *b.entry(bucket_index(v as f64, native.schema)).or_default() += 1;
+            b
+        });
+        assert_eq!(native.buckets, expected);
+    }
+
+    #[test]
+    fn clamps_to_range() {
+        for schema in SCHEMAS {
+            let hist = NativeHistogram::<u32>::new(BOUNDS, schema);
+            hist.native.observe(f64::MIN_POSITIVE);
+            hist.native.observe(f64::INFINITY);
+            let native = hist.native.to_sparse();
+            assert_eq!(
+                native.buckets().collect::<Vec<_>>(),
+                vec![
+                    (bucket_index(2f64.powi(MIN_EXPONENT), schema), 1),
+                    (bucket_index(2f64.powi(MAX_EXPONENT), schema), 1),
+                ],
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_invalid_schema() {
+        NativeHistogram::<u32>::new(BOUNDS, MAX_SCHEMA + 1);
+    }
+}
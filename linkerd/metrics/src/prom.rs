@@ -1,9 +1,17 @@
+use super::{
+    filter::{self, Filter},
+    protobuf::{self, proto},
+};
 use std::fmt;
 use std::marker::{PhantomData, Sized};
 
 /// Writes a block of metrics in prometheus-formatted output.
+///
+/// The `Encoder` determines the exposition format in which metrics are
+/// written, and it is passed through `FmtMetrics` implementations to the
+/// metrics that they describe.
 pub trait FmtMetrics {
-    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+    fn fmt_metrics(&self, f: &mut Encoder<'_>) -> fmt::Result;
 
     fn as_display(&self) -> DisplayMetrics<&Self>
     where
@@ -21,6 +29,49 @@ pub trait FmtMetrics {
     }
 }
 
+/// An exposition format in which metrics are written.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// The Prometheus text format.
+    Prometheus,
+    /// The OpenMetrics text format, which includes exemplars and creation
+    /// times.
+    OpenMetrics,
+    /// The Prometheus protobuf format, which includes native histograms'
+    /// buckets.
+    Protobuf,
+}
+
+/// Writes metrics in an exposition format.
+///
+/// Text formats are written as metrics are formatted. Protobuf `MetricFamily`
+/// messages are built directly from each metric's values.
+///
+/// When a `Filter` is set, metric families that it does not select are skipped
+/// before they are formatted. Text samples whose labels are dropped are held
+/// until the encoder moves on to the next family, so that they may be
+/// aggregated.
+pub struct Encoder<'a> {
+    target: Target<'a>,
+    filter: Option<&'a Filter>,
+
+    /// Aggregates the samples of the current text family, if labels are
+    /// dropped.
+    family: Option<filter::Family>,
+
+    /// Summaries retain all of their labels, since their quantiles cannot be
+    /// summed.
+    is_summary: bool,
+}
+
+enum Target<'a> {
+    Text {
+        w: &'a mut dyn fmt::Write,
+        openmetrics: bool,
+    },
+    Protobuf(&'a mut Vec<proto::MetricFamily>),
+}
+
 /// Adapts `FmtMetrics` to `fmt::Display`.
 pub struct DisplayMetrics<F>(F);
 
@@ -29,7 +80,8 @@ pub struct AndThen<A, B>(A, B);
 
 impl<F: FmtMetrics> fmt::Display for DisplayMetrics<F> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.0.fmt_metrics(f)
+        self.0
+            .fmt_metrics(&mut Encoder::text(f, Format::Prometheus))
     }
 }
 
@@ -48,15 +100,10 @@ pub trait FmtMetric {
     const KIND: &'static str;
 
     /// Writes a metric with the given name and no labels.
-    fn fmt_metric<N: fmt::Display>(&self, f: &mut fmt::Formatter<'_>, name: N) -> fmt::Result;
+    fn fmt_metric<N: fmt::Display>(&self, f: &mut Encoder<'_>, name: N) -> fmt::Result;
 
     /// Writes a metric with the given name and labels.
-    fn fmt_metric_labeled<N, L>(
-        &self,
-        f: &mut fmt::Formatter<'_>,
-        name: N,
-        labels: L,
-    ) -> fmt::Result
+    fn fmt_metric_labeled<N, L>(&self, f: &mut Encoder<'_>, name: N, labels: L) -> fmt::Result
     where
         N: fmt::Display,
         L: FmtLabels;
@@ -83,21 +130,19 @@ impl<'a, N: fmt::Display, M: FmtMetric> Metric<'a, N, M> {
     }
 
     /// Formats help messages for this metric.
-    pub fn fmt_help(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        writeln!(f, "# HELP {} {}", self.name, self.help)?;
-        writeln!(f, "# TYPE {} {}", self.name, M::KIND)?;
-        Ok(())
+    pub fn fmt_help(&self, f: &mut Encoder<'_>) -> fmt::Result {
+        f.fmt_help(&self.name, self.help, M::KIND)
     }
 
     /// Formats a single metric without labels.
-    pub fn fmt_metric(&self, f: &mut fmt::Formatter<'_>, metric: &M) -> fmt::Result {
+    pub fn fmt_metric(&self, f: &mut Encoder<'_>, metric: &M) -> fmt::Result {
         metric.fmt_metric(f, &self.name)
     }
 
     /// Formats a single metric with labels.
     pub fn fmt_metric_labeled<L: FmtLabels>(
         &self,
-        f: &mut fmt::Formatter<'_>,
+        f: &mut Encoder<'_>,
         metric: &M,
         labels: &L,
     ) -> fmt::Result {
@@ -107,7 +152,7 @@ impl<'a, N: fmt::Display, M: FmtMetric> Metric<'a, N, M> {
     /// Formats a single metric across labeled scopes.
     pub fn fmt_scopes<'s, L, S: 's, I, F>(
         &self,
-        f: &mut fmt::Formatter<'_>,
+        f: &mut Encoder<'_>,
         scopes: I,
         to_metric: F,
     ) -> fmt::Result
@@ -146,8 +191,243 @@ impl<N: Clone + fmt::Display, M: FmtMetric> Clone for Metric<'_, N, M> {
 
 impl<N: Copy + fmt::Display, M: FmtMetric> Copy for Metric<'_, N, M> {}
 
+/// Stands in for the labels of samples that have none.
+pub(crate) enum NoLabels {}
+
+/// Adapts `FmtLabels` to `fmt::Display`.
+pub(crate) struct DisplayLabels<L>(pub(crate) L);
+
+/// Returns the name of the OpenMetrics family of a counter.
+pub(crate) fn counter_family(name: &str) -> &str {
+    name.strip_suffix("_total").unwrap_or(name)
+}
+
+// ===== impl Encoder =====
+
+impl<'a> Encoder<'a> {
+    /// Writes metrics in the given text format.
+    ///
+    /// # Panics
+    ///
+    /// If `format` is `Format::Protobuf`.
+    pub(crate) fn text(w: &'a mut dyn fmt::Write, format: Format) -> Self {
+        assert_ne!(format, Format::Protobuf, "protobuf is not a text format");
+        Self::new(Target::Text {
+            w,
+            openmetrics: format == Format::OpenMetrics,
+        })
+    }
+
+    /// Collects metrics as protobuf `MetricFamily` messages.
+    pub(crate) fn protobuf(families: &'a mut Vec<proto::MetricFamily>) -> Self {
+        Self::new(Target::Protobuf(families))
+    }
+
+    fn new(target: Target<'a>) -> Self {
+        Self {
+            target,
+            filter: None,
+            family: None,
+            is_summary: false,
+        }
+    }
+
+    /// Only writes the metrics selected by the given filter.
+    ///
+    /// Protobuf metrics are aggregated once they have all been collected (see
+    /// `Filter::aggregate_protobuf`).
+    pub(crate) fn with_filter(mut self, filter: &'a Filter) -> Self {
+        if filter.drops_labels() && matches!(self.target, Target::Text { .. }) {
+            self.family = Some(filter::Family::default());
+        }
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Writes any samples that are held for aggregation.
+    pub(crate) fn finish(mut self) -> fmt::Result {
+        self.flush()
+    }
+
+    pub fn format(&self) -> Format {
+        match self.target {
+            Target::Text {
+                openmetrics: false, ..
+            } => Format::Prometheus,
+            Target::Text {
+                openmetrics: true, ..
+            } => Format::OpenMetrics,
+            Target::Protobuf(_) => Format::Protobuf,
+        }
+    }
+
+    /// Prepares to write a metric of the given kind, returning false if its
+    /// family is not selected by the filter.
+    pub(crate) fn start_metric(&mut self, kind: &str, name: impl fmt::Display) -> bool {
+        self.is_summary = kind == "summary";
+        self.selects(name)
+    }
+
+    fn selects(&self, name: impl fmt::Display) -> bool {
+        match self.filter {
+            Some(filter) if !filter.selects_all() => filter.selects(&name.to_string()),
+            _ => true,
+        }
+    }
+
+    fn fmt_help(&mut self, name: impl fmt::Display, help: &str, kind: &str) -> fmt::Result {
+        let name = name.to_string();
+        if !self.selects(&name) {
+            return Ok(());
+        }
+        self.flush()?;
+
+        match self.target {
+            Target::Text {
+                ref mut w,
+                openmetrics,
+            } => {
+                // OpenMetrics counter families are named without the `_total`
+                // suffix of their samples.
+                let family = if openmetrics && kind == "counter" {
+                    counter_family(&name)
+                } else {
+                    &name
+                };
+                writeln!(w, "# HELP {} {}", family, help)?;
+                writeln!(w, "# TYPE {} {}", family, kind)
+            }
+            Target::Protobuf(ref mut families) => {
+                families.push(proto::MetricFamily {
+                    name: Some(name),
+                    help: Some(help.to_string()),
+                    r#type: Some(protobuf::metric_type(kind) as i32),
+                    metric: vec![],
+                });
+                Ok(())
+            }
+        }
+    }
+
+    /// Writes a single text sample.
+    ///
+    /// Fails if metrics are being encoded as protobuf.
+    pub(crate) fn fmt_sample<N, L>(
+        &mut self,
+        name: N,
+        labels: Option<&L>,
+        value: f64,
+    ) -> fmt::Result
+    where
+        N: fmt::Display,
+        L: FmtLabels,
+    {
+        self.fmt_sample_with(name, labels, value, None)
+    }
+
+    /// Writes a single text sample, followed by the given trailer (e.g. an
+    /// exemplar).
+    ///
+    /// Fails if metrics are being encoded as protobuf.
+    pub(crate) fn fmt_sample_with<N, L>(
+        &mut self,
+        name: N,
+        labels: Option<&L>,
+        value: f64,
+        trailer: Option<&dyn fmt::Display>,
+    ) -> fmt::Result
+    where
+        N: fmt::Display,
+        L: FmtLabels,
+    {
+        let w = match self.target {
+            Target::Text { ref mut w, .. } => w,
+            Target::Protobuf(_) => return Err(fmt::Error),
+        };
+
+        if let (Some(family), Some(filter)) = (self.family.as_mut(), self.filter) {
+            if !self.is_summary {
+                let labels = labels.map(protobuf::labels).unwrap_or_default();
+                family.add(
+                    name.to_string(),
+                    filter.retain_labels(labels),
+                    value,
+                    trailer.map(ToString::to_string),
+                );
+                return Ok(());
+            }
+        }
+
+        match labels {
+            Some(labels) => write!(w, "{}{{{}}} {}", name, DisplayLabels(labels), value)?,
+            None => write!(w, "{} {}", name, value)?,
+        }
+        if let Some(trailer) = trailer {
+            write!(w, " {}", trailer)?;
+        }
+        writeln!(w)
+    }
+
+    /// Adds a metric to the protobuf family with the given name, returning it
+    /// so that its value may be set.
+    ///
+    /// Returns `None` if metrics are being written as text.
+    pub(crate) fn proto_metric<N, L>(
+        &mut self,
+        kind: &str,
+        name: N,
+        labels: Option<&L>,
+    ) -> Option<&mut proto::Metric>
+    where
+        N: fmt::Display,
+        L: FmtLabels,
+    {
+        let families = match self.target {
+            Target::Text { .. } => return None,
+            Target::Protobuf(ref mut families) => families,
+        };
+
+        // Metrics that are written without help text form their own family.
+        let name = name.to_string();
+        if families.last().map(|f| f.name() != name).unwrap_or(true) {
+            families.push(proto::MetricFamily {
+                name: Some(name),
+                r#type: Some(protobuf::metric_type(kind) as i32),
+                ..Default::default()
+            });
+        }
+        let family = families.last_mut().expect("family must exist");
+        family.metric.push(proto::Metric {
+            label: labels.map(protobuf::label_pairs).unwrap_or_default(),
+            ..Default::default()
+        });
+        family.metric.last_mut()
+    }
+
+    fn flush(&mut self) -> fmt::Result {
+        match (self.family.as_mut(), &mut self.target) {
+            (Some(family), Target::Text { w, .. }) => family.flush(*w),
+            _ => Ok(()),
+        }
+    }
+}
+
+// ===== impl DisplayLabels =====
+
+impl<L: FmtLabels> fmt::Display for DisplayLabels<L> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt_labels(f)
+    }
+}
+
 // ===== impl FmtLabels =====
 
+impl FmtLabels for NoLabels {
+    fn fmt_labels(&self, _: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {}
+    }
+}
+
 impl<'a, A: FmtLabels + 'a> FmtLabels for &'a A {
     fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         (*self).fmt_labels(f)
@@ -192,14 +472,14 @@ impl<A: FmtLabels, B: FmtLabels> FmtLabels for (Option<A>, B) {
 
 impl<'a, A: FmtMetrics + 'a> FmtMetrics for &'a A {
     #[inline]
-    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fn fmt_metrics(&self, f: &mut Encoder<'_>) -> fmt::Result {
         (*self).fmt_metrics(f)
     }
 }
 
 impl<M: FmtMetrics> FmtMetrics for Option<M> {
     #[inline]
-    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fn fmt_metrics(&self, f: &mut Encoder<'_>) -> fmt::Result {
         if let Some(m) = self.as_ref() {
             m.fmt_metrics(f)?;
         }
@@ -209,7 +489,7 @@ impl<M: FmtMetrics> FmtMetrics for Option<M> {
 
 impl<A: FmtMetrics, B: FmtMetrics> FmtMetrics for AndThen<A, B> {
     #[inline]
-    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fn fmt_metrics(&self, f: &mut Encoder<'_>) -> fmt::Result {
         self.0.fmt_metrics(f)?;
         self.1.fmt_metrics(f)?;
 
@@ -218,7 +498,7 @@ impl<A: FmtMetrics, B: FmtMetrics> FmtMetrics for AndThen<A, B> {
 }
 
 impl FmtMetrics for () {
-    fn fmt_metrics(&self, _: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fn fmt_metrics(&self, _: &mut Encoder<'_>) -> fmt::Result {
         Ok(())
     }
 }
@@ -0,0 +1,279 @@
+//! Encodes metrics in the Prometheus protobuf exposition format.
+//!
+//! Each metric family is encoded as a length-delimited
+//! `io.prometheus.client.MetricFamily` message. Unlike the text formats, these
+//! messages describe the sparse buckets of native histograms.
+
+use super::{
+    prom::{DisplayLabels, Encoder},
+    Filter, FmtLabels, FmtMetrics,
+};
+use prost::Message;
+use std::fmt;
+
+pub(crate) mod proto {
+    include!("gen/io.prometheus.client.rs");
+}
+
+use self::proto::MetricType;
+
+pub(crate) type Labels = Vec<(String, String)>;
+
+/// A parsed line, holding the unparsed text that follows its labels.
+pub(crate) struct Sample<'t> {
+    pub(crate) name: &'t str,
+    pub(crate) labels: Labels,
+    pub(crate) value: &'t str,
+}
+
+/// Encodes the metrics selected by the filter as a stream of length-delimited
+/// `MetricFamily` messages.
+pub(crate) fn encode<M: FmtMetrics + ?Sized>(
+    metrics: &M,
+    filter: &Filter,
+) -> Result<Vec<u8>, fmt::Error> {
+    let mut families = Vec::new();
+    let mut encoder = Encoder::protobuf(&mut families).with_filter(filter);
+    metrics.fmt_metrics(&mut encoder)?;
+    encoder.finish()?;
+    filter.aggregate_protobuf(&mut families);
+
+    let mut buf = Vec::new();
+    for family in families {
+        family
+            .encode_length_delimited(&mut buf)
+            .expect("buffer must have sufficient capacity");
+    }
+    Ok(buf)
+}
+
+pub(crate) fn metric_type(ty: &str) -> MetricType {
+    match ty {
+        "counter" => MetricType::Counter,
+        "gauge" => MetricType::Gauge,
+        "summary" => MetricType::Summary,
+        "histogram" => MetricType::Histogram,
+        "gaugehistogram" => MetricType::GaugeHistogram,
+        _ => MetricType::Untyped,
+    }
+}
+
+/// Describes a metric's labels as name-value pairs.
+pub(crate) fn labels<L: FmtLabels>(labels: &L) -> Labels {
+    let labels = DisplayLabels(labels).to_string();
+    parse_labels(&labels)
+        .map(|(labels, _)| labels)
+        .unwrap_or_default()
+}
+
+/// Describes a metric's labels as protobuf label pairs.
+pub(crate) fn label_pairs<L: FmtLabels>(ls: &L) -> Vec<proto::LabelPair> {
+    labels(ls)
+        .into_iter()
+        .map(|(name, value)| proto::LabelPair {
+            name: Some(name),
+            value: Some(value),
+        })
+        .collect()
+}
+
+/// Parses a `name{labels} value` sample.
+pub(crate) fn parse_sample(line: &str) -> Option<Sample<'_>> {
+    let name_end = line.find(['{', ' '])?;
+    let name = &line[..name_end];
+    let (labels, rest) = match line[name_end..].strip_prefix('{') {
+        Some(labels) => parse_labels(labels)?,
+        None => (Labels::new(), &line[name_end..]),
+    };
+
+    Some(Sample {
+        name,
+        labels,
+        value: rest.trim(),
+    })
+}
+
+/// Parses a comma-separated list of `key="value"` labels, which ends with a
+/// closing brace or with the input, returning the text that follows it.
+fn parse_labels(mut ls: &str) -> Option<(Labels, &str)> {
+    let mut labels = Labels::new();
+    loop {
+        ls = ls.trim_start_matches(',');
+        if ls.is_empty() {
+            return Some((labels, ls));
+        }
+        if let Some(rest) = ls.strip_prefix('}') {
+            return Some((labels, rest));
+        }
+        let (key, r) = ls.split_once("=\"")?;
+        let mut value = String::new();
+        let mut chars = r.char_indices();
+        let end = loop {
+            match chars.next()? {
+                (i, '"') => break i,
+                (_, '\\') => match chars.next()?.1 {
+                    'n' => value.push('\n'),
+                    c => value.push(c),
+                },
+                (_, c) => value.push(c),
+            }
+        };
+        labels.push((key.trim().to_string(), value));
+        ls = &r[end + 1..];
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::*;
+    use crate::{
+        histogram::{Bounds, Bucket},
+        Counter, Gauge, NativeHistogram,
+    };
+
+    crate::metrics! {
+        requests_total: Counter { "Total requests" },
+        open: Gauge { "Open connections" },
+        process_start_time_seconds: Gauge { "Unused" },
+        latency_ms: NativeHistogram<u32> { "Latency" }
+    }
+
+    static BOUNDS: &Bounds = &Bounds(&[Bucket::Le(1.0), Bucket::Le(10.0), Bucket::Inf]);
+
+    struct Label(&'static str, &'static str);
+
+    impl FmtLabels for Label {
+        fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}=\"{}\"", self.0, self.1)
+        }
+    }
+
+    struct Fmt<F>(F);
+
+    impl<F: Fn(&mut Encoder<'_>) -> fmt::Result> FmtMetrics for Fmt<F> {
+        fn fmt_metrics(&self, f: &mut Encoder<'_>) -> fmt::Result {
+            (self.0)(f)
+        }
+    }
+
+    fn decode(mut buf: &[u8]) -> Vec<proto::MetricFamily> {
+        let mut families = vec![];
+        while !buf.is_empty() {
+            families.push(proto::MetricFamily::decode_length_delimited(&mut buf).unwrap());
+        }
+        families
+    }
+
+    fn label(name: &str, value: &str) -> proto::LabelPair {
+        proto::LabelPair {
+            name: Some(name.to_string()),
+            value: Some(value.to_string()),
+        }
+    }
+
+    #[test]
+    fn counters_and_gauges() {
+        let families = decode(
+            &encode(
+                &Fmt(|f: &mut Encoder<'_>| {
+                    requests_total.fmt_help(f)?;
+                    requests_total.fmt_metric_labeled(
+                        f,
+                        &Counter::from(3),
+                        &(Label("direction", "inbound"), Label("path", "/a\\\"b")),
+                    )?;
+                    requests_total.fmt_metric_labeled(
+                        f,
+                        &Counter::from(4),
+                        &(Label("direction", "outbound"), Label("path", "")),
+                    )?;
+                    open.fmt_help(f)?;
+                    open.fmt_metric(f, &Gauge::from(2))?;
+                    // Metrics without help text form their own family.
+                    process_start_time_seconds.fmt_metric(f, &Gauge::from(1))
+                }),
+                &Filter::default(),
+            )
+            .unwrap(),
+        );
+        assert_eq!(families.len(), 3);
+
+        assert_eq!(families[0].name(), "requests_total");
+        assert_eq!(families[0].help(), "Total requests");
+        assert_eq!(families[0].r#type(), MetricType::Counter);
+        assert_eq!(families[0].metric.len(), 2);
+        assert_eq!(
+            families[0].metric[0].label,
+            vec![label("direction", "inbound"), label("path", "/a\"b")]
+        );
+        assert_eq!(families[0].metric[1].counter.as_ref().unwrap().value(), 4.0);
+
+        assert_eq!(families[1].r#type(), MetricType::Gauge);
+        assert!(families[1].metric[0].label.is_empty());
+        assert_eq!(families[1].metric[0].gauge.as_ref().unwrap().value(), 2.0);
+
+        assert_eq!(families[2].name(), "process_start_time_seconds");
+        assert!(families[2].help.is_none());
+        assert_eq!(families[2].r#type(), MetricType::Gauge);
+    }
+
+    #[test]
+    fn native_histograms() {
+        let ok = NativeHistogram::<u32>::new(BOUNDS, 3);
+        for v in [0u32, 1, 1, 2, 3] {
+            ok.add(v);
+        }
+        let empty = NativeHistogram::<u32>::new(BOUNDS, 3);
+
+        let families = decode(
+            &encode(
+                &Fmt(|f: &mut Encoder<'_>| {
+                    latency_ms.fmt_help(f)?;
+                    latency_ms.fmt_metric_labeled(f, &ok, &Label("status", "200"))?;
+                    latency_ms.fmt_metric_labeled(f, &empty, &Label("status", "500"))
+                }),
+                &Filter::default(),
+            )
+            .unwrap(),
+        );
+        assert_eq!(families.len(), 1);
+        let metrics = &families[0].metric;
+        assert_eq!(metrics.len(), 2);
+        assert_eq!(metrics[0].label, vec![label("status", "200")]);
+
+        let hist = metrics[0].histogram.as_ref().unwrap();
+        assert_eq!(hist.sample_count(), 5);
+        assert_eq!(hist.sample_sum(), 7.0);
+        assert_eq!(
+            hist.bucket
+                .iter()
+                .map(|b| (b.upper_bound(), b.cumulative_count()))
+                .collect::<Vec<_>>(),
+            vec![(1.0, 3), (10.0, 5), (f64::INFINITY, 5)]
+        );
+        assert_eq!(hist.schema(), 3);
+        assert_eq!(hist.zero_count(), 1);
+        // With schema 3, 1 falls in bucket 0, 2 in bucket 8, and 3 in bucket
+        // 13.
+        assert_eq!(
+            hist.positive_span
+                .iter()
+                .map(|s| (s.offset(), s.length()))
+                .collect::<Vec<_>>(),
+            vec![(0, 1), (7, 1), (4, 1)]
+        );
+        assert_eq!(hist.positive_delta, vec![2, -1, 0]);
+
+        let empty = metrics[1].histogram.as_ref().unwrap();
+        assert_eq!(empty.schema(), 3);
+        assert_eq!(
+            empty
+                .positive_span
+                .iter()
+                .map(|s| (s.offset(), s.length()))
+                .collect::<Vec<_>>(),
+            vec![(0, 0)]
+        );
+    }
+}
@@ -0,0 +1,271 @@
+//! Encodes metrics as Prometheus remote-write requests.
+//!
+//! Metrics are formatted as Prometheus text and each sample is converted into
+//! a time series. Requests are compressed with the Snappy block format, as the
+//! protocol requires.
+
+use super::{protobuf::parse_sample, FmtMetrics};
+use prost::Message;
+use std::time::SystemTime;
+
+mod proto {
+    include!("gen/prometheus.rs");
+}
+
+use self::proto::metric_metadata::MetricType;
+
+/// The `Content-Type` of remote-write requests.
+pub const CONTENT_TYPE: &str = "application/x-protobuf";
+
+/// The `Content-Encoding` of remote-write requests.
+pub const CONTENT_ENCODING: &str = "snappy";
+
+/// The version of the remote-write protocol, sent in the
+/// `X-Prometheus-Remote-Write-Version` header.
+pub const VERSION: &str = "0.1.0";
+
+/// Encodes a snapshot of the given metrics as a compressed remote-write
+/// request body.
+///
+/// The given labels are added to every time series, unless the series already
+/// has a label with the same name.
+pub fn encode(metrics: &impl FmtMetrics, labels: &[(String, String)], now: SystemTime) -> Vec<u8> {
+    let text = metrics.as_display().to_string();
+    let timestamp = crate::unix_time(now).as_millis() as i64;
+    let req = write_request(&text, labels, timestamp);
+    snappy_compress(&req.encode_to_vec())
+}
+
+fn write_request(
+    text: &str,
+    extra_labels: &[(String, String)],
+    timestamp: i64,
+) -> proto::WriteRequest {
+    let mut req = proto::WriteRequest::default();
+    for line in text.lines() {
+        if let Some(help) = line.strip_prefix("# HELP ") {
+            let (name, help) = help.split_once(' ').unwrap_or((help, ""));
+            req.metadata.push(proto::MetricMetadata {
+                metric_family_name: name.to_string(),
+                help: help.to_string(),
+                ..Default::default()
+            });
+            continue;
+        }
+        if let Some(ty) = line.strip_prefix("# TYPE ") {
+            let (name, ty) = ty.split_once(' ').unwrap_or((ty, ""));
+            let ty = match ty {
+                "counter" => MetricType::Counter,
+                "gauge" => MetricType::Gauge,
+                "histogram" => MetricType::Histogram,
+                "summary" => MetricType::Summary,
+                _ => MetricType::Unknown,
+            };
+            match req.metadata.last_mut() {
+                Some(md) if md.metric_family_name == name => md.set_type(ty),
+                _ => {
+                    let mut md = proto::MetricMetadata {
+                        metric_family_name: name.to_string(),
+                        ..Default::default()
+                    };
+                    md.set_type(ty);
+                    req.metadata.push(md);
+                }
+            }
+            continue;
+        }
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+
+        let sample = match parse_sample(line) {
+            Some(sample) => sample,
+            None => continue,
+        };
+        let value = match sample
+            .value
+            .split(' ')
+            .next()
+            .and_then(|v| v.parse::<f64>().ok())
+        {
+            Some(value) => value,
+            None => continue,
+        };
+
+        let mut labels = sample.labels;
+        for (name, value) in extra_labels {
+            if !labels.iter().any(|(n, _)| n == name) {
+                labels.push((name.clone(), value.clone()));
+            }
+        }
+        labels.push(("__name__".to_string(), sample.name.to_string()));
+        labels.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        req.timeseries.push(proto::TimeSeries {
+            labels: labels
+                .into_iter()
+                .map(|(name, value)| proto::Label { name, value })
+                .collect(),
+            samples: vec![proto::Sample { value, timestamp }],
+        });
+    }
+    req
+}
+
+/// Encodes bytes in the Snappy block format.
+///
+/// The input is written as a series of uncompressed literals, which every
+/// Snappy decoder accepts. Metrics compress well, but requests are sent
+/// infrequently enough that this is not worth an additional dependency.
+fn snappy_compress(input: &[u8]) -> Vec<u8> {
+    const MAX_LITERAL: usize = 1 << 16;
+
+    let mut out = Vec::with_capacity(input.len() + input.len() / MAX_LITERAL * 3 + 8);
+    // The preamble is the uncompressed length as a little-endian varint.
+    let mut len = input.len() as u64;
+    while len >= 0x80 {
+        out.push((len as u8) | 0x80);
+        len >>= 7;
+    }
+    out.push(len as u8);
+
+    for chunk in input.chunks(MAX_LITERAL) {
+        // A literal's tag holds its length minus one. Lengths of more than 60
+        // bytes follow the tag in 1 or 2 little-endian bytes.
+        let n = chunk.len() - 1;
+        if n < 60 {
+            out.push((n as u8) << 2);
+        } else if n < 1 << 8 {
+            out.push(60 << 2);
+            out.push(n as u8);
+        } else {
+            out.push(61 << 2);
+            out.extend_from_slice(&(n as u16).to_le_bytes());
+        }
+        out.extend_from_slice(chunk);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{metrics, Counter, Gauge};
+    use std::fmt;
+
+    /// Decodes Snappy-compressed literals.
+    fn snappy_decompress(mut input: &[u8]) -> Vec<u8> {
+        let mut len = 0u64;
+        let mut shift = 0;
+        loop {
+            let b = input[0];
+            input = &input[1..];
+            len |= u64::from(b & 0x7f) << shift;
+            shift += 7;
+            if b & 0x80 == 0 {
+                break;
+            }
+        }
+
+        let mut out = Vec::with_capacity(len as usize);
+        while !input.is_empty() {
+            let tag = input[0];
+            assert_eq!(tag & 0b11, 0, "only literals are expected");
+            let (n, rest) = match tag >> 2 {
+                60 => (input[1] as usize, &input[2..]),
+                61 => (
+                    u16::from_le_bytes([input[1], input[2]]) as usize,
+                    &input[3..],
+                ),
+                n => (n as usize, &input[1..]),
+            };
+            out.extend_from_slice(&rest[..n + 1]);
+            input = &rest[n + 1..];
+        }
+        assert_eq!(out.len() as u64, len);
+        out
+    }
+
+    metrics! {
+        request_total: Counter { "Total requests" },
+        open_connections: Gauge { "Open connections" }
+    }
+
+    struct Pod(&'static str);
+    impl crate::FmtLabels for Pod {
+        fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "pod=\"{}\",instance=\"ignored\"", self.0)
+        }
+    }
+
+    struct Metrics;
+    impl FmtMetrics for Metrics {
+        fn fmt_metrics(&self, f: &mut crate::Encoder<'_>) -> fmt::Result {
+            request_total.fmt_help(f)?;
+            request_total.fmt_metric_labeled(f, &Counter::from(3), &Pod("a"))?;
+            open_connections.fmt_help(f)?;
+            let gauge = Gauge::default();
+            gauge.incr();
+            open_connections.fmt_metric(f, &gauge)?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn snappy_roundtrip() {
+        for len in [0, 1, 60, 61, 256, 257, 70_000] {
+            let input = (0..len).map(|i| i as u8).collect::<Vec<_>>();
+            assert_eq!(snappy_decompress(&snappy_compress(&input)), input);
+        }
+    }
+
+    #[test]
+    fn encodes_time_series() {
+        let labels = vec![
+            ("instance".to_string(), "proxy-1".to_string()),
+            ("cluster".to_string(), "east".to_string()),
+        ];
+        let now = std::time::UNIX_EPOCH + std::time::Duration::from_millis(1_650_000_000_123);
+        let body = encode(&Metrics, &labels, now);
+        let req = proto::WriteRequest::decode(&*snappy_decompress(&body)).unwrap();
+
+        assert_eq!(req.timeseries.len(), 2);
+        fn labels_of(ts: &proto::TimeSeries) -> Vec<(&str, &str)> {
+            ts.labels
+                .iter()
+                .map(|l| (l.name.as_str(), l.value.as_str()))
+                .collect()
+        }
+        assert_eq!(
+            labels_of(&req.timeseries[0]),
+            [
+                ("__name__", "request_total"),
+                ("cluster", "east"),
+                ("instance", "ignored"),
+                ("pod", "a"),
+            ]
+        );
+        assert_eq!(
+            req.timeseries[0].samples,
+            [proto::Sample {
+                value: 3.0,
+                timestamp: 1_650_000_000_123,
+            }]
+        );
+        assert_eq!(
+            labels_of(&req.timeseries[1]),
+            [
+                ("__name__", "open_connections"),
+                ("cluster", "east"),
+                ("instance", "proxy-1"),
+            ]
+        );
+        assert_eq!(req.timeseries[1].samples[0].value, 1.0);
+
+        assert_eq!(req.metadata.len(), 2);
+        assert_eq!(req.metadata[0].metric_family_name, "request_total");
+        assert_eq!(req.metadata[0].r#type(), MetricType::Counter);
+        assert_eq!(req.metadata[0].help, "Total requests");
+        assert_eq!(req.metadata[1].r#type(), MetricType::Gauge);
+    }
+}
@@ -3,14 +3,32 @@ use hyper::Body;
 use std::io::Write;
 use tracing::trace;
 
-use super::FmtMetrics;
+use super::{
+    prom::{Encoder, Format},
+    protobuf, Filter, FmtMetrics,
+};
 
 /// Serve Prometheues metrics.
+///
+/// Metrics are written in the Prometheus protobuf format or the OpenMetrics
+/// text format when the client accepts them; otherwise, the Prometheus text
+/// format is used. Native histograms are only exposed in the protobuf format.
 #[derive(Debug, Clone)]
 pub struct Serve<M> {
     metrics: M,
 }
 
+const OPENMETRICS: &str = "application/openmetrics-text";
+const OPENMETRICS_CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+const PROTOBUF: &str = "application/vnd.google.protobuf";
+const PROTOBUF_PARAMS: &[&str] = &[
+    "proto=io.prometheus.client.MetricFamily",
+    "encoding=delimited",
+];
+const PROTOBUF_CONTENT_TYPE: &str =
+    "application/vnd.google.protobuf; proto=io.prometheus.client.MetricFamily; encoding=delimited";
+
 // ===== impl Serve =====
 
 impl<M> Serve<M> {
@@ -30,26 +48,291 @@ impl<M> Serve<M> {
                     .unwrap_or(false)
             })
     }
+
+    /// Determines the exposition format from the request's `Accept` header,
+    /// preferring the protobuf format when it is acceptable.
+    fn format<B>(req: &http::Request<B>) -> Format {
+        if Self::accepts(req, PROTOBUF, PROTOBUF_PARAMS) {
+            Format::Protobuf
+        } else if Self::accepts(req, OPENMETRICS, &[]) {
+            Format::OpenMetrics
+        } else {
+            Format::Prometheus
+        }
+    }
+
+    /// Returns true if the request accepts the given media type with all of the
+    /// given parameters.
+    fn accepts<B>(req: &http::Request<B>, media_type: &str, required: &[&str]) -> bool {
+        req.headers()
+            .get_all(http::header::ACCEPT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|media_range| {
+                let mut params = media_range.split(';').map(str::trim);
+                if !params
+                    .next()
+                    .unwrap_or_default()
+                    .eq_ignore_ascii_case(media_type)
+                {
+                    return false;
+                }
+                let params = params.collect::<Vec<_>>();
+                // Media ranges with a zero quality value are not acceptable.
+                let refused = params.iter().any(|p| {
+                    p.strip_prefix("q=")
+                        .and_then(|q| q.parse::<f32>().ok())
+                        .map(|q| q <= 0.0)
+                        .unwrap_or(false)
+                });
+                !refused && required.iter().all(|r| params.contains(r))
+            })
+    }
 }
 
 impl<M: FmtMetrics> Serve<M> {
     pub fn serve<B>(&self, req: http::Request<B>) -> std::io::Result<http::Response<Body>> {
+        self.serve_filtered(req, &Filter::default())
+    }
+
+    /// Serves only the metrics selected by the given filter.
+    pub fn serve_filtered<B>(
+        &self,
+        req: http::Request<B>,
+        filter: &Filter,
+    ) -> std::io::Result<http::Response<Body>> {
+        let format = Self::format(&req);
+        let content_type = match format {
+            Format::Prometheus => "text/plain",
+            Format::OpenMetrics => OPENMETRICS_CONTENT_TYPE,
+            Format::Protobuf => PROTOBUF_CONTENT_TYPE,
+        };
+
         if Self::is_gzip(&req) {
-            trace!("gzipping metrics");
+            trace!(?format, "gzipping metrics");
             let mut writer = GzEncoder::new(Vec::<u8>::new(), CompressionOptions::fast());
-            write!(&mut writer, "{}", self.metrics.as_display())?;
+            self.write_metrics(&mut writer, format, filter)?;
             Ok(http::Response::builder()
                 .header(http::header::CONTENT_ENCODING, "gzip")
-                .header(http::header::CONTENT_TYPE, "text/plain")
+                .header(http::header::CONTENT_TYPE, content_type)
                 .body(writer.finish()?.into())
                 .expect("Response must be valid"))
         } else {
             let mut writer = Vec::<u8>::new();
-            write!(&mut writer, "{}", self.metrics.as_display())?;
+            self.write_metrics(&mut writer, format, filter)?;
             Ok(http::Response::builder()
-                .header(http::header::CONTENT_TYPE, "text/plain")
+                .header(http::header::CONTENT_TYPE, content_type)
                 .body(Body::from(writer))
                 .expect("Response must be valid"))
         }
     }
+
+    fn write_metrics(
+        &self,
+        writer: &mut impl Write,
+        format: Format,
+        filter: &Filter,
+    ) -> std::io::Result<()> {
+        if filter.is_empty() && format == Format::Prometheus {
+            return write!(writer, "{}", self.metrics.as_display());
+        }
+
+        if format == Format::Protobuf {
+            let buf = protobuf::encode(&self.metrics, filter).map_err(fmt_error)?;
+            return writer.write_all(&buf);
+        }
+
+        let mut text = String::new();
+        let mut encoder = Encoder::text(&mut text, format).with_filter(filter);
+        self.metrics.fmt_metrics(&mut encoder).map_err(fmt_error)?;
+        encoder.finish().map_err(fmt_error)?;
+        writer.write_all(text.as_bytes())?;
+        if format == Format::OpenMetrics {
+            writeln!(writer, "# EOF")?;
+        }
+        Ok(())
+    }
+}
+
+fn fmt_error(error: std::fmt::Error) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::Other, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        histogram::{Bounds, Bucket},
+        protobuf::proto,
+        Counter, Histogram, NativeHistogram,
+    };
+    use prost::Message;
+    use std::fmt;
+
+    crate::metrics! {
+        requests_total: Counter { "Total requests" },
+        latency_ms: Histogram<u64> { "Request latency" },
+        native_latency_ms: NativeHistogram<u32> { "Native request latency" }
+    }
+
+    static BOUNDS: &Bounds = &Bounds(&[Bucket::Le(10.0), Bucket::Le(100.0), Bucket::Inf]);
+
+    struct Metrics {
+        requests: Counter,
+        latency: Histogram<u64>,
+        native_latency: Option<NativeHistogram<u32>>,
+    }
+
+    impl FmtMetrics for Metrics {
+        fn fmt_metrics(&self, f: &mut Encoder<'_>) -> fmt::Result {
+            requests_total.fmt_help(f)?;
+            requests_total.fmt_metric(f, &self.requests)?;
+            latency_ms.fmt_help(f)?;
+            latency_ms.fmt_metric(f, &self.latency)?;
+            if let Some(native) = self.native_latency.as_ref() {
+                native_latency_ms.fmt_help(f)?;
+                native_latency_ms.fmt_metric(f, native)?;
+            }
+            Ok(())
+        }
+    }
+
+    async fn get(accept: &str) -> (String, String) {
+        let (content_type, body) = get_bytes(accept, None).await;
+        (content_type, String::from_utf8(body).unwrap())
+    }
+
+    async fn get_bytes(
+        accept: &str,
+        native_latency: Option<NativeHistogram<u32>>,
+    ) -> (String, Vec<u8>) {
+        let metrics = Metrics {
+            requests: Counter::new(),
+            latency: Histogram::new(BOUNDS),
+            native_latency,
+        };
+        metrics.requests.incr();
+        metrics.latency.add(5u64);
+        metrics
+            .latency
+            .add_with_trace_id(50u64, "4bf92f3577b34da6a3ce929d0e0e4736");
+
+        let rsp = Serve::new(metrics)
+            .serve(
+                http::Request::builder()
+                    .header(http::header::ACCEPT, accept)
+                    .body(())
+                    .unwrap(),
+            )
+            .unwrap();
+        let content_type = rsp.headers()[http::header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body = hyper::body::to_bytes(rsp.into_body()).await.unwrap();
+        (content_type, body.to_vec())
+    }
+
+    #[tokio::test]
+    async fn prometheus_by_default() {
+        let (content_type, body) = get("text/plain;version=0.0.4").await;
+        assert_eq!(content_type, "text/plain");
+        assert_eq!(
+            body.lines().collect::<Vec<_>>(),
+            [
+                "# HELP requests_total Total requests",
+                "# TYPE requests_total counter",
+                "requests_total 1",
+                "# HELP latency_ms Request latency",
+                "# TYPE latency_ms histogram",
+                "latency_ms_bucket{le=\"10\"} 1",
+                "latency_ms_bucket{le=\"100\"} 2",
+                "latency_ms_bucket{le=\"+Inf\"} 2",
+                "latency_ms_count 2",
+                "latency_ms_sum 55",
+            ]
+        );
+
+        let (content_type, _) = get("application/openmetrics-text;q=0,text/plain;q=0.5").await;
+        assert_eq!(content_type, "text/plain");
+    }
+
+    #[tokio::test]
+    async fn openmetrics() {
+        let (content_type, body) = get(
+            "application/openmetrics-text;version=1.0.0,text/plain;version=0.0.4;q=0.5,*/*;q=0.1",
+        )
+        .await;
+        assert_eq!(content_type, OPENMETRICS_CONTENT_TYPE);
+
+        let lines = body.lines().collect::<Vec<_>>();
+        let expected: &[&str] = &[
+            "# HELP requests Total requests",
+            "# TYPE requests counter",
+            "requests_total 1",
+            "requests_created ",
+            "# HELP latency_ms Request latency",
+            "# TYPE latency_ms histogram",
+            "latency_ms_bucket{le=\"10\"} 1",
+            "latency_ms_bucket{le=\"100\"} 2 # {trace_id=\"4bf92f3577b34da6a3ce929d0e0e4736\"} 50 ",
+            "latency_ms_bucket{le=\"+Inf\"} 2",
+            "latency_ms_count 2",
+            "latency_ms_sum 55",
+            "latency_ms_created ",
+            "# EOF",
+        ];
+        assert_eq!(lines.len(), expected.len(), "{}", body);
+        for (line, expected) in lines.iter().zip(expected) {
+            // Timestamps vary, so only their presence is checked.
+            if expected.ends_with(' ') {
+                assert!(line.starts_with(expected), "{} != {}", line, expected);
+            } else {
+                assert_eq!(line, expected);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn protobuf() {
+        let native = NativeHistogram::new(BOUNDS, 0);
+        native.add(3u32);
+        native.add(4u32);
+        let (content_type, body) = get_bytes(
+            "application/vnd.google.protobuf;proto=io.prometheus.client.MetricFamily;encoding=delimited;q=0.7,\
+             application/openmetrics-text;version=1.0.0;q=0.5,text/plain;version=0.0.4;q=0.3",
+            Some(native),
+        )
+        .await;
+        assert_eq!(content_type, PROTOBUF_CONTENT_TYPE);
+
+        let mut buf = &body[..];
+        let mut families = vec![];
+        while !buf.is_empty() {
+            families.push(proto::MetricFamily::decode_length_delimited(&mut buf).unwrap());
+        }
+        assert_eq!(
+            families.iter().map(|f| f.name()).collect::<Vec<_>>(),
+            ["requests_total", "latency_ms", "native_latency_ms"]
+        );
+
+        let classic = families[1].metric[0].histogram.as_ref().unwrap();
+        assert_eq!(classic.sample_count(), 2);
+        assert_eq!(classic.bucket.len(), 3);
+        assert!(classic.schema.is_none());
+
+        // 3 falls in (2, 4] and 4 is the upper bound of the same bucket.
+        let native = families[2].metric[0].histogram.as_ref().unwrap();
+        assert_eq!(native.sample_count(), 2);
+        assert_eq!(native.sample_sum(), 7.0);
+        assert_eq!(native.schema(), 0);
+        assert_eq!(native.positive_span.len(), 1);
+        assert_eq!(native.positive_span[0].offset(), 2);
+        assert_eq!(native.positive_span[0].length(), 1);
+        assert_eq!(native.positive_delta, vec![2]);
+
+        // Without the protobuf parameters, the text format is used.
+        let (content_type, _) = get("application/vnd.google.protobuf;q=0.7,text/plain").await;
+        assert_eq!(content_type, "text/plain");
+    }
 }
@@ -1,4 +1,4 @@
-use crate::{FmtLabels, FmtMetric, Metric};
+use crate::{Encoder, FmtLabels, FmtMetric, Metric};
 use parking_lot::Mutex;
 use std::{
     borrow::Borrow,
@@ -73,7 +73,7 @@ where
     /// Formats a metric across all instances of `Metrics` in the registry.
     pub fn fmt_by<N, M>(
         &self,
-        f: &mut fmt::Formatter<'_>,
+        f: &mut Encoder<'_>,
         metric: Metric<'_, N, M>,
         get_metric: impl Fn(&V) -> &M,
     ) -> fmt::Result
@@ -97,7 +97,7 @@ where
     /// Formats a metric across all instances of `Metrics` in the registry.
     pub fn fmt_by_locked<N, M>(
         &self,
-        f: &mut fmt::Formatter<'_>,
+        f: &mut Encoder<'_>,
         metric: Metric<'_, N, M>,
         get_metric: impl Fn(&V) -> &M,
     ) -> fmt::Result
@@ -1,7 +1,11 @@
 // This module is inspired by hdrhistogram-go, which is distributed under the
 // MIT license. Copyright (c) 2014 Coda Hale
 
-use crate::{Counter, Factor, FmtLabels, FmtMetric};
+use crate::{
+    prom::{Encoder, NoLabels},
+    protobuf::proto,
+    Counter, Factor, FmtLabels, FmtMetric,
+};
 pub use hdrhistogram::{AdditionError, CreationError, Histogram, RecordError};
 use parking_lot::{MappedMutexGuard, Mutex, MutexGuard};
 use std::fmt;
@@ -192,41 +196,55 @@ impl<F> Summary<F> {
 impl<F: Factor> FmtMetric for Summary<F> {
     const KIND: &'static str = "summary";
 
-    fn fmt_metric<N: fmt::Display>(&self, f: &mut fmt::Formatter<'_>, name: N) -> fmt::Result {
-        {
-            let report = self.lock_report();
-            for q in self.quantiles.iter() {
-                let v = Counter::<F>::from(report.value_at_quantile(*q));
-                v.fmt_metric_labeled(f, &name, FmtQuantile(q))?;
-            }
-        }
-        self.count.fmt_metric(f, format_args!("{}_count", name))?;
-        self.sum.fmt_metric(f, format_args!("{}_sum", name))?;
-        Ok(())
+    fn fmt_metric<N: fmt::Display>(&self, f: &mut Encoder<'_>, name: N) -> fmt::Result {
+        self.fmt_summary(f, name, None::<&NoLabels>)
     }
 
-    fn fmt_metric_labeled<N, L>(
-        &self,
-        f: &mut fmt::Formatter<'_>,
-        name: N,
-        labels: L,
-    ) -> fmt::Result
+    fn fmt_metric_labeled<N, L>(&self, f: &mut Encoder<'_>, name: N, labels: L) -> fmt::Result
     where
         N: fmt::Display,
         L: FmtLabels,
     {
+        self.fmt_summary(f, name, Some(&labels))
+    }
+}
+
+impl<F: Factor> Summary<F> {
+    fn fmt_summary<N, L>(&self, f: &mut Encoder<'_>, name: N, labels: Option<&L>) -> fmt::Result
+    where
+        N: fmt::Display,
+        L: FmtLabels,
+    {
+        if !f.start_metric(Self::KIND, &name) {
+            return Ok(());
+        }
+
+        if let Some(metric) = f.proto_metric(Self::KIND, &name, labels) {
+            let report = self.lock_report();
+            metric.summary = Some(proto::Summary {
+                sample_count: Some(self.count.value() as u64),
+                sample_sum: Some(self.sum.value()),
+                quantile: self
+                    .quantiles
+                    .iter()
+                    .map(|q| proto::Quantile {
+                        quantile: Some(*q),
+                        value: Some(F::factor(report.value_at_quantile(*q))),
+                    })
+                    .collect(),
+            });
+            return Ok(());
+        }
+
         {
             let report = self.lock_report();
             for q in self.quantiles.iter() {
-                let v = Counter::<F>::from(report.value_at_quantile(*q));
-                v.fmt_metric_labeled(f, &name, (FmtQuantile(q), &labels))?;
+                let v = F::factor(report.value_at_quantile(*q));
+                f.fmt_sample(&name, Some(&(FmtQuantile(q), labels)), v)?;
             }
         }
-        self.count
-            .fmt_metric_labeled(f, format_args!("{}_count", name), &labels)?;
-        self.sum
-            .fmt_metric_labeled(f, format_args!("{}_sum", name), &labels)?;
-        Ok(())
+        f.fmt_sample(format_args!("{}_count", name), labels, self.count.value())?;
+        f.fmt_sample(format_args!("{}_sum", name), labels, self.sum.value())
     }
 }
 
@@ -255,7 +273,7 @@ mod tests {
     }
 
     impl FmtMetrics for Fmt {
-        fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn fmt_metrics(&self, f: &mut Encoder<'_>) -> fmt::Result {
             struct Label;
             impl FmtLabels for Label {
                 fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
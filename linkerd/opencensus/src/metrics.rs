@@ -1,4 +1,4 @@
-use linkerd_metrics::{metrics, Counter, FmtMetrics};
+use linkerd_metrics::{metrics, Counter, Encoder, FmtMetrics};
 use std::fmt;
 use std::sync::Arc;
 
@@ -43,7 +43,7 @@ impl Registry {
 }
 
 impl FmtMetrics for Report {
-    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fn fmt_metrics(&self, f: &mut Encoder<'_>) -> fmt::Result {
         opencensus_span_export_streams.fmt_help(f)?;
         opencensus_span_export_streams.fmt_metric(f, &self.0.streams)?;
 
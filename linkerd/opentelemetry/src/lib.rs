@@ -0,0 +1,282 @@
+#![deny(
+    warnings,
+    rust_2018_idioms,
+    clippy::disallowed_methods,
+    clippy::disallowed_types
+)]
+#![forbid(unsafe_code)]
+
+pub mod metrics;
+
+use futures::stream::{Stream, StreamExt};
+use http_body::Body as HttpBody;
+use linkerd_error::Error;
+use metrics::Registry;
+pub use opentelemetry_proto as proto;
+use opentelemetry_proto::collector::trace::v1::{
+    trace_service_client::TraceServiceClient, ExportTraceServiceRequest,
+};
+use opentelemetry_proto::resource::v1::Resource;
+use opentelemetry_proto::trace::v1::{ResourceSpans, ScopeSpans, Span};
+use tokio::time;
+use tonic::{self as grpc, body::BoxBody, client::GrpcService};
+use tracing::{debug, trace};
+
+pub async fn export_spans<T, S>(client: T, resource: Resource, spans: S, metrics: Registry)
+where
+    T: GrpcService<BoxBody> + Clone,
+    T::Error: Into<Error>,
+    T::ResponseBody: Default + HttpBody<Data = tonic::codegen::Bytes> + Send + 'static,
+    <T::ResponseBody as HttpBody>::Error: Into<Error> + Send,
+    S: Stream<Item = Span> + Unpin,
+{
+    debug!("Span exporter running");
+    SpanExporter::new(client, resource, spans, metrics)
+        .run()
+        .await
+}
+
+/// SpanExporter sends batches of spans to the given TraceService gRPC service.
+///
+/// Unlike the OpenCensus exporter, each batch is sent as a unary request.
+/// Batches that cannot be exported are dropped rather than retried so that
+/// an unavailable collector does not cause spans to accumulate.
+struct SpanExporter<T, S> {
+    client: T,
+    resource: Resource,
+    spans: S,
+    metrics: Registry,
+}
+
+#[derive(Debug)]
+struct SpanRxClosed;
+
+// === impl SpanExporter ===
+
+impl<T, S> SpanExporter<T, S>
+where
+    T: GrpcService<BoxBody>,
+    T::Error: Into<Error>,
+    T::ResponseBody: Default + HttpBody<Data = tonic::codegen::Bytes> + Send + 'static,
+    <T::ResponseBody as HttpBody>::Error: Into<Error> + Send,
+    S: Stream<Item = Span> + Unpin,
+{
+    const MAX_BATCH_SIZE: usize = 1000;
+    const MAX_BATCH_IDLE: time::Duration = time::Duration::from_secs(10);
+    const EXPORT_TIMEOUT: time::Duration = time::Duration::from_secs(10);
+
+    fn new(client: T, resource: Resource, spans: S, metrics: Registry) -> Self {
+        Self {
+            client,
+            resource,
+            spans,
+            metrics,
+        }
+    }
+
+    async fn run(self) {
+        let Self {
+            client,
+            resource,
+            mut spans,
+            mut metrics,
+        } = self;
+
+        // Holds the batch of pending spans. Cleared as the spans are flushed.
+        // Contains no more than MAX_BATCH_SIZE spans.
+        let mut accum = Vec::new();
+
+        let mut svc = TraceServiceClient::new(client);
+        loop {
+            // Collect spans into a batch.
+            let collect = Self::collect_batch(&mut spans, &mut accum).await;
+
+            // If we collected spans, flush them.
+            if !accum.is_empty() {
+                let count = accum.len();
+                let msg = ExportTraceServiceRequest {
+                    resource_spans: vec![ResourceSpans {
+                        resource: Some(resource.clone()),
+                        scope_spans: vec![ScopeSpans {
+                            spans: std::mem::take(&mut accum),
+                            ..Default::default()
+                        }],
+                        ..Default::default()
+                    }],
+                };
+                trace!(spans = count, "Sending batch");
+
+                let export = svc.export(grpc::Request::new(msg));
+                match time::timeout(Self::EXPORT_TIMEOUT, export).await {
+                    Ok(Ok(rsp)) => {
+                        metrics.send(count as u64);
+                        if let Some(partial) = rsp.into_inner().partial_success {
+                            if partial.rejected_spans > 0 {
+                                debug!(
+                                    rejected = partial.rejected_spans,
+                                    message = %partial.error_message,
+                                    "Collector rejected spans",
+                                );
+                            }
+                        }
+                    }
+                    Ok(Err(status)) => {
+                        metrics.failure();
+                        debug!(%status, spans = count, "Failed to export spans");
+                    }
+                    Err(_) => {
+                        metrics.failure();
+                        debug!(spans = count, "Span export timed out");
+                    }
+                }
+            }
+
+            // If the span source was closed, end the task.
+            if let Err(SpanRxClosed) = collect {
+                debug!("Span channel lost");
+                return;
+            }
+        }
+    }
+
+    /// Collects spans from the proxy into `accum`.
+    ///
+    /// Returns an error when the span sream has completed. An error may be
+    /// returned after accumulating spans.
+    async fn collect_batch(spans: &mut S, accum: &mut Vec<Span>) -> Result<(), SpanRxClosed> {
+        loop {
+            if accum.len() == Self::MAX_BATCH_SIZE {
+                trace!(capacity = Self::MAX_BATCH_SIZE, "Batch capacity reached");
+                return Ok(());
+            }
+
+            tokio::select! {
+                biased;
+
+                res = spans.next() => match res {
+                    Some(span) => {
+                        trace!(?span, "Adding to batch");
+                        accum.push(span);
+                    }
+                    None => return Err(SpanRxClosed),
+                },
+
+                // Don't hold spans indefinitely. Return if we hit an idle
+                // timeout and spans have been collected.
+                _ = time::sleep(Self::MAX_BATCH_IDLE) => {
+                    if !accum.is_empty() {
+                        trace!(spans = accum.len(), "Flushing spans due to inactivitiy");
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future;
+    use opentelemetry_proto::{
+        collector::trace::v1::ExportTraceServiceResponse,
+        common::v1::{any_value, AnyValue, KeyValue},
+    };
+    use std::{
+        future::Future,
+        pin::Pin,
+        task::{Context, Poll},
+    };
+    use tokio::sync::mpsc;
+    use tokio_stream::wrappers::ReceiverStream;
+
+    /// An in-process collector that records export requests.
+    #[derive(Clone)]
+    struct Collector(mpsc::UnboundedSender<ExportTraceServiceRequest>);
+
+    impl tower::Service<http::Request<BoxBody>> for Collector {
+        type Response = http::Response<BoxBody>;
+        type Error = std::convert::Infallible;
+        type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: http::Request<BoxBody>) -> Self::Future {
+            let export = self.clone();
+            Box::pin(async move {
+                let mut grpc = tonic::server::Grpc::new(tonic::codec::ProstCodec::default());
+                Ok(grpc.unary(export, req).await)
+            })
+        }
+    }
+
+    impl tonic::server::UnaryService<ExportTraceServiceRequest> for Collector {
+        type Response = ExportTraceServiceResponse;
+        type Future = future::Ready<Result<grpc::Response<Self::Response>, grpc::Status>>;
+
+        fn call(&mut self, req: grpc::Request<ExportTraceServiceRequest>) -> Self::Future {
+            let _ = self.0.send(req.into_inner());
+            future::ok(grpc::Response::new(Default::default()))
+        }
+    }
+
+    fn span(name: &str) -> Span {
+        Span {
+            trace_id: vec![1; 16],
+            span_id: vec![2; 8],
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn exports_batches() {
+        let (collector_tx, mut collector_rx) = mpsc::unbounded_channel();
+        let (spans_tx, spans_rx) = mpsc::channel(10);
+        let (registry, report) = metrics::new();
+        let resource = Resource {
+            attributes: vec![KeyValue {
+                key: "service.name".to_string(),
+                value: Some(AnyValue {
+                    value: Some(any_value::Value::StringValue("linkerd-proxy".to_string())),
+                }),
+            }],
+            dropped_attributes_count: 0,
+        };
+
+        let exporter = tokio::spawn(export_spans(
+            Collector(collector_tx),
+            resource.clone(),
+            ReceiverStream::new(spans_rx),
+            registry,
+        ));
+
+        spans_tx.send(span("a")).await.unwrap();
+        spans_tx.send(span("b")).await.unwrap();
+
+        // The batch is flushed once the exporter is idle.
+        let req = collector_rx.recv().await.expect("batch must be exported");
+        assert_eq!(req.resource_spans.len(), 1);
+        let resource_spans = &req.resource_spans[0];
+        assert_eq!(resource_spans.resource.as_ref(), Some(&resource));
+        let names = resource_spans.scope_spans[0]
+            .spans
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["a", "b"]);
+
+        // Pending spans are flushed when the span channel closes.
+        spans_tx.send(span("c")).await.unwrap();
+        drop(spans_tx);
+        let req = collector_rx.recv().await.expect("batch must be exported");
+        assert_eq!(req.resource_spans[0].scope_spans[0].spans.len(), 1);
+        exporter.await.expect("exporter must complete");
+
+        let metrics = format!("{}", linkerd_metrics::FmtMetrics::as_display(&report));
+        assert!(metrics.contains("opentelemetry_span_export_requests 2"));
+        assert!(metrics.contains("opentelemetry_span_exports 3"));
+    }
+}
@@ -11,10 +11,12 @@ use linkerd_proxy_core::Resolve;
 pub mod buffer;
 pub mod from_resolve;
 pub mod make_endpoint;
+pub mod record_load;
 
 pub use self::buffer::Buffer;
 pub use self::from_resolve::FromResolve;
 pub use self::make_endpoint::MakeEndpoint;
+pub use self::record_load::{Loads, RecordLoad};
 
 pub type Stack<N, R, E> = MakeEndpoint<FromResolve<R, E>, N>;
 
@@ -0,0 +1,262 @@
+use futures::{prelude::*, ready};
+use parking_lot::RwLock;
+use pin_project::pin_project;
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+use tower::{
+    discover::Change,
+    load::{peak_ewma::Cost, Load},
+};
+
+/// The load of each discovered endpoint, as observed by a balancer.
+///
+/// Loads are recorded lazily: a balancer only records an endpoint's load the
+/// first time it is observed and after each time it is read, so that
+/// balancing decisions do not pay for loads that nobody inspects.
+#[derive(Debug)]
+pub struct Loads<K>(Arc<RwLock<HashMap<K, Arc<Slot>>>>);
+
+/// Wraps a discovery stream of PeakEWMA-instrumented services so that each
+/// endpoint's load is recorded when the balancer inspects it.
+#[pin_project]
+#[derive(Debug)]
+pub struct RecordLoad<D, K> {
+    #[pin]
+    inner: D,
+    loads: Loads<K>,
+}
+
+#[derive(Debug)]
+pub struct Recorded<S> {
+    inner: S,
+    slot: Arc<Slot>,
+}
+
+#[derive(Debug)]
+struct Slot {
+    requested: AtomicBool,
+    /// The bits of the most recently recorded load, or NaN if no load has
+    /// been recorded.
+    load: AtomicU64,
+}
+
+// === impl Loads ===
+
+impl<K: Eq + Hash> Loads<K> {
+    /// Returns the most recently recorded load of the endpoint and requests
+    /// that its next load be recorded.
+    pub fn get(&self, key: &K) -> Option<f64> {
+        let loads = self.0.read();
+        let slot = loads.get(key)?;
+        slot.requested.store(true, Ordering::Release);
+        let load = f64::from_bits(slot.load.load(Ordering::Acquire));
+        if load.is_nan() {
+            return None;
+        }
+        Some(load)
+    }
+}
+
+impl<K> Clone for Loads<K> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<K> Default for Loads<K> {
+    fn default() -> Self {
+        Self(Default::default())
+    }
+}
+
+// === impl RecordLoad ===
+
+impl<D, K> RecordLoad<D, K> {
+    pub fn new(inner: D, loads: Loads<K>) -> Self {
+        Self { inner, loads }
+    }
+}
+
+impl<D, K, S, E> Stream for RecordLoad<D, K>
+where
+    D: TryStream<Ok = Change<K, S>, Error = E>,
+    K: Clone + Eq + Hash,
+{
+    type Item = Result<Change<K, Recorded<S>>, E>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let change = match ready!(this.inner.try_poll_next(cx)) {
+            Some(Ok(change)) => change,
+            Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+            None => return Poll::Ready(None),
+        };
+        let change = match change {
+            Change::Insert(key, inner) => {
+                let slot = Arc::new(Slot::default());
+                this.loads.0.write().insert(key.clone(), slot.clone());
+                Change::Insert(key, Recorded { inner, slot })
+            }
+            Change::Remove(key) => {
+                this.loads.0.write().remove(&key);
+                Change::Remove(key)
+            }
+        };
+        Poll::Ready(Some(Ok(change)))
+    }
+}
+
+// === impl Recorded ===
+
+impl<S> Load for Recorded<S>
+where
+    S: Load<Metric = Cost>,
+{
+    type Metric = Cost;
+
+    fn load(&self) -> Cost {
+        let cost = self.inner.load();
+        // Only record the load if it has been requested since it was last
+        // recorded, so the balancer's hot path is a single atomic load.
+        if self.slot.requested.load(Ordering::Relaxed)
+            && self.slot.requested.swap(false, Ordering::Acquire)
+        {
+            let load = cost_value(cost);
+            self.slot.load.store(load.to_bits(), Ordering::Release);
+        }
+        cost
+    }
+}
+
+impl<S, Req> tower::Service<Req> for Recorded<S>
+where
+    S: tower::Service<Req>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    #[inline]
+    fn call(&mut self, req: Req) -> Self::Future {
+        self.inner.call(req)
+    }
+}
+
+// === impl Slot ===
+
+impl Default for Slot {
+    fn default() -> Self {
+        // An endpoint's first load is always recorded.
+        Self {
+            requested: AtomicBool::new(true),
+            load: AtomicU64::new(f64::NAN.to_bits()),
+        }
+    }
+}
+
+/// `Cost` does not expose its value, so it is recovered from the type's
+/// `Debug` output (e.g. `Cost(1234.5)`). This is only done when a load has
+/// been requested.
+fn cost_value(cost: Cost) -> f64 {
+    format!("{:?}", cost)
+        .trim_start_matches("Cost(")
+        .trim_end_matches(')')
+        .parse()
+        .unwrap_or(f64::NAN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tower::{
+        load::{CompleteOnResponse, PeakEwma},
+        Service,
+    };
+
+    /// Asserts that `load` is within 1% of the expected number of
+    /// nanoseconds, since an endpoint's default RTT decays over time.
+    #[track_caller]
+    fn assert_load(load: Option<f64>, expected: f64) {
+        let load = load.expect("load must be recorded");
+        assert!(
+            (load - expected).abs() < expected / 100.0,
+            "{} != {}",
+            load,
+            expected
+        );
+    }
+
+    #[test]
+    fn cost_values() {
+        let svc = PeakEwma::new(
+            tower::service_fn(|()| async { Ok::<_, ()>(()) }),
+            Duration::from_millis(20),
+            Duration::from_secs(10).as_nanos() as f64,
+            CompleteOnResponse::default(),
+        );
+        assert_load(Some(cost_value(svc.load())), 20_000_000.0);
+    }
+
+    #[tokio::test]
+    async fn records_loads() {
+        let svc = |rtt| {
+            PeakEwma::new(
+                tower::service_fn(|()| async { Ok::<_, ()>(()) }),
+                Duration::from_millis(rtt),
+                Duration::from_secs(10).as_nanos() as f64,
+                CompleteOnResponse::default(),
+            )
+        };
+        let changes = futures::stream::iter(vec![
+            Ok::<_, ()>(Change::Insert("a", svc(10))),
+            Ok(Change::Insert("b", svc(20))),
+            Ok(Change::Remove("a")),
+        ]);
+        let loads = Loads::default();
+        let mut discover = RecordLoad::new(changes, loads.clone());
+
+        let mut a = match discover.next().await {
+            Some(Ok(Change::Insert("a", a))) => a,
+            _ => panic!("expected insert"),
+        };
+        let b = match discover.next().await {
+            Some(Ok(Change::Insert("b", b))) => b,
+            _ => panic!("expected insert"),
+        };
+        assert_eq!(loads.get(&"a"), None, "loads are recorded when observed");
+
+        a.load();
+        b.load();
+        assert_load(loads.get(&"a"), 10_000_000.0);
+        assert_load(loads.get(&"b"), 20_000_000.0);
+
+        // Loads are only recorded again once they have been read.
+        a.load();
+        let _rsp = a.call(());
+        a.load();
+        assert_load(loads.get(&"a"), 10_000_000.0);
+        a.load();
+        assert_load(loads.get(&"a"), 20_000_000.0);
+
+        assert!(matches!(
+            discover.next().await,
+            Some(Ok(Change::Remove("a")))
+        ));
+        assert_eq!(loads.get(&"a"), None);
+        assert_load(loads.get(&"b"), 20_000_000.0);
+    }
+}
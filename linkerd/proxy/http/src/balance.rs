@@ -1,6 +1,7 @@
 use crate::Error;
 use hyper::body::HttpBody;
 pub use hyper_balance::{PendingUntilFirstData, PendingUntilFirstDataBody};
+pub use linkerd_proxy_discover::{Loads, RecordLoad};
 use rand::thread_rng;
 use std::{hash::Hash, marker::PhantomData, time::Duration};
 use tower::discover::Discover;
@@ -18,6 +19,11 @@ pub struct Layer<A, B> {
     _marker: PhantomData<fn(A) -> B>,
 }
 
+/// Like `Layer`, but balances over a discovery stream that is accompanied by
+/// `Loads` in which each endpoint's load is recorded.
+#[derive(Debug)]
+pub struct RecordedLayer<A, B>(Layer<A, B>);
+
 // === impl Layer ===
 
 pub fn layer<A, B>(default_rtt: Duration, decay: Duration) -> Layer<A, B> {
@@ -28,6 +34,10 @@ pub fn layer<A, B>(default_rtt: Duration, decay: Duration) -> Layer<A, B> {
     }
 }
 
+pub fn layer_recorded<A, B>(default_rtt: Duration, decay: Duration) -> RecordedLayer<A, B> {
+    RecordedLayer(layer(default_rtt, decay))
+}
+
 impl<A, B> Clone for Layer<A, B> {
     fn clone(&self) -> Self {
         Self {
@@ -57,3 +67,33 @@ where
         Balance::from_rng(loaded, &mut thread_rng()).expect("RNG must be valid")
     }
 }
+
+// === impl RecordedLayer ===
+
+impl<A, B> Clone for RecordedLayer<A, B> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<D, S, A, B> tower::layer::Layer<(D, Loads<D::Key>)> for RecordedLayer<A, B>
+where
+    A: HttpBody,
+    B: HttpBody,
+    D: Discover<Service = S>,
+    D::Key: Clone + Eq + Hash,
+    S: tower::Service<http::Request<A>, Response = http::Response<B>>,
+    S::Error: Into<Error>,
+    Balance<RecordLoad<PeakEwmaDiscover<D, PendingUntilFirstData>, D::Key>, http::Request<A>>:
+        tower::Service<http::Request<A>>,
+{
+    type Service =
+        Balance<RecordLoad<PeakEwmaDiscover<D, PendingUntilFirstData>, D::Key>, http::Request<A>>;
+
+    fn layer(&self, (discover, loads): (D, Loads<D::Key>)) -> Self::Service {
+        let instrument = PendingUntilFirstData::default();
+        let loaded = PeakEwmaDiscover::new(discover, self.0.default_rtt, self.0.decay, instrument);
+        Balance::from_rng(RecordLoad::new(loaded, loads), &mut thread_rng())
+            .expect("RNG must be valid")
+    }
+}
@@ -21,7 +21,26 @@ use tracing::{debug, debug_span, trace_span};
 pub struct Settings {
     pub initial_stream_window_size: Option<u32>,
     pub initial_connection_window_size: Option<u32>,
+    /// How long to wait for a PING acknowledgement before closing the
+    /// connection.
     pub keepalive_timeout: Option<Duration>,
+    /// How often PING frames are sent. Defaults to a quarter of the
+    /// `keepalive_timeout`.
+    pub keepalive_interval: Option<Duration>,
+
+    /// Limits the number of streams a client may open concurrently on a
+    /// connection. Only applies to servers.
+    pub max_concurrent_streams: Option<u32>,
+    /// Limits the size of the headers a client may send. Only applies to
+    /// servers.
+    pub max_header_list_size: Option<u32>,
+    /// Limits how long a connection may be used before the server sends a
+    /// GOAWAY so that clients reconnect. Only applies to servers.
+    pub max_connection_age: Option<Duration>,
+    /// Limits how long a connection that has reached its maximum age may
+    /// complete its in-flight requests before it is closed. Defaults to 30
+    /// seconds. Only applies to servers.
+    pub max_connection_age_grace: Option<Duration>,
 }
 
 #[derive(Debug)]
@@ -84,6 +103,8 @@ where
             initial_connection_window_size,
             initial_stream_window_size,
             keepalive_timeout,
+            keepalive_interval,
+            ..
         } = self.h2_settings;
 
         let connect = self
@@ -105,7 +126,7 @@ where
                 if let Some(timeout) = keepalive_timeout {
                     // XXX(eliza): is this a reasonable interval between
                     // PING frames?
-                    let interval = timeout / 4;
+                    let interval = keepalive_interval.unwrap_or(timeout / 4);
                     builder
                         .http2_keep_alive_timeout(timeout)
                         .http2_keep_alive_interval(interval)
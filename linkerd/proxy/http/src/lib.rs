@@ -18,6 +18,7 @@ pub mod h1;
 pub mod h2;
 mod header_from_target;
 pub mod insert;
+pub mod limits;
 pub mod normalize_uri;
 pub mod orig_proto;
 mod override_authority;
@@ -34,6 +35,7 @@ pub use self::{
     detect::DetectHttp,
     glue::{HyperServerSvc, UpgradeBody},
     header_from_target::NewHeaderFromTarget,
+    limits::{LimitBody, LimitRequests, RequestLimits},
     normalize_uri::{MarkAbsoluteForm, NewNormalizeUri},
     override_authority::{AuthorityOverride, NewOverrideAuthority},
     retain::Retain,
@@ -0,0 +1,284 @@
+use futures::{future, TryFutureExt};
+use http::HeaderMap;
+use linkerd_error::Error;
+use linkerd_stack::layer;
+use pin_project::pin_project;
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+use thiserror::Error;
+use tower::Service;
+
+/// Bounds the size of requests accepted by a server.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct RequestLimits {
+    /// The maximum size of a request's header block, in bytes.
+    pub max_header_bytes: Option<usize>,
+
+    /// The maximum size of a request's body, in bytes.
+    pub max_body_bytes: Option<u64>,
+}
+
+#[derive(Clone, Debug, Error)]
+#[error("request headers are {size} bytes, exceeding the {limit} byte limit")]
+pub struct HeadersTooLarge {
+    size: usize,
+    limit: usize,
+}
+
+#[derive(Clone, Debug, Error)]
+#[error("request body exceeds the {limit} byte limit")]
+pub struct BodyTooLarge {
+    limit: u64,
+}
+
+/// Rejects requests that exceed a set of `RequestLimits`.
+///
+/// Requests whose header blocks or declared `content-length` exceed the
+/// limits fail before they are dispatched to the inner service. Otherwise,
+/// the request body fails once it has produced more than the allowed number of
+/// bytes.
+#[derive(Clone, Debug)]
+pub struct LimitRequests<S> {
+    limits: RequestLimits,
+    inner: S,
+}
+
+/// A request body that fails once it exceeds a `RequestLimits`' body limit.
+///
+/// Bodies without a limit are passed through unchanged.
+#[pin_project]
+#[derive(Debug)]
+pub struct LimitBody<B> {
+    #[pin]
+    inner: B,
+    limit: Option<BodyLimit>,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct BodyLimit {
+    remaining: u64,
+    limit: u64,
+}
+
+// === impl LimitRequests ===
+
+impl<S> LimitRequests<S> {
+    pub fn new(limits: RequestLimits, inner: S) -> Self {
+        Self { limits, inner }
+    }
+
+    pub fn layer(limits: RequestLimits) -> impl layer::Layer<S, Service = Self> + Clone {
+        layer::mk(move |inner| Self::new(limits, inner))
+    }
+}
+
+impl<B, S> Service<http::Request<B>> for LimitRequests<S>
+where
+    S: Service<http::Request<LimitBody<B>>>,
+    S::Error: Into<Error>,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = future::Either<
+        future::ErrInto<S::Future, Error>,
+        future::Ready<Result<Self::Response, Error>>,
+    >;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        let RequestLimits {
+            max_header_bytes,
+            max_body_bytes,
+        } = self.limits;
+
+        if let Some(limit) = max_header_bytes {
+            let size = header_bytes(req.headers());
+            if size > limit {
+                tracing::info!(size, limit, "Request headers too large");
+                return future::Either::Right(future::err(HeadersTooLarge { size, limit }.into()));
+            }
+        }
+
+        if let Some(limit) = max_body_bytes {
+            if matches!(content_length(req.headers()), Some(len) if len > limit) {
+                tracing::info!(limit, "Request body too large");
+                return future::Either::Right(future::err(BodyTooLarge { limit }.into()));
+            }
+        }
+        let req = req.map(|inner| LimitBody {
+            inner,
+            limit: max_body_bytes.map(|limit| BodyLimit {
+                remaining: limit,
+                limit,
+            }),
+        });
+
+        future::Either::Left(self.inner.call(req).err_into::<Error>())
+    }
+}
+
+/// Measures a header block as it would be encoded in HTTP/1, i.e. with a
+/// separator and line ending for each field.
+fn header_bytes(headers: &HeaderMap) -> usize {
+    headers
+        .iter()
+        .map(|(name, value)| name.as_str().len() + value.len() + 4)
+        .sum()
+}
+
+fn content_length(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get(http::header::CONTENT_LENGTH)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+}
+
+// === impl LimitBody ===
+
+impl<B> http_body::Body for LimitBody<B>
+where
+    B: http_body::Body,
+    B::Error: Into<Error>,
+{
+    type Data = B::Data;
+    type Error = Error;
+
+    #[inline]
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        use bytes::Buf;
+
+        let this = self.project();
+        let data = match futures::ready!(this.inner.poll_data(cx)) {
+            Some(Ok(data)) => data,
+            Some(Err(e)) => return Poll::Ready(Some(Err(e.into()))),
+            None => return Poll::Ready(None),
+        };
+
+        if let Some(BodyLimit { remaining, limit }) = this.limit {
+            let len = data.remaining() as u64;
+            if len > *remaining {
+                tracing::info!(limit = *limit, "Request body too large");
+                return Poll::Ready(Some(Err(BodyTooLarge { limit: *limit }.into())));
+            }
+            *remaining -= len;
+        }
+        Poll::Ready(Some(Ok(data)))
+    }
+
+    #[inline]
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        self.project().inner.poll_trailers(cx).map_err(Into::into)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_http_box::BoxBody;
+    use linkerd_stack::{service_fn, ServiceExt};
+
+    fn mk(
+        limits: RequestLimits,
+    ) -> LimitRequests<
+        impl Service<
+                http::Request<LimitBody<hyper::Body>>,
+                Response = http::Response<BoxBody>,
+                Error = Error,
+                Future = impl Send,
+            > + Clone,
+    > {
+        LimitRequests::new(
+            limits,
+            service_fn(|req: http::Request<LimitBody<hyper::Body>>| async move {
+                let body = hyper::body::to_bytes(req.into_body()).await?;
+                Ok::<_, Error>(http::Response::new(BoxBody::new(hyper::Body::from(body))))
+            }),
+        )
+    }
+
+    #[test]
+    fn rejects_large_headers() {
+        let svc = mk(RequestLimits {
+            max_header_bytes: Some(16),
+            ..Default::default()
+        });
+
+        let req = http::Request::builder()
+            .header("x-small", "ok")
+            .body(hyper::Body::empty())
+            .unwrap();
+        let rsp = tokio_test::block_on(svc.clone().oneshot(req));
+        assert!(rsp.is_ok());
+
+        let req = http::Request::builder()
+            .header("x-large", "0123456789")
+            .body(hyper::Body::empty())
+            .unwrap();
+        let err = tokio_test::block_on(svc.oneshot(req)).unwrap_err();
+        assert!(err.is::<HeadersTooLarge>(), "{}", err);
+    }
+
+    #[test]
+    fn rejects_large_content_length() {
+        let svc = mk(RequestLimits {
+            max_body_bytes: Some(4),
+            ..Default::default()
+        });
+        let req = http::Request::builder()
+            .header(http::header::CONTENT_LENGTH, "5")
+            .body(hyper::Body::from("hello"))
+            .unwrap();
+        let err = tokio_test::block_on(svc.oneshot(req)).unwrap_err();
+        assert!(err.is::<BodyTooLarge>(), "{}", err);
+    }
+
+    #[test]
+    fn passes_unlimited_bodies() {
+        let svc = mk(RequestLimits::default());
+        let req = http::Request::builder()
+            .header("x-large", "0123456789")
+            .body(hyper::Body::from("hello world"))
+            .unwrap();
+        let rsp = tokio_test::block_on(svc.oneshot(req));
+        assert!(rsp.is_ok());
+    }
+
+    #[test]
+    fn rejects_large_streamed_body() {
+        let svc = mk(RequestLimits {
+            max_body_bytes: Some(8),
+            ..Default::default()
+        });
+
+        let req = http::Request::new(hyper::Body::from("hello"));
+        let rsp = tokio_test::block_on(svc.clone().oneshot(req));
+        assert!(rsp.is_ok());
+
+        let req = http::Request::new(hyper::Body::from("hello world"));
+        let err = tokio_test::block_on(svc.oneshot(req)).unwrap_err();
+        assert!(err.is::<BodyTooLarge>(), "{}", err);
+    }
+}
@@ -3,26 +3,41 @@ use crate::{
     client_handle::SetClientHandle,
     glue::{HyperServerSvc, UpgradeBody},
     h2::Settings as H2Settings,
+    limits::RequestLimits,
     trace, upgrade, Version,
 };
+use futures::{future, ready, TryFuture};
 use linkerd_error::Error;
 use linkerd_io::{self as io, PeerAddr};
 use linkerd_stack::{layer, NewService, Param};
+use pin_project::pin_project;
+use rand::Rng;
 use std::{
     future::Future,
     pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     task::{Context, Poll},
+    time::Duration,
 };
+use tokio::time;
 use tower::Service;
 use tracing::debug;
 
 type Server = hyper::server::conn::Http<trace::Executor>;
 
+const DEFAULT_MAX_CONNECTION_AGE_GRACE: Duration = Duration::from_secs(30);
+
+const MIN_HTTP1_MAX_BUF_SIZE: usize = 8 * 1024;
+
 #[derive(Clone, Debug)]
 pub struct NewServeHttp<N> {
     inner: N,
     server: Server,
     drain: drain::Watch,
+    max_connection_age: Option<MaxAge>,
 }
 
 #[derive(Clone, Debug)]
@@ -31,39 +46,101 @@ pub struct ServeHttp<S> {
     server: Server,
     inner: S,
     drain: drain::Watch,
+    max_connection_age: Option<MaxAge>,
+}
+
+/// Limits how long an HTTP/2 connection is used.
+#[derive(Copy, Clone, Debug)]
+struct MaxAge {
+    age: Duration,
+    /// How long the connection may complete its in-flight requests once it has
+    /// reached its maximum age.
+    grace: Duration,
+}
+
+/// Asks HTTP/1 clients to close their connections once the server has begun
+/// to shut down the connection.
+///
+/// Hyper stops reusing a connection when it is shut down gracefully, but it
+/// does not tell the client, which may race to send another request on the
+/// connection.
+#[derive(Clone, Debug)]
+struct CloseOnDrain<S> {
+    inner: S,
+    draining: Arc<AtomicBool>,
+}
+
+#[pin_project]
+#[derive(Debug)]
+struct CloseOnDrainFuture<F> {
+    #[pin]
+    inner: F,
+    draining: Arc<AtomicBool>,
 }
 
 // === impl NewServeHttp ===
 
 impl<N> NewServeHttp<N> {
+    /// Builds servers with the given HTTP/2 settings.
+    ///
+    /// The request header limit bounds hyper's HTTP/1 read buffer and the
+    /// HTTP/2 header list size, so that oversized header blocks are rejected
+    /// before they are buffered. Other request limits are enforced by
+    /// `LimitRequests`.
     pub fn layer(
         h2: H2Settings,
+        limits: RequestLimits,
         drain: drain::Watch,
     ) -> impl layer::Layer<N, Service = Self> + Clone {
-        layer::mk(move |inner| Self::new(h2, inner, drain.clone()))
+        layer::mk(move |inner| Self::new(h2, limits, inner, drain.clone()))
     }
 
     /// Creates a new `ServeHttp`.
-    fn new(h2: H2Settings, inner: N, drain: drain::Watch) -> Self {
+    fn new(h2: H2Settings, limits: RequestLimits, inner: N, drain: drain::Watch) -> Self {
         let mut server = hyper::server::conn::Http::new().with_executor(trace::Executor::new());
         server
             .http2_initial_stream_window_size(h2.initial_stream_window_size)
             .http2_initial_connection_window_size(h2.initial_connection_window_size);
 
-        // Configure HTTP/2 PING frames
-        if let Some(timeout) = h2.keepalive_timeout {
-            // XXX(eliza): is this a reasonable interval between
-            // PING frames?
-            let interval = timeout / 4;
-            server
-                .http2_keep_alive_timeout(timeout)
-                .http2_keep_alive_interval(interval);
+        // Configure HTTP/2 PING frames. If only an interval is configured,
+        // hyper's default timeout is used.
+        if let Some(interval) = h2
+            .keepalive_interval
+            .or_else(|| h2.keepalive_timeout.map(|t| t / 4))
+        {
+            server.http2_keep_alive_interval(interval);
+            if let Some(timeout) = h2.keepalive_timeout {
+                server.http2_keep_alive_timeout(timeout);
+            }
+        }
+
+        if let Some(max) = h2.max_concurrent_streams {
+            server.http2_max_concurrent_streams(max);
+        }
+        let max_header_list_size = limits
+            .max_header_bytes
+            .map(|max| u32::try_from(max).unwrap_or(u32::MAX));
+        if let Some(max) = match (h2.max_header_list_size, max_header_list_size) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        } {
+            server.http2_max_header_list_size(max);
+        }
+        if let Some(max) = limits.max_header_bytes {
+            // Hyper requires that its buffer hold at least 8KB.
+            server.max_buf_size(max.max(MIN_HTTP1_MAX_BUF_SIZE));
         }
 
         Self {
             inner,
             server,
             drain,
+            max_connection_age: h2.max_connection_age.map(|age| MaxAge {
+                age,
+                grace: h2
+                    .max_connection_age_grace
+                    .unwrap_or(DEFAULT_MAX_CONNECTION_AGE_GRACE),
+            }),
         }
     }
 }
@@ -84,6 +161,7 @@ where
             version,
             server: self.server.clone(),
             drain: self.drain.clone(),
+            max_connection_age: self.max_connection_age,
         }
     }
 }
@@ -114,6 +192,7 @@ where
             inner,
             drain,
             mut server,
+            max_connection_age,
         } = self.clone();
         debug!(?version, "Handling as HTTP");
 
@@ -122,6 +201,12 @@ where
 
             match version {
                 Version::Http1 => {
+                    let draining = Arc::new(AtomicBool::new(false));
+                    let svc = CloseOnDrain {
+                        inner: svc,
+                        draining: draining.clone(),
+                    };
+
                     // Enable support for HTTP upgrades (CONNECT and websockets).
                     let mut conn = server
                         .http1_only(true)
@@ -134,11 +219,13 @@ where
                         }
                         shutdown = drain.signaled() => {
                             debug!("The process is shutting down the connection");
+                            draining.store(true, Ordering::Release);
                             Pin::new(&mut conn).graceful_shutdown();
                             shutdown.release_after(conn).await?;
                         }
                         () = closed => {
                             debug!("The stack is tearing down the connection");
+                            draining.store(true, Ordering::Release);
                             Pin::new(&mut conn).graceful_shutdown();
                             conn.await?;
                         }
@@ -148,6 +235,15 @@ where
                     let mut conn = server
                         .http2_only(true)
                         .serve_connection(io, HyperServerSvc::new(svc));
+                    let max_age = async move {
+                        match max_connection_age {
+                            Some(MaxAge { age, grace }) => {
+                                time::sleep(jitter(age)).await;
+                                grace
+                            }
+                            None => future::pending().await,
+                        }
+                    };
                     tokio::select! {
                         res = &mut conn => {
                             debug!(?res, "The client is shutting down the connection");
@@ -163,6 +259,17 @@ where
                             Pin::new(&mut conn).graceful_shutdown();
                             conn.await?;
                         }
+                        grace = max_age => {
+                            debug!("The connection has reached its maximum age");
+                            Pin::new(&mut conn).graceful_shutdown();
+                            // Dropping the connection closes it.
+                            match time::timeout(grace, conn).await {
+                                Ok(res) => res?,
+                                Err(_) => {
+                                    debug!(?grace, "The connection's grace period has elapsed")
+                                }
+                            }
+                        }
                     }
                 }
             }
@@ -170,3 +277,100 @@ where
         })
     }
 }
+
+/// Shortens a connection age by up to 10% so that connections that were
+/// accepted together are not all closed at the same time.
+fn jitter(age: Duration) -> Duration {
+    age.mul_f64(rand::thread_rng().gen_range(0.9..=1.0))
+}
+
+// === impl CloseOnDrain ===
+
+impl<S, B, RspB> Service<http::Request<B>> for CloseOnDrain<S>
+where
+    S: Service<http::Request<B>, Response = http::Response<RspB>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = CloseOnDrainFuture<S::Future>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    #[inline]
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        CloseOnDrainFuture {
+            inner: self.inner.call(req),
+            draining: self.draining.clone(),
+        }
+    }
+}
+
+// === impl CloseOnDrainFuture ===
+
+impl<F, B> Future for CloseOnDrainFuture<F>
+where
+    F: TryFuture<Ok = http::Response<B>>,
+{
+    type Output = Result<F::Ok, F::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut rsp = ready!(this.inner.try_poll(cx))?;
+        // Upgraded connections are not reused, so there's no need to close them.
+        if this.draining.load(Ordering::Acquire)
+            && rsp.status() != http::StatusCode::SWITCHING_PROTOCOLS
+        {
+            rsp.headers_mut().insert(
+                http::header::CONNECTION,
+                http::HeaderValue::from_static("close"),
+            );
+        }
+        Poll::Ready(Ok(rsp))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jitter_shortens_age() {
+        let age = Duration::from_secs(60);
+        for _ in 0..100 {
+            let jittered = jitter(age);
+            assert!(jittered <= age, "{:?} must not exceed {:?}", jittered, age);
+            assert!(jittered >= age.mul_f64(0.9), "{:?} is too short", jittered);
+        }
+    }
+
+    #[test]
+    fn closes_http1_connections_when_draining() {
+        let draining = Arc::new(AtomicBool::new(false));
+        let mut svc = CloseOnDrain {
+            inner: linkerd_stack::service_fn(|req: http::Request<()>| async move {
+                let mut rsp = http::Response::new(());
+                if req.uri().path() == "/upgrade" {
+                    *rsp.status_mut() = http::StatusCode::SWITCHING_PROTOCOLS;
+                }
+                Ok::<_, Error>(rsp)
+            }),
+            draining: draining.clone(),
+        };
+        let mut call = |path: &str| {
+            let req = http::Request::get(path).body(()).unwrap();
+            tokio_test::block_on(svc.call(req)).unwrap()
+        };
+
+        let rsp = call("/");
+        assert!(rsp.headers().get(http::header::CONNECTION).is_none());
+
+        draining.store(true, Ordering::Release);
+        let rsp = call("/");
+        assert_eq!(rsp.headers()[http::header::CONNECTION], "close");
+        let rsp = call("/upgrade");
+        assert!(rsp.headers().get(http::header::CONNECTION).is_none());
+    }
+}
@@ -1,4 +1,4 @@
-use linkerd_metrics::{metrics, Counter, FmtMetrics, Gauge};
+use linkerd_metrics::{metrics, Counter, Encoder, FmtMetrics, Gauge};
 use parking_lot::Mutex;
 use std::{
     fmt,
@@ -39,7 +39,7 @@ impl Metrics {
 }
 
 impl FmtMetrics for Metrics {
-    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fn fmt_metrics(&self, f: &mut Encoder<'_>) -> fmt::Result {
         if let Ok(dur) = self.expiry.lock().duration_since(UNIX_EPOCH) {
             identity_cert_expiration_timestamp_seconds.fmt_help(f)?;
             identity_cert_expiration_timestamp_seconds
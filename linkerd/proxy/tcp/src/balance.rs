@@ -1,4 +1,5 @@
 use linkerd_error::Error;
+pub use linkerd_proxy_discover::{Loads, RecordLoad};
 use linkerd_stack::layer;
 use rand::thread_rng;
 use std::{hash::Hash, time::Duration};
@@ -26,3 +27,26 @@ where
         Balance::from_rng(loaded, &mut thread_rng()).expect("RNG must be valid")
     })
 }
+
+/// Like `layer`, but balances over a discovery stream that is accompanied by
+/// `Loads` in which each endpoint's load is recorded.
+pub fn layer_recorded<T, D>(
+    default_rtt: Duration,
+    decay: Duration,
+) -> impl tower::layer::Layer<
+    (D, Loads<D::Key>),
+    Service = Balance<RecordLoad<PeakEwmaDiscover<D, CompleteOnResponse>, D::Key>, T>,
+> + Clone
+where
+    D: Discover,
+    D::Key: Clone + Eq + Hash,
+    D::Service: tower::Service<T>,
+    <D::Service as tower::Service<T>>::Error: Into<Error>,
+{
+    layer::mk(move |(discover, loads)| {
+        let loaded =
+            PeakEwmaDiscover::new(discover, default_rtt, decay, CompleteOnResponse::default());
+        Balance::from_rng(RecordLoad::new(loaded, loads), &mut thread_rng())
+            .expect("RNG must be valid")
+    })
+}
@@ -0,0 +1,310 @@
+use super::Authorization;
+use regex::Regex;
+use std::{borrow::Cow, sync::Arc};
+
+/// Authorizes requests that match an HTTP route.
+///
+/// Requests that match a route are authorized by the route's authorizations
+/// instead of the server's.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HttpRoute {
+    /// The route applies to requests that match any of these matches.
+    pub matches: Vec<HttpRouteMatch>,
+    pub authorizations: Vec<Authorization>,
+    pub kind: Arc<str>,
+    pub name: Arc<str>,
+}
+
+/// Matches requests that satisfy all of the specified criteria.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HttpRouteMatch {
+    pub path: Option<PathMatch>,
+    pub method: Option<http::Method>,
+    pub headers: Vec<HeaderMatch>,
+}
+
+#[derive(Clone, Debug)]
+pub enum PathMatch {
+    Exact(String),
+
+    /// Matches paths that begin with the given path segments. For example,
+    /// `/foo` matches `/foo` and `/foo/bar`, but not `/foobar`.
+    Prefix(String),
+
+    Regex(Regex),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HeaderMatch {
+    pub name: http::header::HeaderName,
+    pub value: HeaderValueMatch,
+}
+
+#[derive(Clone, Debug)]
+pub enum HeaderValueMatch {
+    Exact(http::HeaderValue),
+    Regex(Regex),
+}
+
+// === impl HttpRoute ===
+
+impl HttpRoute {
+    pub fn matches<B>(&self, req: &http::Request<B>) -> bool {
+        self.matches.iter().any(|m| m.matches(req))
+    }
+}
+
+// === impl HttpRouteMatch ===
+
+impl HttpRouteMatch {
+    pub fn matches<B>(&self, req: &http::Request<B>) -> bool {
+        if let Some(method) = self.method.as_ref() {
+            if req.method() != method {
+                return false;
+            }
+        }
+
+        if let Some(path) = self.path.as_ref() {
+            if !path.matches(&normalize_path(req.uri().path())) {
+                return false;
+            }
+        }
+
+        self.headers.iter().all(|h| h.matches(req.headers()))
+    }
+}
+
+// === impl PathMatch ===
+
+impl PathMatch {
+    pub fn matches(&self, path: &str) -> bool {
+        match self {
+            Self::Exact(p) => path == p,
+            Self::Prefix(prefix) => {
+                let prefix = prefix.trim_end_matches('/');
+                match path.strip_prefix(prefix) {
+                    Some(rest) => rest.is_empty() || rest.starts_with('/'),
+                    None => false,
+                }
+            }
+            Self::Regex(re) => re.is_match(path),
+        }
+    }
+}
+
+impl PartialEq for PathMatch {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Exact(a), Self::Exact(b)) => a == b,
+            (Self::Prefix(a), Self::Prefix(b)) => a == b,
+            (Self::Regex(a), Self::Regex(b)) => a.as_str() == b.as_str(),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for PathMatch {}
+
+/// Normalizes a request path so that equivalent paths match the same routes:
+/// percent-encoded unreserved characters are decoded, dot segments are
+/// removed, and repeated slashes are collapsed.
+///
+/// Otherwise, a path like `//admin`, `/%61dmin` or `/x/../admin` would not
+/// match a route for `/admin`, though a server would handle it as `/admin`.
+fn normalize_path(path: &str) -> Cow<'_, str> {
+    let needs_normalization =
+        path.contains('%') || path.contains("//") || path.split('/').any(|s| s == "." || s == "..");
+    if !path.starts_with('/') || !needs_normalization {
+        return Cow::Borrowed(path);
+    }
+
+    let decoded = decode_unreserved(path);
+    let mut segments = Vec::new();
+    let mut trailing_slash = false;
+    for segment in decoded.split('/') {
+        trailing_slash = matches!(segment, "" | "." | "..");
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    let mut normalized = String::with_capacity(decoded.len());
+    for segment in segments.iter() {
+        normalized.push('/');
+        normalized.push_str(segment);
+    }
+    if trailing_slash || normalized.is_empty() {
+        normalized.push('/');
+    }
+    Cow::Owned(normalized)
+}
+
+/// Decodes percent-encoded unreserved characters (as defined by RFC 3986).
+/// Other percent-encodings are preserved with uppercase hex digits.
+fn decode_unreserved(path: &str) -> String {
+    let mut decoded = String::with_capacity(path.len());
+    let mut rest = path;
+    while let Some(i) = rest.find('%') {
+        decoded.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let escape = rest
+            .get(1..3)
+            .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()));
+        match escape {
+            Some(hex) => {
+                let c = u8::from_str_radix(hex, 16).expect("must be hex") as char;
+                if c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_' | '~') {
+                    decoded.push(c);
+                } else {
+                    decoded.push('%');
+                    decoded.push_str(&hex.to_ascii_uppercase());
+                }
+                rest = &rest[3..];
+            }
+            None => {
+                decoded.push('%');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+// === impl HeaderMatch ===
+
+impl HeaderMatch {
+    /// Matches if any of the header's values match.
+    pub fn matches(&self, headers: &http::HeaderMap) -> bool {
+        headers
+            .get_all(&self.name)
+            .iter()
+            .any(|v| self.value.matches(v))
+    }
+}
+
+// === impl HeaderValueMatch ===
+
+impl HeaderValueMatch {
+    pub fn matches(&self, value: &http::HeaderValue) -> bool {
+        match self {
+            Self::Exact(v) => value == v,
+            Self::Regex(re) => value.to_str().map(|v| re.is_match(v)).unwrap_or(false),
+        }
+    }
+}
+
+impl PartialEq for HeaderValueMatch {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Exact(a), Self::Exact(b)) => a == b,
+            (Self::Regex(a), Self::Regex(b)) => a.as_str() == b.as_str(),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for HeaderValueMatch {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn req(method: http::Method, uri: &str) -> http::Request<()> {
+        http::Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("x-env", "prod")
+            .body(())
+            .unwrap()
+    }
+
+    #[test]
+    fn path_prefix_matches_segments() {
+        let m = PathMatch::Prefix("/admin".to_string());
+        assert!(m.matches("/admin"));
+        assert!(m.matches("/admin/"));
+        assert!(m.matches("/admin/users"));
+        assert!(!m.matches("/administrator"));
+        assert!(!m.matches("/api"));
+
+        let m = PathMatch::Prefix("/".to_string());
+        assert!(m.matches("/"));
+        assert!(m.matches("/api"));
+    }
+
+    #[test]
+    fn normalizes_paths() {
+        assert_eq!(normalize_path("/admin/users"), "/admin/users");
+        assert_eq!(normalize_path("//admin//users"), "/admin/users");
+        assert_eq!(normalize_path("/%61dmin/%7Eusers"), "/admin/~users");
+        assert_eq!(normalize_path("/admin%2fusers%3a"), "/admin%2Fusers%3A");
+        assert_eq!(normalize_path("/x/../admin"), "/admin");
+        assert_eq!(normalize_path("/./admin/./users/."), "/admin/users/");
+        assert_eq!(normalize_path("/admin/%2e%2e/api"), "/api");
+        assert_eq!(normalize_path("/../.."), "/");
+        assert_eq!(normalize_path("/100%"), "/100%");
+        assert_eq!(normalize_path("/%zz/%"), "/%zz/%");
+    }
+
+    #[test]
+    fn path_normalization_prevents_bypass() {
+        let m = HttpRouteMatch {
+            path: Some(PathMatch::Prefix("/admin".to_string())),
+            ..Default::default()
+        };
+        for uri in [
+            "http://example.com/admin",
+            "http://example.com//admin",
+            "http://example.com/%61dmin",
+            "http://example.com/x/../admin",
+            "http://example.com/./admin/users",
+            "http://example.com/%2e/admin",
+        ] {
+            assert!(
+                m.matches(&req(http::Method::GET, uri)),
+                "{} must match",
+                uri
+            );
+        }
+        assert!(!m.matches(&req(http::Method::GET, "http://example.com/admin/../api")));
+
+        let m = HttpRouteMatch {
+            path: Some(PathMatch::Exact("/admin".to_string())),
+            ..Default::default()
+        };
+        assert!(m.matches(&req(http::Method::GET, "http://example.com///admin")));
+        assert!(m.matches(&req(
+            http::Method::GET,
+            "http://example.com/ADMIN/../%61dmin"
+        )));
+    }
+
+    #[test]
+    fn route_match_requires_all_criteria() {
+        let m = HttpRouteMatch {
+            path: Some(PathMatch::Regex(Regex::new("^/api/v[0-9]+/").unwrap())),
+            method: Some(http::Method::POST),
+            headers: vec![HeaderMatch {
+                name: http::header::HeaderName::from_static("x-env"),
+                value: HeaderValueMatch::Exact(http::HeaderValue::from_static("prod")),
+            }],
+        };
+        assert!(m.matches(&req(http::Method::POST, "http://example.com/api/v1/foo")));
+        assert!(!m.matches(&req(http::Method::GET, "http://example.com/api/v1/foo")));
+        assert!(!m.matches(&req(http::Method::POST, "http://example.com/api/foo")));
+
+        let m = HttpRouteMatch {
+            headers: vec![HeaderMatch {
+                name: http::header::HeaderName::from_static("x-env"),
+                value: HeaderValueMatch::Regex(Regex::new("^stag").unwrap()),
+            }],
+            ..Default::default()
+        };
+        assert!(!m.matches(&req(http::Method::GET, "http://example.com/")));
+    }
+}
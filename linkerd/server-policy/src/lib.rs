@@ -6,10 +6,14 @@
 )]
 #![forbid(unsafe_code)]
 
+mod http_route;
 mod network;
 
-pub use self::network::Network;
-use std::{collections::HashSet, hash::Hash, sync::Arc, time};
+pub use self::{
+    http_route::{HeaderMatch, HeaderValueMatch, HttpRoute, HttpRouteMatch, PathMatch},
+    network::Network,
+};
+use std::{collections::HashSet, fmt, hash::Hash, path::PathBuf, sync::Arc, time};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct ServerPolicy {
@@ -17,6 +21,8 @@ pub struct ServerPolicy {
     pub authorizations: Vec<Authorization>,
     pub kind: Arc<str>,
     pub name: Arc<str>,
+    pub http_routes: Vec<HttpRoute>,
+    pub rate_limit: Option<RateLimit>,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
@@ -45,6 +51,34 @@ pub enum Authentication {
         identities: HashSet<String>,
         suffixes: Vec<Suffix>,
     },
+
+    /// Authenticates HTTP requests that carry a bearer token signed by a key
+    /// in the JWKS file at `jwks_path`. Connections are never authenticated by
+    /// tokens.
+    Jwt {
+        jwks_path: PathBuf,
+        issuer: Option<String>,
+        audiences: Vec<String>,
+    },
+}
+
+/// Limits the rate at which a server admits requests.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RateLimit {
+    /// Limits all requests to the server.
+    pub total: Option<Limit>,
+
+    /// Limits requests from each client identity. Clients without an identity
+    /// share a single limit.
+    pub identity: Option<Limit>,
+}
+
+/// A token bucket that refills at `requests_per_second` and holds at most
+/// `burst` tokens.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Limit {
+    pub requests_per_second: u32,
+    pub burst: u32,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -72,6 +106,13 @@ impl Suffix {
     }
 }
 
+/// Formats the suffix as a wildcard pattern, e.g. `*.cluster.local`.
+impl fmt::Display for Suffix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "*{}", self.ends_with)
+    }
+}
+
 #[cfg(test)]
 mod network_tests {
     use super::Network;
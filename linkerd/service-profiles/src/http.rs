@@ -1,6 +1,8 @@
 mod proxy;
 mod service;
 
+use linkerd_addr::NameAddr;
+use linkerd_exp_backoff::ExponentialBackoff;
 use regex::Regex;
 use std::{
     fmt,
@@ -19,6 +21,9 @@ pub struct Route {
     response_classes: ResponseClasses,
     retries: Option<Retries>,
     timeout: Option<Duration>,
+    mirror: Option<Mirror>,
+    hedge: Option<Hedge>,
+    trace_sampling: Option<TraceSampling>,
 }
 
 #[derive(Clone, Debug)]
@@ -50,9 +55,38 @@ pub enum ResponseMatch {
     },
 }
 
+/// Configures a route to send a copy of a portion of its requests to another
+/// destination. Responses to mirrored requests are discarded.
+#[derive(Clone, Debug)]
+pub struct Mirror {
+    addr: NameAddr,
+    ratio: f32,
+}
+
+/// Configures when a route sends a second attempt for a request that has not
+/// yet received a response. Hedges draw from the route's retry budget.
+#[derive(Clone, Copy, Debug)]
+pub enum Hedge {
+    /// Hedge requests that have not completed within a fixed latency.
+    Latency(Duration),
+
+    /// Hedge requests that have not completed within the given percentile
+    /// (between 0.0 and 1.0) of recently observed latencies.
+    Percentile(f32),
+}
+
+/// Overrides the ratio of the traces started by the proxy that are sampled for
+/// requests on a route.
+#[derive(Clone, Copy, Debug)]
+pub struct TraceSampling {
+    ratio: f32,
+}
+
 #[derive(Clone, Debug)]
 pub struct Retries {
     budget: Arc<Budget>,
+    max_attempts: Option<usize>,
+    backoff: Option<ExponentialBackoff>,
 }
 
 #[derive(Clone, Default)]
@@ -84,6 +118,9 @@ impl Route {
             response_classes: ResponseClasses(response_classes.into()),
             retries: None,
             timeout: None,
+            mirror: None,
+            hedge: None,
+            trace_sampling: None,
         }
     }
 
@@ -103,13 +140,48 @@ impl Route {
         self.timeout
     }
 
-    pub fn set_retries(&mut self, budget: Arc<Budget>) {
-        self.retries = Some(Retries { budget });
+    pub fn set_retries(&mut self, retries: Retries) {
+        self.retries = Some(retries);
     }
 
     pub fn set_timeout(&mut self, timeout: Duration) {
         self.timeout = Some(timeout);
     }
+
+    pub fn mirror(&self) -> Option<&Mirror> {
+        self.mirror.as_ref()
+    }
+
+    /// Mirrors the given ratio of requests (between 0.0 and 1.0) to `addr`.
+    pub fn set_mirror(&mut self, addr: NameAddr, ratio: f32) {
+        self.mirror = Some(Mirror {
+            addr,
+            ratio: ratio.clamp(0.0, 1.0),
+        });
+    }
+
+    pub fn hedge(&self) -> Option<Hedge> {
+        self.hedge
+    }
+
+    pub fn set_hedge(&mut self, hedge: Hedge) {
+        self.hedge = Some(match hedge {
+            Hedge::Percentile(p) => Hedge::Percentile(p.clamp(0.0, 1.0)),
+            latency => latency,
+        });
+    }
+
+    pub fn trace_sampling(&self) -> Option<TraceSampling> {
+        self.trace_sampling
+    }
+
+    /// Samples the given ratio (between 0.0 and 1.0) of the traces that the
+    /// proxy starts for requests on this route.
+    pub fn set_trace_sampling(&mut self, ratio: f32) {
+        self.trace_sampling = Some(TraceSampling {
+            ratio: ratio.clamp(0.0, 1.0),
+        });
+    }
 }
 
 // === impl RequestMatch ===
@@ -187,17 +259,125 @@ impl ResponseMatch {
     }
 }
 
+// === impl Mirror ===
+
+impl Mirror {
+    pub fn addr(&self) -> &NameAddr {
+        &self.addr
+    }
+
+    pub fn ratio(&self) -> f32 {
+        self.ratio
+    }
+}
+
+impl PartialEq for Mirror {
+    fn eq(&self, other: &Self) -> bool {
+        self.addr == other.addr && self.ratio.to_bits() == other.ratio.to_bits()
+    }
+}
+
+impl Eq for Mirror {}
+
+impl Hash for Mirror {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.addr.hash(state);
+        state.write_u32(self.ratio.to_bits());
+    }
+}
+
+// === impl TraceSampling ===
+
+impl TraceSampling {
+    pub fn ratio(&self) -> f32 {
+        self.ratio
+    }
+}
+
+impl PartialEq for TraceSampling {
+    fn eq(&self, other: &Self) -> bool {
+        self.ratio.to_bits() == other.ratio.to_bits()
+    }
+}
+
+impl Eq for TraceSampling {}
+
+impl Hash for TraceSampling {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u32(self.ratio.to_bits());
+    }
+}
+
+// === impl Hedge ===
+
+impl PartialEq for Hedge {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Hedge::Latency(a), Hedge::Latency(b)) => a == b,
+            (Hedge::Percentile(a), Hedge::Percentile(b)) => a.to_bits() == b.to_bits(),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Hedge {}
+
+impl Hash for Hedge {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Hedge::Latency(l) => l.hash(state),
+            Hedge::Percentile(p) => state.write_u32(p.to_bits()),
+        }
+    }
+}
+
 // === impl Retries ===
 
 impl Retries {
+    pub fn new(budget: Arc<Budget>) -> Self {
+        Self {
+            budget,
+            max_attempts: None,
+            backoff: None,
+        }
+    }
+
+    /// Limits the number of times a request may be sent, including the
+    /// original request.
+    pub fn with_max_attempts(self, max_attempts: usize) -> Self {
+        Self {
+            max_attempts: Some(max_attempts),
+            ..self
+        }
+    }
+
+    /// Delays each retry according to the given backoff.
+    pub fn with_backoff(self, backoff: ExponentialBackoff) -> Self {
+        Self {
+            backoff: Some(backoff),
+            ..self
+        }
+    }
+
     pub fn budget(&self) -> &Arc<Budget> {
         &self.budget
     }
+
+    pub fn max_attempts(&self) -> Option<usize> {
+        self.max_attempts
+    }
+
+    pub fn backoff(&self) -> Option<&ExponentialBackoff> {
+        self.backoff.as_ref()
+    }
 }
 
 impl PartialEq for Retries {
     fn eq(&self, other: &Self) -> bool {
         Arc::ptr_eq(&self.budget, &other.budget)
+            && self.max_attempts == other.max_attempts
+            && self.backoff == other.backoff
     }
 }
 
@@ -206,6 +386,7 @@ impl Eq for Retries {}
 impl Hash for Retries {
     fn hash<H: Hasher>(&self, state: &mut H) {
         state.write_usize(Arc::as_ref(&self.budget) as *const _ as usize);
+        self.max_attempts.hash(state);
     }
 }
 
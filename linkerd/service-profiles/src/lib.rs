@@ -150,6 +150,11 @@ impl Receiver {
     fn targets(&self) -> Vec<Target> {
         self.inner.borrow().targets.clone()
     }
+
+    /// Returns a copy of the most recently received profile.
+    pub fn profile(&self) -> Profile {
+        self.inner.borrow().clone()
+    }
 }
 
 // === impl ReceiverStream ===
@@ -74,7 +74,7 @@ fn set_route_retry(route: &mut http::Route, retry_budget: Option<&Arc<Budget>>)
         }
     };
 
-    route.set_retries(budget);
+    route.set_retries(http::Retries::new(budget));
 }
 
 fn set_route_timeout(route: &mut http::Route, timeout: Result<Duration, Duration>) {
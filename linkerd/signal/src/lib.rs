@@ -13,11 +13,57 @@ pub async fn shutdown() {
     imp::shutdown().await
 }
 
+/// Receives requests to reload the proxy's configuration.
+#[derive(Debug)]
+pub struct Reload(imp::Reload);
+
+// === impl Reload ===
+
+impl Reload {
+    /// Listens for reload requests.
+    ///
+    /// On Unix, a reload is requested by sending the proxy a `SIGHUP`. This
+    /// must be called from within a Tokio runtime.
+    pub fn new() -> Self {
+        Self(imp::Reload::new())
+    }
+
+    /// Completes when the next reload is requested.
+    pub async fn recv(&mut self) {
+        self.0.recv().await
+    }
+}
+
+impl Default for Reload {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(unix)]
 mod imp {
-    use tokio::signal::unix::{signal, SignalKind};
+    use tokio::signal::unix::{signal, Signal, SignalKind};
     use tracing::info;
 
+    #[derive(Debug)]
+    pub(super) struct Reload(Signal);
+
+    impl Reload {
+        pub(super) fn new() -> Self {
+            Self(signal(SignalKind::hangup()).expect("Failed to register signal handler"))
+        }
+
+        pub(super) async fn recv(&mut self) {
+            // The stream of signals never ends.
+            self.0.recv().await;
+            info!(
+                // use target to remove 'imp' from output
+                target: "linkerd_proxy::signal",
+                "received SIGHUP, reloading configuration",
+            );
+        }
+    }
+
     pub(super) async fn shutdown() {
         tokio::select! {
             // SIGINT  - To allow Ctrl-c to emulate SIGTERM while developing.
@@ -47,6 +93,20 @@ mod imp {
 mod imp {
     use tracing::info;
 
+    /// Windows has no equivalent of `SIGHUP`, so reloads are never requested.
+    #[derive(Debug)]
+    pub(super) struct Reload(());
+
+    impl Reload {
+        pub(super) fn new() -> Self {
+            Self(())
+        }
+
+        pub(super) async fn recv(&mut self) {
+            std::future::pending::<()>().await
+        }
+    }
+
     pub(super) async fn shutdown() {
         // On Windows, we don't have all the signals, but Windows also
         // isn't our expected deployment target. This implementation allows
@@ -11,7 +11,7 @@ mod service;
 
 pub use self::layer::TrackServiceLayer;
 pub use self::service::TrackService;
-use linkerd_metrics::{metrics, Counter, FmtLabels, FmtMetrics};
+use linkerd_metrics::{metrics, Counter, Encoder, FmtLabels, FmtMetrics};
 use parking_lot::Mutex;
 use std::{collections::HashMap, fmt, hash::Hash, sync::Arc};
 
@@ -65,7 +65,7 @@ impl<L: Hash + Eq> Clone for Registry<L> {
 }
 
 impl<L: FmtLabels + Hash + Eq> FmtMetrics for Registry<L> {
-    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fn fmt_metrics(&self, f: &mut Encoder<'_>) -> fmt::Result {
         let metrics = self.0.lock();
         if metrics.is_empty() {
             return Ok(());
@@ -7,24 +7,33 @@
 #![forbid(unsafe_code)]
 
 mod propagation;
+mod sampler;
 mod service;
 
-pub use self::service::TraceContext;
+pub use self::{
+    propagation::{InvalidPropagation, Propagation, Propagations},
+    sampler::{HeadSampled, NewRouteSampling, RouteSampleRatio, RouteSampling, Sampler},
+    service::TraceContext,
+};
 use bytes::Bytes;
 use linkerd_error::Error;
+use parking_lot::Mutex;
 use rand::Rng;
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::fmt;
+use std::sync::Arc;
 use std::time::SystemTime;
 use thiserror::Error;
 
 const SPAN_ID_LEN: usize = 8;
+const TRACE_ID_LEN: usize = 16;
 
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct Id(Vec<u8>);
 
-#[derive(Debug, Default)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
 pub struct Flags(u8);
 
 #[derive(Debug, Error)]
@@ -39,9 +48,29 @@ pub struct Span {
     pub span_name: String,
     pub start: SystemTime,
     pub end: SystemTime,
-    pub labels: HashMap<&'static str, String>,
+    pub labels: HashMap<Cow<'static, str>, String>,
 }
 
+/// Identifies the span that the proxy records for a request.
+///
+/// `TraceContext` sets this as a request extension, so that the services
+/// handling the request may refer to its trace (e.g. in metric exemplars).
+#[derive(Clone, Debug)]
+pub struct SpanContext {
+    trace_id: Id,
+    span_id: Id,
+    head: Option<HeadSampled>,
+}
+
+/// Labels that the services handling a request record on its span.
+///
+/// `TraceContext` sets this as a request extension for each span that it
+/// records. A span's labels start with the labels recorded on its parent span
+/// so far; labels recorded afterwards only apply to the span that recorded
+/// them.
+#[derive(Clone, Debug, Default)]
+pub struct SpanLabels(Arc<Mutex<HashMap<Cow<'static, str>, String>>>);
+
 pub trait SpanSink {
     fn is_enabled(&self) -> bool;
 
@@ -68,6 +97,22 @@ impl Id {
         rng.fill(bytes.as_mut_slice());
         Self(bytes)
     }
+
+    fn new_trace_id<R: Rng>(rng: &mut R) -> Self {
+        let mut bytes = vec![0; TRACE_ID_LEN];
+        rng.fill(bytes.as_mut_slice());
+        Self(bytes)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<Vec<u8>> for Id {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
 }
 
 impl From<Id> for Vec<u8> {
@@ -97,6 +142,48 @@ impl From<Bytes> for Id {
     }
 }
 
+// === impl SpanContext ===
+
+impl SpanContext {
+    pub fn trace_id(&self) -> &Id {
+        &self.trace_id
+    }
+
+    pub fn span_id(&self) -> &Id {
+        &self.span_id
+    }
+
+    /// Returns true if the span is exported. A route may revise the sampling
+    /// decision for a trace that the proxy started.
+    pub fn is_sampled(&self) -> bool {
+        self.head
+            .as_ref()
+            .map(HeadSampled::is_sampled)
+            .unwrap_or(true)
+    }
+}
+
+// === impl SpanLabels ===
+
+impl SpanLabels {
+    pub fn insert(&self, key: impl Into<Cow<'static, str>>, value: impl Into<String>) {
+        self.0.lock().insert(key.into(), value.into());
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.0.lock().get(key).cloned()
+    }
+
+    /// Returns a new set of labels for a child span.
+    pub fn child(&self) -> Self {
+        Self(Arc::new(Mutex::new(self.0.lock().clone())))
+    }
+
+    fn take(&self) -> HashMap<Cow<'static, str>, String> {
+        std::mem::take(&mut *self.0.lock())
+    }
+}
+
 // === impl Flags ===
 
 impl Flags {
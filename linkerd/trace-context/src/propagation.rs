@@ -3,6 +3,7 @@ use bytes::Bytes;
 use http::header::HeaderValue;
 use linkerd_error::Error;
 use rand::thread_rng;
+use std::{str::FromStr, sync::Arc};
 use thiserror::Error;
 use tracing::{trace, warn};
 
@@ -10,17 +11,44 @@ const HTTP_TRACE_ID_HEADER: &str = "x-b3-traceid";
 const HTTP_SPAN_ID_HEADER: &str = "x-b3-spanid";
 const HTTP_SAMPLED_HEADER: &str = "x-b3-sampled";
 
+const W3C_TRACEPARENT_HEADER: &str = "traceparent";
+const W3C_VERSION: u8 = 0;
+const W3C_INVALID_VERSION: u8 = 0xff;
+const W3C_SAMPLED_FLAG: u8 = 0x01;
+
 const GRPC_TRACE_HEADER: &str = "grpc-trace-bin";
 const GRPC_TRACE_FIELD_TRACE_ID: u8 = 0;
 const GRPC_TRACE_FIELD_SPAN_ID: u8 = 1;
 const GRPC_TRACE_FIELD_TRACE_OPTIONS: u8 = 2;
 
-#[derive(Debug)]
+/// A format used to propagate trace contexts in request headers.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Propagation {
-    Http,
+    /// The `x-b3-*` headers.
+    B3,
+
+    /// The W3C Trace Context `traceparent` header. The `tracestate` header is
+    /// passed through unmodified.
+    W3C,
+
+    /// The binary `grpc-trace-bin` header.
     Grpc,
 }
 
+/// The order in which propagation formats are read from a request. When a
+/// request carries multiple formats, the first one found is used.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Propagations(Arc<[Propagation]>);
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum InvalidPropagation {
+    #[error("unknown trace propagation format: {0}")]
+    Unknown(String),
+
+    #[error("no trace propagation formats specified")]
+    Empty,
+}
+
 #[derive(Debug)]
 pub struct TraceContext {
     pub propagation: Propagation,
@@ -41,8 +69,67 @@ impl TraceContext {
     }
 }
 
-pub fn unpack_trace_context<B>(request: &http::Request<B>) -> Option<TraceContext> {
-    unpack_grpc_trace_context(request).or_else(|| unpack_http_trace_context(request))
+// === impl Propagations ===
+
+impl Propagations {
+    pub fn iter(&self) -> impl Iterator<Item = Propagation> + '_ {
+        self.0.iter().copied()
+    }
+
+    /// Returns the most preferred format, which is used to propagate traces
+    /// that the proxy starts.
+    pub fn preferred(&self) -> Propagation {
+        self.0[0]
+    }
+}
+
+/// By default, the gRPC binary format is preferred, followed by B3 and then
+/// W3C Trace Context.
+impl Default for Propagations {
+    fn default() -> Self {
+        Self(Arc::new([
+            Propagation::Grpc,
+            Propagation::B3,
+            Propagation::W3C,
+        ]))
+    }
+}
+
+/// Parses a comma-separated list of formats: `b3`, `w3c`, or `grpc`.
+impl FromStr for Propagations {
+    type Err = InvalidPropagation;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut formats = Vec::new();
+        for name in s.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            let format = match name {
+                n if n.eq_ignore_ascii_case("b3") => Propagation::B3,
+                n if n.eq_ignore_ascii_case("w3c") => Propagation::W3C,
+                n if n.eq_ignore_ascii_case("grpc") => Propagation::Grpc,
+                n => return Err(InvalidPropagation::Unknown(n.to_string())),
+            };
+            if !formats.contains(&format) {
+                formats.push(format);
+            }
+        }
+        if formats.is_empty() {
+            return Err(InvalidPropagation::Empty);
+        }
+        Ok(Self(formats.into()))
+    }
+}
+
+pub fn unpack_trace_context<B>(
+    request: &http::Request<B>,
+    propagations: &Propagations,
+) -> Option<TraceContext> {
+    propagations
+        .iter()
+        .find_map(|propagation| match propagation {
+            Propagation::Grpc => unpack_grpc_trace_context(request),
+            Propagation::B3 => unpack_http_trace_context(request),
+            Propagation::W3C => unpack_w3c_trace_context(request),
+        })
 }
 
 // Generates a new span id, writes it to the request in the appropriate
@@ -50,7 +137,32 @@ pub fn unpack_trace_context<B>(request: &http::Request<B>) -> Option<TraceContex
 pub fn increment_span_id<B>(request: &mut http::Request<B>, context: &TraceContext) -> Id {
     match context.propagation {
         Propagation::Grpc => increment_grpc_span_id(request, context),
-        Propagation::Http => increment_http_span_id(request),
+        Propagation::B3 => increment_http_span_id(request),
+        Propagation::W3C => increment_w3c_span_id(request, context),
+    }
+}
+
+/// Returns the context for a new trace with a random trace ID and no parent.
+pub fn new_trace_context(propagation: Propagation, sampled: bool) -> TraceContext {
+    TraceContext {
+        propagation,
+        trace_id: Id::new_trace_id(&mut thread_rng()),
+        parent_id: Id::default(),
+        flags: Flags(u8::from(sampled)),
+    }
+}
+
+/// Writes the entire trace context, including its trace ID and sampling
+/// decision, to the request in the context's propagation format.
+pub fn write_trace_context<B>(
+    request: &mut http::Request<B>,
+    context: &TraceContext,
+    span_id: &Id,
+) {
+    match context.propagation {
+        Propagation::Grpc => write_grpc_trace_context(request, context, span_id),
+        Propagation::B3 => write_http_trace_context(request, context, span_id),
+        Propagation::W3C => write_w3c_trace_context(request, context, span_id),
     }
 }
 
@@ -137,14 +249,23 @@ fn parse_grpc_trace_context_field(
     Ok(())
 }
 
-// This code looks significantly weirder if some of the elements are added using
-// the `vec![]` macro, despite clippy's suggestions otherwise...
-#[allow(clippy::vec_init_then_push)]
 fn increment_grpc_span_id<B>(request: &mut http::Request<B>, context: &TraceContext) -> Id {
     let span_id = Id::new_span_id(&mut thread_rng());
 
     trace!(message = "incremented span id", %span_id);
 
+    write_grpc_trace_context(request, context, &span_id);
+    span_id
+}
+
+// This code looks significantly weirder if some of the elements are added using
+// the `vec![]` macro, despite clippy's suggestions otherwise...
+#[allow(clippy::vec_init_then_push)]
+fn write_grpc_trace_context<B>(
+    request: &mut http::Request<B>,
+    context: &TraceContext,
+    span_id: &Id,
+) {
     let mut bytes = Vec::<u8>::new();
 
     // version
@@ -169,7 +290,6 @@ fn increment_grpc_span_id<B>(request: &mut http::Request<B>, context: &TraceCont
     } else {
         warn!("invalid header: {:?}", &bytes_b64);
     }
-    span_id
 }
 
 fn unpack_http_trace_context<B>(request: &http::Request<B>) -> Option<TraceContext> {
@@ -180,7 +300,7 @@ fn unpack_http_trace_context<B>(request: &http::Request<B>) -> Option<TraceConte
         _ => Flags(0),
     };
     Some(TraceContext {
-        propagation: Propagation::Http,
+        propagation: Propagation::B3,
         trace_id,
         parent_id,
         flags,
@@ -202,6 +322,116 @@ fn increment_http_span_id<B>(request: &mut http::Request<B>) -> Id {
     span_id
 }
 
+fn write_http_trace_context<B>(
+    request: &mut http::Request<B>,
+    context: &TraceContext,
+    span_id: &Id,
+) {
+    let headers = request.headers_mut();
+    for (name, value) in [
+        (HTTP_TRACE_ID_HEADER, hex::encode(context.trace_id.as_ref())),
+        (HTTP_SPAN_ID_HEADER, hex::encode(span_id.as_ref())),
+        (
+            HTTP_SAMPLED_HEADER,
+            u8::from(context.is_sampled()).to_string(),
+        ),
+    ] {
+        match HeaderValue::from_str(&value) {
+            Ok(hv) => {
+                headers.insert(name, hv);
+            }
+            Err(_) => warn!("invalid {} header: {:?}", name, value),
+        }
+    }
+}
+
+fn unpack_w3c_trace_context<B>(request: &http::Request<B>) -> Option<TraceContext> {
+    let header = get_header_str(request, W3C_TRACEPARENT_HEADER)?;
+    let context = parse_traceparent(header);
+    if context.is_none() {
+        warn!("invalid {} header: {:?}", W3C_TRACEPARENT_HEADER, header);
+    }
+    context
+}
+
+/// Parses a `traceparent` header of the form
+/// `{version}-{trace-id}-{parent-id}-{trace-flags}`.
+///
+/// Headers with a future version may append additional fields, which are
+/// ignored.
+fn parse_traceparent(header: &str) -> Option<TraceContext> {
+    fn parse_hex(field: &str, len: usize) -> Option<Vec<u8>> {
+        if field.len() != len * 2
+            || !field
+                .bytes()
+                .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+        {
+            return None;
+        }
+        hex::decode(field).ok()
+    }
+
+    let mut fields = header.trim().split('-');
+    let version = parse_hex(fields.next()?, 1)?[0];
+    if version == W3C_INVALID_VERSION {
+        return None;
+    }
+
+    let trace_id = parse_hex(fields.next()?, 16)?;
+    let parent_id = parse_hex(fields.next()?, 8)?;
+    let flags = parse_hex(fields.next()?, 1)?[0];
+    if version == W3C_VERSION && fields.next().is_some() {
+        return None;
+    }
+
+    // All-zero IDs are invalid.
+    if trace_id.iter().all(|b| *b == 0) || parent_id.iter().all(|b| *b == 0) {
+        return None;
+    }
+
+    Some(TraceContext {
+        propagation: Propagation::W3C,
+        trace_id: Id(trace_id),
+        parent_id: Id(parent_id),
+        flags: Flags(flags),
+    })
+}
+
+/// Replaces the `traceparent` header with one that has a new span ID. The
+/// header is always written in the version this proxy supports, and only the
+/// sampled flag is propagated.
+fn increment_w3c_span_id<B>(request: &mut http::Request<B>, context: &TraceContext) -> Id {
+    let span_id = Id::new_span_id(&mut thread_rng());
+
+    trace!(message = "incremented span id", %span_id);
+
+    write_w3c_trace_context(request, context, &span_id);
+    span_id
+}
+
+fn write_w3c_trace_context<B>(
+    request: &mut http::Request<B>,
+    context: &TraceContext,
+    span_id: &Id,
+) {
+    let traceparent = format!(
+        "{:02x}-{}-{}-{:02x}",
+        W3C_VERSION,
+        hex::encode(context.trace_id.as_ref()),
+        hex::encode(span_id.as_ref()),
+        context.flags.0 & W3C_SAMPLED_FLAG,
+    );
+
+    if let Result::Ok(hv) = HeaderValue::from_str(&traceparent) {
+        request.headers_mut().insert(W3C_TRACEPARENT_HEADER, hv);
+    } else {
+        warn!(
+            "invalid {} header: {:?}",
+            W3C_TRACEPARENT_HEADER, traceparent
+        );
+    }
+}
+
 fn get_header_str<'a, B>(request: &'a http::Request<B>, header: &str) -> Option<&'a str> {
     let hv = request.headers().get(header)?;
     hv.to_str()
@@ -235,3 +465,163 @@ fn try_split_to(buf: &mut Bytes, n: usize) -> Result<Bytes, InsufficientBytes> {
         Err(InsufficientBytes)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+    const PARENT_ID: &str = "00f067aa0ba902b7";
+
+    fn req(headers: &[(&'static str, &str)]) -> http::Request<()> {
+        let mut req = http::Request::builder().uri("http://example.com/");
+        for (name, value) in headers {
+            req = req.header(*name, *value);
+        }
+        req.body(()).unwrap()
+    }
+
+    fn traceparent(flags: &str) -> String {
+        format!("00-{}-{}-{}", TRACE_ID, PARENT_ID, flags)
+    }
+
+    #[test]
+    fn b3_context() {
+        let mut req = req(&[
+            (HTTP_TRACE_ID_HEADER, TRACE_ID),
+            (HTTP_SPAN_ID_HEADER, PARENT_ID),
+            (HTTP_SAMPLED_HEADER, "1"),
+        ]);
+
+        let context = unpack_trace_context(&req, &Propagations::default()).expect("must parse");
+        assert_eq!(context.propagation, Propagation::B3);
+        assert_eq!(context.trace_id.to_string(), TRACE_ID);
+        assert_eq!(context.parent_id.to_string(), PARENT_ID);
+        assert!(context.is_sampled());
+
+        let span_id = increment_span_id(&mut req, &context);
+        assert_eq!(
+            req.headers().get(HTTP_SPAN_ID_HEADER).unwrap(),
+            span_id.to_string().as_str()
+        );
+        assert_eq!(req.headers().get(HTTP_TRACE_ID_HEADER).unwrap(), TRACE_ID);
+    }
+
+    #[test]
+    fn b3_short_trace_id_is_padded() {
+        let req = req(&[
+            (HTTP_TRACE_ID_HEADER, "a3ce929d0e0e4736"),
+            (HTTP_SPAN_ID_HEADER, PARENT_ID),
+        ]);
+        let context = unpack_trace_context(&req, &Propagations::default()).expect("must parse");
+        assert_eq!(
+            context.trace_id.to_string(),
+            "0000000000000000a3ce929d0e0e4736"
+        );
+        assert!(!context.is_sampled());
+    }
+
+    #[test]
+    fn w3c_context() {
+        let mut req = req(&[
+            (W3C_TRACEPARENT_HEADER, &traceparent("01")),
+            ("tracestate", "congo=t61rcWkgMzE,rojo=00f067aa0ba902b7"),
+        ]);
+
+        let context = unpack_trace_context(&req, &Propagations::default()).expect("must parse");
+        assert_eq!(context.propagation, Propagation::W3C);
+        assert_eq!(context.trace_id.to_string(), TRACE_ID);
+        assert_eq!(context.parent_id.to_string(), PARENT_ID);
+        assert!(context.is_sampled());
+
+        let span_id = increment_span_id(&mut req, &context);
+        assert_eq!(
+            req.headers().get(W3C_TRACEPARENT_HEADER).unwrap(),
+            format!("00-{}-{}-01", TRACE_ID, span_id).as_str()
+        );
+        assert_eq!(
+            req.headers().get("tracestate").unwrap(),
+            "congo=t61rcWkgMzE,rojo=00f067aa0ba902b7"
+        );
+    }
+
+    #[test]
+    fn w3c_unsampled_context() {
+        let req = req(&[(W3C_TRACEPARENT_HEADER, &traceparent("00"))]);
+        let context = unpack_trace_context(&req, &Propagations::default()).expect("must parse");
+        assert!(!context.is_sampled());
+    }
+
+    #[test]
+    fn w3c_future_version() {
+        let mut req = req(&[(
+            W3C_TRACEPARENT_HEADER,
+            &format!("cc-{}-{}-03-what-the-future-holds", TRACE_ID, PARENT_ID),
+        )]);
+        let context = unpack_trace_context(&req, &Propagations::default()).expect("must parse");
+
+        // The header is downgraded to the supported version and unknown flags
+        // are cleared.
+        let span_id = increment_span_id(&mut req, &context);
+        assert_eq!(
+            req.headers().get(W3C_TRACEPARENT_HEADER).unwrap(),
+            format!("00-{}-{}-01", TRACE_ID, span_id).as_str()
+        );
+    }
+
+    #[test]
+    fn w3c_invalid() {
+        for header in [
+            format!("ff-{}-{}-01", TRACE_ID, PARENT_ID),
+            format!("00-{}-{}-01-extra", TRACE_ID, PARENT_ID),
+            format!("00-{}-{}-01", "0".repeat(32), PARENT_ID),
+            format!("00-{}-{}-01", TRACE_ID, "0".repeat(16)),
+            format!("00-{}-{}-01", TRACE_ID.to_uppercase(), PARENT_ID),
+            format!("00-{}-{}-01", &TRACE_ID[1..], PARENT_ID),
+            format!("00-{}-{}", TRACE_ID, PARENT_ID),
+        ] {
+            let req = req(&[(W3C_TRACEPARENT_HEADER, &header)]);
+            assert!(
+                unpack_trace_context(&req, &Propagations::default()).is_none(),
+                "{} must not parse",
+                header
+            );
+        }
+    }
+
+    #[test]
+    fn preference_order() {
+        let req = req(&[
+            (HTTP_TRACE_ID_HEADER, TRACE_ID),
+            (HTTP_SPAN_ID_HEADER, PARENT_ID),
+            (W3C_TRACEPARENT_HEADER, &traceparent("01")),
+        ]);
+
+        let context = unpack_trace_context(&req, &Propagations::default()).expect("must parse");
+        assert_eq!(context.propagation, Propagation::B3);
+
+        let w3c = "w3c, b3".parse::<Propagations>().unwrap();
+        let context = unpack_trace_context(&req, &w3c).expect("must parse");
+        assert_eq!(context.propagation, Propagation::W3C);
+
+        // Formats that are not listed are ignored.
+        let grpc = "grpc".parse::<Propagations>().unwrap();
+        assert!(unpack_trace_context(&req, &grpc).is_none());
+    }
+
+    #[test]
+    fn parse_propagations() {
+        assert_eq!(
+            "W3C,b3,w3c".parse::<Propagations>().unwrap(),
+            Propagations(Arc::new([Propagation::W3C, Propagation::B3]))
+        );
+        assert!(matches!(
+            "".parse::<Propagations>(),
+            Err(InvalidPropagation::Empty)
+        ));
+        assert!(matches!(
+            "jaeger".parse::<Propagations>(),
+            Err(InvalidPropagation::Unknown(_))
+        ));
+    }
+}
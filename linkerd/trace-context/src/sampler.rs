@@ -0,0 +1,397 @@
+use crate::{
+    propagation::{self, Propagation, TraceContext},
+    Flags, Id,
+};
+use linkerd_stack::{layer, NewService, Param};
+use parking_lot::Mutex;
+use rand::{thread_rng, Rng};
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::time::{Duration, Instant};
+use tracing::trace;
+
+/// Decides whether the traces that the proxy starts are sampled.
+///
+/// Requests that carry a trace context keep the sampling decision of their
+/// parent. When head sampling is enabled, the proxy starts a new trace for
+/// each request without a trace context, sampling `ratio` of these traces and,
+/// optionally, no more than `max_per_second` of them each second. The
+/// decision is recorded in the propagated trace flags so that downstream
+/// proxies honor it.
+///
+/// By default, head sampling is disabled and requests without a trace context
+/// are forwarded unmodified.
+#[derive(Clone, Debug, Default)]
+pub struct Sampler(Option<Arc<Inner>>);
+
+/// A sampling decision made by the proxy for a trace that it started.
+///
+/// This is set as a request extension so that a route's sampling ratio may
+/// revise the decision before the request is forwarded.
+#[derive(Clone, Debug)]
+pub struct HeadSampled(Arc<Head>);
+
+/// A route's override of the ratio of traces started by the proxy that are
+/// sampled.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct RouteSampleRatio(pub Option<f32>);
+
+/// Builds services that apply a route's sampling ratio to the traces started
+/// by the proxy.
+#[derive(Clone, Debug)]
+pub struct NewRouteSampling<N> {
+    inner: N,
+}
+
+#[derive(Clone, Debug)]
+pub struct RouteSampling<S> {
+    inner: S,
+    ratio: Option<f32>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    ratio: f32,
+    limit: Option<RateLimit>,
+}
+
+#[derive(Debug)]
+struct RateLimit {
+    max_per_second: u32,
+    window: Mutex<Window>,
+}
+
+#[derive(Debug)]
+struct Window {
+    start: Instant,
+    sampled: u32,
+}
+
+#[derive(Debug)]
+struct Head {
+    sampler: Sampler,
+    propagation: Propagation,
+    trace_id: Id,
+    span_id: Id,
+    decision: Mutex<Decision>,
+}
+
+#[derive(Copy, Clone, Debug)]
+enum Decision {
+    Unsampled,
+    Sampled {
+        /// The start of the rate limit window that the trace was counted in,
+        /// if sampling is rate limited.
+        window: Option<Instant>,
+    },
+}
+
+// === impl Sampler ===
+
+impl Sampler {
+    /// Enables head sampling of the given ratio (between 0.0 and 1.0) of
+    /// traces, limited to `max_per_second`, if set.
+    pub fn new(ratio: f32, max_per_second: Option<u32>) -> Self {
+        let limit = max_per_second.map(|max_per_second| RateLimit {
+            max_per_second,
+            window: Mutex::new(Window {
+                start: Instant::now(),
+                sampled: 0,
+            }),
+        });
+        Self(Some(Arc::new(Inner {
+            ratio: ratio.clamp(0.0, 1.0),
+            limit,
+        })))
+    }
+
+    /// Starts a new trace for a request that carries no trace context,
+    /// writing the context to the request in the given propagation format.
+    ///
+    /// Returns `None` if head sampling is disabled.
+    pub(crate) fn start_trace<B>(
+        &self,
+        req: &mut http::Request<B>,
+        propagation: Propagation,
+    ) -> Option<(TraceContext, Id, HeadSampled)> {
+        let inner = self.0.as_ref()?;
+        let decision = self.sample(inner.ratio);
+        let sampled = decision.is_sampled();
+        let context = propagation::new_trace_context(propagation, sampled);
+        let span_id = Id::new_span_id(&mut thread_rng());
+        trace!(trace_id = %context.trace_id, %span_id, sampled, "Starting trace");
+        propagation::write_trace_context(req, &context, &span_id);
+
+        let head = HeadSampled(Arc::new(Head {
+            sampler: self.clone(),
+            propagation,
+            trace_id: context.trace_id.clone(),
+            span_id: span_id.clone(),
+            decision: Mutex::new(decision),
+        }));
+        req.extensions_mut().insert(head.clone());
+        Some((context, span_id, head))
+    }
+
+    fn sample(&self, ratio: f32) -> Decision {
+        let inner = match self.0.as_ref() {
+            Some(inner) => inner,
+            None => return Decision::Unsampled,
+        };
+        let ratio = ratio.clamp(0.0, 1.0);
+        if ratio == 0.0 || (ratio < 1.0 && !thread_rng().gen_bool(ratio.into())) {
+            return Decision::Unsampled;
+        }
+        match inner.limit.as_ref() {
+            None => Decision::Sampled { window: None },
+            Some(limit) => match limit.acquire() {
+                Some(window) => Decision::Sampled {
+                    window: Some(window),
+                },
+                None => Decision::Unsampled,
+            },
+        }
+    }
+
+    /// Returns a sampled trace's allotment to the rate limit window that it was
+    /// counted in.
+    fn release(&self, window: Instant) {
+        if let Some(limit) = self.0.as_ref().and_then(|i| i.limit.as_ref()) {
+            limit.release(window);
+        }
+    }
+}
+
+// === impl RateLimit ===
+
+impl RateLimit {
+    const WINDOW: Duration = Duration::from_secs(1);
+
+    /// Counts a trace against the current window, returning the window's
+    /// start if the limit has not been reached.
+    fn acquire(&self) -> Option<Instant> {
+        let now = Instant::now();
+        let mut window = self.window.lock();
+        if now.saturating_duration_since(window.start) >= Self::WINDOW {
+            *window = Window {
+                start: now,
+                sampled: 0,
+            };
+        }
+        if window.sampled < self.max_per_second {
+            window.sampled += 1;
+            return Some(window.start);
+        }
+        None
+    }
+
+    /// Releases a trace counted in the window that started at `start`. Once
+    /// that window has elapsed, there is nothing to release.
+    fn release(&self, start: Instant) {
+        let mut window = self.window.lock();
+        if window.start == start {
+            window.sampled = window.sampled.saturating_sub(1);
+        }
+    }
+}
+
+// === impl HeadSampled ===
+
+impl HeadSampled {
+    pub fn is_sampled(&self) -> bool {
+        self.0.decision.lock().is_sampled()
+    }
+
+    /// Replaces the sampling decision with one made at the given ratio,
+    /// updating the trace context propagated on the request.
+    fn resample<B>(&self, ratio: f32, req: &mut http::Request<B>) {
+        let sampled = {
+            let mut decision = self.0.decision.lock();
+            if let Decision::Sampled {
+                window: Some(window),
+            } = *decision
+            {
+                self.0.sampler.release(window);
+            }
+            *decision = self.0.sampler.sample(ratio);
+            decision.is_sampled()
+        };
+        trace!(ratio, sampled, "Resampled trace");
+
+        let context = TraceContext {
+            propagation: self.0.propagation,
+            trace_id: self.0.trace_id.clone(),
+            parent_id: Id::default(),
+            flags: Flags(u8::from(sampled)),
+        };
+        propagation::write_trace_context(req, &context, &self.0.span_id);
+    }
+}
+
+// === impl Decision ===
+
+impl Decision {
+    fn is_sampled(&self) -> bool {
+        matches!(self, Self::Sampled { .. })
+    }
+}
+
+// === impl NewRouteSampling ===
+
+impl<N> NewRouteSampling<N> {
+    pub fn layer() -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(|inner| Self { inner })
+    }
+}
+
+impl<T, N> NewService<T> for NewRouteSampling<N>
+where
+    T: Param<RouteSampleRatio>,
+    N: NewService<T>,
+{
+    type Service = RouteSampling<N::Service>;
+
+    fn new_service(&self, target: T) -> Self::Service {
+        let RouteSampleRatio(ratio) = target.param();
+        RouteSampling {
+            ratio,
+            inner: self.inner.new_service(target),
+        }
+    }
+}
+
+// === impl RouteSampling ===
+
+impl<B, S> tower::Service<http::Request<B>> for RouteSampling<S>
+where
+    S: tower::Service<http::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: http::Request<B>) -> Self::Future {
+        if let Some(ratio) = self.ratio {
+            if let Some(head) = req.extensions().get::<HeadSampled>().cloned() {
+                head.resample(ratio, &mut req);
+            }
+        }
+        self.inner.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Propagations;
+
+    fn req() -> http::Request<()> {
+        http::Request::builder()
+            .uri("http://example.com/")
+            .body(())
+            .unwrap()
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        let mut req = req();
+        assert!(Sampler::default()
+            .start_trace(&mut req, Propagation::W3C)
+            .is_none());
+        assert!(req.headers().is_empty());
+    }
+
+    #[test]
+    fn records_decision_in_flags() {
+        for (ratio, flags) in [(1.0, "01"), (0.0, "00")] {
+            let mut req = req();
+            let (context, span_id, head) = Sampler::new(ratio, None)
+                .start_trace(&mut req, Propagation::W3C)
+                .expect("trace must start");
+            assert_eq!(head.is_sampled(), ratio == 1.0);
+            assert!(context.parent_id.is_empty());
+            assert_eq!(
+                req.headers().get("traceparent").unwrap(),
+                format!("00-{}-{}-{}", context.trace_id, span_id, flags).as_str(),
+            );
+
+            // Downstream proxies honor the decision.
+            let unpacked = propagation::unpack_trace_context(&req, &Propagations::default())
+                .expect("context must be propagated");
+            assert_eq!(unpacked.trace_id, context.trace_id);
+            assert_eq!(unpacked.parent_id, span_id);
+            assert_eq!(unpacked.is_sampled(), ratio == 1.0);
+        }
+    }
+
+    fn window(decision: Decision) -> Instant {
+        match decision {
+            Decision::Sampled {
+                window: Some(window),
+            } => window,
+            decision => panic!("trace must be sampled in a window: {:?}", decision),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn limits_sampled_traces_per_second() {
+        let sampler = Sampler::new(1.0, Some(2));
+        let first = window(sampler.sample(1.0));
+        assert!(sampler.sample(1.0).is_sampled());
+        assert!(!sampler.sample(1.0).is_sampled());
+
+        sampler.release(first);
+        assert!(sampler.sample(1.0).is_sampled());
+        assert!(!sampler.sample(1.0).is_sampled());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn releases_only_in_the_same_window() {
+        let sampler = Sampler::new(1.0, Some(1));
+        let first = window(sampler.sample(1.0));
+
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert!(sampler.sample(1.0).is_sampled());
+
+        // Releasing a trace from the previous window must not permit more
+        // traces in the current window.
+        sampler.release(first);
+        assert!(!sampler.sample(1.0).is_sampled());
+    }
+
+    #[tokio::test]
+    async fn routes_override_ratio() {
+        let sampler = Sampler::new(0.0, None);
+        let mut req = req();
+        let (context, span_id, head) = sampler
+            .start_trace(&mut req, Propagation::B3)
+            .expect("trace must start");
+        assert!(!head.is_sampled());
+        assert_eq!(req.headers().get("x-b3-sampled").unwrap(), "0");
+
+        let mut route = RouteSampling {
+            inner: tower::service_fn(|req: http::Request<()>| async move {
+                Ok::<_, std::convert::Infallible>(req)
+            }),
+            ratio: Some(1.0),
+        };
+        let req = tower::Service::call(&mut route, req).await.unwrap();
+        assert!(head.is_sampled());
+        assert_eq!(req.headers().get("x-b3-sampled").unwrap(), "1");
+        assert_eq!(
+            req.headers().get("x-b3-traceid").unwrap(),
+            context.trace_id.to_string().as_str(),
+        );
+        assert_eq!(
+            req.headers().get("x-b3-spanid").unwrap(),
+            span_id.to_string().as_str(),
+        );
+    }
+}
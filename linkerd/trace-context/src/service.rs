@@ -1,7 +1,8 @@
-use crate::{propagation, Span, SpanSink};
+use crate::{propagation, Propagations, Sampler, Span, SpanContext, SpanLabels, SpanSink};
 use futures::{future::Either, prelude::*};
 use linkerd_stack::layer;
 use std::{
+    borrow::Cow,
     collections::HashMap,
     future::Future,
     pin::Pin,
@@ -12,54 +13,65 @@ use tracing::{debug, info, trace};
 
 /// A layer that adds distributed tracing instrumentation.
 ///
-/// This layer reads the `traceparent` HTTP header from the request. If this
-/// header is absent, the request is fowarded unmodified.  If the header is
-/// present, a new span will be started in the current trace by creating a new
-/// random span id setting it into the `traceparent` header before forwarding
-/// the request. If the sampled bit of the header was set, we emit metadata
-/// about the span to the given SpanSink when the span is complete, i.e. when
-/// we receive the response.
+/// This layer reads the trace context from the request's headers, trying each
+/// of the configured propagation formats in order. If no trace context is
+/// present, the `Sampler` may start a new trace in the preferred propagation
+/// format; otherwise, the request is fowarded unmodified.  If a trace context
+/// is present, a new span will be started in the current trace by creating a
+/// new random span id setting it into the same header before forwarding the
+/// request. If the trace is sampled, we emit metadata about the span to the
+/// given SpanSink when the span is complete, i.e. when we receive the
+/// response. Inner services may annotate a sampled span with the `SpanLabels`
+/// request extension.
 #[derive(Clone, Debug)]
 pub struct TraceContext<K, S> {
     inner: S,
     sink: K,
+    propagations: Propagations,
+    sampler: Sampler,
 }
 
 // === impl TraceContext ===
 
 impl<K: Clone, S> TraceContext<K, S> {
-    pub fn layer(sink: K) -> impl layer::Layer<S, Service = TraceContext<K, S>> + Clone {
+    pub fn layer(
+        propagations: Propagations,
+        sampler: Sampler,
+        sink: K,
+    ) -> impl layer::Layer<S, Service = TraceContext<K, S>> + Clone {
         layer::mk(move |inner| TraceContext {
             inner,
             sink: sink.clone(),
+            propagations: propagations.clone(),
+            sampler: sampler.clone(),
         })
     }
 
-    fn request_labels<B>(req: &http::Request<B>) -> HashMap<&'static str, String> {
+    fn request_labels<B>(req: &http::Request<B>) -> HashMap<Cow<'static, str>, String> {
         let mut labels = HashMap::with_capacity(5);
-        labels.insert("http.method", format!("{}", req.method()));
+        labels.insert("http.method".into(), format!("{}", req.method()));
         let path = req
             .uri()
             .path_and_query()
             .map(|pq| pq.as_str().to_owned())
             .unwrap_or_default();
-        labels.insert("http.path", path);
+        labels.insert("http.path".into(), path);
         if let Some(authority) = req.uri().authority() {
-            labels.insert("http.authority", authority.as_str().to_string());
+            labels.insert("http.authority".into(), authority.as_str().to_string());
         }
         if let Some(host) = req.headers().get("host") {
             if let Ok(host) = host.to_str() {
-                labels.insert("http.host", host.to_string());
+                labels.insert("http.host".into(), host.to_string());
             }
         }
         labels
     }
 
     fn add_response_labels<B>(
-        mut labels: HashMap<&'static str, String>,
+        mut labels: HashMap<Cow<'static, str>, String>,
         rsp: &http::Response<B>,
-    ) -> HashMap<&'static str, String> {
-        labels.insert("http.status_code", rsp.status().as_str().to_string());
+    ) -> HashMap<Cow<'static, str>, String> {
+        labels.insert("http.status_code".into(), rsp.status().as_str().to_string());
         labels
     }
 }
@@ -85,20 +97,51 @@ where
 
     fn call(&mut self, mut req: http::Request<ReqB>) -> Self::Future {
         if self.sink.is_enabled() {
-            if let Some(context) = propagation::unpack_trace_context(&req) {
-                // Update the trace ID if the request set one and the proxy is configured to emit
-                // spans.
-                let span_id = propagation::increment_span_id(&mut req, &context);
+            let traced = match propagation::unpack_trace_context(&req, &self.propagations) {
+                Some(context) => {
+                    // Update the trace ID if the request set one and the proxy is configured to emit
+                    // spans.
+                    let span_id = propagation::increment_span_id(&mut req, &context);
+                    Some((context, span_id, None))
+                }
+                // Otherwise, start a new trace if head sampling is enabled.
+                None => self
+                    .sampler
+                    .start_trace(&mut req, self.propagations.preferred())
+                    .map(|(context, span_id, head)| (context, span_id, Some(head))),
+            };
+
+            if let Some((context, span_id, head)) = traced {
                 debug!(?span_id, sampled = context.is_sampled());
 
-                if context.is_sampled() {
+                // The sampling decision for a trace started by the proxy may
+                // be revised by a route, so it is checked once the response is
+                // received.
+                if context.is_sampled() || head.is_some() {
                     // If the request has been marked for sampling, record its metadata.
                     let start = SystemTime::now();
                     let req_labels = Self::request_labels(&req);
                     let mut sink = self.sink.clone();
                     let span_name = req.uri().path().to_owned();
+                    let recorded = req
+                        .extensions()
+                        .get::<SpanLabels>()
+                        .map(SpanLabels::child)
+                        .unwrap_or_default();
+                    req.extensions_mut().insert(recorded.clone());
+                    req.extensions_mut().insert(SpanContext {
+                        trace_id: context.trace_id.clone(),
+                        span_id: span_id.clone(),
+                        head: head.clone(),
+                    });
                     return Either::Right(Box::pin(self.inner.call(req).map_ok(move |rsp| {
+                        if !head.map(|h| h.is_sampled()).unwrap_or(true) {
+                            return rsp;
+                        }
+
                         // Emit the completed span with the response metadata.
+                        let mut labels = recorded.take();
+                        labels.extend(Self::add_response_labels(req_labels, &rsp));
                         let span = Span {
                             span_id,
                             trace_id: context.trace_id,
@@ -106,7 +149,7 @@ where
                             span_name,
                             start,
                             end: SystemTime::now(),
-                            labels: Self::add_response_labels(req_labels, &rsp),
+                            labels,
                         };
                         trace!(?span);
                         if let Err(error) = sink.try_send(span) {
@@ -122,3 +165,68 @@ where
         Either::Left(self.inner.call(req))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_error::Error;
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+
+    #[derive(Clone, Default)]
+    struct Spans(Arc<Mutex<Vec<Span>>>);
+
+    impl SpanSink for Spans {
+        fn is_enabled(&self) -> bool {
+            true
+        }
+
+        fn try_send(&mut self, span: Span) -> Result<(), Error> {
+            self.0.lock().push(span);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn records_labels() {
+        let spans = Spans::default();
+        let inner = tower::service_fn(|req: http::Request<()>| async move {
+            assert!(req
+                .extensions()
+                .get::<SpanContext>()
+                .expect("span context must be set")
+                .is_sampled());
+            req.extensions()
+                .get::<SpanLabels>()
+                .expect("labels must be set")
+                .insert("route.name", "default");
+            Ok::<_, std::convert::Infallible>(http::Response::new(()))
+        });
+        let mut svc = layer::Layer::layer(
+            &TraceContext::layer(
+                Propagations::default(),
+                Sampler::new(1.0, None),
+                spans.clone(),
+            ),
+            inner,
+        );
+
+        let mut req = http::Request::builder()
+            .uri("http://example.com/foo")
+            .body(())
+            .unwrap();
+        let parent = SpanLabels::default();
+        parent.insert("peer.address", "10.1.1.1:8080");
+        req.extensions_mut().insert(parent.clone());
+        tower::Service::call(&mut svc, req).await.unwrap();
+
+        let spans = spans.0.lock();
+        assert_eq!(spans.len(), 1);
+        let labels = &spans[0].labels;
+        assert_eq!(labels.get("route.name").unwrap(), "default");
+        assert_eq!(labels.get("peer.address").unwrap(), "10.1.1.1:8080");
+        assert_eq!(labels.get("http.status_code").unwrap(), "200");
+        // Labels recorded on the child span are not recorded on the parent.
+        assert!(parent.get("route.name").is_none());
+    }
+}
@@ -1,46 +1,82 @@
-use std::fmt;
+mod sink;
+
+pub(super) use self::sink::Output;
+use self::sink::Sink;
+use std::{
+    fmt::{self, Write},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
 use tracing::{field, span, Id, Level, Metadata, Subscriber};
 use tracing_subscriber::{
-    field::RecordFields,
     filter::{Directive, FilterFn, Filtered},
-    fmt::{format, FormatFields, FormattedFields},
     layer::{Context, Layer},
     registry::LookupSpan,
 };
 
 pub const TRACE_TARGET: &str = "_access_log";
 
-pub(super) type AccessLogLayer<S> =
-    Filtered<Box<dyn Layer<S> + Send + Sync + 'static>, FilterFn, S>;
+pub(super) type AccessLogLayer<S> = Filtered<Writer, FilterFn, S>;
 
-#[derive(Default)]
-pub(super) struct Writer<F = ApacheCommon> {
-    formatter: F,
-}
+#[derive(Clone, Debug)]
+pub(super) struct Config {
+    pub(super) format: Format,
 
-#[derive(Default)]
-pub(super) struct ApacheCommon {
-    _p: (),
+    /// The fields included in JSON access logs, in order. When unset, all
+    /// recorded fields are included.
+    pub(super) fields: Option<Arc<[String]>>,
+
+    pub(super) output: Output,
+
+    /// The fraction of requests that are logged. Requests that fail or that
+    /// receive a 5XX response are always logged.
+    pub(super) sample_rate: f64,
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub(super) enum Format {
     Apache,
     Json,
 }
 
-struct ApacheCommonVisitor<'writer> {
-    res: fmt::Result,
-    writer: format::Writer<'writer>,
+pub(super) struct Writer {
+    format: Format,
+    fields: Option<Arc<[String]>>,
+    sampler: Sampler,
+    sink: Sink,
 }
 
-pub(super) fn build<S>(format: Format) -> (AccessLogLayer<S>, Directive)
+/// Logs a deterministic fraction of requests.
+#[derive(Debug)]
+struct Sampler {
+    rate: f64,
+    count: AtomicU64,
+}
+
+/// The fields recorded on an access log span, in the order they were
+/// recorded.
+#[derive(Debug, Default)]
+struct Fields(Vec<(&'static str, Value)>);
+
+#[derive(Debug, PartialEq)]
+enum Value {
+    Str(String),
+    U64(u64),
+    I64(i64),
+    Bool(bool),
+}
+
+pub(super) fn build<S>(config: Config) -> (AccessLogLayer<S>, Directive)
 where
     S: Subscriber + for<'span> LookupSpan<'span>,
 {
-    let writer: Box<dyn Layer<S> + Send + Sync + 'static> = match format {
-        Format::Apache => Box::new(Writer::<ApacheCommon>::default()),
-        Format::Json => Box::new(Writer::<format::JsonFields>::default()),
+    let writer = Writer {
+        format: config.format,
+        fields: config.fields,
+        sampler: Sampler::new(config.sample_rate),
+        sink: Sink::spawn(config.output),
     };
 
     let writer = writer.with_filter(
@@ -60,106 +96,225 @@ where
     (writer, directive)
 }
 
+// === impl Config ===
+
+impl Config {
+    pub(super) fn new(format: Format) -> Self {
+        Self {
+            format,
+            fields: None,
+            output: Output::Stderr,
+            sample_rate: 1.0,
+        }
+    }
+}
+
 // === impl Writer ===
 
-impl<S, F> Layer<S> for Writer<F>
+impl<S> Layer<S> for Writer
 where
     S: Subscriber + for<'span> LookupSpan<'span>,
-    F: for<'writer> FormatFields<'writer> + 'static,
 {
     fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
         let span = ctx.span(id).expect("Span not found, this is a bug");
         let mut extensions = span.extensions_mut();
-
-        if extensions.get_mut::<FormattedFields<F>>().is_none() {
-            let mut fields = FormattedFields::<F>::new(String::new());
-            if self
-                .formatter
-                .format_fields(fields.as_writer(), attrs)
-                .is_ok()
-            {
-                extensions.insert(fields);
-            }
+        if extensions.get_mut::<Fields>().is_none() {
+            let mut fields = Fields::default();
+            attrs.record(&mut fields);
+            extensions.insert(fields);
         }
     }
 
     fn on_record(&self, id: &Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
         let span = ctx.span(id).expect("Span not found, this is a bug");
         let mut extensions = span.extensions_mut();
-        if let Some(fields) = extensions.get_mut::<FormattedFields<F>>() {
-            let _ = self.formatter.add_fields(fields, values);
-            return;
+        match extensions.get_mut::<Fields>() {
+            Some(fields) => values.record(fields),
+            None => {
+                let mut fields = Fields::default();
+                values.record(&mut fields);
+                extensions.insert(fields);
+            }
         }
+    }
 
-        let mut fields = FormattedFields::<F>::new(String::new());
-        if self
-            .formatter
-            .format_fields(fields.as_writer(), values)
-            .is_ok()
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let fields = match ctx
+            .span(&id)
+            .and_then(|span| span.extensions_mut().remove::<Fields>())
         {
-            extensions.insert(fields);
+            Some(fields) => fields,
+            None => return,
+        };
+
+        if !self.sampler.sample(&fields) {
+            return;
         }
-    }
 
-    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
-        if let Some(span) = ctx.span(&id) {
-            if let Some(fields) = span.extensions().get::<FormattedFields<F>>() {
-                eprintln!("{}", fields.fields);
-            }
+        let mut line = String::new();
+        let res = match self.format {
+            Format::Apache => fields.fmt_apache(&mut line),
+            Format::Json => fields.fmt_json(self.fields.as_deref(), &mut line),
+        };
+        if res.is_ok() {
+            self.sink.write(line);
         }
     }
 }
 
-impl ApacheCommon {
-    const SKIPPED_FIELDS: &'static [&'static str] = &[
-        "trace_id",
-        "request_bytes",
-        "total_ns",
-        "processing_ns",
-        "response_bytes",
-        "user_agent",
-        "host",
-    ];
+// === impl Sampler ===
+
+impl Sampler {
+    fn new(rate: f64) -> Self {
+        Self {
+            rate: rate.clamp(0.0, 1.0),
+            count: AtomicU64::new(0),
+        }
+    }
+
+    fn sample(&self, fields: &Fields) -> bool {
+        if self.rate >= 1.0 || fields.is_error() {
+            return true;
+        }
+
+        // Log a request each time the running count crosses an integer
+        // multiple of the sample rate, so that exactly `rate` of requests are
+        // logged over time.
+        let n = self.count.fetch_add(1, Ordering::Relaxed) as f64;
+        ((n + 1.0) * self.rate).floor() > (n * self.rate).floor()
+    }
 }
 
-impl<'writer> FormatFields<'writer> for ApacheCommon {
-    fn format_fields<R: RecordFields>(&self, writer: format::Writer<'_>, fields: R) -> fmt::Result {
-        let mut visitor = ApacheCommonVisitor {
-            writer,
-            res: Ok(()),
-        };
-        fields.record(&mut visitor);
-        visitor.res
+// === impl Fields ===
+
+impl Fields {
+    fn get(&self, name: &str) -> Option<&Value> {
+        self.0.iter().find(|(n, _)| *n == name).map(|(_, v)| v)
+    }
+
+    /// A request is considered to have failed if no response status was
+    /// recorded or if the response status is a server error.
+    fn is_error(&self) -> bool {
+        match self.get("status") {
+            Some(Value::U64(status)) => *status >= 500,
+            Some(_) => false,
+            None => true,
+        }
+    }
+
+    fn insert(&mut self, name: &'static str, value: Value) {
+        match self.0.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, v)) => *v = value,
+            None => self.0.push((name, value)),
+        }
     }
 
-    #[inline]
-    fn add_fields(
-        &self,
-        current: &mut FormattedFields<Self>,
-        fields: &span::Record<'_>,
-    ) -> fmt::Result {
-        self.format_fields(current.as_writer(), fields)
+    /// Formats the fields in the Apache Common Log Format.
+    fn fmt_apache(&self, w: &mut impl Write) -> fmt::Result {
+        for (name, value) in self.0.iter() {
+            match *name {
+                "client.addr" => write!(w, "{}", value)?,
+                "client.id" => write!(w, " {} -", value)?,
+                "timestamp" => write!(w, " [{}]", value)?,
+                "method" => write!(w, " \"{}", value)?,
+                "uri" => write!(w, " {}", value)?,
+                "version" => write!(w, " {}\"", value)?,
+                "status" => write!(w, " {}", value)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Formats the fields as a JSON object, including only the selected
+    /// fields if any are configured.
+    fn fmt_json(&self, selected: Option<&[String]>, w: &mut impl Write) -> fmt::Result {
+        w.write_char('{')?;
+        let mut first = true;
+        let mut field = |name: &str, value: &Value| {
+            let sep = if first { "" } else { "," };
+            first = false;
+            write!(w, "{}{}:", sep, JsonStr(name))?;
+            match value {
+                Value::Str(s) => write!(w, "{}", JsonStr(s)),
+                v => write!(w, "{}", v),
+            }
+        };
+
+        match selected {
+            Some(names) => {
+                for name in names {
+                    if let Some(value) = self.get(name) {
+                        field(name, value)?;
+                    }
+                }
+            }
+            None => {
+                for (name, value) in self.0.iter() {
+                    field(name, value)?;
+                }
+            }
+        }
+        w.write_char('}')
     }
 }
 
-impl field::Visit for ApacheCommonVisitor<'_> {
+impl field::Visit for Fields {
     fn record_str(&mut self, field: &field::Field, val: &str) {
-        self.record_debug(field, &format_args!("{}", val))
+        self.insert(field.name(), Value::Str(val.to_string()))
+    }
+
+    fn record_u64(&mut self, field: &field::Field, val: u64) {
+        self.insert(field.name(), Value::U64(val))
+    }
+
+    fn record_i64(&mut self, field: &field::Field, val: i64) {
+        self.insert(field.name(), Value::I64(val))
+    }
+
+    fn record_bool(&mut self, field: &field::Field, val: bool) {
+        self.insert(field.name(), Value::Bool(val))
     }
 
     fn record_debug(&mut self, field: &field::Field, val: &dyn fmt::Debug) {
-        self.res = match field.name() {
-            n if ApacheCommon::SKIPPED_FIELDS.contains(&n) => return,
-            "timestamp" => write!(&mut self.writer, " [{:?}]", val),
-            "client.addr" => write!(&mut self.writer, "{:?}", val),
-            "client.id" => write!(&mut self.writer, " {:?} -", val),
-            "method" => write!(&mut self.writer, " \"{:?}", val),
-            "version" => write!(&mut self.writer, " {:?}\"", val),
-            _ => write!(&mut self.writer, " {:?}", val),
+        self.insert(field.name(), Value::Str(format!("{:?}", val)))
+    }
+}
+
+// === impl Value ===
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Str(s) => s.fmt(f),
+            Self::U64(n) => n.fmt(f),
+            Self::I64(n) => n.fmt(f),
+            Self::Bool(b) => b.fmt(f),
         }
     }
 }
 
+/// Formats a string as a quoted JSON string.
+struct JsonStr<'s>(&'s str);
+
+impl fmt::Display for JsonStr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_char('"')?;
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                '\n' => f.write_str("\\n")?,
+                '\r' => f.write_str("\\r")?,
+                '\t' => f.write_str("\\t")?,
+                c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+                c => f.write_char(c)?,
+            }
+        }
+        f.write_char('"')
+    }
+}
+
 // === impl Format ===
 
 impl std::str::FromStr for Format {
@@ -172,3 +327,63 @@ impl std::str::FromStr for Format {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(status: Option<u64>) -> Fields {
+        let mut fields = Fields::default();
+        fields.insert("client.addr", Value::Str("10.0.0.1:41234".to_string()));
+        fields.insert("client.id", Value::Str("-".to_string()));
+        fields.insert("timestamp", Value::Str("2022-05-01T00:00:00Z".to_string()));
+        fields.insert("method", Value::Str("GET".to_string()));
+        fields.insert("uri", Value::Str("http://example.com/".to_string()));
+        fields.insert("version", Value::Str("HTTP/1.1".to_string()));
+        fields.insert("user_agent", Value::Str("curl/\"7\"".to_string()));
+        if let Some(status) = status {
+            fields.insert("status", Value::U64(status));
+        }
+        fields
+    }
+
+    #[test]
+    fn formats_apache() {
+        let mut line = String::new();
+        fields(Some(200)).fmt_apache(&mut line).unwrap();
+        assert_eq!(
+            line,
+            "10.0.0.1:41234 - - [2022-05-01T00:00:00Z] \"GET http://example.com/ HTTP/1.1\" 200"
+        );
+    }
+
+    #[test]
+    fn formats_selected_json_fields() {
+        let mut line = String::new();
+        let selected = [
+            "status".to_string(),
+            "user_agent".to_string(),
+            "jwt.sub".to_string(),
+        ];
+        fields(Some(200))
+            .fmt_json(Some(&selected), &mut line)
+            .unwrap();
+        assert_eq!(line, r#"{"status":200,"user_agent":"curl/\"7\""}"#);
+    }
+
+    #[test]
+    fn samples_successes() {
+        let sampler = Sampler::new(0.25);
+        let logged = (0..100)
+            .filter(|_| sampler.sample(&fields(Some(200))))
+            .count();
+        assert_eq!(logged, 25);
+
+        // Failures are always logged.
+        assert!((0..10).all(|_| sampler.sample(&fields(Some(503)))));
+        assert!((0..10).all(|_| sampler.sample(&fields(None))));
+
+        let sampler = Sampler::new(0.0);
+        assert!(!(0..100).any(|_| sampler.sample(&fields(Some(200)))));
+    }
+}
@@ -0,0 +1,283 @@
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    os::unix::net::UnixStream,
+    path::{Path, PathBuf},
+    sync::mpsc,
+};
+use tokio::time::{Duration, Instant};
+
+/// The number of access log lines that may be buffered for a background
+/// writer. Lines are dropped when the buffer is full so that a slow sink
+/// never blocks the request path.
+const BUFFER_CAPACITY: usize = 10_000;
+
+/// How long to wait before reconnecting to a Unix socket after a failure.
+const RECONNECT_BACKOFF: Duration = Duration::from_secs(1);
+
+pub(in crate::access_log) const DEFAULT_MAX_FILE_BYTES: u64 = 100 * 1024 * 1024;
+pub(in crate::access_log) const DEFAULT_MAX_FILES: usize = 5;
+
+/// Where access logs are written.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(in crate) enum Output {
+    Stderr,
+
+    /// Writes to a file that is rotated once it reaches `max_bytes`. Up to
+    /// `max_files` rotated files are retained as `<path>.1`, `<path>.2`, etc.
+    File {
+        path: PathBuf,
+        max_bytes: u64,
+        max_files: usize,
+    },
+
+    /// Writes to a Unix stream socket, reconnecting as needed.
+    Unix(PathBuf),
+}
+
+pub(super) enum Sink {
+    Stderr,
+    Background(mpsc::SyncSender<String>),
+}
+
+struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    file: Option<(File, u64)>,
+}
+
+struct UnixSocket {
+    path: PathBuf,
+    stream: Option<UnixStream>,
+    last_failure: Option<Instant>,
+}
+
+// === impl Output ===
+
+impl std::str::FromStr for Output {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("stderr") {
+            return Ok(Self::Stderr);
+        }
+        if let Some(path) = s.strip_prefix("file:").filter(|p| !p.is_empty()) {
+            return Ok(Self::File {
+                path: path.into(),
+                max_bytes: DEFAULT_MAX_FILE_BYTES,
+                max_files: DEFAULT_MAX_FILES,
+            });
+        }
+        if let Some(path) = s.strip_prefix("unix:").filter(|p| !p.is_empty()) {
+            return Ok(Self::Unix(path.into()));
+        }
+        Err("expected 'stderr', 'file:<path>', or 'unix:<path>'")
+    }
+}
+
+// === impl Sink ===
+
+impl Sink {
+    /// Returns a sink for the given output. Files and sockets are written by
+    /// a dedicated thread.
+    pub(super) fn spawn(output: Output) -> Self {
+        let (tx, rx) = mpsc::sync_channel(BUFFER_CAPACITY);
+        let spawned = match output {
+            Output::Stderr => return Self::Stderr,
+            Output::File {
+                path,
+                max_bytes,
+                max_files,
+            } => {
+                let mut file = RotatingFile {
+                    path,
+                    max_bytes,
+                    max_files,
+                    file: None,
+                };
+                Self::spawn_writer(rx, move |line| file.write_line(line))
+            }
+            Output::Unix(path) => {
+                let mut socket = UnixSocket {
+                    path,
+                    stream: None,
+                    last_failure: None,
+                };
+                Self::spawn_writer(rx, move |line| socket.write_line(line))
+            }
+        };
+        match spawned {
+            Ok(()) => Self::Background(tx),
+            Err(error) => {
+                eprintln!("Failed to spawn access log writer: {}", error);
+                Self::Stderr
+            }
+        }
+    }
+
+    fn spawn_writer(
+        rx: mpsc::Receiver<String>,
+        mut write: impl FnMut(&str) -> io::Result<()> + Send + 'static,
+    ) -> io::Result<()> {
+        std::thread::Builder::new()
+            .name("access-log".into())
+            .spawn(move || {
+                let mut failing = false;
+                for line in rx {
+                    match write(&line) {
+                        Ok(()) => failing = false,
+                        Err(error) if !failing => {
+                            eprintln!("Failed to write access log: {}", error);
+                            failing = true;
+                        }
+                        Err(_) => {}
+                    }
+                }
+            })?;
+        Ok(())
+    }
+
+    pub(super) fn write(&self, line: String) {
+        match self {
+            Self::Stderr => eprintln!("{}", line),
+            Self::Background(tx) => {
+                // If the writer is falling behind, drop the line.
+                let _ = tx.try_send(line);
+            }
+        }
+    }
+}
+
+// === impl RotatingFile ===
+
+impl RotatingFile {
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let line_len = line.len() as u64 + 1;
+        let (file, len) = match self.file.take() {
+            Some((_, len)) if len > 0 && len + line_len > self.max_bytes => {
+                self.rotate()?;
+                self.open()?
+            }
+            Some(file) => file,
+            None => self.open()?,
+        };
+        let (file, len) = self.file.insert((file, len));
+        writeln!(file, "{}", line)?;
+        *len += line_len;
+        Ok(())
+    }
+
+    fn open(&self) -> io::Result<(File, u64)> {
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        let len = file.metadata()?.len();
+        Ok((file, len))
+    }
+
+    /// Shifts each rotated file up by one, discarding the oldest, and moves
+    /// the current file to `<path>.1`.
+    fn rotate(&self) -> io::Result<()> {
+        if self.max_files == 0 {
+            return fs::remove_file(&self.path);
+        }
+        for i in (1..self.max_files).rev() {
+            match fs::rename(self.rotated(i), self.rotated(i + 1)) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        fs::rename(&self.path, self.rotated(1))
+    }
+
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", n));
+        path.into()
+    }
+}
+
+// === impl UnixSocket ===
+
+impl UnixSocket {
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let stream = match self.stream.as_mut() {
+            Some(stream) => stream,
+            None => {
+                // Avoid reconnecting for every line while the socket is
+                // unavailable; lines are dropped in the meantime.
+                if let Some(failed) = self.last_failure {
+                    if Instant::now().saturating_duration_since(failed) < RECONNECT_BACKOFF {
+                        return Ok(());
+                    }
+                }
+                match connect(&self.path) {
+                    Ok(stream) => self.stream.insert(stream),
+                    Err(e) => {
+                        self.last_failure = Some(Instant::now());
+                        return Err(e);
+                    }
+                }
+            }
+        };
+
+        if let Err(e) = writeln!(stream, "{}", line) {
+            self.stream = None;
+            self.last_failure = Some(Instant::now());
+            return Err(e);
+        }
+        Ok(())
+    }
+}
+
+fn connect(path: &Path) -> io::Result<UnixStream> {
+    let stream = UnixStream::connect(path)?;
+    stream.set_write_timeout(Some(RECONNECT_BACKOFF))?;
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotates_files() {
+        let dir = std::env::temp_dir().join(format!("linkerd-access-log-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("access.log");
+        let mut file = RotatingFile {
+            path: path.clone(),
+            max_bytes: 8,
+            max_files: 2,
+            file: None,
+        };
+
+        for line in ["aaaaaaa", "bbbbbbb", "ccccccc", "ddddddd"] {
+            file.write_line(line).unwrap();
+        }
+
+        let read = |p: PathBuf| fs::read_to_string(p).unwrap();
+        assert_eq!(read(path.clone()), "ddddddd\n");
+        assert_eq!(read(file.rotated(1)), "ccccccc\n");
+        assert_eq!(read(file.rotated(2)), "bbbbbbb\n");
+        assert!(!file.rotated(3).exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn parses_output() {
+        assert_eq!("stderr".parse(), Ok(Output::Stderr));
+        assert_eq!(
+            "unix:/var/run/access.sock".parse(),
+            Ok(Output::Unix("/var/run/access.sock".into()))
+        );
+        assert!(matches!(
+            "file:/var/log/access.log".parse(),
+            Ok(Output::File { path, .. }) if path == Path::new("/var/log/access.log")
+        ));
+        assert!("file:".parse::<Output>().is_err());
+        assert!("syslog".parse::<Output>().is_err());
+    }
+}
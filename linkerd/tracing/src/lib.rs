@@ -26,15 +26,21 @@ use tracing_subscriber::{
 const ENV_LOG_LEVEL: &str = "LINKERD2_PROXY_LOG";
 const ENV_LOG_FORMAT: &str = "LINKERD2_PROXY_LOG_FORMAT";
 const ENV_ACCESS_LOG: &str = "LINKERD2_PROXY_ACCESS_LOG";
-
-const DEFAULT_LOG_LEVEL: &str = "warn,linkerd=info";
+const ENV_ACCESS_LOG_FIELDS: &str = "LINKERD2_PROXY_ACCESS_LOG_FIELDS";
+const ENV_ACCESS_LOG_OUTPUT: &str = "LINKERD2_PROXY_ACCESS_LOG_OUTPUT";
+const ENV_ACCESS_LOG_MAX_FILE_BYTES: &str = "LINKERD2_PROXY_ACCESS_LOG_MAX_FILE_BYTES";
+const ENV_ACCESS_LOG_MAX_FILES: &str = "LINKERD2_PROXY_ACCESS_LOG_MAX_FILES";
+const ENV_ACCESS_LOG_SAMPLE_RATE: &str = "LINKERD2_PROXY_ACCESS_LOG_SAMPLE_RATE";
+
+/// The tracing filter used when `LINKERD2_PROXY_LOG` is not set.
+pub const DEFAULT_LOG_LEVEL: &str = "warn,linkerd=info";
 const DEFAULT_LOG_FORMAT: &str = "PLAIN";
 
 #[derive(Debug, Default)]
 pub struct Settings {
     filter: Option<String>,
     format: Option<String>,
-    access_log: Option<access_log::Format>,
+    access_log: Option<access_log::Config>,
     is_test: bool,
 }
 
@@ -85,7 +91,7 @@ impl Settings {
         Some(Self {
             filter,
             format: std::env::var(ENV_LOG_FORMAT).ok(),
-            access_log: Self::access_log_format(),
+            access_log: Self::access_log_config(),
             is_test: false,
         })
     }
@@ -94,7 +100,7 @@ impl Settings {
         Self {
             filter: Some(filter),
             format: Some(format),
-            access_log: Self::access_log_format(),
+            access_log: Self::access_log_config(),
             is_test: true,
         }
     }
@@ -106,17 +112,88 @@ impl Settings {
             .to_uppercase()
     }
 
-    fn access_log_format() -> Option<access_log::Format> {
-        let env = std::env::var(ENV_ACCESS_LOG).ok()?;
-        match env.parse() {
-            Ok(format) => Some(format),
-            Err(err) => {
-                eprintln!("Invalid {}={:?}: {}", ENV_ACCESS_LOG, env, err);
+    /// Reads the access log configuration from the environment. Access
+    /// logging is disabled if any of its settings is invalid.
+    fn access_log_config() -> Option<access_log::Config> {
+        match Self::try_access_log_config() {
+            Ok(config) => config,
+            Err(error) => {
+                eprintln!("{}", error);
                 None
             }
         }
     }
 
+    fn try_access_log_config() -> Result<Option<access_log::Config>, Error> {
+        let format = match Self::access_log_env(ENV_ACCESS_LOG)? {
+            Some(format) => format,
+            None => return Ok(None),
+        };
+        let mut config = access_log::Config::new(format);
+
+        if let Ok(fields) = std::env::var(ENV_ACCESS_LOG_FIELDS) {
+            let fields = fields
+                .split(',')
+                .map(str::trim)
+                .filter(|f| !f.is_empty())
+                .map(String::from)
+                .collect::<Vec<_>>();
+            if format != access_log::Format::Json {
+                eprintln!(
+                    "{} is only supported with JSON access logs",
+                    ENV_ACCESS_LOG_FIELDS
+                );
+            }
+            config.fields = Some(fields.into());
+        }
+
+        if let Some(output) = Self::access_log_env(ENV_ACCESS_LOG_OUTPUT)? {
+            config.output = output;
+        }
+        if let access_log::Output::File {
+            max_bytes,
+            max_files,
+            ..
+        } = &mut config.output
+        {
+            if let Some(bytes) = Self::access_log_env(ENV_ACCESS_LOG_MAX_FILE_BYTES)? {
+                *max_bytes = bytes;
+            }
+            if let Some(files) = Self::access_log_env(ENV_ACCESS_LOG_MAX_FILES)? {
+                *max_files = files;
+            }
+        }
+
+        if let Some(rate) = Self::access_log_env::<f64>(ENV_ACCESS_LOG_SAMPLE_RATE)? {
+            if !(0.0..=1.0).contains(&rate) {
+                return Err(format!(
+                    "Invalid {}={:?}: must be between 0.0 and 1.0",
+                    ENV_ACCESS_LOG_SAMPLE_RATE, rate
+                )
+                .into());
+            }
+            config.sample_rate = rate;
+        }
+
+        Ok(Some(config))
+    }
+
+    /// Parses an optional access log environment variable, failing if the
+    /// variable is set to an invalid value.
+    fn access_log_env<T>(name: &str) -> Result<Option<T>, Error>
+    where
+        T: str::FromStr,
+        T::Err: std::fmt::Display,
+    {
+        let env = match std::env::var(name) {
+            Ok(env) => env,
+            Err(_) => return Ok(None),
+        };
+        env.parse()
+            .map(Some)
+            .map_err(|err| format!("Invalid {}={:?}: {}", name, env, err).into())
+    }
+
     fn mk_json<S>(&self) -> Box<dyn Layer<S> + Send + Sync + 'static>
     where
         S: Subscriber + for<'span> LookupSpan<'span>,
@@ -173,8 +250,8 @@ impl Settings {
             .parse_lossy(log_level);
 
         // If access logging is enabled, build the access log layer.
-        let access_log = if let Some(format) = self.access_log {
-            let (access_log, directive) = access_log::build(format);
+        let access_log = if let Some(config) = self.access_log.clone() {
+            let (access_log, directive) = access_log::build(config);
             filter = filter.add_directive(directive);
             Some(access_log)
         } else {
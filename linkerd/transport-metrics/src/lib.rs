@@ -18,7 +18,9 @@ pub use self::{
     server::NewServer,
 };
 use linkerd_errno::Errno;
-use linkerd_metrics::{metrics, Counter, FmtLabels, Gauge, LastUpdate, Store};
+use linkerd_metrics::{
+    latency, metrics, Counter, FmtLabels, Gauge, LastUpdate, NativeHistogram, Store,
+};
 use parking_lot::Mutex;
 use std::{collections::HashMap, fmt, hash::Hash, sync::Arc};
 use tokio::time::{Duration, Instant};
@@ -29,17 +31,32 @@ metrics! {
     tcp_read_bytes_total: Counter { "Total count of bytes read from peers" },
     tcp_write_bytes_total: Counter { "Total count of bytes written to peers" },
 
-    tcp_close_total: Counter { "Total count of closed connections" }
+    tcp_close_total: Counter { "Total count of closed connections" },
+    tcp_connection_duration_ms: NativeHistogram<latency::Ms> {
+        "Elapsed times between connections being opened and closed"
+    }
 }
 
-pub fn new<K: Eq + Hash + FmtLabels>(retain_idle: Duration) -> (Registry<K>, Report<K>) {
+/// Creates a registry whose native connection duration histograms have the
+/// given initial schema.
+pub fn new<K: Eq + Hash + FmtLabels>(
+    retain_idle: Duration,
+    native_schema: i32,
+) -> (Registry<K>, Report<K>) {
     let inner = Arc::new(Mutex::new(Inner::new()));
     let report = Report::new(inner.clone(), retain_idle);
-    (Registry(inner), report)
+    let registry = Registry {
+        inner,
+        native_schema,
+    };
+    (registry, report)
 }
 
 #[derive(Clone, Debug)]
-pub struct Registry<K: Eq + Hash + FmtLabels>(Arc<Mutex<Inner<K>>>);
+pub struct Registry<K: Eq + Hash + FmtLabels> {
+    inner: Arc<Mutex<Inner<K>>>,
+    native_schema: i32,
+}
 
 type Inner<K> = Store<K, Metrics>;
 
@@ -57,6 +74,7 @@ pub struct Metrics {
 #[derive(Debug)]
 struct ByEos {
     last_update: Instant,
+    native_schema: i32,
     metrics: HashMap<Eos, EosMetrics>,
 }
 
@@ -69,16 +87,22 @@ struct ByEos {
 struct Eos(Option<Errno>);
 
 /// Holds metrics for a class of end-of-stream.
-#[derive(Debug, Default)]
+#[derive(Debug)]
 struct EosMetrics {
     close_total: Counter,
+    connection_duration: NativeHistogram<latency::Ms>,
 }
 
 // === impl Registry ===
 
 impl<K: Eq + Hash + FmtLabels> Registry<K> {
     pub fn metrics(&self, labels: K) -> Arc<Metrics> {
-        self.0.lock().get_or_default(labels).clone()
+        let native_schema = self.native_schema;
+        self.inner
+            .lock()
+            .entry(labels)
+            .or_insert_with(|| Arc::new(Metrics::new(native_schema)))
+            .clone()
     }
 }
 
@@ -95,6 +119,18 @@ impl FmtLabels for Eos {
 
 // === impl Metrics ===
 
+impl Metrics {
+    fn new(native_schema: i32) -> Self {
+        Self {
+            open_total: Counter::default(),
+            open_connections: Gauge::default(),
+            write_bytes_total: Counter::default(),
+            read_bytes_total: Counter::default(),
+            by_eos: Arc::new(Mutex::new(ByEos::new(native_schema))),
+        }
+    }
+}
+
 impl LastUpdate for Metrics {
     fn last_update(&self) -> Instant {
         self.by_eos.lock().last_update
@@ -103,15 +139,33 @@ impl LastUpdate for Metrics {
 
 // === impl ByEos ===
 
-impl Default for ByEos {
-    fn default() -> Self {
+impl ByEos {
+    fn new(native_schema: i32) -> Self {
         Self {
             metrics: HashMap::new(),
+            native_schema,
             last_update: Instant::now(),
         }
     }
 }
 
+impl Default for ByEos {
+    fn default() -> Self {
+        Self::new(latency::DEFAULT_SCHEMA)
+    }
+}
+
+// === impl EosMetrics ===
+
+impl EosMetrics {
+    fn new(native_schema: i32) -> Self {
+        Self {
+            close_total: Counter::default(),
+            connection_duration: NativeHistogram::latency(native_schema),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -129,8 +183,8 @@ mod tests {
         }
 
         let retain_idle_for = Duration::from_secs(1);
-        let (r, report) = super::new(retain_idle_for);
-        let mut registry = r.0.lock();
+        let (r, report) = super::new(retain_idle_for, super::latency::DEFAULT_SCHEMA);
+        let mut registry = r.inner.lock();
 
         let before_update = Instant::now();
         let metrics = registry.entry(Target(123)).or_default().clone();
@@ -161,4 +215,32 @@ mod tests {
 
         drop((registry, report));
     }
+
+    #[test]
+    fn records_connection_duration() {
+        use linkerd_metrics::{FmtLabels, FmtMetrics};
+        use std::fmt;
+        use tokio::time::Duration;
+
+        #[derive(Clone, Debug, Hash, Eq, PartialEq)]
+        struct Target(usize);
+        impl FmtLabels for Target {
+            fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "n=\"{}\"", self.0)
+            }
+        }
+
+        let (registry, report) =
+            super::new(Duration::from_secs(10), super::latency::DEFAULT_SCHEMA);
+        let sensor = super::Sensor::open(registry.metrics(Target(1)));
+        drop(sensor);
+
+        let text = report.as_display().to_string();
+        assert!(
+            text.lines()
+                .any(|l| l == "tcp_connection_duration_ms_count{n=\"1\",errno=\"\"} 1"),
+            "{}",
+            text
+        );
+    }
 }
@@ -1,8 +1,8 @@
 use super::{
-    tcp_close_total, tcp_open_connections, tcp_open_total, tcp_read_bytes_total,
-    tcp_write_bytes_total, EosMetrics, Inner,
+    tcp_close_total, tcp_connection_duration_ms, tcp_open_connections, tcp_open_total,
+    tcp_read_bytes_total, tcp_write_bytes_total, EosMetrics, Inner,
 };
-use linkerd_metrics::{FmtLabels, FmtMetric, FmtMetrics, Metric};
+use linkerd_metrics::{Encoder, FmtLabels, FmtMetric, FmtMetrics, Metric};
 use parking_lot::Mutex;
 use std::{fmt, hash::Hash, sync::Arc};
 use tokio::time::{Duration, Instant};
@@ -29,7 +29,7 @@ impl<K: Eq + Hash + FmtLabels + 'static> Report<K> {
     /// Formats a metric across all instances of `EosMetrics` in the registry.
     fn fmt_eos_by<N, M>(
         inner: &Inner<K>,
-        f: &mut fmt::Formatter<'_>,
+        f: &mut Encoder<'_>,
         metric: Metric<'_, N, M>,
         get_metric: impl Fn(&EosMetrics) -> &M,
     ) -> fmt::Result
@@ -49,7 +49,7 @@ impl<K: Eq + Hash + FmtLabels + 'static> Report<K> {
 }
 
 impl<K: Eq + Hash + FmtLabels + 'static> FmtMetrics for Report<K> {
-    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fn fmt_metrics(&self, f: &mut Encoder<'_>) -> fmt::Result {
         let mut metrics = self.metrics.lock();
         if metrics.is_empty() {
             return Ok(());
@@ -70,6 +70,11 @@ impl<K: Eq + Hash + FmtLabels + 'static> FmtMetrics for Report<K> {
         tcp_close_total.fmt_help(f)?;
         Self::fmt_eos_by(&*metrics, f, tcp_close_total, |e| &e.close_total)?;
 
+        tcp_connection_duration_ms.fmt_help(f)?;
+        Self::fmt_eos_by(&*metrics, f, tcp_connection_duration_ms, |e| {
+            &e.connection_duration
+        })?;
+
         metrics.retain_since(Instant::now() - self.retain_idle);
 
         Ok(())
@@ -8,6 +8,7 @@ use tokio::time::Instant;
 #[derive(Debug)]
 pub struct Sensor {
     metrics: Option<Arc<Metrics>>,
+    opened_at: Instant,
 }
 
 pub type SensorIo<T> = io::SensorIo<T, Sensor>;
@@ -18,9 +19,11 @@ impl Sensor {
     pub(crate) fn open(metrics: Arc<Metrics>) -> Self {
         metrics.open_total.incr();
         metrics.open_connections.incr();
-        metrics.by_eos.lock().last_update = Instant::now();
+        let now = Instant::now();
+        metrics.by_eos.lock().last_update = now;
         Self {
             metrics: Some(metrics),
+            opened_at: now,
         }
     }
 }
@@ -48,12 +51,17 @@ impl io::Sensor for Sensor {
             m.open_connections.decr();
 
             let mut by_eos = m.by_eos.lock();
+            let native_schema = by_eos.native_schema;
             let class = by_eos
                 .metrics
                 .entry(Eos(eos))
-                .or_insert_with(EosMetrics::default);
+                .or_insert_with(|| EosMetrics::new(native_schema));
             class.close_total.incr();
-            by_eos.last_update = Instant::now();
+            let now = Instant::now();
+            class
+                .connection_duration
+                .add(now.saturating_duration_since(self.opened_at));
+            by_eos.last_update = now;
         }
     }
 
@@ -90,13 +90,13 @@ fn main() {
             ),
         }
 
-        if let Some(oc) = app.opencensus_addr() {
-            match oc.identity.value() {
-                None => info!("OpenCensus tracing collector at {}", oc.addr),
+        if let Some((protocol, addr)) = app.tracing_addr() {
+            match addr.identity.value() {
+                None => info!("{} tracing collector at {}", protocol, addr.addr),
                 Some(tls) => {
                     info!(
-                        "OpenCensus tracing collector at {} ({})",
-                        oc.addr, tls.server_id
+                        "{} tracing collector at {} ({})",
+                        protocol, addr.addr, tls.server_id
                     )
                 }
             }
@@ -0,0 +1,37 @@
+//! gRPC bindings for OpenTelemetry.
+//!
+//! Vendored from <https://github.com/open-telemetry/opentelemetry-proto/>.
+
+#![deny(
+    warnings,
+    rust_2018_idioms,
+    clippy::disallowed_methods,
+    clippy::disallowed_types
+)]
+#![forbid(unsafe_code)]
+
+pub mod collector {
+    pub mod trace {
+        pub mod v1 {
+            include!("gen/opentelemetry.proto.collector.trace.v1.rs");
+        }
+    }
+}
+
+pub mod common {
+    pub mod v1 {
+        include!("gen/opentelemetry.proto.common.v1.rs");
+    }
+}
+
+pub mod resource {
+    pub mod v1 {
+        include!("gen/opentelemetry.proto.resource.v1.rs");
+    }
+}
+
+pub mod trace {
+    pub mod v1 {
+        include!("gen/opentelemetry.proto.trace.v1.rs");
+    }
+}